@@ -0,0 +1,4 @@
+-- Add migration script here
+-- If true, other users need the system owner's approval before message info reveals the owner
+ALTER TABLE systems
+ADD COLUMN message_info_requires_consent BOOLEAN NOT NULL DEFAULT FALSE;
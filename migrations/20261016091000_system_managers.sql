@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Users a system trusts to see more of it than everyone else (e.g. a partner or therapist)
+CREATE TABLE system_managers (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    user_id TEXT NOT NULL,
+    -- A user can only be a manager of a system once
+    UNIQUE (system_id, user_id)
+) STRICT;
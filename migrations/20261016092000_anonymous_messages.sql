@@ -0,0 +1,29 @@
+-- Add migration script here
+-- Anonymous messages aren't linked to a member, so message logs need to track their system directly
+CREATE TABLE message_logs_new (
+    id INTEGER NOT NULL PRIMARY KEY,
+    -- NULL if the message was sent anonymously
+    member_id INTEGER REFERENCES members (id),
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    message_id TEXT UNIQUE NOT NULL
+) STRICT;
+
+INSERT INTO
+    message_logs_new (id, member_id, system_id, message_id)
+SELECT
+    message_logs.id,
+    message_logs.member_id,
+    members.system_id,
+    message_logs.message_id
+FROM
+    message_logs
+    JOIN members ON members.id = message_logs.member_id;
+
+DROP TABLE message_logs;
+
+ALTER TABLE message_logs_new
+RENAME TO message_logs;
+
+-- Messages starting with this prefix are sent anonymously. NULL disables anonymous messages
+ALTER TABLE systems
+ADD COLUMN anonymous_prefix TEXT;
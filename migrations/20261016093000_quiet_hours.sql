@@ -0,0 +1,11 @@
+-- Add migration script here
+-- IANA timezone name (e.g. Europe/London). NULL means UTC
+ALTER TABLE systems
+ADD COLUMN timezone TEXT;
+
+-- Hours (in the system's timezone) between which proxying is paused. Both NULL means no quiet hours
+ALTER TABLE systems
+ADD COLUMN quiet_hours_start INTEGER CHECK (quiet_hours_start BETWEEN 0 AND 23);
+
+ALTER TABLE systems
+ADD COLUMN quiet_hours_end INTEGER CHECK (quiet_hours_end BETWEEN 0 AND 23);
@@ -0,0 +1,16 @@
+-- Add migration script here
+-- A record of actions taken on a system, for owners (and moderators) to look back on
+CREATE TABLE audit_logs (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    -- The Slack user that performed the action
+    actor_id TEXT NOT NULL,
+    -- See audit.rs for all actions
+    action INTEGER NOT NULL,
+    -- Free-form details about the action, e.g. how it was carried out
+    details TEXT,
+    -- STRICT tables can't store CURRENT_TIMESTAMP in an INTEGER column
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL
+) STRICT;
+
+CREATE INDEX audit_logs_system_id ON audit_logs (system_id);
@@ -0,0 +1,5 @@
+-- Add migration script here
+-- Which notifications the bot DMs the system owner. See NotificationPreference in system.rs
+-- Defaults to only errors, since successes are already shown where the action happened
+ALTER TABLE systems
+ADD COLUMN notifications INTEGER NOT NULL DEFAULT 1;
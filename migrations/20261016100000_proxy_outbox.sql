@@ -0,0 +1,15 @@
+-- Add migration script here
+-- Proxy operations that have started but not finished deleting the original message.
+-- A row is inserted before posting, updated once the proxied message is posted, and removed once the original is deleted.
+-- Any rows left on startup are from operations interrupted by a crash, and are resolved by the recovery job.
+CREATE TABLE proxy_outbox (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    channel_id TEXT NOT NULL,
+    -- The message being replaced
+    original_ts TEXT NOT NULL,
+    -- See proxy.rs for all kinds. 0 = sent by the user, 1 = already proxied by the bot
+    original_kind INTEGER NOT NULL,
+    -- NULL until the proxied message is posted
+    posted_ts TEXT
+) STRICT;
@@ -0,0 +1,16 @@
+-- Add migration script here
+-- Files the bot hosts itself, like name recordings uploaded through Slack
+CREATE TABLE blobs (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    content_type TEXT NOT NULL,
+    data BLOB NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) STRICT;
+
+-- Systems waiting to upload a name recording for a member, started with /members record
+CREATE TABLE member_recording_process (
+    system_id INTEGER NOT NULL PRIMARY KEY REFERENCES systems (id),
+    member_id INTEGER NOT NULL REFERENCES members (id),
+    started_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) STRICT;
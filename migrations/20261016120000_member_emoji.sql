@@ -0,0 +1,3 @@
+-- Add migration script here
+-- A custom workspace emoji shown next to the member when they're switched to. Stored without colons
+ALTER TABLE members ADD COLUMN emoji TEXT;
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- If true, untriggered messages sent while no one is fronting get an ephemeral prompt to proxy them as a recently used member
+ALTER TABLE systems
+ADD COLUMN suggest_members BOOLEAN NOT NULL DEFAULT FALSE;
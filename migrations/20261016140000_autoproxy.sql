@@ -0,0 +1,12 @@
+-- Add migration script here
+-- How messages without a trigger are proxied. See system.rs for all modes. 1 (front) is how proxying worked before modes
+ALTER TABLE systems
+ADD COLUMN autoproxy_mode INTEGER NOT NULL DEFAULT 1;
+
+-- The member to proxy as in member mode
+ALTER TABLE systems
+ADD COLUMN autoproxy_member_id INTEGER REFERENCES members (id);
+
+-- The member whose trigger was used last, proxied as in latch mode
+ALTER TABLE systems
+ADD COLUMN latched_member_id INTEGER REFERENCES members (id);
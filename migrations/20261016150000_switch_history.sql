@@ -0,0 +1,12 @@
+-- Add migration script here
+-- Every time a system's fronting member changed, or a switch was logged without changing it
+CREATE TABLE switches (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    -- NULL when switching to the base account
+    member_id INTEGER REFERENCES members (id),
+    -- STRICT tables can't store CURRENT_TIMESTAMP in an INTEGER column
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP NOT NULL
+) STRICT;
+
+CREATE INDEX switches_system_id ON switches (system_id, created_at);
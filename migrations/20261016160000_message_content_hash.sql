@@ -0,0 +1,6 @@
+-- Add migration script here
+-- A short hash of the original message's text, for spotting messages Slack sent twice
+ALTER TABLE message_logs
+ADD COLUMN content_hash TEXT;
+
+CREATE INDEX message_logs_content_hash ON message_logs (system_id, content_hash);
@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Channels where a system's messages are never proxied
+CREATE TABLE proxy_blacklist (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    channel_id TEXT NOT NULL,
+    -- A channel can only be blacklisted once per system
+    UNIQUE (system_id, channel_id)
+) STRICT;
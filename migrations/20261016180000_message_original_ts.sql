@@ -0,0 +1,6 @@
+-- Add migration script here
+-- The user's message a proxied message was made from, so edits to it can update the proxied copy
+ALTER TABLE message_logs
+ADD COLUMN original_ts TEXT;
+
+CREATE INDEX message_logs_original_ts ON message_logs (original_ts);
@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Redacted message event payloads, kept for a short while when event capture is turned on
+CREATE TABLE captured_events (
+    id INTEGER NOT NULL PRIMARY KEY,
+    payload TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) STRICT;
+
+CREATE INDEX captured_events_created_at ON captured_events (created_at);
@@ -0,0 +1,3 @@
+-- Add migration script here
+-- An avatar for proxied messages, so the profile picture can be a detailed one for info cards
+ALTER TABLE members ADD COLUMN proxy_avatar_url TEXT;
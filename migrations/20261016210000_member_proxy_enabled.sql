@@ -0,0 +1,3 @@
+-- Add migration script here
+-- Members that are kept for their info, but never proxied as
+ALTER TABLE members ADD COLUMN proxy_enabled BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- A member's command name is their primary alias: a short name to use in commands
+ALTER TABLE aliases ADD COLUMN is_primary INTEGER NOT NULL DEFAULT 0 CHECK (is_primary IN (0, 1));
+
+-- Each member has at most one command name
+CREATE UNIQUE INDEX aliases_primary_member_id ON aliases (member_id) WHERE is_primary;
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- When a proxied message was sent, for message stats.
+-- STRICT tables don't allow TIMESTAMP, so it's stored as text like the other tables' timestamps
+ALTER TABLE message_logs
+ADD COLUMN sent_at TEXT;
+
+-- Message IDs are Slack timestamps, so existing messages can be filled in from them
+UPDATE message_logs
+SET sent_at = datetime(CAST(message_id AS REAL), 'unixepoch');
+
+CREATE INDEX message_logs_member_sent_at ON message_logs (system_id, member_id, sent_at);
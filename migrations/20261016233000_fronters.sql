@@ -0,0 +1,25 @@
+-- Add migration script here
+-- Everyone fronting in a system, in the order they were switched in.
+-- The system's currently_fronting_member_id stays as the primary fronter, which autoproxy uses
+CREATE TABLE fronters (
+    system_id INTEGER NOT NULL REFERENCES systems (id) ON DELETE CASCADE,
+    member_id INTEGER NOT NULL REFERENCES members (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (system_id, member_id)
+);
+
+INSERT INTO fronters (system_id, member_id, position)
+SELECT id, currently_fronting_member_id, 0
+FROM systems
+WHERE currently_fronting_member_id IS NOT NULL;
+
+CREATE TRIGGER check_fronter_is_enabled
+BEFORE INSERT ON fronters
+FOR EACH ROW
+BEGIN
+    SELECT
+        RAISE(ABORT, 'Cannot add a disabled member as a fronter')
+    WHERE EXISTS (
+        SELECT 1 FROM members WHERE id = NEW.member_id AND enabled = FALSE
+    );
+END;
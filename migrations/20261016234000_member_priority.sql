@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Decides which member wins when triggers from several members match a message equally well. Higher wins
+ALTER TABLE members
+ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- Imports waiting on the user to decide what to do with members that look like ones they already have.
+-- A row is inserted when the conflicts form is opened, and removed once it's submitted.
+-- Forms that are never submitted leave their row behind, and it's cleared out after a day.
+CREATE TABLE pending_imports (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    -- The members being imported, as exported members in JSON
+    members TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) STRICT;
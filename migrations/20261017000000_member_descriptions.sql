@@ -0,0 +1,4 @@
+-- Add migration script here
+-- A long-form bio, in Slack mrkdwn
+ALTER TABLE members
+ADD COLUMN description TEXT;
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- As YYYY-MM-DD, or MM-DD if the year is left out
+ALTER TABLE members
+ADD COLUMN birthday TEXT;
+
+-- Labelled fields a system attaches to a member, like their age or role. Shown in the order they were added
+CREATE TABLE member_fields (
+    id INTEGER NOT NULL PRIMARY KEY,
+    member_id INTEGER NOT NULL REFERENCES members (id),
+    name TEXT NOT NULL COLLATE NOCASE,
+    value TEXT NOT NULL,
+    UNIQUE (member_id, name)
+) STRICT;
@@ -0,0 +1,20 @@
+-- Add migration script here
+-- IDs that systems and members have in other services, so integrations can find them again without matching names.
+-- Importing from the same place again uses these to update members instead of adding them again.
+CREATE TABLE external_ids (
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    -- NULL when the ID is the system's own
+    member_id INTEGER REFERENCES members (id),
+    -- The service the ID is from, e.g. pluralkit
+    provider TEXT NOT NULL,
+    -- The ID there, which stays the same when the member or system is renamed
+    external_id TEXT NOT NULL,
+    PRIMARY KEY (system_id, provider, external_id)
+) STRICT;
+
+-- A member or system has at most one ID per provider
+CREATE UNIQUE INDEX external_ids_member_provider ON external_ids (member_id, provider)
+WHERE member_id IS NOT NULL;
+
+CREATE UNIQUE INDEX external_ids_system_provider ON external_ids (system_id, provider)
+WHERE member_id IS NULL;
@@ -0,0 +1,17 @@
+-- Add migration script here
+-- The channel a proxied message was posted in, so it can be deleted later. NULL for messages logged before this,
+-- and for the user's own messages indexed by a backfill
+ALTER TABLE message_logs
+ADD COLUMN channel_id TEXT;
+
+-- Channels where a system's proxied messages are deleted after a while
+CREATE TABLE ephemeral_channels (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    channel_id TEXT NOT NULL,
+    -- How long proxied messages are kept for
+    hours INTEGER NOT NULL,
+    UNIQUE (system_id, channel_id)
+) STRICT;
+
+CREATE INDEX message_logs_channel_sent_at ON message_logs (system_id, channel_id, sent_at);
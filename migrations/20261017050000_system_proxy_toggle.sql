@@ -0,0 +1,4 @@
+-- Add migration script here
+-- If false, none of the system's messages are proxied, without changing its triggers or autoproxy
+ALTER TABLE systems
+ADD COLUMN proxy_enabled BOOLEAN NOT NULL DEFAULT TRUE;
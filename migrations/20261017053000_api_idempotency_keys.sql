@@ -0,0 +1,13 @@
+-- Add migration script here
+-- Responses to API requests sent with an Idempotency-Key header, so a retried request returns the first response
+-- instead of being carried out again. Kept for a day
+CREATE TABLE api_idempotency_keys (
+    system_id INTEGER NOT NULL REFERENCES systems (id) ON DELETE CASCADE,
+    key TEXT NOT NULL,
+    -- Which endpoint the key was used for, e.g. "proxy"
+    endpoint TEXT NOT NULL,
+    -- The JSON response. NULL while the first request is still being handled
+    response TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (system_id, key)
+) STRICT;
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- How many days to keep the system's message logs for. NULL keeps them forever
+ALTER TABLE systems
+ADD COLUMN log_retention_days INTEGER;
+
+CREATE INDEX message_logs_sent_at ON message_logs (sent_at);
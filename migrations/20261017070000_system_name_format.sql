@@ -0,0 +1,9 @@
+-- Add migration script here
+-- A tag added to the names of the system's proxied messages, like PluralKit's system tags
+ALTER TABLE systems
+ADD COLUMN system_tag TEXT;
+
+-- How the names of the system's proxied messages are built, with {name} and {system_tag} placeholders.
+-- NULL uses the default format
+ALTER TABLE systems
+ADD COLUMN name_format TEXT;
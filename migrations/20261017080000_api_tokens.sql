@@ -0,0 +1,6 @@
+-- Add migration script here
+-- A token for sending messages through the API as the system's members. NULL turns the API off for the system
+ALTER TABLE systems
+ADD COLUMN api_token TEXT;
+
+CREATE UNIQUE INDEX systems_api_token ON systems (api_token);
@@ -0,0 +1,8 @@
+-- Add migration script here
+-- How many hours without a switch before the owner is reminded to log one. NULL turns reminders off
+ALTER TABLE systems
+ADD COLUMN switch_reminder_hours INTEGER;
+
+-- When the owner was last reminded, or when reminders were turned on. Reminders wait a full interval after this
+ALTER TABLE systems
+ADD COLUMN switch_reminded_at TEXT;
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- If true, a proxied message's original is kept until the owner confirms the proxy through an ephemeral prompt
+ALTER TABLE systems
+ADD COLUMN hold_back BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Who can look the system up with /system info and /members list. 0 is public, 1 is private
+ALTER TABLE systems
+ADD COLUMN visibility INTEGER NOT NULL DEFAULT 0;
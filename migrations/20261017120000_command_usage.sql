@@ -0,0 +1,9 @@
+-- Add migration script here
+-- How many times each command was run per day. Who ran them and with what isn't recorded
+CREATE TABLE command_usage (
+    day TEXT NOT NULL,
+    -- The subcommand path, e.g. "members add"
+    command TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (day, command)
+);
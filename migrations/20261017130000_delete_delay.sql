@@ -0,0 +1,4 @@
+-- Add migration script here
+-- How long to wait between posting a proxied message and deleting its original, in milliseconds
+ALTER TABLE systems
+ADD COLUMN delete_delay_ms INTEGER NOT NULL DEFAULT 0;
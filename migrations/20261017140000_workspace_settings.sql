@@ -0,0 +1,11 @@
+-- Add migration script here
+-- Settings for the workspace the bot runs in. The bot only runs in one workspace, so there's at most one row
+CREATE TABLE workspace_settings (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    -- The name the bot posts its own messages under, instead of the app's name
+    display_name TEXT,
+    -- The picture the bot posts its own messages with, instead of the app's icon
+    icon_url TEXT,
+    -- Shown under the bot's own messages
+    footer TEXT
+);
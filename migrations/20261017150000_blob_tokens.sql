@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Blobs were served at their row ID, so anyone could walk through every recording. They're now served at a random
+-- token instead. Existing blobs get one here, and recording URLs pointing at the old ID are moved over to it.
+ALTER TABLE blobs ADD COLUMN token TEXT;
+
+UPDATE blobs SET token = lower(hex(randomblob(16)));
+
+CREATE UNIQUE INDEX blobs_token ON blobs (token);
+
+UPDATE members
+SET name_recording_url = substr(name_recording_url, 1, length(name_recording_url) - length(blobs.id)) || blobs.token
+FROM blobs
+WHERE members.system_id = blobs.system_id
+    AND members.name_recording_url LIKE '%/blobs/' || blobs.id;
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- API tokens are stored as SHA-256 hashes instead of in plaintext, so a copy of the database can't be used to send
+-- messages. The plaintext of existing tokens can't be recovered to hash them, so they're revoked and systems need to
+-- make a new one with /system api-token.
+DROP INDEX systems_api_token;
+
+ALTER TABLE systems
+DROP COLUMN api_token;
+
+ALTER TABLE systems
+ADD COLUMN api_token_hash TEXT;
+
+CREATE UNIQUE INDEX systems_api_token_hash ON systems (api_token_hash);
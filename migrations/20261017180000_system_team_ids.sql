@@ -0,0 +1,5 @@
+-- Add migration script here
+-- The Slack workspace a system's owner authorized it from, so moderators can only inspect systems in their own
+-- workspace. NULL for systems authorized before this was recorded
+ALTER TABLE systems
+ADD COLUMN team_id TEXT;
@@ -0,0 +1,532 @@
+//! The HTTP API, for external tools like a desktop app for quickly sending messages as a member, or `plura-cli`.
+//!
+//! Requests are authenticated with a system's API token from `/system api-token`, sent as
+//! `Authorization: Bearer <token>`. Errors are returned as JSON with an `error` message. Requests that post or switch
+//! can be retried safely by sending the same `Idempotency-Key` header each time. Each system is [rate limited](authenticate).
+
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::json;
+use slack_morphism::prelude::*;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    BOT_TOKEN, fields, home,
+    models::{
+        Alias, IdempotencyKey, export::Export, idempotency_key::Claim, member::MemberRef, system,
+        trust::Trusted, user, viewer::Viewer,
+    },
+    ops,
+    proxy::{ProxyOptions, ProxyRequest},
+    rate_limit, render,
+};
+
+/// The start of every API token, so they're easy to recognise
+pub const TOKEN_PREFIX: &str = "plura_";
+
+/// The header scripts set to make retrying a request safe. See [`idempotent`]
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// The longest idempotency key accepted
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// A request to post a message as a member
+#[derive(Deserialize, Debug)]
+pub struct ProxyBody {
+    /// The ID of the channel to post in
+    channel: String,
+    /// The member's ID or alias
+    member: String,
+    text: String,
+}
+
+/// A failed request, returned with its status code
+#[derive(Debug)]
+struct ApiError(StatusCode, &'static str);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+/// Reports an error on our end, hiding the details from the caller
+fn internal(error: &impl std::fmt::Debug) -> ApiError {
+    error!(?error, "Error handling API request");
+    ops::report("api", error);
+    ApiError(StatusCode::INTERNAL_SERVER_ERROR, "Something went wrong")
+}
+
+/// `POST /api/v1/systems/{id}/proxy`: posts a message as one of the system's members.
+///
+/// Responds with the posted message's timestamp as `{"ts": "..."}`. Supports [idempotency keys](idempotent).
+#[tracing::instrument(skip(state, client, headers, body), fields(system_id))]
+pub async fn proxy_handler(
+    Path(id): Path<i64>,
+    State(state): State<user::State>,
+    Extension(client): Extension<Arc<SlackHyperClient>>,
+    Extension(system_id): Extension<system::Id<Trusted>>,
+    headers: HeaderMap,
+    Json(body): Json<ProxyBody>,
+) -> Response {
+    match proxy(id, system_id, &headers, body, &client, &state).await {
+        Ok(response) => Json(response).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn proxy(
+    id: i64,
+    system_id: system::Id<Trusted>,
+    headers: &HeaderMap,
+    body: ProxyBody,
+    client: &SlackHyperClient,
+    state: &user::State,
+) -> Result<serde_json::Value, ApiError> {
+    let system_id = authorize(id, system_id)?;
+
+    idempotent(
+        system_id,
+        headers,
+        "proxy",
+        state,
+        post_message(system_id, body, client, state),
+    )
+    .await
+}
+
+async fn post_message(
+    system_id: system::Id<Trusted>,
+    body: ProxyBody,
+    client: &SlackHyperClient,
+    state: &user::State,
+) -> Result<serde_json::Value, ApiError> {
+    if body.text.trim().is_empty() {
+        return Err(ApiError(StatusCode::BAD_REQUEST, "The message is empty"));
+    }
+
+    let system = system_id
+        .fetch(&state.db)
+        .await
+        .map_err(|error| internal(&error))?;
+
+    let Ok(member_ref) = body.member.parse::<MemberRef>();
+    let member_id = member_ref
+        .validate_by_system(system_id, &state.db)
+        .await
+        .map_err(|error| internal(&error))?
+        .ok_or(ApiError(StatusCode::NOT_FOUND, "The member doesn't exist"))?;
+
+    let member = member_id
+        .fetch(&state.db)
+        .await
+        .map_err(|error| internal(&error))?;
+
+    if !member.enabled {
+        return Err(ApiError(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "The member is disabled",
+        ));
+    }
+
+    let channel_id = SlackChannelId::new(body.channel);
+
+    // The bot can post in channels the owner isn't in, which a token shouldn't give access to
+    if !is_channel_member(client, &channel_id, &system.owner_id.id.0).await? {
+        return Err(ApiError(
+            StatusCode::FORBIDDEN,
+            "You aren't in that channel, or the bot can't see it",
+        ));
+    }
+
+    debug!("Proxying message from the API");
+
+    ProxyRequest {
+        channel_id,
+        content: SlackMessageContent::new().with_text(body.text),
+        identity: member.into(),
+        thread_ts: None,
+        files: Vec::new(),
+        options: ProxyOptions::default(),
+    }
+    .send(client, &system, &state.db)
+    .await
+    .map(|ts| json!({ "ts": ts.0 }))
+    .map_err(|error| {
+        warn!(?error, "Failed to proxy message from the API");
+        ApiError(StatusCode::BAD_GATEWAY, "Slack didn't accept the message")
+    })
+}
+
+/// `GET /api/v1/systems/{id}/members`: lists the system's members, with their aliases.
+#[tracing::instrument(skip(state), fields(system_id))]
+pub async fn members_handler(
+    Path(id): Path<i64>,
+    State(state): State<user::State>,
+    Extension(system_id): Extension<system::Id<Trusted>>,
+) -> Response {
+    match members(id, system_id, &state).await {
+        Ok(members) => Json(members).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn members(
+    id: i64,
+    system_id: system::Id<Trusted>,
+    state: &user::State,
+) -> Result<serde_json::Value, ApiError> {
+    let system_id = authorize(id, system_id)?;
+
+    let system = system_id
+        .fetch(&state.db)
+        .await
+        .map_err(|error| internal(&error))?;
+
+    let members = system
+        .members(&state.db)
+        .await
+        .map_err(|error| internal(&error))?;
+
+    let aliases = Alias::fetch_by_system_id(system_id, &state.db)
+        .await
+        .map_err(|error| internal(&error))?;
+
+    Ok(render::json::member_list(&members, &aliases, Viewer::Owner))
+}
+
+/// Who to switch to
+#[derive(Deserialize, Debug)]
+pub struct SwitchBody {
+    /// The IDs or aliases of the members to switch to, primary fronter first. Empty to switch to the base account
+    members: Vec<String>,
+}
+
+/// `POST /api/v1/systems/{id}/switch`: changes who's fronting, like `/members switch`.
+///
+/// Responds with the IDs of the members now fronting as `{"fronters": [...]}`. Supports [idempotency keys](idempotent).
+#[tracing::instrument(skip(state, client, headers, body), fields(system_id))]
+pub async fn switch_handler(
+    Path(id): Path<i64>,
+    State(state): State<user::State>,
+    Extension(client): Extension<Arc<SlackHyperClient>>,
+    Extension(system_id): Extension<system::Id<Trusted>>,
+    headers: HeaderMap,
+    Json(body): Json<SwitchBody>,
+) -> Response {
+    match switch(id, system_id, &headers, body, &client, &state).await {
+        Ok(response) => Json(response).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn switch(
+    id: i64,
+    system_id: system::Id<Trusted>,
+    headers: &HeaderMap,
+    body: SwitchBody,
+    client: &SlackHyperClient,
+    state: &user::State,
+) -> Result<serde_json::Value, ApiError> {
+    let system_id = authorize(id, system_id)?;
+
+    idempotent(
+        system_id,
+        headers,
+        "switch",
+        state,
+        set_fronters(system_id, body, client, state),
+    )
+    .await
+}
+
+async fn set_fronters(
+    system_id: system::Id<Trusted>,
+    body: SwitchBody,
+    client: &SlackHyperClient,
+    state: &user::State,
+) -> Result<serde_json::Value, ApiError> {
+    let mut fronter_ids = Vec::new();
+
+    for member in body.members {
+        let Ok(member_ref) = member.parse::<MemberRef>();
+        let member_id = member_ref
+            .validate_by_system(system_id, &state.db)
+            .await
+            .map_err(|error| internal(&error))?
+            .ok_or(ApiError(StatusCode::NOT_FOUND, "A member doesn't exist"))?;
+
+        let member = member_id
+            .fetch(&state.db)
+            .await
+            .map_err(|error| internal(&error))?;
+
+        if !member.enabled || !member.proxy_enabled {
+            return Err(ApiError(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "A member is disabled or has proxying turned off",
+            ));
+        }
+
+        if !fronter_ids.contains(&member_id) {
+            fronter_ids.push(member_id);
+        }
+    }
+
+    system_id
+        .set_fronters(&fronter_ids, &state.db)
+        .await
+        .map_err(|error| internal(&error))?;
+
+    let system = system_id
+        .fetch(&state.db)
+        .await
+        .map_err(|error| internal(&error))?;
+
+    info!(member_ids = ?fronter_ids, "Switched from the API");
+    home::refresh(&system.owner_id.id.0, client, &state.db).await;
+
+    let fronters = fronter_ids
+        .into_iter()
+        .map(|member_id| member_id.id)
+        .collect::<Vec<_>>();
+
+    Ok(json!({ "fronters": fronters }))
+}
+
+/// `GET /api/v1/systems/{id}/export`: the same JSON export as `/system export`.
+#[tracing::instrument(skip(state), fields(system_id))]
+pub async fn export_handler(
+    Path(id): Path<i64>,
+    State(state): State<user::State>,
+    Extension(system_id): Extension<system::Id<Trusted>>,
+) -> Response {
+    match export(id, system_id, &state).await {
+        Ok(json) => ([(header::CONTENT_TYPE, "application/json")], json).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn export(
+    id: i64,
+    system_id: system::Id<Trusted>,
+    state: &user::State,
+) -> Result<String, ApiError> {
+    let system_id = authorize(id, system_id)?;
+
+    let system = system_id
+        .fetch(&state.db)
+        .await
+        .map_err(|error| internal(&error))?;
+
+    Export::collect(&system, &state.db)
+        .await
+        .and_then(|export| export.to_json())
+        .map_err(|error| internal(&error))
+}
+
+/// Carries out a request that changes something, unless its `Idempotency-Key` header was already used by the system.
+///
+/// A repeated key gets the response stored from the first request, without carrying it out again. Keys are only
+/// stored for requests that succeed, so a failed request can be retried with the same key.
+async fn idempotent(
+    system_id: system::Id<Trusted>,
+    headers: &HeaderMap,
+    endpoint: &str,
+    state: &user::State,
+    request: impl Future<Output = Result<serde_json::Value, ApiError>>,
+) -> Result<serde_json::Value, ApiError> {
+    let Some(key) = headers.get(IDEMPOTENCY_KEY) else {
+        return request.await;
+    };
+
+    let key = key
+        .to_str()
+        .ok()
+        .filter(|key| !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LENGTH)
+        .ok_or(ApiError(
+            StatusCode::BAD_REQUEST,
+            "The idempotency key must be 1 to 255 visible ASCII characters",
+        ))?;
+
+    match IdempotencyKey::claim(system_id, key, endpoint, &state.db)
+        .await
+        .map_err(|error| internal(&error))?
+    {
+        Claim::Claimed => {}
+        Claim::Used {
+            endpoint: used_for, ..
+        } if used_for != endpoint => {
+            return Err(ApiError(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "The idempotency key was already used for a different endpoint",
+            ));
+        }
+        Claim::Used {
+            response: Some(response),
+            ..
+        } => {
+            debug!("Repeating the response for an idempotency key");
+            return serde_json::from_str(&response).map_err(|error| internal(&error));
+        }
+        Claim::Used { response: None, .. } => {
+            return Err(ApiError(
+                StatusCode::CONFLICT,
+                "A request with the same idempotency key is still being handled",
+            ));
+        }
+    }
+
+    match request.await {
+        Ok(response) => {
+            if let Err(error) =
+                IdempotencyKey::complete(system_id, key, &response.to_string(), &state.db).await
+            {
+                // The request itself went through, so it's still reported as a success
+                error!(
+                    ?error,
+                    "Failed to store the response for an idempotency key"
+                );
+            }
+
+            Ok(response)
+        }
+        Err(error) => {
+            if let Err(error) = IdempotencyKey::release(system_id, key, &state.db).await {
+                warn!(?error, "Failed to release an idempotency key");
+            }
+
+            Err(error)
+        }
+    }
+}
+
+/// Middleware checking a request's API token, then rate limiting the system it's for.
+///
+/// Requests without a valid token are turned away before they're counted, so made up tokens can't run up counts of
+/// their own. The token's system is passed on to the handler as an extension, for [`authorize`] to check.
+///
+/// Responses have `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset` (seconds until the count
+/// resets) headers. Requests over the limit get a `429 Too Many Requests` with a `Retry-After` header.
+pub async fn authenticate(
+    State(state): State<user::State>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let system_id = match system_for_token(request.headers(), &state).await {
+        Ok(system_id) => system_id,
+        Err(error) => return error.into_response(),
+    };
+
+    request.extensions_mut().insert(system_id);
+
+    let Some(limiter) = rate_limit::API.as_ref() else {
+        return next.run(request).await;
+    };
+
+    let usage = limiter.hit(system_id.id);
+
+    // Rounded up, so clients waiting this long are always in the next window
+    let reset = usage.reset.as_secs() + u64::from(usage.reset.subsec_nanos() > 0);
+
+    let mut response = if usage.allowed {
+        next.run(request).await
+    } else {
+        debug!(%system_id, "System is over the API rate limit");
+
+        let mut response =
+            ApiError(StatusCode::TOO_MANY_REQUESTS, "Too many requests").into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(reset));
+        response
+    };
+
+    let headers = response.headers_mut();
+    headers.insert("x-ratelimit-limit", HeaderValue::from(limiter.limit()));
+    headers.insert("x-ratelimit-remaining", HeaderValue::from(usage.remaining));
+    headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+
+    response
+}
+
+/// The system a request's API token is for
+async fn system_for_token(
+    headers: &HeaderMap,
+    state: &user::State,
+) -> Result<system::Id<Trusted>, ApiError> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .filter(|token| token.starts_with(TOKEN_PREFIX))
+        .ok_or(ApiError(StatusCode::UNAUTHORIZED, "Missing API token"))?;
+
+    system::Id::fetch_by_api_token(token, &state.db)
+        .await
+        .map_err(|error| internal(&error))?
+        .ok_or(ApiError(StatusCode::UNAUTHORIZED, "Invalid API token"))
+}
+
+/// Checks the system the request's API token is for, from [`authenticate`], is system `id`
+fn authorize(id: i64, system_id: system::Id<Trusted>) -> Result<system::Id<Trusted>, ApiError> {
+    if system_id.id != id {
+        return Err(ApiError(
+            StatusCode::FORBIDDEN,
+            "The API token is for a different system",
+        ));
+    }
+
+    fields!(system_id = %system_id);
+
+    Ok(system_id)
+}
+
+/// Whether a user is in a channel, going through the channel's members page by page
+async fn is_channel_member(
+    client: &SlackHyperClient,
+    channel_id: &SlackChannelId,
+    user_id: &SlackUserId,
+) -> Result<bool, ApiError> {
+    let session = client.open_session(&BOT_TOKEN);
+    let mut cursor = None;
+
+    loop {
+        let response = match session
+            .conversations_members(
+                &SlackApiConversationsMembersRequest::new()
+                    .with_channel(channel_id.clone())
+                    .with_limit(1000)
+                    .opt_cursor(cursor),
+            )
+            .await
+        {
+            Ok(response) => response,
+            Err(SlackClientError::ApiError(error)) => {
+                debug!(code = error.code, "Couldn't list channel members");
+                return Ok(false);
+            }
+            Err(error) => return Err(internal(&error)),
+        };
+
+        if response.members.contains(user_id) {
+            return Ok(true);
+        }
+
+        cursor = response
+            .response_metadata
+            .and_then(|metadata| metadata.next_cursor)
+            .filter(|cursor| !cursor.0.is_empty());
+
+        if cursor.is_none() {
+            return Ok(false);
+        }
+    }
+}
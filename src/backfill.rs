@@ -0,0 +1,281 @@
+//! Backfilling: applying a system's triggers to messages sent before the system was set up.
+//!
+//! A backfill scans a channel's history for the owner's messages that match one of their triggers.
+//! Matching messages are either indexed into the message logs, or proxied like they would have been if sent now.
+//! Backfills run in the background and are slowed down on purpose, so they don't eat into the rate limits live proxying needs.
+
+use std::{
+    collections::HashSet,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use time::OffsetDateTime;
+use tracing::{Instrument, debug, error, info};
+
+use crate::{
+    BOT_TOKEN,
+    events::{ProxyAs, rewrite_content},
+    models::{self, MessageLog},
+    notify::{self, Notification},
+    proxy::{Original, ProxyOptions, ProxyRequest},
+};
+
+/// The most days a backfill can go back
+pub const MAX_DAYS: u16 = 30;
+
+/// How many messages to fetch per page of history
+const PAGE_SIZE: u16 = 100;
+/// How long to wait between pages of history
+const PAGE_DELAY: Duration = Duration::from_secs(3);
+/// How long to wait between proxying messages. Slack allows about one message per second per channel
+const PROXY_DELAY: Duration = Duration::from_millis(1500);
+/// How many pages to scan between progress updates
+const PROGRESS_EVERY: usize = 5;
+
+/// Systems with a backfill running
+static RUNNING: LazyLock<Mutex<HashSet<i64>>> = LazyLock::new(Mutex::default);
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the Slack API
+    SlackApi,
+    /// Error while calling the database
+    Sqlx,
+}
+
+/// What to do with matching messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Record which member sent the message, without changing it
+    Index,
+    /// Repost the message under the member and delete the original
+    Proxy,
+}
+
+/// How a backfill went
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Summary {
+    /// Messages looked at
+    pub scanned: usize,
+    /// Messages that matched a trigger
+    pub matched: usize,
+    /// Matching messages that couldn't be indexed or proxied
+    pub failed: usize,
+}
+
+/// How many backfills are running right now
+pub fn running() -> usize {
+    RUNNING
+        .lock()
+        .expect("Backfill lock to not be poisoned")
+        .len()
+}
+
+/// Starts a backfill in the background.
+///
+/// Returns false if the system already has a backfill running.
+pub fn start(
+    client: std::sync::Arc<SlackHyperClient>,
+    db: SqlitePool,
+    system: models::System,
+    channel_id: SlackChannelId,
+    days: u16,
+    mode: Mode,
+) -> bool {
+    if !RUNNING
+        .lock()
+        .expect("Backfill lock to not be poisoned")
+        .insert(system.id.id)
+    {
+        return false;
+    }
+
+    tokio::spawn(
+        async move {
+            let result = run(&client, &db, &system, &channel_id, days, mode).await;
+
+            RUNNING
+                .lock()
+                .expect("Backfill lock to not be poisoned")
+                .remove(&system.id.id);
+
+            let notification = match result {
+                Ok(summary) => {
+                    info!(?summary, "Backfill finished");
+                    Notification::BackfillFinished {
+                        channel_id,
+                        mode,
+                        summary,
+                    }
+                }
+                Err(error) => {
+                    error!(?error, "Backfill failed");
+                    Notification::BackfillFailed { channel_id }
+                }
+            };
+
+            notify::send(&client, &db, &system.owner_id, notification).await;
+        }
+        .in_current_span(),
+    );
+
+    true
+}
+
+#[tracing::instrument(skip(client, db, system), fields(system_id = %system.id))]
+async fn run(
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+    system: &models::System,
+    channel_id: &SlackChannelId,
+    days: u16,
+    mode: Mode,
+) -> Result<Summary, Error> {
+    let oldest = OffsetDateTime::now_utc() - time::Duration::days(i64::from(days));
+    let oldest = SlackTs(format!("{}.000000", oldest.unix_timestamp()));
+
+    let session = client.open_session(&BOT_TOKEN);
+    let mut summary = Summary::default();
+    let mut cursor = None;
+    let mut pages = 0;
+
+    loop {
+        let history = session
+            .conversations_history(
+                &SlackApiConversationsHistoryRequest::new()
+                    .with_channel(channel_id.clone())
+                    .with_oldest(oldest.clone())
+                    .with_limit(PAGE_SIZE)
+                    .opt_cursor(cursor),
+            )
+            .await
+            .change_context(Error::SlackApi)?;
+
+        for message in history.messages {
+            summary.scanned += 1;
+            backfill_message(client, db, system, channel_id, message, mode, &mut summary).await?;
+        }
+
+        pages += 1;
+        if pages % PROGRESS_EVERY == 0 {
+            notify::send(
+                client,
+                db,
+                &system.owner_id,
+                Notification::BackfillProgress {
+                    channel_id: channel_id.clone(),
+                    summary,
+                },
+            )
+            .await;
+        }
+
+        cursor = history
+            .response_metadata
+            .and_then(|metadata| metadata.next_cursor)
+            .filter(|cursor| !cursor.0.is_empty());
+
+        if cursor.is_none() || !history.has_more.unwrap_or(false) {
+            return Ok(summary);
+        }
+
+        tokio::time::sleep(PAGE_DELAY).await;
+    }
+}
+
+#[tracing::instrument(skip_all, fields(message_id = %message.origin.ts))]
+async fn backfill_message(
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+    system: &models::System,
+    channel_id: &SlackChannelId,
+    message: SlackHistoryMessage,
+    mode: Mode,
+    summary: &mut Summary,
+) -> Result<(), Error> {
+    // Only plain messages the owner sent themselves
+    if message.subtype.is_some()
+        || message.sender.bot_id.is_some()
+        || message
+            .sender
+            .user
+            .as_ref()
+            .is_none_or(|user| system.owner_id != *user)
+    {
+        return Ok(());
+    }
+
+    let Some(text) = message.content.text.as_deref() else {
+        return Ok(());
+    };
+
+    let Some(member) = system
+        .find_member_by_trigger_rules(db, text)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        return Ok(());
+    };
+
+    if MessageLog::fetch_by_message_id(&message.origin.ts, db)
+        .await
+        .change_context(Error::Sqlx)?
+        .is_some()
+    {
+        debug!("Message already logged");
+        return Ok(());
+    }
+
+    summary.matched += 1;
+
+    let result = match mode {
+        // The user's own message is indexed, which the bot can't delete, so its channel isn't recorded
+        Mode::Index => MessageLog::insert(
+            Some(member.id),
+            system.id,
+            &message.origin.ts,
+            None,
+            None,
+            None,
+            db,
+        )
+        .await
+        .map(|_| ())
+        .change_context(Error::Sqlx),
+        Mode::Proxy => {
+            let proxy_as = ProxyAs::Member(member);
+            let mut content = message.content;
+            rewrite_content(&mut content, &proxy_as);
+
+            let result = ProxyRequest {
+                channel_id: channel_id.clone(),
+                files: content.files.take().unwrap_or_default(),
+                content,
+                identity: proxy_as.into(),
+                thread_ts: message.origin.thread_ts,
+                options: ProxyOptions {
+                    replaces: Some(Original::User(message.origin.ts)),
+                    ..ProxyOptions::default()
+                },
+            }
+            .send(client, system, db)
+            .await
+            .map(|_| ())
+            .change_context(Error::SlackApi);
+
+            tokio::time::sleep(PROXY_DELAY).await;
+            result
+        }
+    };
+
+    if let Err(error) = result {
+        debug!(?error, "Failed to backfill message");
+        summary.failed += 1;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,238 @@
+//! A terminal client for Plura's API, and an example of using it.
+//!
+//! Get a token with `/system api-token` in Slack, then set `PLURA_URL`, `PLURA_SYSTEM` and `PLURA_TOKEN`
+//! (or pass `--url`, `--system` and `--token`).
+#![warn(clippy::pedantic, clippy::nursery, missing_docs, clippy::cargo)]
+#![allow(clippy::multiple_crate_versions)]
+
+use std::{path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use error_stack::{Result, ResultExt, report};
+use oauth2::reqwest;
+use serde_json::{Value, json};
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+enum Error {
+    /// Error sending the request
+    Request,
+    /// The API returned an error
+    Api,
+    /// Error reading the API's response
+    Response,
+    /// Error writing the output
+    Output,
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// The bot's base URL, e.g. https://plura.wobbl.in
+    #[arg(long, env = "PLURA_URL")]
+    url: String,
+    /// Your system's ID, shown when making a token
+    #[arg(long, env = "PLURA_SYSTEM")]
+    system: i64,
+    /// Your API token, from /system api-token
+    #[arg(long, env = "PLURA_TOKEN", hide_env_values = true)]
+    token: String,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Lists your members
+    Members {
+        /// Print the API's JSON instead of a list
+        #[arg(long)]
+        json: bool,
+    },
+    /// Switches who's fronting. Leave out members to switch to your base account
+    Switch {
+        /// The IDs or aliases of the members to switch to, primary fronter first
+        members: Vec<String>,
+    },
+    /// Sends a message as a member
+    Send {
+        /// The ID of the channel to send the message in
+        #[arg(long)]
+        channel: String,
+        /// The ID or alias of the member to send the message as
+        #[arg(long)]
+        member: String,
+        /// The message
+        text: String,
+    },
+    /// Exports your system as JSON, like /system export
+    Export {
+        /// Where to write the export. Prints it if left out
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Talks to one system's API
+struct Client {
+    http: reqwest::Client,
+    base: String,
+    token: String,
+}
+
+impl Client {
+    fn new(cli: &Cli) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base: format!(
+                "{}/api/v1/systems/{}",
+                cli.url.trim_end_matches('/'),
+                cli.system
+            ),
+            token: cli.token.clone(),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<String, Error> {
+        let request = self.http.get(format!("{}/{path}", self.base));
+        self.send(request).await
+    }
+
+    async fn post(&self, path: &str, body: &Value) -> Result<Value, Error> {
+        let request = self
+            .http
+            .post(format!("{}/{path}", self.base))
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+
+        let response = self.send(request).await?;
+        serde_json::from_str(&response).change_context(Error::Response)
+    }
+
+    /// Sends a request with the token, turning error responses into [`Error::Api`] with the API's message
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<String, Error> {
+        let response = request
+            .bearer_auth(&self.token)
+            .send()
+            .await
+            .change_context(Error::Request)?;
+
+        let status = response.status();
+        let body = response.text().await.change_context(Error::Response)?;
+
+        if !status.is_success() {
+            let message = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|body| body["error"].as_str().map(str::to_owned))
+                .unwrap_or(body);
+
+            return Err(report!(Error::Api).attach_printable(format!("{status}: {message}")));
+        }
+
+        Ok(body)
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let client = Client::new(&cli);
+
+    match run(cli.command, &client).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(command: Command, client: &Client) -> Result<(), Error> {
+    match command {
+        Command::Members { json } => {
+            let body = client.get("members").await?;
+
+            if json {
+                println!("{body}");
+                return Ok(());
+            }
+
+            let members: Vec<Value> =
+                serde_json::from_str(&body).change_context(Error::Response)?;
+
+            for member in members {
+                print_member(&member);
+            }
+        }
+        Command::Switch { members } => {
+            let response = client
+                .post("switch", &json!({ "members": members }))
+                .await?;
+
+            match response["fronters"].as_array().map(Vec::as_slice) {
+                None | Some([]) => println!("Switched to your base account"),
+                Some(fronters) => println!(
+                    "Switched to {}",
+                    fronters
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+        }
+        Command::Send {
+            channel,
+            member,
+            text,
+        } => {
+            let response = client
+                .post(
+                    "proxy",
+                    &json!({ "channel": channel, "member": member, "text": text }),
+                )
+                .await?;
+
+            println!("Sent message {}", response["ts"].as_str().unwrap_or("?"));
+        }
+        Command::Export { output } => {
+            let export = client.get("export").await?;
+
+            match output {
+                Some(path) => std::fs::write(&path, export).change_context(Error::Output)?,
+                None => println!("{export}"),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints a member from `GET members` on one line, e.g. `3  Alice (Alice Smith) [al, ali]`
+fn print_member(member: &Value) {
+    let mut line = format!(
+        "{}  {}",
+        member["id"],
+        member["display_name"].as_str().unwrap_or_default()
+    );
+
+    if let Some(full_name) = member["full_name"].as_str() {
+        line.push_str(&format!(" ({full_name})"));
+    }
+
+    if let Some(aliases) = member["aliases"].as_array()
+        && !aliases.is_empty()
+    {
+        let aliases = aliases
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        line.push_str(&format!(" [{aliases}]"));
+    }
+
+    if member["enabled"] == false {
+        line.push_str(" (disabled)");
+    }
+
+    println!("{line}");
+}
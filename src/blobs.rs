@@ -0,0 +1,30 @@
+//! Serves files the bot hosts itself, like name recordings.
+
+use axum::{
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use tracing::error;
+
+use crate::models::{Blob, user};
+
+#[tracing::instrument(skip_all)]
+pub async fn blob_handler(Path(token): Path<String>, State(state): State<user::State>) -> Response {
+    match Blob::fetch(&token, &state.db).await {
+        Ok(Some(blob)) => (
+            [
+                (header::CONTENT_TYPE, blob.content_type),
+                // Uploads come from users, so browsers shouldn't guess a more dangerous type than the one stored
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            ],
+            blob.data,
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            error!(?error, "Error fetching blob");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Blocks slack-morphism can't represent yet, and a message request that can carry them.
+
+use serde::Serialize;
+use slack_morphism::prelude::*;
+
+/// A file that has been uploaded to Slack
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SlackFileReference {
+    pub id: SlackFileId,
+}
+
+/// An image block showing a file uploaded to Slack.
+///
+/// slack-morphism's [`SlackImageBlock`] only supports images by URL.
+/// See <https://github.com/abdolence/slack-morphism-rust/issues/320>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename = "image")]
+pub struct SlackFileImageBlock {
+    pub slack_file: SlackFileReference,
+    pub alt_text: String,
+}
+
+impl SlackFileImageBlock {
+    pub const fn new(file_id: SlackFileId) -> Self {
+        Self {
+            slack_file: SlackFileReference { id: file_id },
+            alt_text: String::new(),
+        }
+    }
+}
+
+/// Any block that can be sent in a message
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Block {
+    Slack(SlackBlock),
+    FileImage(SlackFileImageBlock),
+}
+
+impl From<SlackBlock> for Block {
+    fn from(block: SlackBlock) -> Self {
+        Self::Slack(block)
+    }
+}
+
+impl From<SlackFileImageBlock> for Block {
+    fn from(block: SlackFileImageBlock) -> Self {
+        Self::FileImage(block)
+    }
+}
+
+/// A `chat.postMessage` request whose blocks can include ones slack-morphism doesn't support
+#[derive(Debug, Serialize)]
+pub struct PostMessageRequest {
+    #[serde(flatten)]
+    request: SlackApiChatPostMessageRequest,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    blocks: Vec<Block>,
+}
+
+impl PostMessageRequest {
+    /// Wraps a request, sending `extra_blocks` after the blocks already in its content
+    pub fn new(mut request: SlackApiChatPostMessageRequest, extra_blocks: Vec<Block>) -> Self {
+        let blocks = request
+            .content
+            .blocks
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(Block::from)
+            .chain(extra_blocks)
+            .collect();
+
+        Self { request, blocks }
+    }
+}
+
+/// A `chat.update` request whose blocks can include ones slack-morphism doesn't support
+#[derive(Debug, Serialize)]
+pub struct UpdateMessageRequest {
+    #[serde(flatten)]
+    request: SlackApiChatUpdateRequest,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    blocks: Vec<Block>,
+}
+
+impl UpdateMessageRequest {
+    /// Wraps a request, sending `extra_blocks` after the blocks already in its content
+    pub fn new(mut request: SlackApiChatUpdateRequest, extra_blocks: Vec<Block>) -> Self {
+        let blocks = request
+            .content
+            .blocks
+            .take()
+            .unwrap_or_default()
+            .into_iter()
+            .map(Block::from)
+            .chain(extra_blocks)
+            .collect();
+
+        Self { request, blocks }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn image() -> SlackFileImageBlock {
+        SlackFileImageBlock::new(SlackFileId::new("F0123456".into()))
+    }
+
+    #[test]
+    fn file_image_block() {
+        let expected = json!({
+            "type": "image",
+            "slack_file": { "id": "F0123456" },
+            "alt_text": "",
+        });
+
+        assert_eq!(serde_json::to_value(image()).unwrap(), expected);
+        assert_eq!(
+            serde_json::to_value(Block::from(image())).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn post_message_blocks() {
+        let request = SlackApiChatPostMessageRequest::new(
+            SlackChannelId::new("C0123456".into()),
+            SlackMessageContent::new()
+                .with_text("hi".into())
+                .with_blocks(vec![SlackSectionBlock::new().with_text(md!("hi")).into()]),
+        );
+
+        let json =
+            serde_json::to_string(&PostMessageRequest::new(request, vec![image().into()])).unwrap();
+        assert_eq!(json.matches("\"blocks\"").count(), 1);
+
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(value["channel"], "C0123456");
+        assert_eq!(value["text"], "hi");
+        assert_eq!(value["blocks"][0]["type"], "section");
+        assert_eq!(value["blocks"][1]["type"], "image");
+        assert_eq!(value["blocks"][1]["slack_file"]["id"], "F0123456");
+    }
+
+    #[test]
+    fn no_blocks() {
+        let request = SlackApiChatUpdateRequest::new(
+            SlackChannelId::new("C0123456".into()),
+            SlackMessageContent::new().with_text("hi".into()),
+            SlackTs::new("1700000000.000100".into()),
+        );
+
+        let value = serde_json::to_value(UpdateMessageRequest::new(request, Vec::new())).unwrap();
+        assert!(value.get("blocks").is_none());
+        assert_eq!(value["ts"], "1700000000.000100");
+    }
+}
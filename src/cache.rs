@@ -0,0 +1,115 @@
+//! Short-lived caches of rendered output that takes a lot of queries to build: member lists and home tabs.
+//!
+//! Entries belong to a system and expire after [`TTL`]. Anything that changes what they show calls [`invalidate`] or
+//! [`invalidate_member`], so the TTL only matters for a change that lands while an entry is being rendered.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use slack_morphism::prelude::*;
+use tracing::trace;
+
+use crate::models::{member, system, trust::Trusted, viewer::Viewer};
+
+/// How long a render is kept for
+const TTL: Duration = Duration::from_secs(60);
+
+/// `/members list` output, per viewer: the blocks of each member and the JSON for `--format json`
+pub static MEMBER_LISTS: Cache<Viewer, (Vec<Vec<SlackBlock>>, serde_json::Value)> = Cache::new();
+/// Home tabs. Only the owner sees their home tab, so there's one per system
+pub static HOME_TABS: Cache<(), SlackView> = Cache::new();
+
+struct Entry<V> {
+    rendered: V,
+    /// The members shown, so changing one of them invalidates the entry
+    member_ids: Vec<i64>,
+    rendered_at: Instant,
+}
+
+impl<V> Entry<V> {
+    fn expired(&self) -> bool {
+        self.rendered_at.elapsed() > TTL
+    }
+}
+
+/// Renders keyed by system and `K`
+pub struct Cache<K, V> {
+    entries: LazyLock<Mutex<HashMap<(i64, K), Entry<V>>>>,
+}
+
+impl<K, V> Cache<K, V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    const fn new() -> Self {
+        Self {
+            entries: LazyLock::new(Mutex::default),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<(i64, K), Entry<V>>> {
+        self.entries
+            .lock()
+            .expect("Render cache lock to not be poisoned")
+    }
+
+    /// The cached render for `system_id`, if it hasn't expired
+    pub fn get(&self, system_id: system::Id<Trusted>, key: K) -> Option<V> {
+        let mut entries = self.lock();
+        let key = (system_id.id, key);
+
+        if entries.get(&key).is_some_and(Entry::expired) {
+            entries.remove(&key);
+        }
+
+        let rendered = entries.get(&key).map(|entry| entry.rendered.clone());
+        trace!(hit = rendered.is_some(), "Checked render cache");
+        rendered
+    }
+
+    /// Caches a render for `system_id` that shows `member_ids`
+    pub fn insert(
+        &self,
+        system_id: system::Id<Trusted>,
+        key: K,
+        member_ids: impl IntoIterator<Item = member::Id<Trusted>>,
+        rendered: V,
+    ) {
+        let mut entries = self.lock();
+
+        entries.retain(|_, entry| !entry.expired());
+        entries.insert(
+            (system_id.id, key),
+            Entry {
+                rendered,
+                member_ids: member_ids
+                    .into_iter()
+                    .map(|member_id| member_id.id)
+                    .collect(),
+                rendered_at: Instant::now(),
+            },
+        );
+    }
+
+    fn retain(&self, keep: impl Fn(i64, &Entry<V>) -> bool) {
+        self.lock()
+            .retain(|(system_id, _), entry| keep(*system_id, entry));
+    }
+}
+
+/// Drops everything cached for a system
+pub fn invalidate(system_id: system::Id<Trusted>) {
+    MEMBER_LISTS.retain(|cached, _| cached != system_id.id);
+    HOME_TABS.retain(|cached, _| cached != system_id.id);
+}
+
+/// Drops everything cached that shows a member
+pub fn invalidate_member(member_id: member::Id<Trusted>) {
+    MEMBER_LISTS.retain(|_, entry| !entry.member_ids.contains(&member_id.id));
+    HOME_TABS.retain(|_, entry| !entry.member_ids.contains(&member_id.id));
+}
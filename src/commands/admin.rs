@@ -0,0 +1,337 @@
+use std::sync::Arc;
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::{debug, info, warn};
+
+use super::{debug::Debug, selftest};
+use crate::{
+    env, metrics,
+    models::{AuditLog, CommandUsage, System, Trigger, user, workspace::Branding},
+    render,
+};
+
+/// How many audit log entries are shown when inspecting a system
+const INSPECT_AUDIT_LOGS: u16 = 20;
+/// How many days of command usage are shown in stats
+const USAGE_DAYS: u16 = 30;
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Commands for the people running the bot.
+///
+/// Only users listed in the OPERATORS environment variable can use these.
+/// Users listed in MODERATORS can use /plura-admin inspect, for systems in their own workspace.
+pub enum Admin {
+    /// Shows how proxy attempts have turned out and which errors Slack returned since the bot started, and which
+    /// commands were run over the last 30 days
+    Stats,
+    /// Shows a user's system: its members, triggers and recent audit log. Nothing can be changed from here.
+    ///
+    /// Moderators can only inspect systems authorized from the workspace they're in.
+    Inspect {
+        /// The user whose system to inspect
+        user: String,
+    },
+    /// Posts, edits and deletes a message in the SELFTEST_CHANNEL, opens a modal, and shows how long each took
+    Selftest,
+    /// Changes the name, icon and footer of the bot's own messages, like notifications and channel announcements.
+    ///
+    /// Only the options given are changed, and an empty value goes back to the default. Shows the current branding
+    /// if nothing is given.
+    Branding {
+        /// The name to post under
+        #[arg(long)]
+        name: Option<String>,
+        /// A URL to the picture to post with
+        #[arg(long)]
+        icon: Option<String>,
+        /// Text shown under each message
+        #[arg(long)]
+        footer: Option<String>,
+    },
+    #[clap(subcommand)]
+    Debug(Debug),
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error running a debug command
+    Debug,
+    /// Error while calling the database
+    Sqlx,
+}
+
+impl Admin {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        client: Arc<SlackHyperClient>,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        if !self.allowed(&event.user_id) {
+            warn!(user_id = %event.user_id, "Non-operator tried to use an admin command");
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("You aren't an operator of this bot.".into()),
+            ));
+        }
+
+        match self {
+            Self::Stats => Self::stats(&state).await,
+            Self::Inspect { user } => Self::inspect(event, &client, &state, &user).await,
+            Self::Selftest => Ok(Self::selftest(event, &client).await),
+            Self::Branding { name, icon, footer } => {
+                Self::branding(&state, name, icon, footer).await
+            }
+            Self::Debug(debug) => debug
+                .run(event, state)
+                .await
+                .change_context(CommandError::Debug),
+        }
+    }
+
+    /// Whether the user can run this command. Moderators can only inspect systems; operators can run anything
+    fn allowed(&self, user_id: &SlackUserId) -> bool {
+        match self {
+            Self::Inspect { .. } => is_operator(user_id) || is_moderator(user_id),
+            Self::Stats | Self::Selftest | Self::Branding { .. } | Self::Debug(_) => {
+                is_operator(user_id)
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(event, client, state))]
+    async fn inspect(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+        user: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        let Some(user_id) = user::parse_and_trust(user, client).await else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Invalid user".into()),
+            ));
+        };
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        let Some(system) = System::fetch_by_user_id(&user_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+        else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("That user doesn't have a system.".into()),
+            ));
+        };
+
+        if !is_operator(&event.user_id) {
+            let team_id = system
+                .id
+                .team_id(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            if team_id.is_some_and(|team_id| team_id != event.team_id) {
+                warn!(moderator_id = %event.user_id, system_id = %system.id, "Moderator tried to inspect a system in another workspace");
+                return Ok(SlackCommandEventResponse::new(
+                    SlackMessageContent::new()
+                        .with_text("That user's system is in a different workspace.".into()),
+                ));
+            }
+        }
+
+        // Inspecting someone else's system is logged, so moderators can be held to account for it
+        info!(moderator_id = %event.user_id, system_id = %system.id, "Moderator inspected a system");
+
+        let members = system
+            .members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let triggers = Trigger::fetch_by_system_id(system.id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let audit_logs = AuditLog::fetch_recent(system.id, INSPECT_AUDIT_LOGS, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(render::inspection(
+                &system,
+                &members,
+                &triggers,
+                &audit_logs,
+            )),
+        ))
+    }
+
+    async fn selftest(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+    ) -> SlackCommandEventResponse {
+        let Some(channel_id) = env::selftest_channel() else {
+            return SlackCommandEventResponse::new(SlackMessageContent::new().with_text(
+                "Set SELFTEST_CHANNEL to a channel the bot is in to run the self-test.".into(),
+            ));
+        };
+
+        let steps = selftest::run(client, channel_id.into(), event.trigger_id).await;
+
+        SlackCommandEventResponse::new(selftest::render(&steps))
+    }
+
+    #[tracing::instrument(skip(state))]
+    async fn branding(
+        state: &SlackClientEventsUserState,
+        name: Option<String>,
+        icon: Option<String>,
+        footer: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        let mut branding = Branding::fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let changed = name.is_some() || icon.is_some() || footer.is_some();
+
+        if let Some(icon) = &icon
+            && !icon.is_empty()
+            && url::Url::parse(icon).is_err()
+        {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("The icon has to be a URL.".into()),
+            ));
+        }
+
+        // An empty value clears the setting
+        let set = |value: Option<String>, current: &mut Option<String>| {
+            if let Some(value) = value {
+                *current = Some(value).filter(|value| !value.trim().is_empty());
+            }
+        };
+
+        set(name, &mut branding.display_name);
+        set(icon, &mut branding.icon_url);
+        set(footer, &mut branding.footer);
+
+        if changed {
+            branding
+                .save(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            info!(?branding, "Updated workspace branding");
+        }
+
+        let describe = |value: Option<&str>| {
+            value.map_or_else(|| "_default_".to_string(), |value| format!("`{value}`"))
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(slack_blocks![some_into(
+                SlackSectionBlock::new().with_text(md!(
+                    "*{}*\nName: {}\nIcon: {}\nFooter: {}",
+                    if changed {
+                        "Branding updated"
+                    } else {
+                        "Current branding"
+                    },
+                    describe(branding.display_name.as_deref()),
+                    describe(branding.icon_url.as_deref()),
+                    describe(branding.footer.as_deref())
+                ))
+            )]),
+        ))
+    }
+
+    async fn stats(
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Showing proxy stats");
+        let attempts = metrics::proxy_attempts();
+        let total: u64 = attempts.iter().map(|(_, count)| count).sum();
+
+        let breakdown = attempts
+            .iter()
+            .map(|(outcome, count)| format!("- `{}`: {count}", outcome.label()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let partial_failures = metrics::partial_failures()
+            .iter()
+            .map(|(cleanup, count)| format!("- `{}`: {count}", cleanup.label()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let slack_errors = metrics::slack_api_errors();
+        let slack_errors = if slack_errors.is_empty() {
+            "None".to_string()
+        } else {
+            slack_errors
+                .iter()
+                .map(|(code, count)| format!("- `{code}`: {count}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let usage = {
+            let states = state.read().await;
+            let user_state = states.get_user_state::<user::State>().unwrap();
+
+            CommandUsage::totals(USAGE_DAYS, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?
+        };
+
+        let usage = if usage.is_empty() {
+            "None".to_string()
+        } else {
+            usage
+                .iter()
+                .map(|usage| format!("- `{}`: {}", usage.command, usage.count))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(slack_blocks![
+                some_into(SlackSectionBlock::new().with_text(md!(
+                    "*Proxy attempts since startup:* {}\n{}",
+                    total,
+                    breakdown
+                ))),
+                some_into(
+                    SlackSectionBlock::new()
+                        .with_text(md!("*Failed after posting:*\n{}", partial_failures))
+                ),
+                some_into(
+                    SlackSectionBlock::new()
+                        .with_text(md!("*Slack API errors since startup:*\n{}", slack_errors))
+                ),
+                some_into(SlackSectionBlock::new().with_text(md!(
+                    "*Commands run in the last {} days:*\n{}",
+                    USAGE_DAYS,
+                    usage
+                )))
+            ]),
+        ))
+    }
+}
+
+/// Whether the user is listed as an operator
+fn is_operator(user_id: &SlackUserId) -> bool {
+    env::operators().is_some_and(|operators| is_listed(&operators, user_id))
+}
+
+/// Whether the user is listed as a moderator
+fn is_moderator(user_id: &SlackUserId) -> bool {
+    env::moderators().is_some_and(|moderators| is_listed(&moderators, user_id))
+}
+
+/// Whether the user is in a comma-separated list of user IDs
+fn is_listed(list: &str, user_id: &SlackUserId) -> bool {
+    list.split(',').any(|listed| listed.trim() == user_id.0)
+}
@@ -1,10 +1,12 @@
-use error_stack::{Result, ResultExt};
+use error_stack::{Report, Result, ResultExt};
 use slack_morphism::prelude::*;
 use tracing::debug;
 
+use super::Format;
 use crate::{
-    fetch_member, fetch_system,
+    cache, fetch_member, fetch_system,
     models::{self, alias, member::MemberRef, trust::Untrusted, user},
+    render,
 };
 
 #[derive(clap::Subcommand, Debug)]
@@ -12,6 +14,7 @@ use crate::{
 /// An alias is a unique identifier for a member within a system.
 ///
 /// You can use aliases to refer to members without knowing their member ID in other commands.
+/// A member's command name (see /members command-name) is one of their aliases.
 ///
 /// Also see:
 /// - /members for managing members and their profiles.
@@ -32,6 +35,12 @@ pub enum Alias {
     List {
         /// If specified, lists the aliases for the given member.
         member: Option<MemberRef>,
+        /// How to show the output: blocks, text or json
+        #[clap(long, value_enum, default_value_t)]
+        format: Format,
+        /// Which page to show, for long lists
+        #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        page: u16,
     },
     /// Edit an alias
     Edit {
@@ -59,7 +68,11 @@ impl Alias {
         match self {
             Self::Add { member, alias } => Self::create_alias(event, &state, member, alias).await,
             Self::Delete { alias } => Self::delete_alias(event, &state, alias).await,
-            Self::List { member } => Self::list_aliases(event, &state, member).await,
+            Self::List {
+                member,
+                format,
+                page,
+            } => Self::list_aliases(event, &state, member, format, page).await,
             Self::Edit { alias, new_alias } => {
                 Self::edit_alias(event, &state, alias, new_alias).await
             }
@@ -90,9 +103,10 @@ impl Alias {
             ));
         }
 
-        models::Alias::insert(member_id, system_id, alias, &user_state.db)
-            .await
-            .change_context(CommandError::Sqlx)?;
+        if let Err(error) = models::Alias::insert(member_id, system_id, alias, &user_state.db).await
+        {
+            return taken_response(error);
+        }
 
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text("Alias created successfully.".to_string()),
@@ -126,6 +140,9 @@ impl Alias {
             .await
             .change_context(CommandError::Sqlx)?;
 
+        // Aliases don't know their system, so the cache is cleared here instead of by the model
+        cache::invalidate(system_id);
+
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text("Alias deleted successfully.".to_string()),
         ))
@@ -136,6 +153,8 @@ impl Alias {
         event: SlackCommandEvent,
         state: &SlackClientEventsUserState,
         member: Option<MemberRef>,
+        format: Format,
+        page: u16,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         debug!("Listing aliases");
         let states = state.read().await;
@@ -143,20 +162,24 @@ impl Alias {
 
         fetch_system!(event, user_state => system_id);
 
-        let aliases = if let Some(member) = member {
+        let (aliases, command) = if let Some(member) = member {
             debug!("Fetching aliases by member");
             fetch_member!(member, user_state, system_id => member_id);
 
-            models::Alias::fetch_by_member_id(member_id, &user_state.db)
+            let aliases = models::Alias::fetch_by_member_id(member_id, &user_state.db)
                 .await
-                .change_context(CommandError::Sqlx)?
+                .change_context(CommandError::Sqlx)?;
+
+            (aliases, format!("aliases list {member_id}"))
         } else {
-            models::Alias::fetch_by_system_id(system_id, &user_state.db)
+            let aliases = models::Alias::fetch_by_system_id(system_id, &user_state.db)
                 .await
-                .change_context(CommandError::Sqlx)?
+                .change_context(CommandError::Sqlx)?;
+
+            (aliases, "aliases list".to_string())
         };
 
-        if aliases.is_empty() {
+        if aliases.is_empty() && format != Format::Json {
             debug!("No aliases found");
             return Ok(SlackCommandEventResponse::new(
                 SlackMessageContent::new().with_text("No aliases found.".into()),
@@ -165,6 +188,7 @@ impl Alias {
 
         debug!(len = aliases.len(), "Found aliases");
 
+        let json = render::json::aliases(&aliases);
         let alias_blocks = aliases
             .into_iter()
             .map(|alias| {
@@ -173,16 +197,20 @@ impl Alias {
                     md!("Alias: {}", alias.alias),
                 ];
 
+                let title = if alias.is_primary {
+                    format!("*Alias {}* (command name)", alias.id)
+                } else {
+                    format!("*Alias {}*", alias.id)
+                };
+
                 SlackSectionBlock::new()
-                    .with_text(md!("*Alias {}*", alias.id))
+                    .with_text(md!(title))
                     .with_fields(fields)
             })
-            .map(Into::into)
+            .map(|section| vec![section.into()])
             .collect();
 
-        Ok(SlackCommandEventResponse::new(
-            SlackMessageContent::new().with_blocks(alias_blocks),
-        ))
+        Ok(format.respond_paged(alias_blocks, &json, page, &command))
     }
 
     #[tracing::instrument(skip(event, state), fields(system_id))]
@@ -208,13 +236,27 @@ impl Alias {
             ));
         };
 
-        alias
-            .change_alias(new_alias, &user_state.db)
+        if let Err(error) = alias
+            .change_alias(system_id, new_alias, &user_state.db)
             .await
-            .change_context(CommandError::Sqlx)?;
+        {
+            return taken_response(error);
+        }
+
+        cache::invalidate(system_id);
 
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text("Alias updated successfully.".to_string()),
         ))
     }
 }
+
+/// Tells the user who already has an alias, or fails the command for any other error
+fn taken_response(error: Report<alias::Error>) -> Result<SlackCommandEventResponse, CommandError> {
+    match error.current_context() {
+        alias::Error::Taken(_) => Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!("{}.", error.current_context())),
+        )),
+        alias::Error::Sqlx => Err(error.change_context(CommandError::Sqlx)),
+    }
+}
@@ -0,0 +1,112 @@
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::{debug, info};
+
+use crate::{
+    fetch_member, fetch_system,
+    models::{member::MemberRef, system::AutoproxyMode, user},
+    render::MemberReference,
+};
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Choose who messages without a trigger are sent as.
+///
+/// Triggers and the anonymous prefix always take priority over autoproxy.
+pub enum Autoproxy {
+    /// Don't proxy messages without a trigger
+    Off,
+    /// Proxy messages as the fronting member (see /members switch). This is the default
+    Front,
+    /// Proxy messages as whichever member's trigger you used last
+    Latch,
+    /// Always proxy messages as one member
+    Member {
+        /// The member to proxy as
+        member: MemberRef,
+    },
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the database
+    Sqlx,
+}
+
+impl Autoproxy {
+    #[tracing::instrument(skip_all, fields(system_id))]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing autoproxy mode");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let (mode, member_id, response) = match self {
+            Self::Off => (
+                AutoproxyMode::Off,
+                None,
+                "Autoproxy is off. Only messages with a trigger will be proxied.".to_string(),
+            ),
+            Self::Front => (
+                AutoproxyMode::Front,
+                None,
+                "Messages without a trigger will be proxied as your fronting member.".to_string(),
+            ),
+            Self::Latch => (
+                AutoproxyMode::Latch,
+                None,
+                "Messages without a trigger will be proxied as the last member whose trigger you used."
+                    .to_string(),
+            ),
+            Self::Member { member } => {
+                fetch_member!(member, user_state, system_id => member_id);
+
+                let member = member_id
+                    .fetch(&user_state.db)
+                    .await
+                    .change_context(CommandError::Sqlx)?;
+
+                if !member.enabled {
+                    return Ok(SlackCommandEventResponse::new(
+                        SlackMessageContent::new().with_text(format!(
+                            "That member is disabled. You can use `/members enable {member_id}` to enable them."
+                        )),
+                    ));
+                }
+
+                if !member.proxy_enabled {
+                    return Ok(SlackCommandEventResponse::new(
+                        SlackMessageContent::new().with_text(format!(
+                            "Messages can't be proxied as that member. You can use `/members proxy {member_id} on` to turn proxying on."
+                        )),
+                    ));
+                }
+
+                (
+                    AutoproxyMode::Member,
+                    Some(member_id),
+                    format!(
+                        "Messages without a trigger will be proxied as {}.",
+                        MemberReference::new(&member).mrkdwn()
+                    ),
+                )
+            }
+        };
+
+        system_id
+            .set_autoproxy(mode, member_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(%mode, "Updated autoproxy mode");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+}
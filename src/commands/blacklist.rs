@@ -0,0 +1,151 @@
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::debug;
+
+use super::parse_channel;
+use crate::{fetch_system, models::user};
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Channels where your messages are never proxied, even with a trigger or autoproxy.
+///
+/// Messages you send in a blacklisted channel are left exactly as you sent them.
+pub enum Blacklist {
+    /// Stops proxying your messages in a channel
+    Add {
+        /// The channel to blacklist (e.g. #general)
+        channel: String,
+    },
+    /// Starts proxying your messages in a channel again
+    Remove {
+        /// The channel to remove from the blacklist (e.g. #general)
+        channel: String,
+    },
+    /// Lists the channels your messages aren't proxied in
+    List,
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the database
+    Sqlx,
+}
+
+impl Blacklist {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        match self {
+            Self::Add { channel } => Self::add(event, &state, &channel).await,
+            Self::Remove { channel } => Self::remove(event, &state, &channel).await,
+            Self::List => Self::list(event, &state).await,
+        }
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn add(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        channel: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Blacklisting channel");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let Some(channel_id) = parse_channel(channel) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Invalid channel".into()),
+            ));
+        };
+
+        system_id
+            .blacklist_channel(&channel_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Your messages in {} won't be proxied anymore.",
+                channel_id.to_slack_format()
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn remove(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        channel: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Removing channel from blacklist");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let Some(channel_id) = parse_channel(channel) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Invalid channel".into()),
+            ));
+        };
+
+        let result = system_id
+            .unblacklist_channel(&channel_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if result.rows_affected() == 0 {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("That channel isn't blacklisted.".into()),
+            ));
+        }
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Your messages in {} will be proxied again.",
+                channel_id.to_slack_format()
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn list(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Listing blacklisted channels");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let channels = system_id
+            .blacklisted_channels(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if channels.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("Your messages are proxied in every channel.".into()),
+            ));
+        }
+
+        let channels = channels
+            .iter()
+            .map(|channel| format!("- {}", channel.to_slack_format()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(slack_blocks![some_into(
+                SlackSectionBlock::new().with_text(md!("*Blacklisted channels*\n{}", channels))
+            )]),
+        ))
+    }
+}
@@ -0,0 +1,64 @@
+//! Treating identical commands a user runs at the same time as one.
+//!
+//! Double tapping send on mobile can run a command twice, which would open two member creation forms or add a
+//! trigger twice. A command is ignored while the same user is running it, and for [`WINDOW`] after they started it.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use slack_morphism::prelude::*;
+
+/// How long after a command starts that running it again is treated as a duplicate
+const WINDOW: Duration = Duration::from_secs(1);
+
+type Key = (SlackUserId, String);
+
+struct Recent {
+    started: Instant,
+    running: bool,
+}
+
+/// Commands users ran recently. Entries are cleared out once they finish and [`WINDOW`] has passed
+static RECENT: LazyLock<Mutex<HashMap<Key, Recent>>> = LazyLock::new(Mutex::default);
+
+/// A command that's running. Dropping it marks the command as finished
+pub struct Running(Key);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if let Some(recent) = RECENT
+            .lock()
+            .expect("Recent commands lock to not be poisoned")
+            .get_mut(&self.0)
+        {
+            recent.running = false;
+        }
+    }
+}
+
+/// Starts running `command` for `user_id`, or returns [`None`] if it's a duplicate of one they just ran
+pub fn start(user_id: &SlackUserId, command: &str) -> Option<Running> {
+    let mut recent = RECENT
+        .lock()
+        .expect("Recent commands lock to not be poisoned");
+
+    recent.retain(|_, recent| recent.running || recent.started.elapsed() < WINDOW);
+
+    let key = (user_id.clone(), command.to_string());
+    if recent.contains_key(&key) {
+        return None;
+    }
+
+    recent.insert(
+        key.clone(),
+        Recent {
+            started: Instant::now(),
+            running: true,
+        },
+    );
+
+    Some(Running(key))
+}
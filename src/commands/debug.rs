@@ -0,0 +1,279 @@
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::debug;
+
+use crate::{
+    backfill,
+    events::{self, capture, replay},
+    models::{self, captured_event, trust::Untrusted, user},
+    notify,
+};
+
+/// How many stored events are listed at once
+const STORED_EVENTS_SHOWN: u16 = 20;
+/// Replays are against the system as it is now, so settings changed since the event can change the outcome
+const NOW_NOTE: &str = "_Blacklisting, quiet hours and matching reflect the system as it is now._";
+/// Stored events are redacted, so some triggers can't be matched against them
+const REDACTED_NOTE: &str =
+    "_Stored events are redacted. Regex triggers matching the middle of a message won't match._";
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Commands for looking into how the bot is handling messages.
+///
+/// None of these proxy, switch, or latch anything.
+pub enum Debug {
+    /// Shows what the bot would do with a recently received message event, without doing it.
+    ///
+    /// Events are only kept if DEBUG_CAPTURE_EVENTS is set.
+    EventReplay {
+        /// How many events back to go. 0 is the most recent
+        #[clap(default_value_t = 0)]
+        index: usize,
+    },
+    /// Lists the most recent events stored in the database.
+    ///
+    /// Events are only stored if DEBUG_EVENT_RETENTION_MINUTES is set.
+    StoredEvents,
+    /// Shows what the bot would do with a stored event, without doing it
+    StoredReplay {
+        /// The ID of the stored event
+        id: captured_event::Id<Untrusted>,
+    },
+    /// Shows the sizes of the bot's in-memory caches
+    CacheStats,
+    /// Shows who a message would be proxied as
+    Resolve {
+        /// The message text to match
+        text: String,
+        /// Whose system to match against (e.g. @user). Defaults to your own
+        #[clap(long)]
+        user: Option<String>,
+    },
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while matching the message
+    Matching,
+}
+
+impl Debug {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        match self {
+            Self::EventReplay { index } => Self::event_replay(index, &state).await,
+            Self::StoredEvents => Self::stored_events(&state).await,
+            Self::StoredReplay { id } => Self::stored_replay(id, &state).await,
+            Self::CacheStats => Ok(Self::cache_stats()),
+            Self::Resolve { text, user } => Self::resolve(event, &state, &text, user).await,
+        }
+    }
+
+    #[tracing::instrument(skip(state))]
+    async fn event_replay(
+        index: usize,
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Replaying captured event");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        if capture::capacity() == 0 {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(
+                    "Events aren't being captured. Set DEBUG_CAPTURE_EVENTS to capture them."
+                        .into(),
+                ),
+            ));
+        }
+
+        let Some(captured) = capture::get(index) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "There's no event at that index. {} events are captured.",
+                    capture::count()
+                )),
+            ));
+        };
+
+        let received_at = captured.received_at;
+        let mut lines = vec![format!(
+            "*Event {index}*, received <!date^{}^{{date_num}} {{time_secs}}|{received_at}>",
+            received_at.unix_timestamp()
+        )];
+
+        lines.extend(
+            replay::dry_run(captured.event, &user_state.db)
+                .await
+                .change_context(CommandError::Matching)?,
+        );
+        lines.push(NOW_NOTE.into());
+
+        Ok(report(&lines))
+    }
+
+    #[tracing::instrument(skip(state))]
+    async fn stored_events(
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Listing stored events");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        let events = models::CapturedEvent::fetch_recent(STORED_EVENTS_SHOWN, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if events.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(
+                    "No events are stored. Set DEBUG_EVENT_RETENTION_MINUTES to store them.".into(),
+                ),
+            ));
+        }
+
+        let mut lines = vec!["*Stored events*".to_string()];
+        lines.extend(events.iter().map(|event| {
+            let summary = capture::restore(event).map_or_else(
+                |_| "unreadable".to_string(),
+                |message| {
+                    format!(
+                        "{} in {}",
+                        message
+                            .sender
+                            .user
+                            .map_or_else(|| "no user".into(), |user| user.to_slack_format()),
+                        message.origin.channel.map_or_else(
+                            || "no channel".into(),
+                            |channel| channel.to_slack_format()
+                        )
+                    )
+                },
+            );
+
+            format!("- `{}` at {} UTC: {summary}", event.id.id, event.created_at)
+        }));
+
+        Ok(report(&lines))
+    }
+
+    #[tracing::instrument(skip(state))]
+    async fn stored_replay(
+        id: captured_event::Id<Untrusted>,
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Replaying stored event");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        let Some(captured) = models::CapturedEvent::fetch(id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+        else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("That event doesn't exist, or has been pruned.".into()),
+            ));
+        };
+
+        let event = capture::restore(&captured).change_context(CommandError::Matching)?;
+
+        let mut lines = vec![format!(
+            "*Stored event `{}`*, from {} UTC",
+            captured.id.id, captured.created_at
+        )];
+        lines.extend(
+            replay::dry_run(event, &user_state.db)
+                .await
+                .change_context(CommandError::Matching)?,
+        );
+        lines.push(NOW_NOTE.into());
+        lines.push(REDACTED_NOTE.into());
+
+        Ok(report(&lines))
+    }
+
+    fn cache_stats() -> SlackCommandEventResponse {
+        debug!("Showing cache stats");
+
+        report(&[
+            "*In-memory caches*".into(),
+            format!(
+                "- Captured events: {}/{}",
+                capture::count(),
+                capture::capacity()
+            ),
+            format!(
+                "- Messages waiting on a suggestion: {}",
+                events::suggest::stashed()
+            ),
+            format!(
+                "- Users tracked for notification rate limits: {}",
+                notify::tracked_users()
+            ),
+            format!("- Backfills running: {}", backfill::running()),
+            format!("- Custom command names: {}", super::custom_command_names()),
+        ])
+    }
+
+    #[tracing::instrument(skip(event, state, text))]
+    async fn resolve(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        text: &str,
+        user: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Resolving message");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        let system = match user {
+            Some(user) => {
+                let Some(user_id) = user::parse_slack_user_id(&user) else {
+                    return Ok(SlackCommandEventResponse::new(
+                        SlackMessageContent::new().with_text("Invalid user".into()),
+                    ));
+                };
+
+                models::System::fetch_by_user_id(&user_id, &user_state.db).await
+            }
+            None => {
+                models::System::fetch_by_user_id(&user::Id::new(event.user_id), &user_state.db)
+                    .await
+            }
+        }
+        .change_context(CommandError::Sqlx)?;
+
+        let Some(system) = system else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("That user doesn't have a system.".into()),
+            ));
+        };
+
+        let mut lines = vec![format!("System: `{}`", system.id)];
+        lines.extend(
+            replay::describe_match(
+                &system,
+                SlackMessageContent::new().with_text(text.into()),
+                &user_state.db,
+            )
+            .await
+            .change_context(CommandError::Matching)?,
+        );
+
+        Ok(report(&lines))
+    }
+}
+
+fn report(lines: &[String]) -> SlackCommandEventResponse {
+    SlackCommandEventResponse::new(SlackMessageContent::new().with_blocks(slack_blocks![
+        some_into(SlackSectionBlock::new().with_text(md!("{}", lines.join("\n"))))
+    ]))
+}
@@ -0,0 +1,335 @@
+//! `/system diagnose`: checks the common reasons proxying doesn't work for a system.
+
+use std::time::Duration;
+
+use oauth2::reqwest;
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::debug;
+
+use crate::{
+    BOT_TOKEN,
+    models::{self, Trigger},
+    oauth::USER_SCOPES,
+};
+
+/// How long to wait for an avatar to respond before calling it unreachable
+const AVATAR_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The result of a single check
+pub struct Check {
+    name: &'static str,
+    passed: bool,
+    details: String,
+}
+
+impl Check {
+    fn pass(name: &'static str, details: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: true,
+            details: details.into(),
+        }
+    }
+
+    fn fail(name: &'static str, details: impl Into<String>) -> Self {
+        Self {
+            name,
+            passed: false,
+            details: details.into(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let icon = if self.passed {
+            ":white_check_mark:"
+        } else {
+            ":x:"
+        };
+
+        format!("{icon} *{}*: {}", self.name, self.details)
+    }
+}
+
+/// Runs every check against the system
+#[tracing::instrument(skip_all, fields(system_id = %system.id))]
+pub async fn run(
+    system: &models::System,
+    channel_id: &SlackChannelId,
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+) -> Vec<Check> {
+    debug!("Diagnosing system");
+    let http = reqwest::Client::builder()
+        .timeout(AVATAR_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    vec![
+        check_proxy_enabled(system),
+        check_token(system, &http).await,
+        check_channel(channel_id, client).await,
+        check_triggers(system, db).await,
+        check_avatars(system, &http, db).await,
+    ]
+}
+
+/// Only checks the system's token, for showing alongside the system's info
+#[tracing::instrument(skip_all, fields(system_id = %system.id))]
+pub async fn token_health(system: &models::System) -> Check {
+    let http = reqwest::Client::builder()
+        .timeout(AVATAR_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+
+    check_token(system, &http).await
+}
+
+#[derive(serde::Deserialize)]
+struct AuthTestResponse {
+    ok: bool,
+    error: Option<String>,
+    user_id: Option<String>,
+}
+
+/// Checks that the system's token works, belongs to the owner, and has every scope we need
+async fn check_token(system: &models::System, http: &reqwest::Client) -> Check {
+    const NAME: &str = "Slack authorization";
+
+    // slack-morphism doesn't expose response headers, which is where Slack lists a token's scopes
+    let response = match http
+        .post("https://slack.com/api/auth.test")
+        .bearer_auth(system.slack_oauth_token.expose())
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(error) => {
+            debug!(?error, "Failed to call auth.test");
+            return Check::fail(
+                NAME,
+                "Couldn't reach Slack to check your token. Try again later.",
+            );
+        }
+    };
+
+    let scopes = response
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|scopes| scopes.to_str().ok())
+        .unwrap_or_default()
+        .split(',')
+        .map(|scope| scope.trim().to_string())
+        .collect::<Vec<_>>();
+
+    let body = response
+        .text()
+        .await
+        .ok()
+        .and_then(|body| serde_json::from_str::<AuthTestResponse>(&body).ok());
+
+    let Some(AuthTestResponse { ok, error, user_id }) = body else {
+        return Check::fail(NAME, "Slack gave an unexpected response. Try again later.");
+    };
+
+    if !ok {
+        return Check::fail(
+            NAME,
+            format!(
+                "Your token doesn't work (`{}`). Run `/system reauth` to fix this.",
+                error.as_deref().unwrap_or("unknown error")
+            ),
+        );
+    }
+
+    if user_id.as_deref() != Some(system.owner_id.0.as_str()) {
+        return Check::fail(
+            NAME,
+            "Your token belongs to a different account. Run `/system reauth` to fix this.",
+        );
+    }
+
+    let missing = USER_SCOPES
+        .iter()
+        .filter(|scope| !scopes.iter().any(|granted| granted == *scope))
+        .copied()
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        Check::pass(NAME, "Your token is valid.")
+    } else {
+        Check::fail(
+            NAME,
+            format!(
+                "Your token is missing the `{}` scope(s). Run `/system reauth` to fix this.",
+                missing.join("`, `")
+            ),
+        )
+    }
+}
+
+/// Checks that the bot is in the channel the command was run in, so it can see messages there
+async fn check_channel(channel_id: &SlackChannelId, client: &SlackHyperClient) -> Check {
+    const NAME: &str = "Bot in this channel";
+
+    let info = client
+        .open_session(&BOT_TOKEN)
+        .conversations_info(&SlackApiConversationsInfoRequest::new(channel_id.clone()))
+        .await;
+
+    match info {
+        Ok(info) if info.channel.flags.is_member.unwrap_or(false) => {
+            Check::pass(NAME, "The bot can see messages in this channel.")
+        }
+        Ok(_) => Check::fail(
+            NAME,
+            "The bot isn't in this channel, so your messages here won't be proxied. Add it with `/invite`.",
+        ),
+        Err(error) => {
+            debug!(?error, "Failed to fetch channel info");
+
+            let code = match &error {
+                SlackClientError::ApiError(error) => error.code.as_str(),
+                _ => "unknown error",
+            };
+
+            Check::fail(
+                NAME,
+                format!(
+                    "The bot can't access this channel (`{code}`), so your messages here won't be proxied."
+                ),
+            )
+        }
+    }
+}
+
+/// Checks that proxying hasn't been turned off for the whole system
+fn check_proxy_enabled(system: &models::System) -> Check {
+    const NAME: &str = "Proxying";
+
+    if system.proxy_enabled {
+        Check::pass(NAME, "Proxying is on.")
+    } else {
+        Check::fail(
+            NAME,
+            "Proxying is turned off for your system. Turn it back on with `/system proxy on`.",
+        )
+    }
+}
+
+/// Checks that no two members have triggers that match the same message
+async fn check_triggers(system: &models::System, db: &SqlitePool) -> Check {
+    const NAME: &str = "Trigger conflicts";
+
+    let triggers = match Trigger::fetch_by_system_id(system.id, db).await {
+        Ok(triggers) => triggers,
+        Err(error) => {
+            debug!(?error, "Failed to fetch triggers");
+            return Check::fail(NAME, "Couldn't load your triggers. Try again later.");
+        }
+    };
+
+    let conflicts = triggers
+        .iter()
+        .enumerate()
+        .flat_map(|(index, trigger)| {
+            triggers[index + 1..]
+                .iter()
+                .filter(move |other| trigger.conflicts_with(other))
+                .map(move |other| {
+                    format!(
+                        "`{}` ({}) and `{}` ({})",
+                        trigger.text, trigger.id, other.text, other.id
+                    )
+                })
+        })
+        .collect::<Vec<_>>();
+
+    if conflicts.is_empty() {
+        Check::pass(NAME, "None of your members' triggers overlap.")
+    } else {
+        Check::fail(
+            NAME,
+            format!(
+                "These triggers overlap, so the wrong member may be used: {}. Change them with `/triggers edit`, or set which member wins with the trigger priority in `/members edit`.",
+                conflicts.join(", ")
+            ),
+        )
+    }
+}
+
+/// Checks that the avatar every enabled member's messages are proxied with can be loaded
+async fn check_avatars(system: &models::System, http: &reqwest::Client, db: &SqlitePool) -> Check {
+    const NAME: &str = "Profile pictures";
+
+    let members = match system.members(db).await {
+        Ok(members) => members,
+        Err(error) => {
+            debug!(?error, "Failed to fetch members");
+            return Check::fail(NAME, "Couldn't load your members. Try again later.");
+        }
+    };
+
+    let checks = members
+        .into_iter()
+        .filter(|member| member.enabled)
+        .filter_map(|member| {
+            // The avatar proxied messages are sent with
+            let url = member.proxy_avatar_url.or(member.profile_picture_url)?;
+            Some(async move {
+                let reachable = http
+                    .get(&url)
+                    .send()
+                    .await
+                    .is_ok_and(|response| response.status().is_success());
+
+                (member.display_name, reachable)
+            })
+        });
+
+    let unreachable = futures::future::join_all(checks)
+        .await
+        .into_iter()
+        .filter(|(_, reachable)| !reachable)
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+
+    if unreachable.is_empty() {
+        Check::pass(NAME, "All profile pictures load.")
+    } else {
+        Check::fail(
+            NAME,
+            format!(
+                "Slack won't be able to show the profile pictures of {}. Change them with `/members edit`.",
+                unreachable.join(", ")
+            ),
+        )
+    }
+}
+
+/// Renders the checks as a checklist
+pub fn render(checks: &[Check]) -> Vec<SlackBlock> {
+    let passed = checks.iter().filter(|check| check.passed).count();
+
+    let summary = if passed == checks.len() {
+        "Everything looks good!".to_string()
+    } else {
+        format!(
+            "{} of {} checks failed.",
+            checks.len() - passed,
+            checks.len()
+        )
+    };
+
+    let checklist = checks
+        .iter()
+        .map(Check::render)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    slack_blocks![
+        some_into(SlackHeaderBlock::new(pt!("System diagnosis"))),
+        some_into(SlackSectionBlock::new().with_text(md!("{}", checklist))),
+        some_into(SlackContextBlock::new(vec![md!("{}", summary)]))
+    ]
+}
@@ -0,0 +1,175 @@
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::debug;
+
+use super::parse_channel;
+use crate::{fetch_system, models::user};
+
+/// The longest messages can be kept for in an ephemeral channel: 30 days
+const MAX_HOURS: u32 = 30 * 24;
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Channels where your proxied messages are deleted after a while, e.g. for venting.
+///
+/// Only messages proxied after a channel is made ephemeral are deleted. Your own unproxied messages are left alone.
+pub enum Ephemeral {
+    /// Deletes your proxied messages in a channel once they're a number of hours old
+    Add {
+        /// The channel to make ephemeral (e.g. #venting)
+        channel: String,
+        /// How many hours to keep messages for
+        #[clap(value_parser = clap::value_parser!(u32).range(1..=i64::from(MAX_HOURS)))]
+        hours: u32,
+    },
+    /// Stops deleting your proxied messages in a channel
+    Remove {
+        /// The channel to keep messages in again (e.g. #venting)
+        channel: String,
+    },
+    /// Lists the channels your proxied messages are deleted in
+    List,
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the database
+    Sqlx,
+}
+
+impl Ephemeral {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        match self {
+            Self::Add { channel, hours } => Self::add(event, &state, &channel, hours).await,
+            Self::Remove { channel } => Self::remove(event, &state, &channel).await,
+            Self::List => Self::list(event, &state).await,
+        }
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn add(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        channel: &str,
+        hours: u32,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Making channel ephemeral");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let Some(channel_id) = parse_channel(channel) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Invalid channel".into()),
+            ));
+        };
+
+        system_id
+            .set_ephemeral_channel(&channel_id, hours, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Your proxied messages in {} will be deleted {} after they're sent.",
+                channel_id.to_slack_format(),
+                describe_hours(i64::from(hours))
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn remove(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        channel: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Removing ephemeral channel");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let Some(channel_id) = parse_channel(channel) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Invalid channel".into()),
+            ));
+        };
+
+        let result = system_id
+            .remove_ephemeral_channel(&channel_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if result.rows_affected() == 0 {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("That channel isn't ephemeral.".into()),
+            ));
+        }
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Your proxied messages in {} will be kept again.",
+                channel_id.to_slack_format()
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn list(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Listing ephemeral channels");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let channels = system_id
+            .ephemeral_channels(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if channels.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("Your proxied messages are kept in every channel.".into()),
+            ));
+        }
+
+        let channels = channels
+            .iter()
+            .map(|(channel, hours)| {
+                format!(
+                    "- {}: deleted after {}",
+                    channel.to_slack_format(),
+                    describe_hours(*hours)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(slack_blocks![some_into(
+                SlackSectionBlock::new().with_text(md!("*Ephemeral channels*\n{}", channels))
+            )]),
+        ))
+    }
+}
+
+/// e.g. "1 hour", "12 hours", "2 days"
+fn describe_hours(hours: i64) -> String {
+    match hours {
+        1 => "1 hour".to_string(),
+        hours if hours % 24 == 0 && hours >= 48 => format!("{} days", hours / 24),
+        24 => "1 day".to_string(),
+        hours => format!("{hours} hours"),
+    }
+}
@@ -0,0 +1,173 @@
+use std::sync::Arc;
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::{debug, info};
+
+use crate::{
+    BOT_TOKEN, fetch_system,
+    interactions::import,
+    models::{export, user},
+    pluralkit,
+};
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Brings your members over from another bot.
+///
+/// Imported members are added alongside your existing ones. Triggers and aliases you already use are skipped.
+/// If some members look like ones you already have, a form opens to choose whether to skip, overwrite or duplicate each of them.
+///
+/// Run an import again to bring over what changed since: members you imported before are updated instead of added again,
+/// so the other bot can stay the place you edit your members.
+pub enum Import {
+    /// Imports your members, proxy tags and display names from PluralKit.
+    ///
+    /// Either run pk;export and paste the link PluralKit sends you, or run pk;token and paste your token.
+    /// Proxy tags become triggers, and each member's PluralKit ID becomes an alias.
+    Pluralkit {
+        /// The pk;export link or PluralKit token
+        source: String,
+    },
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while adding the imported members
+    Import,
+    /// Error while calling the Slack API
+    SlackApi,
+}
+
+impl Import {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        client: Arc<SlackHyperClient>,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        match self {
+            Self::Pluralkit { source } => {
+                Self::import_pluralkit(event, &client, &state, &source).await
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(system_id))]
+    async fn import_pluralkit(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+        source: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let Some(source) = pluralkit::Source::parse(source) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(
+                    "That link isn't a PluralKit export. Use the link PluralKit sends you after pk;export.".into(),
+                ),
+            ));
+        };
+
+        let members = match pluralkit::fetch_members(&source).await {
+            Ok(members) => members,
+            Err(error) => {
+                debug!(?error, "Failed to fetch members from PluralKit");
+
+                let reason = match error.current_context() {
+                    pluralkit::Error::InvalidToken => {
+                        "PluralKit didn't accept that token. Run pk;token to get your current one."
+                    }
+                    pluralkit::Error::Format => {
+                        "That doesn't look like a PluralKit export. Make sure you pasted the whole link."
+                    }
+                    pluralkit::Error::Request => {
+                        "Couldn't reach PluralKit. The export link may have expired, or PluralKit may be down. Try again later."
+                    }
+                };
+
+                return Ok(SlackCommandEventResponse::new(
+                    SlackMessageContent::new().with_text(reason.into()),
+                ));
+            }
+        };
+
+        let Some(example_alias) = members
+            .first()
+            .and_then(|member| member.aliases.first())
+            .cloned()
+        else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("Your PluralKit system doesn't have any members to import.".into()),
+            ));
+        };
+
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let conflicts = export::find_conflicts(&members, &system, &user_state.db)
+            .await
+            .change_context(CommandError::Import)?;
+
+        if !conflicts.is_empty() {
+            debug!(
+                count = conflicts.len(),
+                "Asking how to resolve import conflicts"
+            );
+
+            let pending_id = export::save_pending_import(&members, system_id, &user_state.db)
+                .await
+                .change_context(CommandError::Import)?;
+            let existing = system
+                .members(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            client
+                .open_session(&BOT_TOKEN)
+                .views_open(&SlackApiViewsOpenRequest::new(
+                    event.trigger_id,
+                    import::conflicts_view(
+                        pending_id,
+                        "PluralKit",
+                        &members,
+                        &conflicts,
+                        &existing,
+                    ),
+                ))
+                .await
+                .attach_printable("Error opening import conflicts view")
+                .change_context(CommandError::SlackApi)?;
+
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(
+                    "Some of these members look like ones you already have. Choose what to do with them in the form that just opened.".into(),
+                ),
+            ));
+        }
+
+        let summary = export::import_members(&members, &[], system_id, &user_state.db)
+            .await
+            .change_context(CommandError::Import)?;
+
+        info!(?summary, "Imported members from PluralKit");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "{} Their PluralKit IDs work as aliases, so you can use e.g. `/members info {}`.",
+                import::summary_text(summary),
+                example_alias
+            )),
+        ))
+    }
+}
@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::debug;
+
+use crate::{fetch_system, models::user};
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Managers are users you trust to see more of your system than everyone else.
+///
+/// For example, managers can see member IDs, aliases and disabled members in /members list.
+/// They cannot change anything about your system.
+pub enum Managers {
+    /// Adds a manager to your system
+    Add {
+        /// The user to add (e.g. @user)
+        user: String,
+    },
+    /// Removes a manager from your system
+    Remove {
+        /// The user to remove (e.g. @user)
+        user: String,
+    },
+    /// Lists your system's managers
+    List,
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the database
+    Sqlx,
+}
+
+impl Managers {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        client: Arc<SlackHyperClient>,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        match self {
+            Self::Add { user } => Self::add_manager(event, &client, &state, user).await,
+            Self::Remove { user } => Self::remove_manager(event, &client, &state, user).await,
+            Self::List => Self::list_managers(event, &state).await,
+        }
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(system_id))]
+    async fn add_manager(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+        user: String,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Adding manager");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let Some(user_id) = user::parse_and_trust(&user, client).await else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Invalid user ID".into()),
+            ));
+        };
+
+        system_id
+            .add_manager(&user_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Added {} as a manager of your system.",
+                user_id.to_slack_format()
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(system_id))]
+    async fn remove_manager(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+        user: String,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Removing manager");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let Some(user_id) = user::parse_and_trust(&user, client).await else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Invalid user ID".into()),
+            ));
+        };
+
+        let result = system_id
+            .remove_manager(&user_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if result.rows_affected() == 0 {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("That user isn't a manager.".into()),
+            ));
+        }
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Removed {} as a manager of your system.",
+                user_id.to_slack_format()
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn list_managers(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Listing managers");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let managers = system_id
+            .managers(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if managers.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Your system has no managers.".into()),
+            ));
+        }
+
+        let managers = managers
+            .iter()
+            .map(|manager| format!("- {}", manager.to_slack_format()))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(slack_blocks![some_into(
+                SlackSectionBlock::new().with_text(md!("*Managers*\n{}", managers))
+            )]),
+        ))
+    }
+}
@@ -1,18 +1,24 @@
 use std::sync::Arc;
 
-use error_stack::{Result, ResultExt, report};
-use futures::TryStreamExt;
+use error_stack::{Result, ResultExt};
 use slack_morphism::prelude::*;
+use time::OffsetDateTime;
 use tracing::{debug, info, trace};
 
+use super::{Format, member_fields::Fields};
 use crate::{
-    BOT_TOKEN, fetch_member, fetch_system, fields,
+    BOT_TOKEN, cache,
+    events::recording,
+    fetch_member, fetch_system, fields, home, interactions,
     models::{
-        self,
+        self, AuditLog, MessageLog, Switch, audit,
         member::{self, MemberRef, View},
         trust::Untrusted,
         user,
+        viewer::Viewer,
     },
+    render::{self, MemberReference},
+    timeparse,
 };
 
 #[derive(clap::Subcommand, Debug)]
@@ -32,7 +38,7 @@ use crate::{
 pub enum Member {
     /// Adds a new member to your system. Expect a popup to fill in the member info!
     Add,
-    /// Disables/Deletes a member from your system.
+    /// Disables a member in your system.
     ///
     /// This doesn't actually "delete" the member entirely, nor does it delete messages sent by this member.
     /// Rather, the member is disabled and cannot be accessed. This is for moderation purposes.
@@ -40,9 +46,21 @@ pub enum Member {
     ///
     /// Disabling a member also prevents them from being accessed via their aliases or triggers.
     Disable {
-        /// The member to delete
+        /// The member to disable
         member: MemberRef,
     },
+    /// Permanently deletes a member from your system
+    ///
+    /// Unlike `/members disable`, this can't be undone. The member, their triggers, aliases, fields and name recording are all deleted.
+    /// You'll be asked whether to also delete the logs of their proxied messages, or keep them without the member.
+    /// Run with --confirm to open the confirmation popup.
+    Delete {
+        /// The member to delete
+        member_id: MemberRef,
+        /// Confirms you want to delete the member for good
+        #[clap(long, action)]
+        confirm: bool,
+    },
     /// Enables a member from your system.
     ///
     /// This will re-enable the member and allow them to be accessed again.
@@ -55,7 +73,11 @@ pub enum Member {
     /// This will display information about the member, including their name, pronouns, and other details.
     Info {
         /// The member to get info about. You must use the member's ID, which you can get from /members list.
-        member_id: MemberRef,
+        /// Leave blank to pick from a list
+        member_id: Option<MemberRef>,
+        /// How to show the output: blocks, text or json
+        #[clap(long, value_enum, default_value_t)]
+        format: Format,
     },
     /// Lists all members in a system
     ///
@@ -64,6 +86,12 @@ pub enum Member {
     List {
         /// The system to list members from. If left blank, defaults to your system.
         system: Option<String>,
+        /// How to show the output: blocks, text or json
+        #[clap(long, value_enum, default_value_t)]
+        format: Format,
+        /// Which page to show, for long lists
+        #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        page: u16,
     },
     /// Edits a member's info
     ///
@@ -72,19 +100,75 @@ pub enum Member {
         /// The member to edit.
         member_id: MemberRef,
     },
+    /// Records how a member's name is pronounced
+    ///
+    /// After running this, send the bot a short audio clip in a DM within 10 minutes.
+    /// The clip will be shown on the member's profile.
+    Record {
+        /// The member to record
+        member_id: MemberRef,
+    },
+    #[clap(subcommand)]
+    Fields(Fields),
+    /// Sets the emoji shown next to a member when you switch to them
+    ///
+    /// The emoji must be a custom emoji in this workspace (e.g. :my-member:). Leave it blank to remove the emoji.
+    Emoji {
+        /// The member to set the emoji for
+        member_id: MemberRef,
+        /// The emoji to use
+        emoji: Option<String>,
+    },
+    /// Sets the short name you use for a member in commands
+    ///
+    /// Command names are lowercase with no spaces (e.g. "alex"), and work anywhere a member ID or alias does.
+    /// They're shown next to the member in lists. Leave the name blank to remove it.
+    CommandName {
+        /// The member to name
+        member_id: MemberRef,
+        /// The command name
+        name: Option<String>,
+    },
+    /// Turns proxying as a member on or off
+    ///
+    /// A member with proxying off keeps their profile, triggers and aliases, but messages are never proxied as them.
+    /// This is for members you want to keep records of, unlike `/members disable` which hides the member entirely.
+    Proxy {
+        /// The member to change
+        member_id: MemberRef,
+        /// Whether messages can be proxied as the member (on/off)
+        #[clap(value_parser = clap::builder::BoolishValueParser::new(), action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Shows how many messages have been proxied as your members
+    ///
+    /// Counts cover the past day, week, month, and all time. Leave the member blank to see everyone.
+    Stats {
+        /// The member to show stats for
+        member_id: Option<MemberRef>,
+    },
     /// Switch to a different member
     ///
     /// You can switch to a different member by providing their ID or username.
+    /// List several members to co-front (e.g. `/members switch alex sam`). The first one is the primary fronter,
+    /// who messages are proxied as with front autoproxy.
     /// Alternatively, you can use `/members switch --base` to revert to your base account,
     /// and the bot will not rewrite messages under a member profile.
-    #[group(required = true)]
     Switch {
-        /// The member to switch to.
+        /// The members to switch to, primary fronter first. Leave blank (without --base) to pick from a list
         #[clap(group = "member")]
-        member_id: Option<MemberRef>,
+        member_ids: Vec<MemberRef>,
         /// Don't switch to another member, just message with the base account
         #[clap(long, short, action, group = "member", alias = "none")]
         base: bool,
+        /// Only add the switch to your history (see /system history), without changing who messages are proxied as.
+        /// History only keeps the primary fronter
+        #[clap(long, action, requires = "member")]
+        log_only: bool,
+        /// When the switch happened, for logging a switch after the fact (e.g. "yesterday 22:00", "2h ago").
+        /// Times are in your system's timezone. Only works with --log-only
+        #[clap(long, requires = "log_only")]
+        at: Option<String>,
     },
 }
 
@@ -94,6 +178,10 @@ pub enum CommandError {
     SlackApi,
     /// Error while calling the database
     Sqlx,
+    /// Error while working out who is viewing the system
+    Viewer,
+    /// Error running the fields command
+    Fields,
 }
 
 impl Member {
@@ -113,23 +201,62 @@ impl Member {
             }
             Self::Disable { member } => Self::disable(event, &state, member).await,
             Self::Enable { member } => Self::enable(event, &state, member).await,
-            Self::Info { member_id } => Self::member_info(event, &state, member_id).await,
+            Self::Delete { member_id, confirm } => {
+                Self::delete(event, &client, &state, member_id, confirm).await
+            }
+            Self::Info {
+                member_id: Some(member_id),
+                format,
+            } => Self::member_info(event, &state, member_id, format).await,
+            Self::Info {
+                member_id: None, ..
+            } => Self::pick_member(event, &state, false).await,
             Self::Edit { member_id } => {
                 Self::edit_member(event, client.open_session(&BOT_TOKEN), &state, member_id).await
             }
-            Self::List { system } => Self::list_members(event, state, system).await,
-            Self::Switch { member_id, base } => {
-                Self::switch_member(event, state, member_id, base).await
+            Self::List {
+                system,
+                format,
+                page,
+            } => Self::list_members(event, &client, state, system, format, page).await,
+            Self::Switch {
+                member_ids,
+                base,
+                log_only,
+                at,
+            } => {
+                if member_ids.is_empty() && !base {
+                    return Self::pick_member(event, &state, true).await;
+                }
+
+                Self::switch_member(event, &client, state, member_ids, log_only, at).await
+            }
+            Self::Proxy { member_id, enabled } => {
+                Self::proxy(event, &state, member_id, enabled).await
+            }
+            Self::Stats { member_id } => Self::stats(event, &state, member_id).await,
+            Self::Record { member_id } => Self::record(event, &state, member_id).await,
+            Self::Emoji { member_id, emoji } => {
+                Self::emoji(event, &client, &state, member_id, emoji).await
+            }
+            Self::CommandName { member_id, name } => {
+                Self::command_name(event, &state, member_id, name).await
             }
+            Self::Fields(fields) => fields
+                .run(event, state)
+                .await
+                .change_context(CommandError::Fields),
         }
     }
 
-    #[tracing::instrument(skip(event, state), fields(system_id))]
+    #[tracing::instrument(skip(event, client, state), fields(system_id))]
     async fn switch_member(
         event: SlackCommandEvent,
+        client: &SlackHyperClient,
         state: SlackClientEventsUserState,
-        member_ref: Option<MemberRef>,
-        base: bool,
+        member_refs: Vec<MemberRef>,
+        log_only: bool,
+        at: Option<String>,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         trace!("Switching member");
         let states = state.read().await;
@@ -137,11 +264,38 @@ impl Member {
 
         fetch_system!(event, user_state => system_id);
 
-        let new_active_member_id = if base {
-            None
-        } else {
-            debug!(requested_member_id = ?&member_ref, "Validating member ID");
-            fetch_member!(member_ref.as_ref().unwrap(), user_state, system_id => member_id);
+        let at = match at {
+            Some(at) => {
+                let system = system_id
+                    .fetch(&user_state.db)
+                    .await
+                    .change_context(CommandError::Sqlx)?;
+                let now = OffsetDateTime::now_utc();
+
+                match timeparse::parse(&at, now, system.timezone()) {
+                    Ok(at) if at > now => {
+                        return Ok(SlackCommandEventResponse::new(
+                            SlackMessageContent::new()
+                                .with_text("Switches can't be logged in the future.".into()),
+                        ));
+                    }
+                    Ok(at) => Some(at),
+                    Err(error) => {
+                        return Ok(SlackCommandEventResponse::new(
+                            SlackMessageContent::new().with_text(error.to_string()),
+                        ));
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let mut new_fronter_ids = Vec::new();
+
+        // With --base, no members are given, leaving no one fronting
+        for member_ref in &member_refs {
+            debug!(requested_member_id = ?member_ref, "Validating member ID");
+            fetch_member!(member_ref, user_state, system_id => member_id);
 
             if !member_id
                 .enabled(&user_state.db)
@@ -151,44 +305,127 @@ impl Member {
                 debug!("Member is disabled");
 
                 return Ok(SlackCommandEventResponse::new(
-                    SlackMessageContent::new()
-                        .with_text("The member you're trying to switch to is disabled! Either re-enable them or choose another member.".into()),
+                    SlackMessageContent::new().with_text(format!(
+                        "Member {member_id} is disabled! Either re-enable them or choose another member."
+                    )),
                 ));
             }
 
-            Some(member_id)
-        };
+            // Logging a switch doesn't proxy as the member, so it's fine for members kept for records
+            if !log_only
+                && !member_id
+                    .proxy_enabled(&user_state.db)
+                    .await
+                    .change_context(CommandError::Sqlx)?
+            {
+                debug!("Member has proxying turned off");
+
+                return Ok(SlackCommandEventResponse::new(
+                    SlackMessageContent::new().with_text(format!(
+                        "Messages can't be proxied as member {member_id}. Use `/members proxy {member_id} on` to turn proxying on, or `/members switch --log-only` to only log the switch."
+                    )),
+                ));
+            }
+
+            if !new_fronter_ids.contains(&member_id) {
+                new_fronter_ids.push(member_id);
+            }
+        }
 
-        debug!(target_member_id = ?new_active_member_id, "Changing active member");
+        let new_active_member_id = new_fronter_ids.first().copied();
 
-        let new_member = system_id
-            .change_fronting_member(new_active_member_id, &user_state.db)
-            .await;
+        if log_only {
+            debug!(target_member_id = ?new_active_member_id, "Logging switch");
 
-        let response = match new_member {
-            Ok(Some(member)) => {
-                info!(member_name = %member.full_name, member_id = %member.id, "Successfully switched to member");
-                format!("Switch to member {}", member.full_name)
+            match at {
+                Some(at) => {
+                    Switch::record_at(system_id, new_active_member_id, at, &user_state.db).await
+                }
+                None => Switch::record(system_id, new_active_member_id, &user_state.db).await,
             }
-            Ok(None) => {
+            .change_context(CommandError::Sqlx)?;
+
+            let name = match new_active_member_id {
+                Some(member_id) => {
+                    let member = member_id
+                        .fetch(&user_state.db)
+                        .await
+                        .change_context(CommandError::Sqlx)?;
+
+                    MemberReference::new(&member).with_full_name().mrkdwn()
+                }
+                None => "your base account".into(),
+            };
+
+            info!("Logged switch");
+
+            let when = at.map_or_else(String::new, |at| {
+                format!(
+                    " at <!date^{}^{{date_short_pretty}} {{time}}|{at}>",
+                    at.unix_timestamp()
+                )
+            });
+
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "Logged a switch to {name}{when}. Messages are still proxied the same way."
+                )),
+            ));
+        }
+
+        debug!(target_member_ids = ?new_fronter_ids, "Changing fronters");
+
+        system_id
+            .set_fronters(&new_fronter_ids, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let mut names = Vec::new();
+        for member_id in &new_fronter_ids {
+            let member = member_id
+                .fetch(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            names.push(MemberReference::new(&member).with_full_name().mrkdwn());
+        }
+
+        let response = match names.as_slice() {
+            [] => {
                 info!("Successfully switched to base account");
                 "Switched to base account".into()
             }
-            Err(e) => return Err(e.change_context(CommandError::Sqlx)),
+            [name] => {
+                info!(member_id = ?new_active_member_id, "Successfully switched to member");
+                format!("Switched to {name}")
+            }
+            [primary, others @ ..] => {
+                info!(member_ids = ?new_fronter_ids, "Successfully switched to co-fronters");
+                format!(
+                    "Switched to {primary}, co-fronting with {}. {primary} is the primary fronter.",
+                    others.join(", ")
+                )
+            }
         };
 
+        home::refresh(&event.user_id, client, &user_state.db).await;
+
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text(response),
         ))
     }
 
-    #[tracing::instrument(skip(event, state), fields(user_id, system_id))]
+    #[tracing::instrument(skip(event, client, state), fields(user_id, system_id))]
     async fn list_members(
         event: SlackCommandEvent,
+        client: &SlackHyperClient,
         state: SlackClientEventsUserState,
         system: Option<String>,
+        format: Format,
+        page: u16,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         trace!("Listing all members");
+        let command = format!("members list {}", system.as_deref().unwrap_or_default());
         let states = state.read().await;
         let user_state = states.get_user_state::<user::State>().unwrap();
 
@@ -197,7 +434,7 @@ impl Member {
         // If the user ID is invalid, return an error.
         // There's probably a better way to write this behaviour but I'm not sure how.
         let Some((user_id, is_author)) = system.map_or_else(
-            || Some((user::Id::new(event.user_id), true)),
+            || Some((user::Id::new(event.user_id.clone()), true)),
             |u| user::parse_slack_user_id(&u).map(|id| (id, false)),
         ) else {
             debug!("Invalid user ID provided in system parameter");
@@ -226,48 +463,49 @@ impl Member {
 
         fields!(system_id = %system.id);
 
-        let member_blocks = sqlx::query!(
-            "
-                SELECT
-                    members.id,
-                    display_name,
-                    full_name,
-                    enabled,
-                    GROUP_CONCAT(aliases.alias, ', ') as aliases
-                FROM
-                    members
-                JOIN
-                    aliases ON members.id = aliases.member_id
-                WHERE
-                    members.system_id = $1
-                GROUP BY members.id
-            ",
-            system.id
-        )
-        .fetch(&user_state.db)
-        .map_ok(|member| {
-            let fields = [
-                Some(md!("*Member ID*: {}", member.id)),
-                Some(md!("*Display Name*: {}", member.display_name)),
-                Some(md!("*Aliases: {}", member.aliases)),
-                Some(md!("*Disabled*")).filter(|_| !member.enabled),
-            ]
-            .into_iter()
-            .flatten()
-            .collect();
-
-            SlackSectionBlock::new()
-                .with_text(md!("*{}*", member.full_name))
-                .with_fields(fields)
-        })
-        .map_ok(Into::into)
-        .map_err(|err| report!(err).change_context(CommandError::Sqlx))
-        .try_collect()
-        .await?;
+        let viewer = Viewer::resolve(&event.user_id.into(), &system, client, &user_state.db)
+            .await
+            .change_context(CommandError::Viewer)?;
 
-        Ok(SlackCommandEventResponse::new(
-            SlackMessageContent::new().with_blocks(member_blocks),
-        ))
+        debug!(?viewer, "Resolved viewer");
+
+        if !viewer.sees_system(&system) {
+            debug!("System is private");
+            return Ok(super::system::private_response());
+        }
+
+        let (member_blocks, json) = if let Some(cached) = cache::MEMBER_LISTS.get(system.id, viewer)
+        {
+            cached
+        } else {
+            let members = system
+                .members(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            let aliases = if viewer.sees_management_details() {
+                models::Alias::fetch_by_system_id(system.id, &user_state.db)
+                    .await
+                    .change_context(CommandError::Sqlx)?
+            } else {
+                Vec::new()
+            };
+
+            let json = render::json::member_list(&members, &aliases, viewer);
+            let member_ids = members.iter().map(|member| member.id).collect::<Vec<_>>();
+            let rendered = (render::member_list(members, &aliases, viewer), json);
+            cache::MEMBER_LISTS.insert(system.id, viewer, member_ids, rendered.clone());
+            rendered
+        };
+
+        if member_blocks.is_empty() && format != Format::Json {
+            debug!("No members found");
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("No members found.".into()),
+            ));
+        }
+
+        Ok(format.respond_paged(member_blocks, &json, page, &command))
     }
 
     #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
@@ -295,12 +533,12 @@ impl Member {
             ));
         }
 
-        let system_fronting_member_id = system_id
-            .currently_fronting_member_id(&user_state.db)
+        let fronters = system_id
+            .fronters(&user_state.db)
             .await
             .change_context(CommandError::Sqlx)?;
 
-        if system_fronting_member_id.is_some_and(|id| id == member_id) {
+        if fronters.contains(&member_id) {
             return Ok(SlackCommandEventResponse::new(
                 SlackMessageContent::new().with_text("Cannot disable the currently fronting member. You can use `/members switch` to switch to another member.".into()),
             ));
@@ -311,11 +549,80 @@ impl Member {
             .await
             .change_context(CommandError::Sqlx)?;
 
+        let log_id = AuditLog::record(
+            system_id,
+            &event.user_id.into(),
+            audit::Action::MemberDisabled,
+            Some(member_id.to_string()),
+            &user_state.db,
+        )
+        .await
+        .change_context(CommandError::Sqlx)?;
+
+        info!("Member disabled");
+
         Ok(SlackCommandEventResponse::new(
-            SlackMessageContent::new().with_text("Member disabled".into()),
+            SlackMessageContent::new().with_blocks(slack_blocks![
+                some_into(SlackSectionBlock::new().with_text(md!("Member disabled"))),
+                some_into(SlackActionsBlock::new(vec![
+                    SlackBlockButtonElement::new("undo_member_disable".into(), pt!("Undo"))
+                        .with_value(log_id.to_string())
+                        .into()
+                ])),
+                some_into(SlackContextBlock::new(vec![md!(
+                    "You can undo this for {} minutes.",
+                    audit::UNDO_WINDOW_MINUTES
+                )]))
+            ]),
         ))
     }
 
+    #[tracing::instrument(skip(event, client, state), fields(user_id = %event.user_id, system_id, member_id))]
+    async fn delete(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+        member_ref: MemberRef,
+        confirm: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member delete command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member_ref, user_state, system_id => member_id);
+
+        let member = member_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if !confirm {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "Deleting {} can't be undone. Run the command again with `--confirm` to continue, or use `/members disable` to hide them instead.",
+                    MemberReference::new(&member).mrkdwn()
+                )),
+            ));
+        }
+
+        client
+            .open_session(&BOT_TOKEN)
+            .views_open(&SlackApiViewsOpenRequest::new(
+                event.trigger_id.clone(),
+                interactions::member::delete_view(&member),
+            ))
+            .await
+            .attach_printable("Error opening view")
+            .change_context(CommandError::SlackApi)?;
+
+        debug!("Opened member delete view");
+
+        Ok(SlackCommandEventResponse::new(SlackMessageContent::new()))
+    }
+
     #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
     async fn enable(
         event: SlackCommandEvent,
@@ -346,16 +653,250 @@ impl Member {
             .await
             .change_context(CommandError::Sqlx)?;
 
+        AuditLog::record(
+            system_id,
+            &event.user_id.into(),
+            audit::Action::MemberEnabled,
+            Some(member_id.to_string()),
+            &user_state.db,
+        )
+        .await
+        .change_context(CommandError::Sqlx)?;
+
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text("Member enabled".into()),
         ))
     }
 
+    #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
+    async fn command_name(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        member_ref: MemberRef,
+        name: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member command name command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member_ref, user_state, system_id => member_id);
+
+        let name = match name {
+            Some(name) => {
+                let Some(name) = member::normalize_command_name(&name) else {
+                    return Ok(SlackCommandEventResponse::new(
+                        SlackMessageContent::new().with_text(
+                            "Command names can't have spaces, or be just a number.".into(),
+                        ),
+                    ));
+                };
+
+                if member::Id::fetch_by_alias(&name, system_id, &user_state.db)
+                    .await
+                    .change_context(CommandError::Sqlx)?
+                    .is_some_and(|owner| owner != member_id)
+                {
+                    return Ok(SlackCommandEventResponse::new(
+                        SlackMessageContent::new()
+                            .with_text(format!("`{name}` is already an alias of another member.")),
+                    ));
+                }
+
+                Some(name)
+            }
+            None => None,
+        };
+
+        member_id
+            .set_command_name(system_id, name.as_deref(), &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(?name, "Updated member command name");
+
+        let response = name.map_or_else(
+            || "Removed the member's command name. It still works as an alias.".to_string(),
+            |name| format!("The member's command name is now `{name}`."),
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
+    async fn proxy(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        member_ref: MemberRef,
+        enabled: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member proxy command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member_ref, user_state, system_id => member_id);
+
+        member_id
+            .set_proxy_enabled(enabled, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(enabled, "Updated member proxy status");
+
+        let response = if enabled {
+            "Messages can be proxied as the member again."
+        } else {
+            "Messages won't be proxied as the member anymore. Their profile is kept as-is."
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response.into()),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id, member_id))]
+    async fn stats(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        member_ref: Option<MemberRef>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member stats command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let member_id = match member_ref {
+            Some(member_ref) => {
+                fetch_member!(member_ref, user_state, system_id => member_id);
+                Some(member_id)
+            }
+            None => None,
+        };
+
+        let stats = MessageLog::stats(system_id, member_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if stats.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("No messages have been proxied yet.".into()),
+            ));
+        }
+
+        let members = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+            .members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(render::message_stats(&stats, &members)),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
+    async fn record(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        member_ref: MemberRef,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member record command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member_ref, user_state, system_id => member_id);
+
+        system_id
+            .start_recording(member_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!("Waiting for name recording");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Send me an audio clip of the name in a DM within the next 10 minutes. Clips can be up to {} seconds long.",
+                recording::MAX_DURATION.as_secs()
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(user_id = %event.user_id, system_id, member_id))]
+    async fn emoji(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+        member_ref: MemberRef,
+        emoji: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member emoji command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member_ref, user_state, system_id => member_id);
+
+        let emoji = emoji.map(|emoji| emoji.trim_matches(':').to_string());
+
+        if let Some(ref emoji) = emoji {
+            let workspace_emoji = client
+                .open_session(&BOT_TOKEN)
+                .emoji_list()
+                .await
+                .change_context(CommandError::SlackApi)?;
+
+            if !workspace_emoji
+                .emoji
+                .contains_key(&SlackEmojiName::new(emoji.clone()))
+            {
+                debug!(emoji, "Emoji isn't in the workspace");
+                return Ok(SlackCommandEventResponse::new(
+                    SlackMessageContent::new().with_text(format!(
+                        "`:{emoji}:` isn't a custom emoji in this workspace."
+                    )),
+                ));
+            }
+        }
+
+        member_id
+            .set_emoji(emoji.as_deref(), &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(?emoji, "Updated member emoji");
+
+        let response = emoji.map_or_else(
+            || "Removed the member's emoji.".to_string(),
+            |emoji| format!("The member's emoji is now :{emoji}:"),
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
     #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
     async fn member_info(
         event: SlackCommandEvent,
         state: &SlackClientEventsUserState,
         member_ref: MemberRef,
+        format: Format,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         trace!("Running member info command");
 
@@ -376,40 +917,78 @@ impl Member {
             return Ok(SlackCommandEventResponse::new(
                 SlackMessageContent::new().with_text(format!(
                     "Member {} is not enabled. You can use `/members enable {}` to enable them.",
-                    member.full_name, member.id
+                    MemberReference::new(&member).with_full_name().mrkdwn(),
+                    member.id
                 )),
             ));
         }
 
-        let system_fronting_member_id = system_id
-            .currently_fronting_member_id(&user_state.db)
+        let fronting = system_id
+            .fronters(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+            .contains(&member.id);
+        let fields = models::MemberField::fetch_by_member_id(member.id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let json = render::json::member_card(&member, &fields, fronting, Viewer::Owner);
+        let blocks = render::member_card(member, &fields, fronting, Viewer::Owner);
+
+        Ok(format.respond(blocks, &json))
+    }
+
+    /// Responds with a menu of members, for commands run without saying which member.
+    ///
+    /// `switching` picks who to switch to, with the base account as an option. Otherwise it picks a member to show.
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn pick_member(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        switching: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Offering a member picker");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let members = system
+            .members(&user_state.db)
             .await
             .change_context(CommandError::Sqlx)?;
 
-        let blocks = slack_blocks![
-            some_into(SlackHeaderBlock::new(member.full_name.into())),
-            some_into(SlackDividerBlock::new()),
-            some_into(
-                SlackSectionBlock::new()
-                    .with_text(md!(
-                        "*{}*\n{}{}",
-                        member.display_name,
-                        member.pronouns.unwrap_or_default(),
-                        member
-                            .name_pronunciation
-                            .map(|pronunciation| format!(" - {pronunciation}"))
-                            .unwrap_or_default()
-                    ))
-                    .opt_accessory(member.profile_picture_url.and_then(|url| Some(
-                        SlackSectionBlockElement::Image(SlackBlockImageElement::new(
-                            url.parse().ok()?,
-                            "Profile picture".into()
-                        ))
-                    )))
-            ),
-            optionally_into(system_fronting_member_id.is_some_and(|id| id == member.id) => SlackSectionBlock::new().with_text(md!("*Fronting*")))
-            // TO-DO: fields
-        ];
+        // Switching to a member whose messages can't be proxied would be refused anyway
+        let members = members
+            .into_iter()
+            .filter(|member| !switching || member.proxy_enabled)
+            .collect::<Vec<_>>();
+
+        if members.is_empty() && !switching {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("You don't have any members yet!".into()),
+            ));
+        }
+
+        let blocks = if switching {
+            render::member_picker(
+                "Who's fronting?",
+                interactions::member::PICK_SWITCH_ACTION,
+                &members,
+                true,
+            )
+        } else {
+            render::member_picker(
+                "Which member?",
+                interactions::member::PICK_INFO_ACTION,
+                &members,
+                false,
+            )
+        };
 
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_blocks(blocks),
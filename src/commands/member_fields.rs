@@ -0,0 +1,127 @@
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::debug;
+
+use crate::{
+    fetch_member, fetch_system,
+    models::{MemberField, member::MemberRef, user},
+};
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Labelled fields on a member's profile, like their age, role or likes.
+///
+/// Fields are shown in /members info and on message info, in the order they were added.
+/// Birthdays have their own spot in /members edit.
+pub enum Fields {
+    /// Sets a field on a member, replacing it if the member already has it
+    Set {
+        /// The member to set the field on
+        member_id: MemberRef,
+        /// The field's name, as one word (e.g. age, role, likes)
+        name: String,
+        /// What the field says
+        #[clap(required = true, num_args = 1.., trailing_var_arg = true)]
+        value: Vec<String>,
+    },
+    /// Removes a field from a member
+    Remove {
+        /// The member to remove the field from
+        member_id: MemberRef,
+        /// The field's name
+        name: String,
+    },
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the database
+    Sqlx,
+}
+
+impl Fields {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        match self {
+            Self::Set {
+                member_id,
+                name,
+                value,
+            } => Self::set(event, &state, member_id, &name, &value.join(" ")).await,
+            Self::Remove { member_id, name } => Self::remove(event, &state, member_id, &name).await,
+        }
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id, member_id))]
+    async fn set(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        member_ref: MemberRef,
+        name: &str,
+        value: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Setting member field");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member_ref, user_state, system_id => member_id);
+
+        if name.chars().count() > MemberField::MAX_NAME_LENGTH
+            || value.chars().count() > MemberField::MAX_VALUE_LENGTH
+        {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "Field names can be up to {} characters, and values up to {}.",
+                    MemberField::MAX_NAME_LENGTH,
+                    MemberField::MAX_VALUE_LENGTH
+                )),
+            ));
+        }
+
+        MemberField::set(member_id, name, value, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Set *{name}* on member {member_id}. See it with `/members info {member_id}`."
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id, member_id))]
+    async fn remove(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        member_ref: MemberRef,
+        name: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Removing member field");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member_ref, user_state, system_id => member_id);
+
+        let removed = MemberField::remove(member_id, name, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let response = if removed {
+            format!("Removed *{name}* from member {member_id}.")
+        } else {
+            format!("Member {member_id} doesn't have a *{name}* field.")
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+}
@@ -5,25 +5,100 @@
 //! A command is internally handled by [`clap`], which parses the command line arguments and executes the corresponding command.
 //! This is a surprisingly effective way to handle slack slash commands, and provides a standard interface and documentation through help commands.
 
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
 
+mod admin;
 mod alias;
+mod autoproxy;
+mod blacklist;
+mod coalesce;
+mod debug;
+mod diagnose;
+mod ephemeral;
+mod import;
+mod managers;
 mod member;
+mod member_fields;
+mod selftest;
+mod settings;
 mod system;
 mod trigger;
 
+use admin::Admin;
 use alias::Alias;
 use axum::{Extension, Json};
-use clap::{Parser, error::ErrorKind};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, error::ErrorKind};
 use error_stack::ResultExt;
 use slack_morphism::prelude::*;
-use tracing::{Level, debug, error, trace};
+use tracing::{Level, debug, error, trace, warn};
 
 use member::Member;
 use system::System;
 use trigger::Trigger;
 
-use crate::fields;
+use crate::{
+    env, fields,
+    models::{CommandUsage, user},
+    ops, render, shutdown,
+};
+
+/// Prefix of the action IDs of the buttons that turn the page of a list
+pub const PAGE_ACTION_PREFIX: &str = "list_page_";
+
+/// How many entries a list shows per page. Messages can only have 50 blocks, and an entry is a block or two
+const PAGE_SIZE: usize = 20;
+
+/// The commands slash commands run when registered under a different name, from `COMMAND_NAMES`.
+///
+/// Self-hosters may register e.g. `/pk` instead of `/members`. Names not in here are expected to match a command.
+static COMMAND_NAMES: LazyLock<HashMap<String, String>> = LazyLock::new(|| {
+    let commands = Command::command();
+
+    env::command_names()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .filter_map(|pair| {
+            let Some((name, command)) = pair.split_once('=') else {
+                warn!(
+                    pair,
+                    "Ignoring malformed COMMAND_NAMES entry. Expected name=command"
+                );
+                return None;
+            };
+
+            let name = name.trim().trim_start_matches('/');
+            let command = command.trim().trim_start_matches('/');
+
+            if commands.find_subcommand(command).is_none() {
+                warn!(
+                    name,
+                    command, "Ignoring COMMAND_NAMES entry for a command that doesn't exist"
+                );
+                return None;
+            }
+
+            Some((name.to_string(), command.to_string()))
+        })
+        .collect()
+});
+
+/// How many custom command names are registered
+pub fn custom_command_names() -> usize {
+    COMMAND_NAMES.len()
+}
+
+/// The command a slash command runs, given the name it was registered under
+fn resolve_command_name(registered: &str) -> &str {
+    let registered = registered.trim_start_matches('/');
+
+    COMMAND_NAMES
+        .get(registered)
+        .map_or(registered, String::as_str)
+}
 
 #[derive(clap::Parser, Debug)]
 #[command(color(clap::ColorChoice::Never))]
@@ -38,6 +113,8 @@ enum Command {
     Aliases(Alias),
     /// Provides an explanation of this bot.
     Explain,
+    #[clap(subcommand, name = "plura-admin", hide = true)]
+    Admin(Admin),
 }
 
 impl Command {
@@ -66,9 +143,23 @@ impl Command {
                 .await
                 .change_context(CommandError::Aliases),
             Self::Explain => Ok(Self::explain()),
+            Self::Admin(admin) => admin
+                .run(event, client, state)
+                .await
+                .change_context(CommandError::Admin),
         }
     }
 
+    /// Whether this lists something, so it can be run again by the page buttons
+    const fn is_list(&self) -> bool {
+        matches!(
+            self,
+            Self::Members(Member::List { .. })
+                | Self::Triggers(Trigger::List { .. })
+                | Self::Aliases(Alias::List { .. })
+        )
+    }
+
     fn explain() -> SlackCommandEventResponse {
         SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text(
@@ -86,8 +177,153 @@ impl Command {
     }
 }
 
+/// How a list or info command shows its output
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Formatted Slack blocks
+    #[default]
+    Blocks,
+    /// Plain text, without any layout
+    Text,
+    /// A JSON code block, for scripting against the bot
+    Json,
+}
+
+impl Format {
+    /// Responds with `blocks`, or with `json` for [`Self::Json`]
+    pub fn respond(
+        self,
+        blocks: Vec<SlackBlock>,
+        json: &serde_json::Value,
+    ) -> SlackCommandEventResponse {
+        let content = match self {
+            Self::Blocks => SlackMessageContent::new().with_blocks(blocks),
+            Self::Text => SlackMessageContent::new().with_text(render::plain_text(&blocks)),
+            Self::Json => SlackMessageContent::new().with_text(format!(
+                "```\n{}\n```",
+                serde_json::to_string_pretty(json).unwrap_or_default()
+            )),
+        };
+
+        SlackCommandEventResponse::new(content)
+    }
+
+    /// Responds with one page of `entries`, or with all of `json` for [`Self::Json`].
+    ///
+    /// Each entry is the blocks of one list item, so an item is never split across pages.
+    /// `command` is the list command without `--page`, like `triggers list 12`. The page buttons run it again.
+    pub fn respond_paged(
+        self,
+        entries: Vec<Vec<SlackBlock>>,
+        json: &serde_json::Value,
+        page: u16,
+        command: &str,
+    ) -> SlackCommandEventResponse {
+        if self == Self::Json {
+            return self.respond(entries.concat(), json);
+        }
+
+        let (entries, page, pages) = page_of(entries, page);
+        let mut blocks = entries.concat();
+
+        if pages > 1 {
+            blocks.push(
+                SlackContextBlock::new(vec![md!(
+                    "Page {} of {}. Use `--page` to jump to another page.",
+                    page,
+                    pages
+                )])
+                .into(),
+            );
+
+            if self == Self::Blocks {
+                let buttons = [
+                    (page > 1).then(|| page_button("previous", "Previous", command, page - 1)),
+                    (page < pages).then(|| page_button("next", "Next", command, page + 1)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+
+                blocks.push(SlackActionsBlock::new(buttons).into());
+            }
+        }
+
+        self.respond(blocks, json)
+    }
+}
+
+/// Picks page `page` of `entries`, clamped to the pages there are. Returns the page, its number and the page count
+fn page_of<T>(entries: Vec<T>, page: u16) -> (Vec<T>, usize, usize) {
+    let pages = entries.len().div_ceil(PAGE_SIZE).max(1);
+    let page = usize::from(page).clamp(1, pages);
+
+    let entries = entries
+        .into_iter()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .collect();
+
+    (entries, page, pages)
+}
+
+/// A button that runs `command` again for another page
+fn page_button(id: &str, label: &str, command: &str, page: usize) -> SlackActionBlockElement {
+    SlackBlockButtonElement::new(format!("{PAGE_ACTION_PREFIX}{id}").into(), pt!("{}", label))
+        .with_value(format!("{command} --page {page}"))
+        .into()
+}
+
+/// Handles the page buttons of a list by running the list again and replacing it with the other page
+#[tracing::instrument(skip(event, client, state))]
+pub async fn turn_page(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    client: Arc<SlackHyperClient>,
+    state: SlackClientEventsUserState,
+) -> error_stack::Result<(), CommandError> {
+    let (Some(user), Some(channel), Some(response_url)) =
+        (&event.user, &event.channel, &event.response_url)
+    else {
+        warn!("Page button is missing its user, channel or response URL. Bailing");
+        return Ok(());
+    };
+
+    // Only lists are run again, so a crafted button can't do anything the user didn't ask for
+    let command = match Command::try_parse_from(format!("plura {value}").split_whitespace()) {
+        Ok(command) if command.is_list() => command,
+        _ => {
+            warn!(value, "Page button isn't for a list. Bailing");
+            return Ok(());
+        }
+    };
+
+    let name = value.split_whitespace().next().unwrap_or_default();
+    let command_event = SlackCommandEvent::new(
+        event.team.id.clone(),
+        channel.id.clone(),
+        user.id.clone(),
+        SlackCommandId::new(format!("/{name}")),
+        response_url.clone(),
+        event.trigger_id.clone(),
+    )
+    .with_text(value.to_string());
+
+    let response = command.run(command_event, client.clone(), state).await?;
+
+    client
+        .respond_to_event(
+            response_url,
+            &SlackApiPostWebhookMessageRequest::new(response.content).with_replace_original(true),
+        )
+        .await
+        .change_context(CommandError::Slack)?;
+
+    Ok(())
+}
+
 #[derive(thiserror::Error, displaydoc::Display, Debug)]
-enum CommandError {
+pub enum CommandError {
     /// Error running the members command
     Members,
     /// Error running the triggers command
@@ -96,6 +332,10 @@ enum CommandError {
     System,
     /// Error running the aliases command
     Aliases,
+    /// Error running an admin command
+    Admin,
+    /// Error while calling the Slack API
+    Slack,
 }
 
 // TO-DO: figure out error handling
@@ -104,17 +344,38 @@ pub async fn process_command_event(
     Extension(environment): Extension<Arc<SlackHyperListenerEnvironment>>,
     Extension(event): Extension<SlackCommandEvent>,
 ) -> Json<SlackCommandEventResponse> {
-    let client = environment.client.clone();
-    let state = environment.user_state.clone();
+    Json(
+        handle_command_event(
+            event,
+            environment.client.clone(),
+            environment.user_state.clone(),
+        )
+        .await,
+    )
+}
+
+/// Runs a slash command, however it arrived. Errors are reported, and the user told something went wrong
+pub async fn handle_command_event(
+    event: SlackCommandEvent,
+    client: Arc<SlackHyperClient>,
+    state: SlackClientEventsUserState,
+) -> SlackCommandEventResponse {
+    let Some(_in_flight) = shutdown::track() else {
+        return SlackCommandEventResponse::new(
+            SlackMessageContent::new()
+                .with_text("The bot is restarting. Try again in a moment.".into()),
+        );
+    };
 
     match command_event_callback(event, client, state).await {
-        Ok(response) => Json(response),
+        Ok(response) => response,
         Err(e) => {
             error!(error = ?e, "Error processing command event");
-            Json(SlackCommandEventResponse::new(
+            ops::report("command", &e);
+            SlackCommandEventResponse::new(
                 SlackMessageContent::new()
                     .with_text("Error processing command! Logged to developers".into()),
-            ))
+            )
         }
     }
 }
@@ -127,7 +388,7 @@ async fn command_event_callback(
 ) -> Result<SlackCommandEventResponse, CommandError> {
     trace!(command = ?event.command, "Received command");
 
-    let formatted_command = event.command.0.trim_start_matches('/');
+    let formatted_command = resolve_command_name(&event.command.0);
     let formatted = event.text.as_ref().map_or_else(
         || format!("plura {formatted_command}"),
         |text| format!("plura {formatted_command} {text}"),
@@ -135,11 +396,32 @@ async fn command_event_callback(
 
     fields!(command = &formatted);
 
-    let parser = Command::try_parse_from(formatted.split_whitespace());
+    let Some(_running) = coalesce::start(&event.user_id, &formatted) else {
+        debug!("Same command was just run by this user. Ignoring");
+        return Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text("Already on it!".into()),
+        ));
+    };
+
+    let parser = Command::command()
+        .try_get_matches_from(formatted.split_whitespace())
+        .and_then(|matches| {
+            Command::from_arg_matches(&matches).map(|parser| (parser, subcommand_path(&matches)))
+        });
 
     match parser {
-        Ok(parser) => {
+        Ok((parser, path)) => {
             debug!(?parser, "Parsed command. Running...");
+
+            {
+                let states = state.read().await;
+                let user_state = states.get_user_state::<user::State>().unwrap();
+
+                if let Err(error) = CommandUsage::record(&path, &user_state.db).await {
+                    warn!(?error, "Failed to record command usage");
+                }
+            }
+
             let result = parser.run(event, client, state).await;
             match result {
                 Ok(res) => {
@@ -148,6 +430,7 @@ async fn command_event_callback(
                 }
                 Err(e) => {
                     error!(error = ?e, "Error running command");
+                    ops::report("command", &e);
                     Ok(SlackCommandEventResponse::new(
                         SlackMessageContent::new().with_text(
                             "Error running command! TODO: show error info on slack".into(),
@@ -175,3 +458,73 @@ async fn command_event_callback(
         }
     }
 }
+
+/// The subcommands a command was parsed into, e.g. `members add`, without any of its arguments
+fn subcommand_path(matches: &ArgMatches) -> String {
+    let mut path = Vec::new();
+    let mut matches = matches;
+
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        path.push(name);
+        matches = sub_matches;
+    }
+
+    path.join(" ")
+}
+
+/// Parses a channel from a command argument, either an escaped mention like `<#C123|general>` or a bare channel ID.
+///
+/// Slack only escapes channel mentions if the command has "Escape channels, users, and links" turned on.
+pub fn parse_channel(channel: &str) -> Option<SlackChannelId> {
+    let id = channel
+        .strip_prefix("<#")
+        .and_then(|channel| channel.strip_suffix('>'))
+        .and_then(|channel| channel.split('|').next())
+        .unwrap_or(channel);
+
+    (!id.is_empty() && id.starts_with(['C', 'G']) && id.chars().all(|c| c.is_ascii_alphanumeric()))
+        .then(|| SlackChannelId::new(id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> Result<Command, clap::Error> {
+        Command::try_parse_from(format!("plura {command}").split_whitespace())
+    }
+
+    #[test]
+    fn bare_switch_picks_from_a_list() {
+        assert!(matches!(
+            parse("members switch"),
+            Ok(Command::Members(Member::Switch { member_ids, base: false, .. })) if member_ids.is_empty()
+        ));
+    }
+
+    #[test]
+    fn switch_to_members_or_base() {
+        assert!(matches!(
+            parse("members switch --base"),
+            Ok(Command::Members(Member::Switch { base: true, .. }))
+        ));
+        assert!(parse("members switch alex --base").is_err());
+        assert!(parse("members switch --log-only").is_err());
+    }
+
+    #[test]
+    fn pages_count_entries() {
+        let entries = (0..45).map(|entry| vec![entry, entry]).collect::<Vec<_>>();
+
+        let (first, page, pages) = page_of(entries.clone(), 1);
+        assert_eq!((first.len(), page, pages), (PAGE_SIZE, 1, 3));
+        assert!(first.iter().all(|entry| entry.len() == 2));
+
+        let (last, page, _) = page_of(entries.clone(), 7);
+        assert_eq!((last.first(), page), (Some(&vec![40, 40]), 3));
+        assert_eq!(last.len(), 5);
+
+        let (empty, page, pages) = page_of(Vec::<Vec<u8>>::new(), 0);
+        assert_eq!((empty.len(), page, pages), (0, 1, 1));
+    }
+}
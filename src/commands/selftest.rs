@@ -0,0 +1,192 @@
+//! `/plura-admin selftest`: runs the Slack calls proxying relies on against a test channel, and times each one.
+//!
+//! Meant for checking a deploy, or whether a Slack API change broke something, without waiting for users to notice.
+//! The test channel is set with `SELFTEST_CHANNEL`, and the bot has to be in it.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use slack_morphism::prelude::*;
+use tracing::{debug, info};
+
+use crate::BOT_TOKEN;
+
+/// How a step went
+pub struct Step {
+    name: &'static str,
+    outcome: Outcome,
+}
+
+enum Outcome {
+    Passed(Duration),
+    /// The Slack error code, or the error itself if Slack didn't respond with one
+    Failed(Duration, String),
+    /// An earlier step failed, so there was nothing to run this one on
+    Skipped,
+}
+
+impl Step {
+    fn passed(&self) -> bool {
+        matches!(self.outcome, Outcome::Passed(_))
+    }
+
+    fn render(&self) -> String {
+        match &self.outcome {
+            Outcome::Passed(elapsed) => {
+                format!(
+                    ":white_check_mark: {}: {}ms",
+                    self.name,
+                    elapsed.as_millis()
+                )
+            }
+            Outcome::Failed(elapsed, error) => format!(
+                ":x: {}: failed after {}ms (`{error}`)",
+                self.name,
+                elapsed.as_millis()
+            ),
+            Outcome::Skipped => format!(":heavy_minus_sign: {}: skipped", self.name),
+        }
+    }
+}
+
+/// Runs a step, returning how it went and its result if it passed
+async fn time<T: Send>(
+    name: &'static str,
+    call: impl Future<Output = std::result::Result<T, SlackClientError>> + Send,
+) -> (Step, Option<T>) {
+    let started = Instant::now();
+    let result = call.await;
+    let elapsed = started.elapsed();
+
+    debug!(
+        step = name,
+        ?elapsed,
+        ok = result.is_ok(),
+        "Ran self-test step"
+    );
+
+    match result {
+        Ok(value) => (
+            Step {
+                name,
+                outcome: Outcome::Passed(elapsed),
+            },
+            Some(value),
+        ),
+        Err(error) => {
+            let error = match error {
+                SlackClientError::ApiError(error) => error.code,
+                error => error.to_string(),
+            };
+
+            (
+                Step {
+                    name,
+                    outcome: Outcome::Failed(elapsed, error),
+                },
+                None,
+            )
+        }
+    }
+}
+
+const fn skipped(name: &'static str) -> Step {
+    Step {
+        name,
+        outcome: Outcome::Skipped,
+    }
+}
+
+/// Posts, edits and deletes a message in `channel_id`, then opens a modal for the operator who ran the test
+#[tracing::instrument(skip(client, trigger_id))]
+pub async fn run(
+    client: &SlackHyperClient,
+    channel_id: SlackChannelId,
+    trigger_id: SlackTriggerId,
+) -> Vec<Step> {
+    let session = client.open_session(&BOT_TOKEN);
+    let mut steps = Vec::new();
+
+    let (step, posted) = time(
+        "Post message",
+        session.chat_post_message(&SlackApiChatPostMessageRequest::new(
+            channel_id.clone(),
+            SlackMessageContent::new()
+                .with_text("Plura self-test. This message will be deleted in a moment.".into()),
+        )),
+    )
+    .await;
+    steps.push(step);
+
+    if let Some(posted) = posted {
+        let (step, _) = time(
+            "Edit message",
+            session.chat_update(&SlackApiChatUpdateRequest::new(
+                channel_id.clone(),
+                SlackMessageContent::new().with_text(
+                    "Plura self-test (edited). This message will be deleted in a moment.".into(),
+                ),
+                posted.ts.clone(),
+            )),
+        )
+        .await;
+        steps.push(step);
+
+        let (step, _) = time(
+            "Delete message",
+            session.chat_delete(&SlackApiChatDeleteRequest::new(channel_id, posted.ts)),
+        )
+        .await;
+        steps.push(step);
+    } else {
+        steps.push(skipped("Edit message"));
+        steps.push(skipped("Delete message"));
+    }
+
+    let (step, _) = time(
+        "Open modal",
+        session.views_open(&SlackApiViewsOpenRequest::new(
+            trigger_id,
+            SlackView::Modal(
+                SlackModalView::new(
+                    "Self-test".into(),
+                    slack_blocks![some_into(SlackSectionBlock::new().with_text(md!(
+                        "If you can see this, opening modals works. You can close it."
+                    )))],
+                )
+                .with_close("Close".into()),
+            ),
+        )),
+    )
+    .await;
+    steps.push(step);
+
+    info!(
+        passed = steps.iter().filter(|step| step.passed()).count(),
+        total = steps.len(),
+        "Self-test finished"
+    );
+
+    steps
+}
+
+/// Renders the results of a self-test
+pub fn render(steps: &[Step]) -> SlackMessageContent {
+    let summary = if steps.iter().all(Step::passed) {
+        "*Self-test passed*"
+    } else {
+        "*Self-test failed*"
+    };
+
+    let lines = steps
+        .iter()
+        .map(Step::render)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    SlackMessageContent::new().with_blocks(slack_blocks![some_into(
+        SlackSectionBlock::new().with_text(md!("{}\n{}", summary, lines))
+    )])
+}
@@ -0,0 +1,553 @@
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use time_tz::timezones;
+use tracing::{debug, info};
+
+use crate::{
+    fetch_system,
+    models::{
+        System,
+        system::{NotificationPreference, Visibility},
+        user,
+    },
+};
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// Settings that change how the bot behaves for your system.
+///
+/// Also see:
+/// - /system info to view your system
+pub enum Settings {
+    /// Require your approval before other users can see who sent a member's message.
+    ///
+    /// When enabled, using "Message info" on one of your members' messages will only show the member.
+    /// You will be sent a prompt to approve or deny showing your account to the person who asked.
+    MessageInfoConsent {
+        /// Whether consent is required (on/off)
+        #[clap(value_parser = clap::builder::BoolishValueParser::new(), action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Set a prefix that sends a message anonymously.
+    ///
+    /// Anonymous messages are posted under a neutral name and avatar, and aren't linked to any member.
+    /// Only you can see that an anonymous message was yours through "Message info".
+    /// Leave the prefix blank to turn off anonymous messages.
+    AnonymousPrefix {
+        /// The prefix (e.g. "anon:")
+        prefix: Option<String>,
+    },
+    /// Set a tag added to your members' names when proxying, like PluralKit's system tags.
+    ///
+    /// By default the tag goes after the name (e.g. "Alice 🌸"). Use /system settings name-format to put it elsewhere.
+    /// Leave blank to remove the tag. Anonymous messages never show the tag.
+    Tag {
+        /// The tag (e.g. "🌸" or "| The Garden")
+        tag: Option<String>,
+    },
+    /// Set how your members' names are shown when proxying.
+    ///
+    /// Use {name} for the member's display name and {system_tag} for your system's tag (e.g. "{system_tag} {name}").
+    /// Leave blank to go back to the default, "{name} {system_tag}".
+    NameFormat {
+        /// The format. Must include {name}
+        format: Option<String>,
+    },
+    /// Set your system's timezone, used for quiet hours.
+    ///
+    /// Use a timezone name like "Europe/London" or "America/New_York". Leave blank to use UTC.
+    Timezone {
+        /// The timezone name
+        timezone: Option<String>,
+    },
+    /// Set hours during which your messages won't be proxied (e.g. 9 17 for work hours).
+    ///
+    /// Hours are in your system's timezone (see /system settings timezone), from 0 to 23.
+    /// The window can wrap around midnight (e.g. 22 6). Leave both blank to turn off quiet hours.
+    QuietHours {
+        /// The hour quiet hours start
+        #[clap(value_parser = clap::value_parser!(u8).range(0..24), requires = "end")]
+        start: Option<u8>,
+        /// The hour quiet hours end
+        #[clap(value_parser = clap::value_parser!(u8).range(0..24))]
+        end: Option<u8>,
+    },
+    /// Suggest members to proxy as when no one is fronting.
+    ///
+    /// When enabled, sending a message without a trigger while no one is fronting shows you a private prompt
+    /// with buttons to proxy it as one of your most recently used members.
+    SuggestMembers {
+        /// Whether to suggest members (on/off)
+        #[clap(value_parser = clap::builder::BoolishValueParser::new(), action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Keep your original messages until you confirm the proxied message looks right.
+    ///
+    /// When enabled, the bot posts the proxied message and leaves your original in place, with a private prompt
+    /// to delete the original or undo the proxy. Useful if a trigger has ever caught a message it shouldn't have.
+    HoldBack {
+        /// Whether to hold back originals (on/off)
+        #[clap(value_parser = clap::builder::BoolishValueParser::new(), action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Wait a moment after posting a proxied message before deleting your original.
+    ///
+    /// Some Slack clients flicker or briefly show the messages out of order when they're swapped at once.
+    /// A delay of a few hundred milliseconds usually smooths this out. Set to 0 to delete originals immediately.
+    DeleteDelay {
+        /// How long to wait, in milliseconds (0 to 2000)
+        #[clap(value_parser = clap::value_parser!(u16).range(0..=2000))]
+        milliseconds: u16,
+    },
+    /// Choose how many days the bot remembers who sent your proxied messages.
+    ///
+    /// Older messages stay in Slack, but can no longer be edited, deleted or looked up through the bot.
+    /// Messages in ephemeral channels are kept until they're deleted. Leave blank to remember messages forever.
+    Retention {
+        /// How many days to remember messages for
+        #[clap(value_parser = clap::value_parser!(u16).range(1..))]
+        days: Option<u16>,
+    },
+    /// Choose whether other people can look up your system.
+    ///
+    /// Private systems only show up in /system info and /members list for you.
+    /// Everyone else is told the system is private. Proxied messages still show the member who sent them.
+    Visibility {
+        /// Who can look up your system
+        visibility: Visibility,
+    },
+    /// Choose which notifications the bot DMs you.
+    ///
+    /// Errors are things like a message failing to proxy. Other notifications confirm things like adding a member.
+    Notifications {
+        /// Which notifications to receive
+        preference: NotificationPreference,
+    },
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the database
+    Sqlx,
+}
+
+impl Settings {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        match self {
+            Self::MessageInfoConsent { enabled } => {
+                Self::message_info_consent(event, &state, enabled).await
+            }
+            Self::AnonymousPrefix { prefix } => Self::anonymous_prefix(event, &state, prefix).await,
+            Self::Tag { tag } => Self::tag(event, &state, tag).await,
+            Self::NameFormat { format } => Self::name_format(event, &state, format).await,
+            Self::Timezone { timezone } => Self::timezone(event, &state, timezone).await,
+            Self::QuietHours { start, end } => {
+                Self::quiet_hours(event, &state, start.zip(end)).await
+            }
+            Self::SuggestMembers { enabled } => Self::suggest_members(event, &state, enabled).await,
+            Self::HoldBack { enabled } => Self::hold_back(event, &state, enabled).await,
+            Self::DeleteDelay { milliseconds } => {
+                Self::delete_delay(event, &state, milliseconds).await
+            }
+            Self::Retention { days } => Self::retention(event, &state, days).await,
+            Self::Visibility { visibility } => Self::visibility(event, &state, visibility).await,
+            Self::Notifications { preference } => {
+                Self::notifications(event, &state, preference).await
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn message_info_consent(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        enabled: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing message info consent setting");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_message_info_consent(enabled, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(enabled, "Updated message info consent setting");
+
+        let response = if enabled {
+            "Other users will now need your approval to see who sent your members' messages."
+        } else {
+            "Anyone can now see who sent your members' messages."
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response.into()),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn anonymous_prefix(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        prefix: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing anonymous prefix");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let response = prefix.as_ref().map_or_else(
+            || "Anonymous messages turned off.".to_string(),
+            |prefix| format!("Messages starting with `{prefix}` will now be sent anonymously."),
+        );
+
+        system_id
+            .set_anonymous_prefix(prefix, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!("Updated anonymous prefix");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn tag(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        tag: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing system tag");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let response = tag.as_ref().map_or_else(
+            || "Your system tag has been removed.".to_string(),
+            |tag| format!("Your members' names will now be tagged with `{tag}`."),
+        );
+
+        system_id
+            .set_system_tag(tag, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!("Updated system tag");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn name_format(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        format: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing name format");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        if let Some(ref format) = format
+            && !format.contains("{name}")
+        {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "`{format}` doesn't include `{{name}}`, so your members' names wouldn't be shown."
+                )),
+            ));
+        }
+
+        let response = format!(
+            "Your members' names will now be shown as `{}`.",
+            format.as_deref().unwrap_or(System::DEFAULT_NAME_FORMAT)
+        );
+
+        system_id
+            .set_name_format(format, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!("Updated name format");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn timezone(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        timezone: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing timezone");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        if let Some(ref timezone) = timezone
+            && timezones::get_by_name(timezone).is_none()
+        {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "Unknown timezone `{timezone}`. Use a name like `Europe/London`."
+                )),
+            ));
+        }
+
+        let response = format!(
+            "Your system's timezone is now {}.",
+            timezone.as_deref().unwrap_or("UTC")
+        );
+
+        system_id
+            .set_timezone(timezone, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!("Updated timezone");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn quiet_hours(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        hours: Option<(u8, u8)>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing quiet hours");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        if hours.is_some_and(|(start, end)| start == end) {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("Quiet hours need to start and end at different hours.".into()),
+            ));
+        }
+
+        system_id
+            .set_quiet_hours(hours, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(?hours, "Updated quiet hours");
+
+        let response = hours.map_or_else(
+            || "Quiet hours turned off.".to_string(),
+            |(start, end)| {
+                format!("Your messages won't be proxied between {start}:00 and {end}:00.")
+            },
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn suggest_members(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        enabled: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing member suggestions setting");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_suggest_members(enabled, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(enabled, "Updated member suggestions setting");
+
+        let response = if enabled {
+            "You'll now be offered members to proxy as when no one is fronting."
+        } else {
+            "You won't be offered members to proxy as anymore."
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response.into()),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn hold_back(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        enabled: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing hold back setting");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_hold_back(enabled, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(enabled, "Updated hold back setting");
+
+        let response = if enabled {
+            "Your original messages will now be kept until you confirm the proxied message looks right."
+        } else {
+            "Your original messages will now be deleted as soon as they're proxied."
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response.into()),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn delete_delay(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        milliseconds: u16,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing delete delay");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_delete_delay(milliseconds, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(milliseconds, "Updated delete delay");
+
+        let response = if milliseconds == 0 {
+            "Your original messages will now be deleted as soon as they're proxied.".to_string()
+        } else {
+            format!(
+                "Your original messages will now be deleted {milliseconds}ms after they're proxied."
+            )
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn retention(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        days: Option<u16>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing message log retention");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_log_retention(days, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(?days, "Updated message log retention");
+
+        let response = days.map_or_else(
+            || "Your proxied messages will be remembered forever.".to_string(),
+            |days| {
+                format!(
+                    "Your proxied messages will be forgotten after {days} day{}.",
+                    if days == 1 { "" } else { "s" }
+                )
+            },
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn visibility(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        visibility: Visibility,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing system visibility");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_visibility(visibility, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(?visibility, "Updated system visibility");
+
+        let response = match visibility {
+            Visibility::Public => "Anyone can now look up your system.",
+            Visibility::Private => "Your system is now private. Only you can look it up.",
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response.into()),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn notifications(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        preference: NotificationPreference,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Changing notification preference");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_notification_preference(preference, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!(?preference, "Updated notification preference");
+
+        let response = match preference {
+            NotificationPreference::Always => "You'll now be notified about everything.",
+            NotificationPreference::Errors => "You'll now only be notified about errors.",
+            NotificationPreference::Never => "You won't get any notifications.",
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response.into()),
+        ))
+    }
+}
@@ -4,15 +4,23 @@ use std::sync::Arc;
 use error_stack::{Result, ResultExt};
 use oauth2::CsrfToken;
 use slack_morphism::prelude::*;
-use tokio::runtime::Handle;
-use tracing::{debug, trace};
+use time::OffsetDateTime;
+use time_tz::TimeZone;
+use tracing::{debug, info, trace};
 
+use super::{
+    Format, autoproxy::Autoproxy, blacklist::Blacklist, diagnose, ephemeral::Ephemeral,
+    import::Import, managers::Managers, parse_channel, settings::Settings,
+};
 use crate::{
-    fields,
-    models::{self, user},
-    oauth::create_oauth_client,
+    BOT_TOKEN, api, backfill, env, fields, interactions,
+    models::{self, Switch, export, switch, user, viewer::Viewer},
+    oauth, render, upload,
 };
 
+/// The longest switch reminder interval: 30 days
+const MAX_REMINDER_HOURS: u16 = 30 * 24;
+
 #[derive(clap::Subcommand, Debug)]
 #[clap(verbatim_doc_comment)]
 /// A system is your plural system: a collection of members/profiles.
@@ -32,6 +40,82 @@ pub enum System {
     Info {
         /// The user to get info about (if left blank, defaults to you)
         user: Option<String>,
+        /// How to show the output: blocks, text or json
+        #[clap(long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Change your system's settings
+    #[clap(subcommand)]
+    Settings(Settings),
+    /// Manage users who can see more of your system
+    #[clap(subcommand)]
+    Managers(Managers),
+    /// Choose who messages without a trigger are sent as
+    #[clap(subcommand)]
+    Autoproxy(Autoproxy),
+    /// Manage channels where your messages are never proxied
+    #[clap(subcommand)]
+    Blacklist(Blacklist),
+    /// Turns proxying on or off for all of your messages
+    ///
+    /// While proxying is off, your messages are left exactly as you sent them. Your triggers, autoproxy and fronters are kept,
+    /// so turning it back on picks up where you left off.
+    Proxy {
+        /// Whether your messages are proxied (on/off)
+        #[clap(value_parser = clap::builder::BoolishValueParser::new(), action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Creates a token for sending messages as your members from other apps, replacing any token you had before
+    ///
+    /// Anyone with the token can post as your members in channels you're in, so keep it secret.
+    /// Use --revoke to turn the token off without making a new one.
+    ApiToken {
+        /// Turn off your current token instead of making a new one
+        #[clap(long, action)]
+        revoke: bool,
+    },
+    /// Manage channels where your proxied messages are deleted after a while
+    #[clap(subcommand)]
+    Ephemeral(Ephemeral),
+    /// Checks your setup for common problems, like an expired token or the bot missing from this channel
+    Diagnose,
+    /// Applies your triggers to messages you sent in a channel before setting them up
+    ///
+    /// By default, matching messages are only recorded as sent by the member (e.g. for message info).
+    /// Use --proxy to repost them under the member instead. Reposted messages appear at the bottom of the channel.
+    /// This runs slowly in the background, and you'll be DMed as it progresses.
+    Backfill {
+        /// The channel to backfill (e.g. #general)
+        channel: String,
+        /// How many days back to look
+        #[clap(value_parser = clap::value_parser!(u16).range(1..=i64::from(backfill::MAX_DAYS)))]
+        days: u16,
+        /// Repost matching messages under the member instead of only recording them
+        #[clap(long, action)]
+        proxy: bool,
+    },
+    /// Imports members from another bot
+    #[clap(subcommand)]
+    Import(Import),
+    /// Sends you a JSON file with your members, triggers, aliases and settings, e.g. for backups
+    Export,
+    /// Permanently deletes your system, all of its members, and everything else the bot has stored about you
+    ///
+    /// Expect a popup to confirm! This also signs the bot out of your Slack account. Run /system export first for a copy.
+    Delete,
+    /// DMs you when you haven't logged a switch in a while, e.g. if you use switches as a journal
+    ///
+    /// Give an interval in hours or days (e.g. 6h, 2d, or 12 for 12 hours), or "off" to stop reminders.
+    /// You're reminded again every interval until you switch.
+    Remind {
+        /// How long without a switch before you're reminded, or "off"
+        interval: String,
+    },
+    /// Shows your most recent switches, newest first
+    History {
+        /// How many switches to show
+        #[clap(default_value_t = 10, value_parser = clap::value_parser!(u16).range(1..=i64::from(switch::MAX_HISTORY)))]
+        limit: u16,
     },
 }
 
@@ -39,6 +123,26 @@ pub enum System {
 pub enum CommandError {
     /// Error while calling the database
     Sqlx,
+    /// Error running the settings command
+    Settings,
+    /// Error running the managers command
+    Managers,
+    /// Error running the autoproxy command
+    Autoproxy,
+    /// Error running the blacklist command
+    Blacklist,
+    /// Error running the ephemeral command
+    Ephemeral,
+    /// Error running the import command
+    Import,
+    /// Error while exporting the system
+    Export,
+    /// Error while uploading the export
+    Upload,
+    /// Error while working out who is viewing the system
+    Viewer,
+    /// Error while calling the Slack API
+    SlackApi,
 }
 
 impl System {
@@ -51,16 +155,220 @@ impl System {
     ) -> Result<SlackCommandEventResponse, CommandError> {
         match self {
             Self::Create => Self::create_system(event, state).await,
-            Self::Info { user } => Self::get_system_info(event, client, state, user).await,
+            Self::Info { user, format } => {
+                Self::get_system_info(event, client, state, user, format).await
+            }
             Self::Reauth => Self::reauth(event, state).await,
+            Self::Settings(settings) => settings
+                .run(event, state)
+                .await
+                .change_context(CommandError::Settings),
+            Self::Managers(managers) => managers
+                .run(event, client, state)
+                .await
+                .change_context(CommandError::Managers),
+            Self::Autoproxy(autoproxy) => autoproxy
+                .run(event, state)
+                .await
+                .change_context(CommandError::Autoproxy),
+            Self::Blacklist(blacklist) => blacklist
+                .run(event, state)
+                .await
+                .change_context(CommandError::Blacklist),
+            Self::Ephemeral(ephemeral) => ephemeral
+                .run(event, state)
+                .await
+                .change_context(CommandError::Ephemeral),
+            Self::Proxy { enabled } => Self::proxy(event, &state, enabled).await,
+            Self::ApiToken { revoke } => Self::api_token(event, &state, revoke).await,
+            Self::Diagnose => Self::diagnose(event, client, state).await,
+            Self::Backfill {
+                channel,
+                days,
+                proxy,
+            } => Self::backfill(event, client, state, &channel, days, proxy).await,
+            Self::Import(import) => import
+                .run(event, client, state)
+                .await
+                .change_context(CommandError::Import),
+            Self::Export => Self::export(event, client, state).await,
+            Self::History { limit } => Self::history(event, state, limit).await,
+            Self::Remind { interval } => Self::remind(event, &state, &interval).await,
+            Self::Delete => Self::delete(event, client, state).await,
         }
     }
 
-    async fn reauth(
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn proxy(
         event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        enabled: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running system proxy command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_proxy_enabled(enabled, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        debug!(enabled, "Updated system proxy status");
+
+        let response = if enabled {
+            "Proxying is back on."
+        } else {
+            "Proxying is off. Your messages will be left as you send them until you run `/system proxy on`."
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response.into()),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn remind(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        interval: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running system remind command");
+
+        let Ok(hours) = parse_reminder_interval(interval) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "Couldn't read `{interval}` as an interval. Try something like `6h` or `2d` (up to 30 days), or `off`."
+                )),
+            ));
+        };
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        system_id
+            .set_switch_reminder(hours, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        debug!(?hours, "Updated switch reminder");
+
+        let response = hours.map_or_else(
+            || "Switch reminders are off.".to_string(),
+            |hours| {
+                format!(
+                    "You'll be reminded to log a switch if you haven't switched in {hours} hours."
+                )
+            },
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn api_token(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        revoke: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running system api-token command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        if revoke {
+            system_id
+                .set_api_token(None, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            info!("Revoked API token");
+
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Your API token has been turned off.".into()),
+            ));
+        }
+
+        let token = format!(
+            "{}{}",
+            api::TOKEN_PREFIX,
+            CsrfToken::new_random_len(32).secret()
+        );
+
+        system_id
+            .set_api_token(Some(&token), &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        info!("Created API token");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(slack_blocks![
+                some_into(SlackSectionBlock::new().with_text(md!(
+                    "Here's your new API token. It won't be shown again, and any token you had before no longer works.\n```{}```",
+                    token
+                ))),
+                some_into(SlackContextBlock::new(vec![md!(
+                    "Send messages with `POST {}/api/v1/systems/{}/proxy` and the token as a bearer token. The body is JSON like `{{\"channel\": \"C0123456\", \"member\": \"<ID or alias>\", \"text\": \"Hi!\"}}`",
+                    env::base_url(),
+                    system_id
+                )]))
+            ]),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(system_id))]
+    async fn delete(
+        event: SlackCommandEvent,
+        client: Arc<SlackHyperClient>,
         state: SlackClientEventsUserState,
     ) -> Result<SlackCommandEventResponse, CommandError> {
-        trace!("Reauthenticating system");
+        trace!("Running system delete command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let members = system
+            .members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        client
+            .open_session(&BOT_TOKEN)
+            .views_open(&SlackApiViewsOpenRequest::new(
+                event.trigger_id.clone(),
+                interactions::system::delete_view(members.len()),
+            ))
+            .await
+            .attach_printable("Error opening view")
+            .change_context(CommandError::SlackApi)?;
+
+        debug!("Opened system delete view");
+
+        Ok(SlackCommandEventResponse::new(SlackMessageContent::new()))
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(system_id))]
+    async fn export(
+        event: SlackCommandEvent,
+        client: Arc<SlackHyperClient>,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Exporting system");
 
         let states = state.read().await;
         let user_state = states.get_user_state::<user::State>().unwrap();
@@ -70,29 +378,164 @@ impl System {
             .fetch(&user_state.db)
             .await
             .change_context(CommandError::Sqlx)?;
-        let oauth_client = create_oauth_client();
-
-        let (auth_url, csrf_token) = oauth_client
-            .authorize_url(CsrfToken::new_random)
-            // So we get a regular token as well. Required by oauth2 for some reason
-            .add_extra_param("scope", "commands")
-            .add_extra_param("user_scope", "users.profile:read,chat:write")
-            .url();
-
-        let secret = csrf_token.secret();
-
-        sqlx::query!(
-            r#"
-            INSERT INTO system_oauth_process (owner_id, csrf)
-            VALUES ($1, $2)
-            ON CONFLICT (owner_id) DO UPDATE SET csrf = $2
-            "#,
-            system.owner_id,
-            secret
+
+        let json = export::Export::collect(&system, &user_state.db)
+            .await
+            .and_then(|export| export.to_json())
+            .change_context(CommandError::Export)?;
+
+        let filename = format!("plura-export-{}.json", OffsetDateTime::now_utc().date());
+
+        upload::send_to_dm(
+            &client,
+            &event.user_id,
+            upload::File {
+                filename: &filename,
+                content_type: "application/json",
+                data: json.into_bytes(),
+            },
+            "Here's your system export.",
         )
-        .execute(&user_state.db)
         .await
-        .change_context(CommandError::Sqlx)?;
+        .change_context(CommandError::Upload)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text("Sent your export to your DMs!".into()),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn history(
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+        limit: u16,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Fetching switch history");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let switches = Switch::fetch_recent(system_id, limit, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if switches.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(
+                    "You haven't switched yet. Use `/members switch` to switch members.".into(),
+                ),
+            ));
+        }
+
+        let members = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+            .members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(render::switch_history(&switches, &members)),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(system_id))]
+    async fn backfill(
+        event: SlackCommandEvent,
+        client: Arc<SlackHyperClient>,
+        state: SlackClientEventsUserState,
+        channel: &str,
+        days: u16,
+        proxy: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Starting backfill");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let Some(channel_id) = parse_channel(channel) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Invalid channel".into()),
+            ));
+        };
+
+        let mode = if proxy {
+            backfill::Mode::Proxy
+        } else {
+            backfill::Mode::Index
+        };
+
+        let response = if backfill::start(
+            client,
+            user_state.db.clone(),
+            system,
+            channel_id.clone(),
+            days,
+            mode,
+        ) {
+            format!(
+                "Backfilling the last {days} days of {}. I'll DM you as it goes.",
+                channel_id.to_slack_format()
+            )
+        } else {
+            "You already have a backfill running. Wait for it to finish first.".to_string()
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    #[tracing::instrument(skip_all, fields(system_id))]
+    async fn diagnose(
+        event: SlackCommandEvent,
+        client: Arc<SlackHyperClient>,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        debug!("Diagnosing system");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let checks = diagnose::run(&system, &event.channel_id, &client, &user_state.db).await;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(diagnose::render(&checks)),
+        ))
+    }
+
+    async fn reauth(
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Reauthenticating system");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let auth_url = oauth::authorize_url(&system.owner_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
 
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_blocks(slack_blocks![some_into(
@@ -102,12 +545,13 @@ impl System {
         ))
     }
 
-    #[tracing::instrument(skip_all, fields(user_id, system_id))]
+    #[tracing::instrument(skip_all, fields(user_id, system_id, viewer))]
     async fn get_system_info(
         event: SlackCommandEvent,
         client: Arc<SlackHyperClient>,
         state: SlackClientEventsUserState,
         user: Option<String>,
+        format: Format,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         trace!("Getting system info");
 
@@ -116,15 +560,12 @@ impl System {
 
         // If the input exists, parse it into a user ID.
         // If it doesn't exist, use the user ID of the event.
-        // There's probably a better way to write this behaviour but I'm not sure how.
-        let Some(user_id) = user.map_or_else(
-            || Some(event.user_id.clone().into()),
-            |u| {
-                user::parse_slack_user_id(&u).and_then(|id| {
-                    Handle::current().block_on(async { id.trust(&client).await.ok() })
-                })
-            },
-        ) else {
+        let user_id = match user {
+            Some(user) => user::parse_and_trust(&user, &client).await,
+            None => Some(event.user_id.clone().into()),
+        };
+
+        let Some(user_id) = user_id else {
             return Ok(SlackCommandEventResponse::new(
                 SlackMessageContent::new().with_text("Invalid user ID".into()),
             ));
@@ -140,20 +581,79 @@ impl System {
         if let Some(system) = system {
             fields!(system_id = %system.id);
             debug!("Fetched system");
-            let fronting_member = system
-                .active_member(&user_state.db)
+            let viewer = Viewer::resolve(
+                &event.user_id.clone().into(),
+                &system,
+                &client,
+                &user_state.db,
+            )
+            .await
+            .change_context(CommandError::Viewer)?;
+            fields!(viewer = ?viewer);
+
+            if !viewer.sees_system(&system) {
+                debug!("System is private");
+                return Ok(private_response());
+            }
+
+            let fronting_members = system
+                .fronting_members(&user_state.db)
                 .await
                 .change_context(CommandError::Sqlx)?;
 
-            Ok(SlackCommandEventResponse::new(
-                SlackMessageContent::new().with_blocks(slack_blocks![some_into(
-                    SlackSectionBlock::new().with_text(md!(format!(
-                        "Fronting member: {}",
-                        fronting_member
-                            .map_or_else(|| "No fronting member".to_string(), |m| m.display_name)
-                    )))
-                )]),
-            ))
+            let members = system
+                .members(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            let json = render::json::system_card(&system, &members, &fronting_members, viewer);
+            let mut blocks = render::system_card(&system, &members, fronting_members, viewer);
+
+            if viewer == Viewer::Owner {
+                let counts = render::SystemCounts {
+                    triggers: models::Trigger::fetch_by_system_id(system.id, &user_state.db)
+                        .await
+                        .change_context(CommandError::Sqlx)?
+                        .len(),
+                    aliases: models::Alias::fetch_by_system_id(system.id, &user_state.db)
+                        .await
+                        .change_context(CommandError::Sqlx)?
+                        .len(),
+                    managers: system
+                        .id
+                        .managers(&user_state.db)
+                        .await
+                        .change_context(CommandError::Sqlx)?
+                        .len(),
+                };
+                let token_health = diagnose::token_health(&system).await;
+
+                blocks.extend(render::system_details(
+                    &system,
+                    &members,
+                    &counts,
+                    &token_health.render(),
+                ));
+            }
+
+            if viewer == Viewer::Owner
+                && let Some((start, end)) = system.quiet_hours()
+            {
+                blocks.push(
+                    SlackContextBlock::new(vec![md!(
+                        "Quiet hours: {start}:00 to {end}:00 ({}){}",
+                        system.timezone().name(),
+                        if system.in_quiet_hours() {
+                            " - proxying is paused"
+                        } else {
+                            ""
+                        }
+                    )])
+                    .into(),
+                );
+            }
+
+            Ok(format.respond(blocks, &json))
         } else {
             debug!("User does not have a system");
             Ok(SlackCommandEventResponse::new(
@@ -188,31 +688,9 @@ impl System {
             ));
         }
 
-        let oauth_client = create_oauth_client();
-
-        // Note: we aren't doing PKCE since this is only ran on a trusted server
-
-        let (auth_url, csrf_token) = oauth_client
-            .authorize_url(CsrfToken::new_random)
-            // So we get a regular token as well. Required by oauth2 for some reason
-            .add_extra_param("scope", "commands")
-            .add_extra_param("user_scope", "users.profile:read,chat:write")
-            .url();
-
-        let secret = csrf_token.secret();
-
-        sqlx::query!(
-            r#"
-            INSERT INTO system_oauth_process (owner_id, csrf)
-            VALUES ($1, $2)
-            ON CONFLICT (owner_id) DO UPDATE SET csrf = $2
-            "#,
-            user_id.id,
-            secret
-        )
-        .execute(&user_state.db)
-        .await
-        .change_context(CommandError::Sqlx)?;
+        let auth_url = oauth::authorize_url(&user_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
 
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_blocks(slack_blocks![some_into(
@@ -251,3 +729,36 @@ macro_rules! fetch_system {
         ::tracing::debug!("Fetched system");
     };
 }
+
+/// Reads a switch reminder interval like `6h`, `2d` or `12` (hours) into hours.
+///
+/// Returns [`None`] for `off`, and an error if the interval can't be read or is out of range.
+fn parse_reminder_interval(input: &str) -> std::result::Result<Option<u16>, ()> {
+    let input = input.trim().to_lowercase();
+
+    if input == "off" {
+        return Ok(None);
+    }
+
+    let (number, multiplier) = if let Some(days) = input.strip_suffix('d') {
+        (days, 24)
+    } else {
+        (input.strip_suffix('h').unwrap_or(&input), 1)
+    };
+
+    number
+        .trim()
+        .parse::<u16>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .filter(|hours| (1..=MAX_REMINDER_HOURS).contains(hours))
+        .map(Some)
+        .ok_or(())
+}
+
+/// The response to looking up a private system that isn't yours
+pub fn private_response() -> SlackCommandEventResponse {
+    SlackCommandEventResponse::new(
+        SlackMessageContent::new().with_text("This system is private.".into()),
+    )
+}
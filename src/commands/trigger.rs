@@ -1,17 +1,20 @@
-use error_stack::{Result, ResultExt};
+use error_stack::{Report, Result, ResultExt};
 use slack_morphism::prelude::*;
 use tracing::debug;
 
+use super::Format;
 use crate::{
     fetch_member, fetch_system, fields,
     models::{self, member::MemberRef, trigger, trust::Untrusted, user},
+    render,
 };
 
 #[derive(clap::Subcommand, Debug)]
 #[clap(verbatim_doc_comment)]
 /// A trigger is a special word/phrase that can be used to send a message under a specific members profile
 ///
-/// A trigger can be a prefix to a message or suffix. The prefix/suffix is erased when resending the message.
+/// A trigger can be a prefix to a message, a suffix, text on both sides (e.g. `[text]`) or a regex.
+/// The trigger is erased when resending the message.
 ///
 /// Also see:
 /// - /members to manage the members themselves
@@ -35,6 +38,12 @@ pub enum Trigger {
     List {
         /// If specified, lists the triggers for the given member.
         member: Option<MemberRef>,
+        /// How to show the output: blocks, text or json
+        #[clap(long, value_enum, default_value_t)]
+        format: Format,
+        /// Which page to show, for long lists
+        #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        page: u16,
     },
     /// Edit a trigger
     Edit {
@@ -69,7 +78,11 @@ impl Trigger {
                 content,
             } => Self::create_trigger(event, &state, member, typ, content).await,
             Self::Delete { id } => Self::delete_trigger(event, &state, id).await,
-            Self::List { member } => Self::list_triggers(event, &state, member).await,
+            Self::List {
+                member,
+                format,
+                page,
+            } => Self::list_triggers(event, &state, member, format, page).await,
             Self::Edit { id, typ, content } => {
                 Self::edit_trigger(event, &state, id, typ, content).await
             }
@@ -90,9 +103,18 @@ impl Trigger {
         fetch_system!(event, user_state => system_id);
         fetch_member!(member_id, user_state, system_id => member_id);
 
-        models::Trigger::insert(member_id, system_id, typ, content, &user_state.db)
-            .await
-            .change_context(CommandError::Sqlx)?;
+        if let Err(problem) = typ.validate(&content) {
+            debug!(problem, "Invalid trigger");
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(problem),
+            ));
+        }
+
+        if let Err(error) =
+            models::Trigger::insert(member_id, system_id, typ, content, &user_state.db).await
+        {
+            return taken_response(error);
+        }
 
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text("Trigger created!".into()),
@@ -150,27 +172,33 @@ impl Trigger {
         event: SlackCommandEvent,
         state: &SlackClientEventsUserState,
         member_ref: Option<MemberRef>,
+        format: Format,
+        page: u16,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         let states = state.read().await;
         let user_state = states.get_user_state::<user::State>().unwrap();
 
         fetch_system!(event, user_state => system_id);
 
-        let triggers = if let Some(member_ref) = member_ref {
+        let (triggers, command) = if let Some(member_ref) = member_ref {
             fetch_member!(member_ref, user_state, system_id => member_id);
 
-            member_id
+            let triggers = member_id
                 .fetch_triggers(&user_state.db)
                 .await
-                .change_context(CommandError::Sqlx)?
+                .change_context(CommandError::Sqlx)?;
+
+            (triggers, format!("triggers list {member_id}"))
         } else {
-            system_id
+            let triggers = system_id
                 .list_triggers(&user_state.db)
                 .await
-                .change_context(CommandError::Sqlx)?
+                .change_context(CommandError::Sqlx)?;
+
+            (triggers, "triggers list".to_string())
         };
 
-        if triggers.is_empty() {
+        if triggers.is_empty() && format != Format::Json {
             debug!("No triggers found");
             return Ok(SlackCommandEventResponse::new(
                 SlackMessageContent::new().with_text("No triggers found.".into()),
@@ -179,6 +207,7 @@ impl Trigger {
 
         debug!(len = triggers.len(), "Found triggers");
 
+        let json = render::json::triggers(&triggers);
         let trigger_blocks = triggers
             .into_iter()
             .map(|trigger| {
@@ -191,12 +220,10 @@ impl Trigger {
                     .with_text(md!("*Trigger {}*", trigger.id))
                     .with_fields(fields)
             })
-            .map(Into::into)
+            .map(|section| vec![section.into()])
             .collect();
 
-        Ok(SlackCommandEventResponse::new(
-            SlackMessageContent::new().with_blocks(trigger_blocks),
-        ))
+        Ok(format.respond_paged(trigger_blocks, &json, page, &command))
     }
 
     #[tracing::instrument(skip(event, state), fields(system_id))]
@@ -225,13 +252,39 @@ impl Trigger {
 
         fields!(trigger_id = %trigger_id);
 
-        trigger_id
-            .update(typ, text, &user_state.db)
+        let current = trigger_id
+            .fetch(&user_state.db)
             .await
             .change_context(CommandError::Sqlx)?;
 
+        if let Err(problem) = typ
+            .unwrap_or(current.typ)
+            .validate(text.as_deref().unwrap_or(&current.text))
+        {
+            debug!(problem, "Invalid trigger");
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(problem),
+            ));
+        }
+
+        if let Err(error) = trigger_id.update(typ, text, &user_state.db).await {
+            return taken_response(error);
+        }
+
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text("Updated trigger!".into()),
         ))
     }
 }
+
+/// Tells the user who already has a trigger, or fails the command for any other error
+fn taken_response(
+    error: Report<trigger::Error>,
+) -> Result<SlackCommandEventResponse, CommandError> {
+    match error.current_context() {
+        trigger::Error::Taken(_) => Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!("{}.", error.current_context())),
+        )),
+        trigger::Error::Sqlx => Err(error.change_context(CommandError::Sqlx)),
+    }
+}
@@ -24,6 +24,42 @@ require_envs! {
     encryption_key?, "ENCRYPTION_KEY", String,
     "ENCRYPTION_KEY can be optionally set to a key for encrypting and decrypting the database";
 
+    operators?, "OPERATORS", String,
+    "OPERATORS can be optionally set to a comma-separated list of Slack user IDs allowed to use /plura-admin";
+
+    moderators?, "MODERATORS", String,
+    "MODERATORS can be optionally set to a comma-separated list of Slack user IDs allowed to inspect any system with /plura-admin inspect, without being able to change it";
+
+    command_names?, "COMMAND_NAMES", String,
+    "COMMAND_NAMES can be optionally set to comma-separated name=command pairs for slash commands registered under custom names. E.g pk=members,sys=system";
+
+    debug_capture_events?, "DEBUG_CAPTURE_EVENTS", String,
+    "DEBUG_CAPTURE_EVENTS can be optionally set to how many recent message events to keep in memory for /plura-admin debug event-replay. Off by default";
+
+    debug_event_retention_minutes?, "DEBUG_EVENT_RETENTION_MINUTES", String,
+    "DEBUG_EVENT_RETENTION_MINUTES can be optionally set to how many minutes to store redacted message events in the database for replaying. Off by default";
+
+    error_report_channel?, "ERROR_REPORT_CHANNEL", String,
+    "ERROR_REPORT_CHANNEL can be optionally set to the ID of a Slack channel to post error reports in. The bot has to be in the channel";
+
+    error_report_webhook?, "ERROR_REPORT_WEBHOOK", String,
+    "ERROR_REPORT_WEBHOOK can be optionally set to a webhook URL to post error reports to, e.g. a Slack incoming webhook";
+
+    selftest_channel?, "SELFTEST_CHANNEL", String,
+    "SELFTEST_CHANNEL can be optionally set to the ID of a Slack channel for /plura-admin selftest to post in. The bot has to be in the channel";
+
+    socket_mode?, "SOCKET_MODE", String,
+    "SOCKET_MODE can be optionally set to true to receive Slack events over Socket Mode with SLACK_APP_TOKEN, instead of on the /push, /command and /interaction endpoints";
+
+    preflight_reachability?, "PREFLIGHT_REACHABILITY", String,
+    "PREFLIGHT_REACHABILITY can be optionally set to true to check on startup that the event endpoints can be reached through BASE_URL";
+
+    join_announcement?, "JOIN_ANNOUNCEMENT", String,
+    "JOIN_ANNOUNCEMENT can be optionally set to the message the bot posts when it's added to a channel, or to off to not post one";
+
+    api_rate_limit?, "API_RATE_LIMIT", String,
+    "API_RATE_LIMIT can be optionally set to how many API requests each system can make per minute, or 0 for no limit. Defaults to 60";
+
     base_url, "BASE_URL", String,
     "BASE_URL should be set to the base URL for the bot. E.g https://plura.wobbl.in/";
 }
@@ -0,0 +1,85 @@
+//! Introducing the bot when it's added to a channel.
+//!
+//! Proxied messages are posted under members' names and pictures, which looks a lot like impersonation to anyone who
+//! hasn't come across the bot before. When the bot joins a channel it posts a short note explaining what it does.
+//! `JOIN_ANNOUNCEMENT` replaces the note, or turns it off when set to `off`. Like notifications, it uses the workspace's
+//! [branding](crate::models::workspace::Branding).
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tokio::sync::OnceCell;
+use tracing::debug;
+
+use crate::{BOT_TOKEN, env, notify, slack_error};
+
+/// Posted when the bot joins a channel, unless `JOIN_ANNOUNCEMENT` says otherwise
+const DEFAULT_ANNOUNCEMENT: &str = "\
+:wave: Hi! I repost messages for people who use plural systems, under the name and picture of the member who's talking. \
+Messages from me can show a different name each time, but each one was sent by someone in this channel.\n\
+React with :question: to one of my messages to see who sent it. I keep track of who sent each message so it can be \
+looked up, edited and deleted, but not what it says.";
+
+/// The bot's own user ID, looked up the first time it's needed
+static BOT_USER_ID: OnceCell<SlackUserId> = OnceCell::const_new();
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the Slack API
+    SlackApi,
+}
+
+/// The announcement to post, or [`None`] if it's turned off
+fn announcement() -> Option<String> {
+    match env::join_announcement() {
+        Some(text) if text.trim().eq_ignore_ascii_case("off") => None,
+        Some(text) if !text.trim().is_empty() => Some(text),
+        _ => Some(DEFAULT_ANNOUNCEMENT.to_string()),
+    }
+}
+
+async fn bot_user_id(client: &SlackHyperClient) -> Result<&'static SlackUserId, Error> {
+    BOT_USER_ID
+        .get_or_try_init(|| async {
+            slack_error::traced("auth.test", client.open_session(&BOT_TOKEN).auth_test())
+                .await
+                .map(|response| response.user_id)
+        })
+        .await
+        .change_context(Error::SlackApi)
+}
+
+/// Handles someone joining a channel, posting the announcement if it was the bot
+#[tracing::instrument(skip_all, fields(channel_id = %event.channel))]
+pub async fn handle(
+    event: SlackMemberJoinedChannelEvent,
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let Some(text) = announcement() else {
+        return Ok(());
+    };
+
+    if event.user != *bot_user_id(client).await? {
+        return Ok(());
+    }
+
+    debug!("Bot joined a channel. Announcing");
+    let branding = notify::branding(db).await;
+
+    slack_error::traced(
+        "chat.postMessage",
+        client.open_session(&BOT_TOKEN).chat_post_message(
+            &SlackApiChatPostMessageRequest::new(
+                event.channel,
+                notify::with_footer(&branding, SlackMessageContent::new().with_text(text)),
+            )
+            .opt_username(branding.display_name.clone())
+            .opt_icon_url(branding.icon_url.clone()),
+        ),
+    )
+    .await
+    .change_context(Error::SlackApi)?;
+
+    Ok(())
+}
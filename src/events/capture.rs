@@ -0,0 +1,178 @@
+//! Keeps recent message events, so operators can see how the bot handled them.
+//!
+//! Events can be kept in memory for `/plura-admin debug event-replay`, or redacted and stored in the database for a short
+//! while for `plura replay`. Events hold the content of people's messages, so neither happens unless turned on with
+//! `DEBUG_CAPTURE_EVENTS` or `DEBUG_EVENT_RETENTION_MINUTES`.
+
+use std::{
+    collections::VecDeque,
+    sync::{LazyLock, Mutex},
+};
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{debug, warn};
+
+use crate::{env, models::CapturedEvent};
+
+/// The most events that can be kept, however high `DEBUG_CAPTURE_EVENTS` is set
+const MAX_CAPACITY: usize = 200;
+
+/// How many events to keep. Zero if capturing is off
+static CAPACITY: LazyLock<usize> = LazyLock::new(|| {
+    parse_setting("DEBUG_CAPTURE_EVENTS", env::debug_capture_events()).min(MAX_CAPACITY)
+});
+
+/// How many minutes to store events in the database for. Zero if storing is off
+static RETENTION_MINUTES: LazyLock<u32> = LazyLock::new(|| {
+    parse_setting(
+        "DEBUG_EVENT_RETENTION_MINUTES",
+        env::debug_event_retention_minutes(),
+    )
+});
+
+/// Characters kept at each end of redacted text, which is where prefix and suffix triggers are
+const KEPT_EDGE: usize = 8;
+/// Parts of an event that aren't needed to replay it, and could identify people or link to their files
+const DROPPED_KEYS: &[&str] = &["files", "attachments", "user_profile", "bot_profile"];
+/// Parts of an event with message content in them
+const MASKED_KEYS: &[&str] = &["text", "url"];
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while converting the event to or from JSON
+    Json,
+    /// Error while calling the database
+    Sqlx,
+}
+
+/// Parses a number of things to keep from an optional setting, treating anything invalid as off
+fn parse_setting<T>(name: &str, value: Option<String>) -> T
+where
+    T: std::str::FromStr + Default,
+{
+    let Some(value) = value else {
+        return T::default();
+    };
+
+    value.trim().parse().unwrap_or_else(|_| {
+        warn!(name, value, "Ignoring setting, as it isn't a number");
+        T::default()
+    })
+}
+
+/// Captured events, most recent first
+static EVENTS: Mutex<VecDeque<Captured>> = Mutex::new(VecDeque::new());
+
+/// A message event as it was received
+#[derive(Debug, Clone)]
+pub struct Captured {
+    pub event: SlackMessageEvent,
+    pub received_at: time::OffsetDateTime,
+}
+
+/// Keeps a copy of the event, if capturing is on
+pub fn record(event: &SlackMessageEvent) {
+    let capacity = *CAPACITY;
+
+    if capacity == 0 {
+        return;
+    }
+
+    let mut events = EVENTS
+        .lock()
+        .expect("Event capture lock to not be poisoned");
+    events.truncate(capacity - 1);
+    events.push_front(Captured {
+        event: event.clone(),
+        received_at: time::OffsetDateTime::now_utc(),
+    });
+}
+
+/// The captured event `index` events ago, where 0 is the most recent
+pub fn get(index: usize) -> Option<Captured> {
+    EVENTS
+        .lock()
+        .expect("Event capture lock to not be poisoned")
+        .get(index)
+        .cloned()
+}
+
+/// How many events are captured right now
+pub fn count() -> usize {
+    EVENTS
+        .lock()
+        .expect("Event capture lock to not be poisoned")
+        .len()
+}
+
+/// How many events can be captured. Zero if capturing is off
+pub fn capacity() -> usize {
+    *CAPACITY
+}
+
+/// Redacts the event and stores it in the database, if storing is on
+#[tracing::instrument(skip_all)]
+pub async fn persist(event: &SlackMessageEvent, db: &SqlitePool) -> Result<(), Error> {
+    let retention_minutes = *RETENTION_MINUTES;
+
+    if retention_minutes == 0 {
+        return Ok(());
+    }
+
+    let mut payload = serde_json::to_value(event).change_context(Error::Json)?;
+    redact(&mut payload);
+
+    let id = CapturedEvent::insert(&payload.to_string(), retention_minutes, db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    debug!(id = id.id, "Stored redacted event");
+    Ok(())
+}
+
+/// Turns a stored payload back into an event
+pub fn restore(captured: &CapturedEvent) -> Result<SlackMessageEvent, Error> {
+    serde_json::from_str(&captured.payload).change_context(Error::Json)
+}
+
+/// Removes or masks everything in the event that isn't needed to work out how it would be proxied
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            object.retain(|key, _| !DROPPED_KEYS.contains(&key.as_str()));
+
+            for (key, value) in object.iter_mut() {
+                match value {
+                    serde_json::Value::String(text) if MASKED_KEYS.contains(&key.as_str()) => {
+                        *text = mask(text);
+                    }
+                    value => redact(value),
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Replaces the letters and numbers in the middle of the text with `x`.
+///
+/// The ends are kept so prefix and suffix triggers still match, along with punctuation and whitespace so the message
+/// keeps its shape. Regex triggers matching the middle of a message won't match a redacted one.
+fn mask(text: &str) -> String {
+    let len = text.chars().count();
+    let kept = KEPT_EDGE.min(len / 4);
+
+    text.chars()
+        .enumerate()
+        .map(|(index, c)| {
+            if index < kept || index >= len - kept || !c.is_alphanumeric() {
+                c
+            } else {
+                'x'
+            }
+        })
+        .collect()
+}
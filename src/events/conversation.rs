@@ -0,0 +1,51 @@
+//! The kinds of conversation a message can be proxied in.
+//!
+//! Slack sends messages from public channels, private channels and group DMs the same way, but the bot has to be
+//! added to private channels and group DMs before it can post there. One-to-one DMs between people never have the
+//! bot in them, so messages there are left alone.
+
+use slack_morphism::prelude::*;
+
+/// Where a message was sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversation {
+    Channel,
+    PrivateChannel,
+    GroupDm,
+    /// A DM between two people, or between someone and the bot
+    Dm,
+}
+
+impl Conversation {
+    /// Works out the conversation from a message's origin. Assumes a channel if Slack didn't say
+    pub fn of(origin: &SlackMessageOrigin) -> Self {
+        match origin
+            .channel_type
+            .as_ref()
+            .map(|channel_type| channel_type.0.as_str())
+        {
+            Some("group") => Self::PrivateChannel,
+            Some("mpim") => Self::GroupDm,
+            Some("im") => Self::Dm,
+            _ => Self::Channel,
+        }
+    }
+
+    /// Whether the bot can post proxied messages here, if it's been added
+    pub const fn can_proxy(self) -> bool {
+        !matches!(self, Self::Dm)
+    }
+
+    /// Explains how to give the bot access, for when it tried to post here without it
+    pub const fn missing_access(self) -> &'static str {
+        match self {
+            Self::Channel | Self::Dm => "The bot isn't in this channel. Add it with `/invite`.",
+            Self::PrivateChannel => {
+                "The bot isn't in this private channel, so it can't see or post there. Add it with `/invite`."
+            }
+            Self::GroupDm => {
+                "The bot isn't in this group DM, so it can't post there. Add it from the conversation's details, under Integrations."
+            }
+        }
+    }
+}
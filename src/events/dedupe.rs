@@ -0,0 +1,33 @@
+//! Skipping events Slack delivers more than once.
+//!
+//! Slack retries an event if it doesn't get a response within a few seconds, and proxying a message can take longer
+//! than that. Handling the retry as well would proxy the message twice. Each event's ID is remembered for [`WINDOW`],
+//! which covers all of Slack's retries, and events with an ID that was already seen are dropped.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use slack_morphism::prelude::*;
+
+/// How long an event ID is remembered for. Slack's last retry comes about 5 minutes after the first delivery
+const WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// When each recent event was first delivered. Entries are cleared out once [`WINDOW`] has passed
+static SEEN: LazyLock<Mutex<HashMap<SlackEventId, Instant>>> = LazyLock::new(Mutex::default);
+
+/// Records an event as delivered, returning whether this is the first time it has been
+pub fn first_delivery(event_id: &SlackEventId) -> bool {
+    let mut seen = SEEN.lock().expect("Seen events lock to not be poisoned");
+
+    seen.retain(|_, delivered| delivered.elapsed() < WINDOW);
+
+    if seen.contains_key(event_id) {
+        return false;
+    }
+
+    seen.insert(event_id.clone(), Instant::now());
+    true
+}
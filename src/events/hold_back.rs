@@ -0,0 +1,80 @@
+//! Keeping the original of a proxied message until the owner confirms the proxy looks right.
+//!
+//! Systems with [hold back](crate::models::System::hold_back) on get a private prompt after each proxied message,
+//! to delete their original or undo the proxy. Until they pick one, both messages stay in the channel.
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+
+use crate::{BOT_TOKEN, models};
+
+/// Action ID of the button that deletes the original
+pub const CONFIRM_ACTION: &str = "hold_back_confirm";
+/// Action ID of the button that deletes the proxied message instead
+pub const UNDO_ACTION: &str = "hold_back_undo";
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the Slack API
+    SlackApi,
+}
+
+/// The value both buttons carry: channel, original and proxied message, `_` separated
+fn value(channel_id: &SlackChannelId, original_ts: &SlackTs, proxied_ts: &SlackTs) -> String {
+    format!("{}_{}_{}", channel_id.0, original_ts.0, proxied_ts.0)
+}
+
+/// Parses a button value back into the channel, original and proxied message
+pub fn parse_value(value: &str) -> Option<(SlackChannelId, SlackTs, SlackTs)> {
+    let (channel_id, rest) = value.split_once('_')?;
+    let (original_ts, proxied_ts) = rest.split_once('_')?;
+
+    Some((
+        SlackChannelId::new(channel_id.to_owned()),
+        SlackTs::new(original_ts.to_owned()),
+        SlackTs::new(proxied_ts.to_owned()),
+    ))
+}
+
+/// Sends the owner a private prompt to delete the original of a message that was just proxied, or undo the proxy
+#[tracing::instrument(skip_all, fields(system_id = %system.id))]
+pub async fn prompt(
+    client: &SlackHyperClient,
+    system: &models::System,
+    channel_id: &SlackChannelId,
+    thread_ts: Option<SlackTs>,
+    original_ts: &SlackTs,
+    proxied_ts: &SlackTs,
+) -> Result<(), Error> {
+    let value = value(channel_id, original_ts, proxied_ts);
+
+    client
+        .open_session(&BOT_TOKEN)
+        .chat_post_ephemeral(
+            &SlackApiChatPostEphemeralRequest::new(
+                channel_id.clone(),
+                system.owner_id.clone().into(),
+                SlackMessageContent::new().with_blocks(slack_blocks![
+                    some_into(SlackSectionBlock::new().with_text(md!(
+                        "Your original message is kept until you check the proxied one. Does it look right?"
+                    ))),
+                    some_into(SlackActionsBlock::new(vec![
+                        SlackBlockButtonElement::new(
+                            CONFIRM_ACTION.into(),
+                            pt!("Looks right, delete my original")
+                        )
+                        .with_value(value.clone())
+                        .into(),
+                        SlackBlockButtonElement::new(UNDO_ACTION.into(), pt!("Undo the proxy"))
+                            .with_value(value)
+                            .into(),
+                    ]))
+                ]),
+            )
+            .opt_thread_ts(thread_ts),
+        )
+        .await
+        .change_context(Error::SlackApi)?;
+
+    Ok(())
+}
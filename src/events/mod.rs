@@ -2,9 +2,24 @@
 //!
 //! This is where message rewriting, trigger detection, and message handling logic are implemented.
 
+pub mod announce;
+pub mod capture;
+pub mod conversation;
+pub mod dedupe;
+pub mod hold_back;
+pub mod reaction;
+pub mod recording;
+pub mod replay;
+pub mod shape;
+pub mod suggest;
+
 use std::{convert::Infallible, sync::Arc};
 
-use axum::{Extension, body::Bytes, http::Response};
+use axum::{
+    Extension,
+    body::Bytes,
+    http::{HeaderMap, Response},
+};
 use error_stack::{Result, ResultExt};
 use http_body_util::{BodyExt, Empty, Full, combinators::BoxBody};
 use slack_morphism::prelude::*;
@@ -12,22 +27,14 @@ use sqlx::SqlitePool;
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    BOT_TOKEN, fields,
-    models::{self, trigger, user},
+    BOT_TOKEN, fields, home,
+    metrics::{self, Outcome},
+    models::{self, member, system::AutoproxyMode, trigger, trust::Trusted, user},
+    notify, ops,
+    proxy::{self, Original, ProxyOptions, ProxyRequest},
+    shutdown, slack_error,
 };
 
-#[derive(thiserror::Error, displaydoc::Display, Debug)]
-pub enum RewriteMessageError {
-    /// Error while posting a message to Slack
-    PostMessage,
-    /// Error while deleting a message from Slack
-    DeleteMessage,
-    /// Error while serializing custom image blocks
-    SerializeImageBlocks,
-    /// Error while saving message log to database
-    MessageLog,
-}
-
 #[derive(thiserror::Error, displaydoc::Display, Debug)]
 pub enum PushEventError {
     /// Error while interacting with the Slack API
@@ -40,27 +47,45 @@ pub enum PushEventError {
     MemberChange,
     /// Error while attempting to rewrite the message
     MessageRewrite,
+    /// Error while saving a name recording
+    Recording,
+    /// Error while publishing the home tab
+    Home,
+    /// Error while handling a reaction
+    Reaction,
+    /// Error while announcing the bot in a channel it joined
+    Announce,
 }
 
-#[tracing::instrument(skip(environment, event))]
+#[tracing::instrument(skip(environment, event, headers))]
 pub async fn process_push_event(
     Extension(environment): Extension<Arc<SlackHyperListenerEnvironment>>,
     Extension(event): Extension<SlackPushEvent>,
+    headers: HeaderMap,
 ) -> Response<BoxBody<Bytes, Infallible>> {
     debug!("Received push event!");
 
+    if let Some(retry_num) = headers
+        .get("x-slack-retry-num")
+        .and_then(|value| value.to_str().ok())
+    {
+        let reason = headers
+            .get("x-slack-retry-reason")
+            .and_then(|value| value.to_str().ok());
+        debug!(retry_num, reason, "Slack is retrying an event");
+    }
+
     match event {
         SlackPushEvent::UrlVerification(url_verification) => {
             Response::new(Full::new(url_verification.challenge.into()).boxed())
         }
         SlackPushEvent::EventCallback(event) => {
-            let client = environment.client.clone();
-            let state = environment.user_state.clone();
-            // https://rust-lang.github.io/rust-clippy/master/index.html#large_futures
-            // Into the box you go
-            if let Err(e) = Box::pin(push_event_callback(event, client, state)).await {
-                error!("Error processing push event: {:#?}", e);
-            }
+            handle_push_event(
+                event,
+                environment.client.clone(),
+                environment.user_state.clone(),
+            )
+            .await;
 
             Response::new(Empty::new().boxed())
         }
@@ -71,6 +96,31 @@ pub async fn process_push_event(
     }
 }
 
+/// Handles an event Slack pushed, however it arrived. Errors are logged and reported rather than returned
+pub async fn handle_push_event(
+    event: SlackPushEventCallback,
+    client: Arc<SlackHyperClient>,
+    state: SlackClientEventsUserState,
+) {
+    // Checked first, so a dropped event isn't remembered as one that was handled
+    let Some(_in_flight) = shutdown::track() else {
+        warn!("Shutting down. Dropping push event");
+        return;
+    };
+
+    if !dedupe::first_delivery(&event.event_id) {
+        debug!(event_id = %event.event_id.0, "Event was already delivered. Skipping");
+        return;
+    }
+
+    // https://rust-lang.github.io/rust-clippy/master/index.html#large_futures
+    // Into the box you go
+    if let Err(e) = Box::pin(push_event_callback(event, client, state)).await {
+        error!("Error processing push event: {:#?}", e);
+        ops::report("event", &e);
+    }
+}
+
 #[tracing::instrument(skip(event, state, client))]
 async fn push_event_callback(
     event: SlackPushEventCallback,
@@ -100,6 +150,18 @@ async fn push_event_callback(
             debug!("Message log deleted");
             Ok(())
         }
+        SlackEventCallbackBody::Message(message_event)
+            if recording::is_candidate(&message_event) =>
+        {
+            fields!(event_type = ?SlackMessageEventType::FileShare);
+            let states = state.read().await;
+            let user_state = states.get_user_state::<user::State>().unwrap();
+
+            recording::handle(&message_event, &client, &user_state.db)
+                .await
+                .change_context(PushEventError::Recording)
+                .map(|_| ())
+        }
         SlackEventCallbackBody::Message(message_event)
             if message_event.subtype.is_none()
                 || message_event
@@ -107,12 +169,102 @@ async fn push_event_callback(
                     .as_ref()
                     .is_some_and(|subtype| *subtype == SlackMessageEventType::MessageChanged) =>
         {
+            capture::record(&message_event);
+
+            {
+                let states = state.read().await;
+                let user_state = states.get_user_state::<user::State>().unwrap();
+
+                if let Err(error) = capture::persist(&message_event, &user_state.db).await {
+                    warn!(?error, "Failed to store captured event");
+                }
+            }
+
             handle_message(message_event, &client, &state).await
         }
+        SlackEventCallbackBody::ReactionAdded(reaction_event) => {
+            fields!(event_type = "reaction_added");
+            let states = state.read().await;
+            let user_state = states.get_user_state::<user::State>().unwrap();
+
+            reaction::handle(reaction_event, &client, user_state)
+                .await
+                .change_context(PushEventError::Reaction)
+        }
+        SlackEventCallbackBody::MemberJoinedChannel(join_event) => {
+            fields!(event_type = "member_joined_channel");
+            let states = state.read().await;
+            let user_state = states.get_user_state::<user::State>().unwrap();
+
+            announce::handle(join_event, &client, &user_state.db)
+                .await
+                .change_context(PushEventError::Announce)
+        }
+        SlackEventCallbackBody::AppHomeOpened(home_event)
+            if home_event.tab.as_deref() == Some("home") =>
+        {
+            fields!(event_type = "app_home_opened");
+            let states = state.read().await;
+            let user_state = states.get_user_state::<user::State>().unwrap();
+
+            home::publish(&home_event.user, &client, &user_state.db)
+                .await
+                .change_context(PushEventError::Home)
+        }
         _ => Ok(()),
     }
 }
 
+/// A message event with edits unwrapped, so edited and new messages can be handled the same way
+pub struct Unwrapped {
+    /// Whether the event was for an edit
+    pub edited: bool,
+    pub sender: SlackMessageSender,
+    pub content: Option<SlackMessageContent>,
+    pub origin: SlackMessageOrigin,
+}
+
+impl Unwrapped {
+    /// Unwraps a message event, or returns [`None`] if it changed a message without the user editing it.
+    ///
+    /// Slack sends `message_changed` for link unfurls, reply counts and other updates too. Those are marked hidden,
+    /// or the message inside has no `edited` marker, and handling them like edits would proxy old messages again.
+    pub fn new(message_event: SlackMessageEvent) -> Option<Self> {
+        let changed = message_event
+            .subtype
+            .as_ref()
+            .is_some_and(|subtype| *subtype == SlackMessageEventType::MessageChanged);
+
+        if !changed {
+            return Some(Self {
+                edited: false,
+                sender: message_event.sender,
+                content: message_event.content,
+                origin: message_event.origin,
+            });
+        }
+
+        if message_event.hidden == Some(true) {
+            return None;
+        }
+
+        // Edit events carry the edited message inside them, with the edit's own timestamp at the top level
+        let message = message_event
+            .message
+            .filter(|message| message.edited.is_some())?;
+
+        let mut origin = message_event.origin;
+        origin.ts = message.ts;
+
+        Some(Self {
+            edited: true,
+            sender: message.sender,
+            content: message.content,
+            origin,
+        })
+    }
+}
+
 #[tracing::instrument(skip(client, state, message_event), fields(message_id = ?message_event.origin.ts, sender_id = ?message_event.sender.user))]
 async fn handle_message(
     message_event: SlackMessageEvent,
@@ -125,7 +277,18 @@ async fn handle_message(
     let states = state.read().await;
     let user_state = states.get_user_state::<user::State>().unwrap();
 
-    let Some(user_id) = message_event.sender.user.map(user::Id::new) else {
+    let Some(Unwrapped {
+        edited,
+        sender,
+        content,
+        mut origin,
+    }) = Unwrapped::new(message_event)
+    else {
+        debug!("Message was changed without being edited. Ignoring");
+        return Ok(());
+    };
+
+    let Some(user_id) = sender.user.map(user::Id::new) else {
         debug!("Failed to get user ID");
         return Ok(());
     };
@@ -137,76 +300,333 @@ async fn handle_message(
         .change_context(PushEventError::SystemFetch)?
     else {
         debug!("Failed to fetch system");
+        metrics::record(Outcome::NoSystem);
         return Ok(());
     };
 
     fields!(system_id = %&system.id);
 
-    let Some(ref channel_id) = message_event.origin.channel else {
+    let Some(channel_id) = origin.channel.clone() else {
         debug!("Failed to get channel ID");
         return Ok(());
     };
 
     fields!(channel_id = %&channel_id);
 
-    let Some(content) = message_event.content else {
+    // Blacklisted channels are left alone entirely, before anything else about the message is looked at
+    if system
+        .id
+        .is_blacklisted(&channel_id, &user_state.db)
+        .await
+        .change_context(PushEventError::SystemFetch)?
+    {
+        debug!("Channel is blacklisted. Not proxying");
+        return Ok(());
+    }
+
+    let conversation = conversation::Conversation::of(&origin);
+
+    if !conversation.can_proxy() {
+        debug!(
+            ?conversation,
+            "Bot can't post in this conversation. Not proxying"
+        );
+        return Ok(());
+    }
+
+    if !system.proxy_enabled {
+        debug!("System has proxying turned off. Not proxying");
+        return Ok(());
+    }
+
+    if system.in_quiet_hours() {
+        debug!("System is in quiet hours. Not proxying");
+        return Ok(());
+    }
+
+    let Some(content) = content else {
         debug!("Failed to get message content");
         return Ok(());
     };
 
-    if let Some(ref message_content) = content.text
-        && let Some(member) = system
-            .find_member_by_trigger_rules(&user_state.db, message_content)
+    let fronting = system.currently_fronting_member_id;
+    let proxy_as = find_proxy_as(&mut system, content.text.as_deref(), &user_state.db).await?;
+
+    if system.currently_fronting_member_id != fronting {
+        home::refresh(&system.owner_id, client, &user_state.db).await;
+    }
+
+    let Some(proxy_as) = proxy_as else {
+        debug!("No member to proxy as");
+        metrics::record(Outcome::NoTrigger);
+
+        // Only new messages, as suggesting on every edit would be noisy
+        if system.suggest_members
+            && !edited
+            && let Err(error) = suggest::offer(
+                client,
+                &system,
+                &origin,
+                &channel_id,
+                content,
+                &user_state.db,
+            )
             .await
-            .change_context(PushEventError::MemberFetch)?
+        {
+            warn!(?error, "Failed to suggest members");
+        }
+
+        return Ok(());
+    };
+
+    let content_hash = models::message::content_hash(&content);
+
+    // Edits can't be double-sent, and are expected to match the message they edit
+    if !edited
+        && let Some(hash) = &content_hash
+        && models::MessageLog::is_recent_duplicate(system.id, hash, &origin.ts, &user_state.db)
+            .await
+            .change_context(PushEventError::MessageRewrite)?
     {
-        fields!(member = ?&member);
-        debug!("Member triggered");
+        debug!("Message is a duplicate of one that was just proxied. Deleting it");
+        metrics::record(Outcome::Duplicate);
 
-        if system.auto_switch_on_trigger {
-            system
-                .change_fronting_member(Some(member.id), &user_state.db)
-                .await
-                .change_context(PushEventError::MemberChange)?;
+        if let Err(error) = Original::User(origin.ts)
+            .delete(client, &system, channel_id, &user_state.db)
+            .await
+        {
+            warn!(?error, "Failed to delete duplicate message");
         }
 
-        rewrite_message(
+        return Ok(());
+    }
+
+    // The user's message still exists, so if it was proxied before, deleting it must have failed
+    let existing = if edited {
+        models::MessageLog::fetch_by_original_ts(&origin.ts, &user_state.db)
+            .await
+            .change_context(PushEventError::MessageRewrite)?
+    } else {
+        None
+    };
+
+    if edited && existing.is_none() {
+        origin.thread_ts = thread_of_edit(client, &channel_id, &origin.ts).await?;
+    }
+
+    if let Err(error) = rewrite_message(
+        client,
+        origin,
+        content,
+        content_hash,
+        existing,
+        proxy_as,
+        &system,
+        &user_state.db,
+    )
+    .await
+    {
+        notify::send(
             client,
-            message_event.origin,
-            content,
-            member,
-            &system,
             &user_state.db,
+            &system.owner_id,
+            notify::Notification::ProxyFailed {
+                reason: match slack_error::code(&error) {
+                    // Private channels and group DMs need the bot added in different ways
+                    Some(code) if slack_error::is_missing_access(code) => format!(
+                        "{}: {} (`{code}`)",
+                        error.current_context(),
+                        conversation.missing_access()
+                    ),
+                    _ => slack_error::user_message(&error),
+                },
+                reauth: slack_error::code(&error).is_some_and(slack_error::needs_reauth),
+            },
         )
-        .await
-        .change_context(PushEventError::MessageRewrite)?;
+        .await;
 
-        return Ok(());
+        return Err(error.change_context(PushEventError::MessageRewrite));
     }
 
-    debug!("Member not triggered");
+    Ok(())
+}
+
+/// The thread an edited message is in, if any.
+///
+/// Edit events don't say which thread the message is in, so it's looked up. Asking for a message's replies
+/// returns the thread's parent first, which is the message itself if it isn't a reply.
+async fn thread_of_edit(
+    client: &SlackHyperClient,
+    channel_id: &SlackChannelId,
+    ts: &SlackTs,
+) -> Result<Option<SlackTs>, PushEventError> {
+    Ok(slack_error::traced(
+        "conversations.replies",
+        client.open_session(&BOT_TOKEN).conversations_replies(
+            &SlackApiConversationsRepliesRequest::new(channel_id.clone(), ts.clone()).with_limit(1),
+        ),
+    )
+    .await
+    .change_context(PushEventError::SlackApi)?
+    .messages
+    .into_iter()
+    .next()
+    .map(|parent| parent.origin.ts)
+    .filter(|parent_ts| parent_ts != ts))
+}
+
+/// Why a message was matched to who it's proxied as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, displaydoc::Display)]
+pub enum Reason {
+    /// the anonymous prefix
+    AnonymousPrefix,
+    /// a trigger
+    Trigger,
+    /// autoproxy ({0})
+    Autoproxy(AutoproxyMode),
+}
+
+/// Works out who a message should be proxied as, if anyone.
+///
+/// The anonymous prefix is checked first, then triggers, then the member picked by the system's autoproxy mode.
+/// A trigger may switch the fronting member if the system has auto-switching enabled.
+async fn find_proxy_as(
+    system: &mut models::System,
+    text: Option<&str>,
+    db: &SqlitePool,
+) -> Result<Option<ProxyAs>, PushEventError> {
+    let Some((proxy_as, reason)) = resolve(system, text, db).await? else {
+        return Ok(None);
+    };
+
+    if reason == Reason::Trigger
+        && let ProxyAs::Member(member) = &proxy_as
+    {
+        if system.auto_switch_on_trigger {
+            system
+                .change_fronting_member(Some(member.id), db)
+                .await
+                .change_context(PushEventError::MemberChange)?;
+        }
+
+        if system.autoproxy_mode == AutoproxyMode::Latch
+            && system.latched_member_id != Some(member.id)
+        {
+            system
+                .id
+                .latch(member.id, db)
+                .await
+                .change_context(PushEventError::MemberChange)?;
+        }
+    }
+
+    Ok(Some(proxy_as))
+}
+
+/// Matches a message to who it would be proxied as, without switching or latching anything.
+///
+/// See [`find_proxy_as`] for the order things are checked in.
+pub async fn resolve(
+    system: &models::System,
+    text: Option<&str>,
+    db: &SqlitePool,
+) -> Result<Option<(ProxyAs, Reason)>, PushEventError> {
+    if let Some(text) = text
+        && let Some(prefix) = system
+            .anonymous_prefix
+            .as_ref()
+            .filter(|prefix| text.starts_with(prefix.as_str()))
+    {
+        debug!("Anonymous prefix used");
+
+        return Ok(Some((
+            ProxyAs::Anonymous {
+                trigger_text: prefix.clone(),
+            },
+            Reason::AnonymousPrefix,
+        )));
+    }
 
-    // No triggers ran, so check if there's any actively fronting member
-    if let Some(member_id) = system.currently_fronting_member_id {
-        fields!(member = %&member_id);
-        let member = models::Member::fetch_by_id(member_id, &user_state.db)
+    if let Some(text) = text
+        && let Some(member) = system
+            .find_member_by_trigger_rules(db, text)
             .await
-            .change_context(PushEventError::MemberFetch)?;
+            .change_context(PushEventError::MemberFetch)?
+    {
         fields!(member = ?&member);
+        debug!("Member triggered");
 
-        rewrite_message(
-            client,
-            message_event.origin,
-            content,
-            member.into(),
-            &system,
-            &user_state.db,
-        )
+        return Ok(Some((ProxyAs::Member(member), Reason::Trigger)));
+    }
+
+    debug!("Member not triggered");
+
+    // No triggers ran, so fall back to the autoproxy mode
+    let member_id = match system.autoproxy_mode {
+        AutoproxyMode::Off => None,
+        AutoproxyMode::Front => system.currently_fronting_member_id,
+        AutoproxyMode::Latch => system.latched_member_id,
+        AutoproxyMode::Member => system.autoproxy_member_id,
+    };
+
+    let Some(member_id) = member_id else {
+        debug!(mode = %system.autoproxy_mode, "No member to autoproxy as");
+        return Ok(None);
+    };
+
+    fields!(member = %&member_id);
+    let member = models::Member::fetch_by_id(member_id, db)
         .await
         .change_context(PushEventError::MemberFetch)?;
+    fields!(member = ?&member);
+
+    if !member.enabled || !member.proxy_enabled {
+        debug!("Autoproxy member is disabled, or can't be proxied as");
+        return Ok(None);
     }
 
-    Ok(())
+    Ok(Some((
+        ProxyAs::Member(member.into()),
+        Reason::Autoproxy(system.autoproxy_mode),
+    )))
+}
+
+/// The identity a message is proxied as
+#[derive(Debug)]
+pub enum ProxyAs {
+    /// A member of the system
+    Member(models::DetectedMember),
+    /// A neutral identity that isn't linked to any member
+    Anonymous {
+        /// The anonymous prefix that was used
+        trigger_text: String,
+    },
+}
+
+impl ProxyAs {
+    /// The member the message is proxied as, if it isn't anonymous
+    const fn member_id(&self) -> Option<member::Id<Trusted>> {
+        match self {
+            Self::Member(member) => Some(member.id),
+            Self::Anonymous { .. } => None,
+        }
+    }
+
+    fn trigger(&self) -> (trigger::Type, &str) {
+        match self {
+            Self::Member(member) => (member.typ, &member.trigger_text),
+            Self::Anonymous { trigger_text } => (trigger::Type::Prefix, trigger_text),
+        }
+    }
+}
+
+impl From<ProxyAs> for proxy::Identity {
+    fn from(proxy_as: ProxyAs) -> Self {
+        match proxy_as {
+            ProxyAs::Member(member) => member.into(),
+            ProxyAs::Anonymous { .. } => Self::Anonymous,
+        }
+    }
 }
 
 #[tracing::instrument(skip(client, db, system), fields(system_id = %system.id))]
@@ -214,132 +634,102 @@ async fn rewrite_message(
     client: &SlackHyperClient,
     origin: SlackMessageOrigin,
     mut content: SlackMessageContent,
-    member: models::DetectedMember,
+    content_hash: Option<String>,
+    existing: Option<models::MessageLog>,
+    proxy_as: ProxyAs,
     system: &models::System,
     db: &SqlitePool,
-) -> error_stack::Result<(), RewriteMessageError> {
+) -> error_stack::Result<(), proxy::Error> {
     info!("Rewriting message");
     let Some(channel_id) = origin.channel else {
         warn!("No channel ID found in origin. Bot possibly doesn't have access. Bailing");
         return Ok(());
     };
 
-    let token = SlackApiToken::new(system.slack_oauth_token.expose().into())
-        .with_token_type(SlackApiTokenType::User);
-    let user_session = client.open_session(&token);
-    let bot_session = client.open_session(&BOT_TOKEN);
+    rewrite_content(&mut content, &proxy_as);
 
-    rewrite_content(&mut content, &member);
+    let shape = shape::Shape::of(&content);
+    debug!(%shape, "Worked out message shape");
 
-    let mut custom_image_blocks = Vec::new();
-
-    if let Some(files) = content.files.take() {
-        #[derive(serde::Serialize)]
-        struct CustomSlackFile {
-            id: String,
-        }
-
-        #[derive(serde::Serialize)]
-        struct CustomSlackImageBlock {
-            #[serde(rename = "type")]
-            typ: String,
-            slack_file: CustomSlackFile,
-            alt_text: String,
-        }
-
-        // update files to blocks
-        let blocks = files
-            .into_iter()
-            .filter_map(|file| match file.filetype.map(|f| f.0).as_deref() {
-                Some("png" | "jpg" | "jpeg" | "gif" | "webp") => {
-                    // https://github.com/abdolence/slack-morphism-rust/issues/320
-                    // Some(SlackImageBlock::new(file.permalink?, String::new()).into())
-
-                    custom_image_blocks.push(CustomSlackImageBlock {
-                        typ: "image".to_string(),
-                        slack_file: CustomSlackFile {
-                            id: file.id.0,
-                        },
-                        alt_text: String::new(),
-                    });
-                    None
-                }
-                Some("mp4" | "mpg" | "mpeg" | "mkv" | "avi" | "mov" | "ogv" | "wmv") => {
-                    debug!("user uploaded a video. Can't really embed this.... Attaching to message as a rich content and calling it a day");
-                    Some(SlackMarkdownBlock::new(format!("Video: [{}]({})", file.name?, file.permalink?)).into())
-                }
-                Some(typ) => {
-                    debug!("unknown filetype {}. Don't know how to embed. Attaching to message as a rich content", typ);
-                    Some(SlackMarkdownBlock::new(format!("File attachment: [{}]({})", file.name?, file.permalink?)).into())
-                }
-                None => None,
-            });
-
-        if let Some(slack_blocks) = content.blocks.as_mut() {
-            slack_blocks.extend(blocks);
-        } else {
-            content.blocks = Some(blocks.collect());
-        }
+    if !shape.can_proxy() {
+        debug!("Proxying would lose the message's attachments. Leaving it as-is");
+        return Ok(());
     }
 
-    let message_request = SlackApiChatPostMessageRequest::new(channel_id.clone(), content)
-        .opt_thread_ts(origin.thread_ts)
-        .with_username(member.display_name.clone())
-        .opt_icon_url(member.profile_picture_url.clone());
-
-    let mut request = serde_json::to_value(message_request).unwrap();
+    shape.tidy(&mut content);
 
-    let blocks = request.get_mut("blocks").unwrap().as_array_mut().unwrap();
-    let custom_image_blocks = custom_image_blocks
-        .into_iter()
-        .map(serde_json::to_value)
-        .collect::<std::result::Result<Vec<serde_json::Value>, serde_json::Error>>()
-        .change_context(RewriteMessageError::SerializeImageBlocks)?;
+    if let Some(existing) = existing {
+        if existing.member_id == proxy_as.member_id() {
+            let files = content.files.take().unwrap_or_default();
+            proxy::update(client, &channel_id, &existing.message_id, content, files).await?;
 
-    blocks.extend(custom_image_blocks);
+            return Original::User(origin.ts)
+                .delete(client, system, channel_id, db)
+                .await;
+        }
 
-    let res: SlackApiChatPostMessageResponse = bot_session
-        .http_session_api
-        .http_post(
-            "chat.postMessage",
-            &request,
-            Some(&CHAT_POST_MESSAGE_SPECIAL_LIMIT_RATE_CTL),
-        )
-        .await
-        .change_context(RewriteMessageError::PostMessage)?;
+        // Slack can't change who a posted message is from, so the old copy is replaced with a new one
+        Original::Proxied(existing.message_id)
+            .delete(client, system, channel_id.clone(), db)
+            .await?;
+    }
 
-    models::MessageLog::insert(member.id, &res.ts, db)
-        .await
-        .change_context(RewriteMessageError::MessageLog)?;
+    let proxied_ts = ProxyRequest {
+        channel_id: channel_id.clone(),
+        files: content.files.take().unwrap_or_default(),
+        content,
+        identity: proxy_as.into(),
+        thread_ts: origin.thread_ts.clone(),
+        options: ProxyOptions {
+            replaces: Some(Original::User(origin.ts.clone())),
+            hold_back: system.hold_back,
+            unfurl: shape.unfurl(),
+            content_hash,
+        },
+    }
+    .send(client, system, db)
+    .await?;
 
-    user_session
-        .chat_delete(
-            &SlackApiChatDeleteRequest::new(channel_id.clone(), origin.ts).with_as_user(true),
+    // The proxy went through either way, so a prompt that can't be sent only leaves the original in place
+    if system.hold_back
+        && let Err(error) = hold_back::prompt(
+            client,
+            system,
+            &channel_id,
+            origin.thread_ts,
+            &origin.ts,
+            &proxied_ts,
         )
         .await
-        .change_context(RewriteMessageError::DeleteMessage)?;
+    {
+        warn!(?error, "Failed to prompt about held back original");
+    }
 
     Ok(())
 }
 
-fn rewrite_content(content: &mut SlackMessageContent, member: &models::DetectedMember) {
+/// Strips the trigger text from a message's content
+///
+/// Regex triggers can pick out any part of the message, which can't be mapped back onto the rich text,
+/// so the formatting is dropped and the plain text sent instead.
+pub fn rewrite_content(content: &mut SlackMessageContent, proxy_as: &ProxyAs) {
     debug!("Rewriting message content");
 
-    if let Some(text) = &mut content.text {
-        match member.typ {
-            trigger::Type::Prefix => {
-                if let Some(new_text) = text.strip_prefix(&member.trigger_text) {
-                    *text = new_text.to_string();
-                }
-            }
-            trigger::Type::Suffix => {
-                if let Some(new_text) = text.strip_suffix(&member.trigger_text) {
-                    *text = new_text.to_string();
-                }
-            }
-        }
+    let (typ, trigger_text) = proxy_as.trigger();
+
+    if let Some(text) = &mut content.text
+        && let Some(new_text) = typ
+            .strip(trigger_text, text)
+            .map(std::borrow::Cow::into_owned)
+    {
+        *text = new_text;
     }
 
+    let Some((prefix, suffix)) = typ.edges(trigger_text) else {
+        content.blocks = None;
+        return;
+    };
+
     if let Some(blocks) = &mut content.blocks {
         for block in blocks {
             if let SlackBlock::RichText(richtext) = block {
@@ -348,30 +738,28 @@ fn rewrite_content(content: &mut SlackMessageContent, member: &models::DetectedM
                 // The first and last elements would have the prefix and suffix respectively, so we can filter them
                 let first = elements.get_mut(0).unwrap();
 
-                if let Some(first_text) = first.pointer_mut("/elements/0/text") {
-                    if member.typ == trigger::Type::Prefix {
-                        if let Some(new_text) = first_text
-                            .as_str()
-                            .and_then(|text| text.strip_prefix(&member.trigger_text))
-                            .map(ToString::to_string)
-                        {
-                            *first_text = serde_json::Value::String(new_text);
-                        }
-                    }
+                if let Some(first_text) = first.pointer_mut("/elements/0/text")
+                    && let Some(new_text) = first_text
+                        .as_str()
+                        .and_then(|text| text.strip_prefix(prefix))
+                        .map(ToString::to_string)
+                {
+                    *first_text = serde_json::Value::String(new_text);
                 }
 
                 let last = elements.get_mut(len - 1).unwrap();
 
-                if let Some(last_text) = last.pointer_mut("/elements/0/text") {
-                    if member.typ == trigger::Type::Suffix {
-                        if let Some(new_text) = last_text
-                            .as_str()
-                            .and_then(|text| text.strip_suffix(&member.trigger_text))
-                            .map(ToString::to_string)
-                        {
-                            *last_text = serde_json::Value::String(new_text);
-                        }
-                    }
+                // The suffix is at the end of the last section, which may start with something else like an emoji
+                if let Some(last_text) = last["elements"]
+                    .as_array_mut()
+                    .and_then(|elements| elements.last_mut())
+                    .and_then(|element| element.get_mut("text"))
+                    && let Some(new_text) = last_text
+                        .as_str()
+                        .and_then(|text| text.strip_suffix(suffix))
+                        .map(ToString::to_string)
+                {
+                    *last_text = serde_json::Value::String(new_text);
                 }
             }
         }
@@ -0,0 +1,157 @@
+//! Managing proxied messages by reacting to them.
+//!
+//! - ❌ from the system owner deletes the message
+//! - ❓ from anyone sends them the info card of the member that sent it
+//! - ✏️ from the system owner offers to edit the message. Reactions can't open forms, so this sends a button that does
+//!
+//! Reactions to messages the bot didn't proxy, or with any other emoji, are ignored.
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::debug;
+
+use crate::{
+    BOT_TOKEN, fields,
+    interactions::message,
+    models::{MessageLog, user},
+    proxy::Original,
+};
+
+/// Action ID of the button that opens the edit form after reacting with ✏️
+pub const EDIT_ACTION: &str = "reaction_edit_message";
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    SlackApi,
+    /// Error while deleting the message
+    Delete,
+    /// Error while getting the message's info
+    Info,
+}
+
+/// What a reaction asks the bot to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Delete,
+    Info,
+    Edit,
+}
+
+impl Command {
+    fn parse(reaction: &SlackReactionName) -> Option<Self> {
+        match &*reaction.0 {
+            "x" => Some(Self::Delete),
+            "question" => Some(Self::Info),
+            "pencil2" => Some(Self::Edit),
+            _ => None,
+        }
+    }
+}
+
+/// Handles a reaction being added to a message
+#[tracing::instrument(skip_all, fields(reaction = %event.reaction.0, user_id = %event.user, system_id))]
+pub async fn handle(
+    event: SlackReactionAddedEvent,
+    client: &SlackHyperClient,
+    user_state: &user::State,
+) -> Result<(), Error> {
+    let Some(command) = Command::parse(&event.reaction) else {
+        return Ok(());
+    };
+
+    let SlackReactionsItem::Message(reacted) = event.item else {
+        return Ok(());
+    };
+
+    let Some(channel_id) = reacted.origin.channel else {
+        debug!("Reacted message has no channel. Ignoring");
+        return Ok(());
+    };
+    let message_id = reacted.origin.ts;
+
+    let Some(log) = MessageLog::fetch_by_message_id(&message_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        debug!("Reacted message wasn't proxied. Ignoring");
+        return Ok(());
+    };
+
+    let system = log
+        .system_id
+        .fetch(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    fields!(system_id = %system.id);
+
+    let session = client.open_session(&BOT_TOKEN);
+
+    match command {
+        Command::Info => {
+            let content = message::info_content(
+                log,
+                &event.user,
+                &channel_id,
+                &message_id,
+                client,
+                user_state,
+            )
+            .await
+            .change_context(Error::Info)?;
+
+            let conversation = session
+                .conversations_open(
+                    &SlackApiConversationsOpenRequest::new().with_users(vec![event.user]),
+                )
+                .await
+                .change_context(Error::SlackApi)?
+                .channel;
+
+            session
+                .chat_post_message(&SlackApiChatPostMessageRequest::new(
+                    conversation.id,
+                    content,
+                ))
+                .await
+                .change_context(Error::SlackApi)?;
+
+            debug!("Sent message info");
+        }
+        // Reacting is something anyone can do, so only the owner's reactions change the message
+        _ if system.owner_id != event.user => {
+            debug!("Reaction isn't from the system owner. Ignoring");
+        }
+        Command::Delete => {
+            Original::Proxied(message_id)
+                .delete(client, &system, channel_id, &user_state.db)
+                .await
+                .change_context(Error::Delete)?;
+
+            debug!("Deleted message");
+        }
+        Command::Edit => {
+            let button = SlackBlockButtonElement::new(EDIT_ACTION.into(), pt!("Edit message"))
+                .with_value(format!("{}_{}", channel_id.0, message_id.0));
+
+            session
+                .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
+                    channel_id,
+                    event.user,
+                    SlackMessageContent::new().with_blocks(slack_blocks![
+                        some_into(SlackSectionBlock::new().with_text(md!("Edit that message?"))),
+                        some_into(SlackActionsBlock::new(vec![button.into()]))
+                    ]),
+                ))
+                .await
+                .change_context(Error::SlackApi)?;
+
+            debug!("Offered to edit message");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! Name recordings uploaded by DMing the bot an audio clip after running `/members record`.
+
+use std::time::Duration;
+
+use error_stack::{Result, ResultExt, report};
+use oauth2::reqwest;
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{debug, info};
+
+use crate::{
+    BOT_TOKEN, fields,
+    models::{self, Blob, member, trust::Trusted, user},
+};
+
+/// The longest clip that can be used as a name recording
+pub const MAX_DURATION: Duration = Duration::from_secs(15);
+
+/// The largest clip that can be used as a name recording, in bytes
+const MAX_SIZE: i64 = 2 * 1024 * 1024;
+
+/// Audio formats browsers can play
+const FORMATS: &[&str] = &[
+    "audio/mpeg",
+    "audio/mp4",
+    "audio/x-m4a",
+    "audio/ogg",
+    "audio/wav",
+    "audio/webm",
+    // Slack records audio clips as webm or mp4 "videos"
+    "video/webm",
+    "video/mp4",
+];
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum RecordingError {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    SlackApi,
+    /// Error while downloading the clip from Slack
+    Download,
+}
+
+/// Whether the message is a file shared in a DM with the bot, which might be a name recording
+pub fn is_candidate(message_event: &SlackMessageEvent) -> bool {
+    message_event
+        .origin
+        .channel_type
+        .as_ref()
+        .is_some_and(|channel_type| channel_type.0 == "im")
+        && message_event
+            .subtype
+            .as_ref()
+            .is_some_and(|subtype| *subtype == SlackMessageEventType::FileShare)
+        && message_event
+            .content
+            .as_ref()
+            .and_then(|content| content.files.as_ref())
+            .is_some_and(|files| !files.is_empty())
+}
+
+#[derive(serde::Deserialize)]
+struct FileInfoResponse {
+    ok: bool,
+    error: Option<String>,
+    file: Option<ClipInfo>,
+}
+
+/// The parts of a file we need to validate a clip.
+///
+/// slack-morphism's [`SlackFile`] doesn't include the duration, so files.info is called directly.
+#[derive(serde::Deserialize)]
+struct ClipInfo {
+    mimetype: Option<String>,
+    size: Option<i64>,
+    duration_ms: Option<u64>,
+    url_private_download: Option<String>,
+}
+
+/// Saves the shared file as a name recording if the sender is waiting to upload one.
+///
+/// Returns whether the message was used as a recording.
+#[tracing::instrument(skip_all, fields(user_id, system_id, member_id))]
+pub async fn handle(
+    message_event: &SlackMessageEvent,
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+) -> Result<bool, RecordingError> {
+    let (Some(user_id), Some(channel_id)) = (
+        message_event.sender.user.clone().map(user::Id::new),
+        message_event.origin.channel.clone(),
+    ) else {
+        return Ok(false);
+    };
+
+    fields!(user_id = %&user_id);
+
+    let Some(system) = models::System::fetch_by_user_id(&user_id, db)
+        .await
+        .change_context(RecordingError::Sqlx)?
+    else {
+        return Ok(false);
+    };
+
+    fields!(system_id = %system.id);
+
+    let Some(member_id) = system
+        .id
+        .take_recording(db)
+        .await
+        .change_context(RecordingError::Sqlx)?
+    else {
+        debug!("System isn't waiting on a recording");
+        return Ok(false);
+    };
+
+    fields!(member_id = %member_id);
+
+    // Checked by is_candidate
+    let file = &message_event
+        .content
+        .as_ref()
+        .unwrap()
+        .files
+        .as_ref()
+        .unwrap()[0];
+
+    let http = reqwest::Client::new();
+    let clip = fetch_clip_info(&http, &system, &file.id).await?;
+
+    if let Err(problem) = validate(&clip) {
+        debug!(problem, "Clip isn't usable as a recording");
+
+        // Let them try again with a different clip
+        system
+            .id
+            .start_recording(member_id, db)
+            .await
+            .change_context(RecordingError::Sqlx)?;
+
+        reply(
+            client,
+            &channel_id,
+            &format!("{problem} Send another clip to try again."),
+        )
+        .await?;
+        return Ok(true);
+    }
+
+    let data = download(&http, &system, &clip).await?;
+
+    save(&system, member_id, &clip, &data, db).await?;
+
+    info!("Saved name recording");
+
+    reply(client, &channel_id, "Saved the name recording!").await?;
+
+    Ok(true)
+}
+
+async fn fetch_clip_info(
+    http: &reqwest::Client,
+    system: &models::System,
+    file_id: &SlackFileId,
+) -> Result<ClipInfo, RecordingError> {
+    let response: FileInfoResponse = http
+        .get("https://slack.com/api/files.info")
+        .query(&[("file", file_id.0.as_str())])
+        .bearer_auth(system.slack_oauth_token.expose())
+        .send()
+        .await
+        .change_context(RecordingError::SlackApi)?
+        .text()
+        .await
+        .change_context(RecordingError::SlackApi)
+        .and_then(|body| serde_json::from_str(&body).change_context(RecordingError::SlackApi))?;
+
+    match response {
+        FileInfoResponse {
+            ok: true,
+            file: Some(file),
+            ..
+        } => Ok(file),
+        FileInfoResponse { error, .. } => Err(report!(RecordingError::SlackApi))
+            .attach_printable(format!("files.info failed: {error:?}")),
+    }
+}
+
+/// Checks the clip can be used as a recording, returning the problem to show the user if not
+fn validate(clip: &ClipInfo) -> std::result::Result<(), &'static str> {
+    if !clip
+        .mimetype
+        .as_deref()
+        .is_some_and(|mimetype| FORMATS.contains(&mimetype))
+    {
+        return Err("That doesn't look like an audio clip.");
+    }
+
+    if clip.size.is_none_or(|size| size > MAX_SIZE) {
+        return Err("That clip is too large.");
+    }
+
+    // Clips without a duration are uploaded files rather than recordings, which we can't check the length of
+    if clip
+        .duration_ms
+        .is_some_and(|duration| Duration::from_millis(duration) > MAX_DURATION)
+    {
+        return Err("That clip is too long.");
+    }
+
+    Ok(())
+}
+
+async fn download(
+    http: &reqwest::Client,
+    system: &models::System,
+    clip: &ClipInfo,
+) -> Result<Vec<u8>, RecordingError> {
+    let url = clip
+        .url_private_download
+        .as_deref()
+        .ok_or_else(|| report!(RecordingError::Download))
+        .attach_printable("Clip has no download URL")?;
+
+    http.get(url)
+        .bearer_auth(system.slack_oauth_token.expose())
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .change_context(RecordingError::Download)?
+        .bytes()
+        .await
+        .change_context(RecordingError::Download)
+        .map(|bytes| bytes.to_vec())
+}
+
+async fn save(
+    system: &models::System,
+    member_id: member::Id<Trusted>,
+    clip: &ClipInfo,
+    data: &[u8],
+    db: &SqlitePool,
+) -> Result<(), RecordingError> {
+    // Validated to be present
+    let content_type = clip.mimetype.as_deref().unwrap_or_default();
+
+    let token = Blob::insert(system.id, content_type, data, db)
+        .await
+        .change_context(RecordingError::Sqlx)?;
+
+    member_id
+        .set_name_recording_url(&Blob::url(&token), db)
+        .await
+        .change_context(RecordingError::Sqlx)?;
+
+    Ok(())
+}
+
+async fn reply(
+    client: &SlackHyperClient,
+    channel_id: &SlackChannelId,
+    text: &str,
+) -> Result<(), RecordingError> {
+    client
+        .open_session(&BOT_TOKEN)
+        .chat_post_message(&SlackApiChatPostMessageRequest::new(
+            channel_id.clone(),
+            SlackMessageContent::new().with_text(text.into()),
+        ))
+        .await
+        .change_context(RecordingError::SlackApi)
+        .map(|_| ())
+}
@@ -0,0 +1,140 @@
+//! Dry runs of message handling, for working out why a message was or wasn't proxied.
+//!
+//! Nothing here proxies, switches, or latches anything, so it's safe to run on any event, as many times as needed.
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+
+use super::{ProxyAs, PushEventError, Reason, Unwrapped, resolve, rewrite_content, shape::Shape};
+use crate::models::{self, user};
+
+/// Goes through the same steps as handling the event would, describing what each one decided.
+///
+/// Blacklisting, quiet hours and matching use the system as it is now, which may differ from when the event was sent.
+pub async fn dry_run(
+    event: SlackMessageEvent,
+    db: &SqlitePool,
+) -> Result<Vec<String>, PushEventError> {
+    let Some(unwrapped) = Unwrapped::new(event) else {
+        return Ok(vec![
+            "Kind: change that isn't an edit, like a link preview. It wouldn't be proxied.".into(),
+        ]);
+    };
+
+    let mut lines = vec![
+        format!(
+            "Kind: {}",
+            if unwrapped.edited {
+                "edit"
+            } else {
+                "new message"
+            }
+        ),
+        format!("Text length: {}", text_length(unwrapped.content.as_ref())),
+    ];
+
+    let Some(user_id) = unwrapped.sender.user.map(user::Id::new) else {
+        lines.push("No user sent this, so it wouldn't be proxied.".into());
+        return Ok(lines);
+    };
+
+    lines.push(format!("Sender: {}", user_id.id.0.to_slack_format()));
+
+    let Some(channel_id) = unwrapped.origin.channel else {
+        lines.push("No channel, so it wouldn't be proxied.".into());
+        return Ok(lines);
+    };
+
+    lines.push(format!("Channel: {}", channel_id.to_slack_format()));
+
+    let Some(system) = models::System::fetch_by_user_id(&user_id, db)
+        .await
+        .change_context(PushEventError::SystemFetch)?
+    else {
+        lines.push("The sender has no system, so it wouldn't be proxied.".into());
+        return Ok(lines);
+    };
+
+    lines.push(format!("System: `{}`", system.id));
+
+    let blacklisted = system
+        .id
+        .is_blacklisted(&channel_id, db)
+        .await
+        .change_context(PushEventError::SystemFetch)?;
+
+    lines.push(format!("Channel blacklisted: {}", yes_no(blacklisted)));
+    lines.push(format!(
+        "In quiet hours: {}",
+        yes_no(system.in_quiet_hours())
+    ));
+
+    let Some(content) = unwrapped.content else {
+        lines.push("No content, so it wouldn't be proxied.".into());
+        return Ok(lines);
+    };
+
+    lines.extend(describe_match(&system, content, db).await?);
+
+    Ok(lines)
+}
+
+/// Describes who the message would be proxied as, and the shape it would be left in
+pub async fn describe_match(
+    system: &models::System,
+    mut content: SlackMessageContent,
+    db: &SqlitePool,
+) -> Result<Vec<String>, PushEventError> {
+    let Some((proxy_as, reason)) = resolve(system, content.text.as_deref(), db).await? else {
+        return Ok(vec!["Match: no one, so it wouldn't be proxied".into()]);
+    };
+
+    let matched = match &proxy_as {
+        ProxyAs::Member(member) => format!(
+            "Match: {} (`{}`) by {reason}",
+            member.display_name, member.id
+        ),
+        ProxyAs::Anonymous { trigger_text } => {
+            format!("Match: anonymous by {reason} (`{trigger_text}`)")
+        }
+    };
+
+    let trigger = match (&proxy_as, reason) {
+        (ProxyAs::Member(member), Reason::Trigger) => Some(format!(
+            "Trigger: `{}` ({})",
+            member.trigger_text, member.typ
+        )),
+        _ => None,
+    };
+
+    rewrite_content(&mut content, &proxy_as);
+    let shape = Shape::of(&content);
+
+    Ok([
+        Some(matched),
+        trigger,
+        Some(format!(
+            "Shape after stripping the trigger: {shape}{}",
+            if shape.can_proxy() {
+                ""
+            } else {
+                ", so it wouldn't be proxied"
+            }
+        )),
+    ]
+    .into_iter()
+    .flatten()
+    .collect())
+}
+
+/// How long the message's text is. The text itself isn't shown, as it's someone else's message
+fn text_length(content: Option<&SlackMessageContent>) -> usize {
+    content
+        .and_then(|content| content.text.as_ref())
+        .map_or(0, |text| text.chars().count())
+}
+
+const fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
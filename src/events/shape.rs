@@ -0,0 +1,308 @@
+//! Working out what a message is made of, so messages Slack renders specially keep that rendering when proxied.
+//!
+//! Slack shows emoji-only messages with larger emoji, and link-only messages (like a GIF from Giphy or Tenor) as a preview.
+//! Both only happen when nothing else is in the message, so leftover whitespace from the trigger has to go.
+
+use slack_morphism::prelude::*;
+
+/// The kind of content in a message, after its trigger has been stripped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, displaydoc::Display)]
+pub enum Shape {
+    /// text
+    Text,
+    /// emoji only
+    Emoji,
+    /// a single link
+    Link,
+    /// attachments only
+    Attachments,
+}
+
+impl Shape {
+    /// Works out the shape of a message's content
+    pub fn of(content: &SlackMessageContent) -> Self {
+        let text = content.text.as_deref().unwrap_or_default().trim();
+
+        if text.is_empty() {
+            return if content
+                .attachments
+                .as_ref()
+                .is_some_and(|attachments| !attachments.is_empty())
+            {
+                Self::Attachments
+            } else {
+                Self::Text
+            };
+        }
+
+        if let Some(elements) = rich_text_elements(content) {
+            let mut elements = elements.filter(|element| !is_blank(element)).peekable();
+
+            if elements.peek().is_none() {
+                return Self::Text;
+            }
+
+            let types = elements
+                .map(|element| element["type"].as_str().unwrap_or_default())
+                .collect::<Vec<_>>();
+
+            return match types.as_slice() {
+                ["link"] => Self::Link,
+                types if types.iter().all(|typ| *typ == "emoji") => Self::Emoji,
+                _ => Self::Text,
+            };
+        }
+
+        // Without rich text, fall back to Slack's markup: `:emoji:` and `<https://link|label>`
+        if is_link_markup(text) {
+            Self::Link
+        } else if text.split_whitespace().all(is_emoji_markup) {
+            Self::Emoji
+        } else {
+            Self::Text
+        }
+    }
+
+    /// Tidies up the content so Slack renders it the same way as the original message.
+    ///
+    /// Messages that are only attachments can't be re-posted without losing their media, so they shouldn't be proxied
+    /// at all. See [`Shape::can_proxy`].
+    pub fn tidy(self, content: &mut SlackMessageContent) {
+        match self {
+            Self::Text | Self::Attachments => {}
+            Self::Emoji | Self::Link => {
+                if let Some(text) = &mut content.text {
+                    *text = text.trim().to_string();
+                }
+
+                if let Some(blocks) = &mut content.blocks {
+                    for block in blocks {
+                        if let SlackBlock::RichText(richtext) = block {
+                            remove_blank_elements(richtext);
+                        }
+                    }
+                }
+
+                if self == Self::Link {
+                    // Link previews are re-created by Slack when the proxied message is posted
+                    content.attachments = None;
+                }
+            }
+        }
+    }
+
+    /// Whether a message with this shape can be proxied without degrading it
+    pub const fn can_proxy(self) -> bool {
+        !matches!(self, Self::Attachments)
+    }
+
+    /// Whether Slack should preview the links in the proxied message
+    pub const fn unfurl(self) -> bool {
+        matches!(self, Self::Link)
+    }
+}
+
+/// The inline elements of a message's rich text, if it has any
+fn rich_text_elements(
+    content: &SlackMessageContent,
+) -> Option<impl Iterator<Item = &serde_json::Value>> {
+    let sections = content
+        .blocks
+        .as_ref()?
+        .iter()
+        .filter_map(|block| match block {
+            SlackBlock::RichText(richtext) => richtext["elements"].as_array(),
+            _ => None,
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+
+    if sections.is_empty() {
+        return None;
+    }
+
+    Some(
+        sections
+            .into_iter()
+            .filter_map(|section| section["elements"].as_array())
+            .flatten(),
+    )
+}
+
+/// Whether the element is text with nothing but whitespace, like what's left after stripping a trigger
+fn is_blank(element: &serde_json::Value) -> bool {
+    element["type"] == "text"
+        && element["text"]
+            .as_str()
+            .is_some_and(|text| text.trim().is_empty())
+}
+
+/// Removes blank text elements, and any sections left empty
+fn remove_blank_elements(richtext: &mut serde_json::Value) {
+    let Some(sections) = richtext["elements"].as_array_mut() else {
+        return;
+    };
+
+    for section in sections.iter_mut() {
+        if let Some(elements) = section["elements"].as_array_mut() {
+            elements.retain(|element| !is_blank(element));
+        }
+    }
+
+    sections.retain(|section| {
+        section["elements"]
+            .as_array()
+            .is_none_or(|elements| !elements.is_empty())
+    });
+}
+
+/// Whether the text is a single emoji like `:tada:` or `:wave::skin-tone-2:`
+fn is_emoji_markup(text: &str) -> bool {
+    text.len() > 2
+        && text.starts_with(':')
+        && text.ends_with(':')
+        && text[1..text.len() - 1].split("::").all(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '\''))
+        })
+}
+
+/// Whether the text is a single link like `<https://giphy.com/...>` or `<https://giphy.com/...|label>`
+fn is_link_markup(text: &str) -> bool {
+    text.strip_prefix('<')
+        .and_then(|text| text.strip_suffix('>'))
+        .is_some_and(|link| {
+            (link.starts_with("https://") || link.starts_with("http://"))
+                && !link.contains(['<', '>'])
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn content(value: serde_json::Value) -> SlackMessageContent {
+        serde_json::from_value(value).expect("Content to deserialize")
+    }
+
+    fn text(text: &str) -> SlackMessageContent {
+        content(json!({ "text": text }))
+    }
+
+    /// Content with one rich text section holding `elements`, like Slack sends for typed messages
+    fn rich_text(text: &str, elements: serde_json::Value) -> SlackMessageContent {
+        content(json!({
+            "text": text,
+            "blocks": [{
+                "type": "rich_text",
+                "elements": [{ "type": "rich_text_section", "elements": elements }],
+            }],
+        }))
+    }
+
+    #[test]
+    fn markup() {
+        assert_eq!(Shape::of(&text("hello there")), Shape::Text);
+        assert_eq!(Shape::of(&text(" :tada: ")), Shape::Emoji);
+        assert_eq!(Shape::of(&text(":tada: :wave::skin-tone-2:")), Shape::Emoji);
+        assert_eq!(
+            Shape::of(&text("<https://giphy.com/gifs/abc|abc>")),
+            Shape::Link
+        );
+        assert_eq!(
+            Shape::of(&text("<https://tenor.com/view/abc>")),
+            Shape::Link
+        );
+        assert_eq!(
+            Shape::of(&text("look <https://tenor.com/view/abc>")),
+            Shape::Text
+        );
+        assert_eq!(Shape::of(&text("<@U0123456>")), Shape::Text);
+        assert_eq!(Shape::of(&text(":tada: nice")), Shape::Text);
+        assert_eq!(Shape::of(&text("::")), Shape::Text);
+        assert_eq!(Shape::of(&text(":not:emoji:")), Shape::Text);
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(Shape::of(&text("  ")), Shape::Text);
+        assert_eq!(
+            Shape::of(&content(
+                json!({ "text": "", "attachments": [{ "fallback": "file" }] })
+            )),
+            Shape::Attachments
+        );
+        assert_eq!(
+            Shape::of(&content(json!({ "text": "", "attachments": [] }))),
+            Shape::Text
+        );
+    }
+
+    #[test]
+    fn rich() {
+        assert_eq!(
+            Shape::of(&rich_text(
+                " :tada:",
+                json!([
+                    { "type": "text", "text": " " },
+                    { "type": "emoji", "name": "tada" },
+                    { "type": "emoji", "name": "wave" },
+                ])
+            )),
+            Shape::Emoji
+        );
+        assert_eq!(
+            Shape::of(&rich_text(
+                " <https://giphy.com/gifs/abc>",
+                json!([
+                    { "type": "text", "text": "  " },
+                    { "type": "link", "url": "https://giphy.com/gifs/abc" },
+                ])
+            )),
+            Shape::Link
+        );
+        assert_eq!(
+            Shape::of(&rich_text(
+                "see <https://giphy.com/gifs/abc>",
+                json!([
+                    { "type": "text", "text": "see " },
+                    { "type": "link", "url": "https://giphy.com/gifs/abc" },
+                ])
+            )),
+            Shape::Text
+        );
+        assert_eq!(
+            Shape::of(&rich_text(
+                "<https://a.example> <https://b.example>",
+                json!([
+                    { "type": "link", "url": "https://a.example" },
+                    { "type": "text", "text": " " },
+                    { "type": "link", "url": "https://b.example" },
+                ])
+            )),
+            Shape::Text
+        );
+    }
+
+    #[test]
+    fn tidy_link() {
+        let mut message = content(json!({
+            "text": " <https://giphy.com/gifs/abc> ",
+            "attachments": [{ "fallback": "preview" }],
+        }));
+
+        let shape = Shape::of(&message);
+        shape.tidy(&mut message);
+
+        assert_eq!(shape, Shape::Link);
+        assert_eq!(
+            message.text.as_deref(),
+            Some("<https://giphy.com/gifs/abc>")
+        );
+        assert!(message.attachments.is_none());
+    }
+}
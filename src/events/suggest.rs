@@ -0,0 +1,137 @@
+//! Suggesting members to proxy an untriggered message as.
+//!
+//! The message is stashed in memory while the owner decides, so it can be proxied without fetching it again.
+//! Stashed messages expire after [`STASH_TTL`], or are lost if the bot restarts; the prompt then just says it expired.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::debug;
+
+use crate::{BOT_TOKEN, models, render::MemberReference};
+
+/// How many members to suggest
+const SUGGESTIONS: i64 = 3;
+/// How long a message waits to be proxied before the suggestion expires
+const STASH_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Action ID prefix for suggestion buttons. Each button gets its index appended, as action IDs must be unique in a block
+pub const ACTION_PREFIX: &str = "proxy_suggestion";
+
+/// Messages waiting on a suggestion to be picked, keyed by [`stash_key`]
+static STASH: LazyLock<Mutex<HashMap<String, Stashed>>> = LazyLock::new(Mutex::default);
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    SlackApi,
+}
+
+/// A message waiting for the owner to pick a member
+#[derive(Debug, Clone)]
+pub struct Stashed {
+    pub content: SlackMessageContent,
+    pub thread_ts: Option<SlackTs>,
+    stashed_at: Instant,
+}
+
+fn stash_key(channel_id: &SlackChannelId, message_id: &SlackTs) -> String {
+    format!("{}_{}", channel_id.0, message_id.0)
+}
+
+/// Takes a stashed message, if it hasn't expired
+pub fn take(channel_id: &SlackChannelId, message_id: &SlackTs) -> Option<Stashed> {
+    STASH
+        .lock()
+        .expect("Suggestion stash lock to not be poisoned")
+        .remove(&stash_key(channel_id, message_id))
+        .filter(|stashed| stashed.stashed_at.elapsed() < STASH_TTL)
+}
+
+/// How many messages are waiting on a suggestion, including expired ones that haven't been cleaned up yet
+pub fn stashed() -> usize {
+    STASH
+        .lock()
+        .expect("Suggestion stash lock to not be poisoned")
+        .len()
+}
+
+/// Sends the owner a private prompt to proxy their message as one of their recently used members.
+///
+/// Does nothing if the system hasn't proxied as any member yet.
+#[tracing::instrument(skip_all, fields(system_id = %system.id))]
+pub async fn offer(
+    client: &SlackHyperClient,
+    system: &models::System,
+    origin: &SlackMessageOrigin,
+    channel_id: &SlackChannelId,
+    content: SlackMessageContent,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let members = system
+        .recently_used_members(SUGGESTIONS, db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    if members.is_empty() {
+        debug!("No recently used members to suggest");
+        return Ok(());
+    }
+
+    {
+        let mut stash = STASH
+            .lock()
+            .expect("Suggestion stash lock to not be poisoned");
+        stash.retain(|_, stashed| stashed.stashed_at.elapsed() < STASH_TTL);
+        stash.insert(
+            stash_key(channel_id, &origin.ts),
+            Stashed {
+                content,
+                thread_ts: origin.thread_ts.clone(),
+                stashed_at: Instant::now(),
+            },
+        );
+    }
+
+    let buttons = members
+        .iter()
+        .enumerate()
+        .map(|(index, member)| {
+            SlackBlockButtonElement::new(
+                format!("{ACTION_PREFIX}_{index}").into(),
+                pt!(MemberReference::new(member).plain()),
+            )
+            .with_value(format!("{}_{}_{}", member.id, channel_id.0, origin.ts.0))
+            .into()
+        })
+        .collect();
+
+    client
+        .open_session(&BOT_TOKEN)
+        .chat_post_ephemeral(
+            &SlackApiChatPostEphemeralRequest::new(
+                channel_id.clone(),
+                system.owner_id.clone().into(),
+                SlackMessageContent::new().with_blocks(slack_blocks![
+                    some_into(
+                        SlackSectionBlock::new()
+                            .with_text(md!("No one is fronting. Proxy that message as..."))
+                    ),
+                    some_into(SlackActionsBlock::new(buttons))
+                ]),
+            )
+            .opt_thread_ts(origin.thread_ts.clone()),
+        )
+        .await
+        .change_context(Error::SlackApi)?;
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! `GET /healthz`: whether the bot is working, for container orchestrators and uptime checks.
+//!
+//! Responds with `503 Service Unavailable` if the database can't be used, as restarting might help. A Slack token
+//! that doesn't work only marks the bot as degraded, since restarting won't fix it, and Slack being down shouldn't
+//! get the bot restarted over and over.
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{Extension, Json, extract::State, http::StatusCode};
+use serde_json::{Value, json};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::debug;
+
+use crate::{BOT_TOKEN, models::user};
+
+/// How long a Slack token check is reused for, so frequent health checks don't each call Slack
+const SLACK_CHECK_TTL: Duration = Duration::from_secs(60);
+
+/// The last Slack token check: when it ran, and the error code if it failed
+static SLACK_CHECK: LazyLock<Mutex<Option<(Instant, Option<String>)>>> =
+    LazyLock::new(Mutex::default);
+
+#[tracing::instrument(skip_all)]
+pub async fn health_handler(
+    State(state): State<user::State>,
+    Extension(client): Extension<Arc<SlackHyperClient>>,
+) -> (StatusCode, Json<Value>) {
+    let database = check_database(&state.db).await;
+    let migrations = check_migrations(&state.db).await;
+    let slack_error = check_slack(&client).await;
+
+    let database_ok = database.is_ok();
+    let migrations_ok = migrations.as_ref().is_ok_and(Vec::is_empty);
+
+    let (code, status) = if !database_ok || !migrations_ok {
+        (StatusCode::SERVICE_UNAVAILABLE, "down")
+    } else if slack_error.is_some() {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "ok")
+    };
+
+    debug!(status, "Checked health");
+
+    let body = json!({
+        "status": status,
+        "database": {
+            "ok": database_ok,
+            "error": database.err(),
+            "connections": state.db.size(),
+            "idle_connections": state.db.num_idle(),
+        },
+        "migrations": match migrations {
+            Ok(pending) => json!({ "ok": pending.is_empty(), "pending": pending }),
+            Err(error) => json!({ "ok": false, "error": error }),
+        },
+        "slack": {
+            "ok": slack_error.is_none(),
+            "error": slack_error,
+        },
+    });
+
+    (code, Json(body))
+}
+
+/// Runs a query, to check the database can be reached
+async fn check_database(db: &SqlitePool) -> Result<(), String> {
+    sqlx::query_scalar!(r#"SELECT 1 as "ok!: i64""#)
+        .fetch_one(db)
+        .await
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+/// The versions of migrations this build has that haven't been applied to the database
+async fn check_migrations(db: &SqlitePool) -> Result<Vec<i64>, String> {
+    let applied = sqlx::query_scalar!("SELECT version FROM _sqlx_migrations WHERE success = TRUE")
+        .fetch_all(db)
+        .await
+        .map_err(|error| error.to_string())?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    Ok(sqlx::migrate!()
+        .migrations
+        .iter()
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect())
+}
+
+/// Checks the bot token still works, returning Slack's error code if it doesn't
+async fn check_slack(client: &SlackHyperClient) -> Option<String> {
+    let cached = SLACK_CHECK
+        .lock()
+        .expect("Slack health check lock to not be poisoned")
+        .clone()
+        .filter(|(checked_at, _)| checked_at.elapsed() < SLACK_CHECK_TTL);
+
+    if let Some((_, error)) = cached {
+        return error;
+    }
+
+    let error = match client.open_session(&BOT_TOKEN).auth_test().await {
+        Ok(_) => None,
+        Err(SlackClientError::ApiError(error)) => Some(error.code),
+        Err(error) => Some(error.to_string()),
+    };
+
+    *SLACK_CHECK
+        .lock()
+        .expect("Slack health check lock to not be poisoned") =
+        Some((Instant::now(), error.clone()));
+
+    error
+}
@@ -0,0 +1,340 @@
+//! The bot's App Home tab, showing the user their system at a glance.
+//!
+//! The tab is published whenever the user opens it, and again whenever the system switches,
+//! so the fronting member at the top of it is current without running a command.
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{debug, info, warn};
+
+use crate::{
+    BOT_TOKEN, cache,
+    models::{
+        Member, Switch, System, member,
+        trust::Trusted,
+        user::{self, State},
+        viewer::Viewer,
+    },
+    render::{self, MemberReference},
+};
+
+/// Action ID of the "Switch" button next to each member
+pub const SWITCH_ACTION: &str = "home_switch_member";
+/// Action ID of the "Switch to base account" button
+pub const SWITCH_BASE_ACTION: &str = "home_switch_base";
+/// Action ID of the "Edit" button next to each member
+pub const EDIT_ACTION: &str = "home_edit_member";
+
+/// How many switches are shown on the tab
+const RECENT_SWITCHES: u16 = 5;
+/// How many members are shown on the tab. Each member takes two blocks, and a view can only have 100
+const MEMBERS_SHOWN: usize = 40;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    Slack,
+}
+
+/// Renders the user's home tab and publishes it
+#[tracing::instrument(skip(client, db))]
+pub async fn publish(
+    user_id: &SlackUserId,
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let view = match System::fetch_by_user_id(&user::Id::new(user_id.clone()), db)
+        .await
+        .change_context(Error::Sqlx)?
+    {
+        Some(system) => {
+            if let Some(view) = cache::HOME_TABS.get(system.id, ()) {
+                view
+            } else {
+                let fronting = system
+                    .fronting_members(db)
+                    .await
+                    .change_context(Error::Sqlx)?;
+                let members = system.members(db).await.change_context(Error::Sqlx)?;
+                let switches = Switch::fetch_recent(system.id, RECENT_SWITCHES, db)
+                    .await
+                    .change_context(Error::Sqlx)?;
+
+                let view = system_view(&system, &members, fronting, &switches);
+                cache::HOME_TABS.insert(
+                    system.id,
+                    (),
+                    members.iter().map(|member| member.id),
+                    view.clone(),
+                );
+                view
+            }
+        }
+        None => no_system_view(),
+    };
+
+    client
+        .open_session(&BOT_TOKEN)
+        .views_publish(&SlackApiViewsPublishRequest::new(user_id.clone(), view))
+        .await
+        .attach_printable("Failed to publish home tab")
+        .change_context(Error::Slack)?;
+
+    debug!("Published home tab");
+
+    Ok(())
+}
+
+/// Publishes the home tab again after something on it changed, like the fronting member.
+///
+/// Failing to refresh shouldn't fail whatever changed it, so errors are only logged.
+pub async fn refresh(user_id: &SlackUserId, client: &SlackHyperClient, db: &SqlitePool) {
+    if let Err(error) = publish(user_id, client, db).await {
+        warn!(?error, "Failed to refresh home tab");
+    }
+}
+
+fn no_system_view() -> SlackView {
+    SlackView::Home(SlackHomeView::new(slack_blocks![
+        some_into(SlackHeaderBlock::new("Welcome!".into())),
+        some_into(SlackSectionBlock::new().with_text(md!(
+            "You don't have a system yet. Run `/system create` to get started, then `/members add` to add your members."
+        )))
+    ]))
+}
+
+fn system_view(
+    system: &System,
+    members: &[Member],
+    fronting: Vec<Member>,
+    switches: &[Switch],
+) -> SlackView {
+    let member_count = members.iter().filter(|member| member.enabled).count();
+    let fronter_ids = fronting.iter().map(|member| member.id).collect::<Vec<_>>();
+    let status = format!(
+        "{} · {member_count} {}",
+        if fronting.is_empty() {
+            "No one fronting".to_string()
+        } else {
+            format!(
+                "Fronting: {}",
+                fronting
+                    .iter()
+                    .map(|member| MemberReference::new(member).mrkdwn())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        },
+        if member_count == 1 {
+            "member"
+        } else {
+            "members"
+        }
+    );
+
+    let mut blocks = vec![
+        SlackHeaderBlock::new("Your system".into()).into(),
+        SlackContextBlock::new(vec![md!(status)]).into(),
+    ];
+
+    blocks.extend(render::system_card(
+        system,
+        members,
+        fronting,
+        Viewer::Owner,
+    ));
+
+    if !fronter_ids.is_empty() {
+        blocks.push(
+            SlackActionsBlock::new(vec![
+                SlackBlockButtonElement::new(
+                    SWITCH_BASE_ACTION.into(),
+                    pt!("Switch to base account"),
+                )
+                .into(),
+            ])
+            .into(),
+        );
+    }
+
+    blocks.push(SlackDividerBlock::new().into());
+    blocks.push(SlackHeaderBlock::new("Members".into()).into());
+
+    let enabled = members
+        .iter()
+        .filter(|member| member.enabled)
+        .collect::<Vec<_>>();
+
+    if enabled.is_empty() {
+        blocks.push(
+            SlackSectionBlock::new()
+                .with_text(md!("No members yet. Add one with `/members add`."))
+                .into(),
+        );
+    }
+
+    for member in enabled.iter().take(MEMBERS_SHOWN) {
+        blocks.push(
+            MemberReference::new(member)
+                .with_full_name()
+                .section()
+                .into(),
+        );
+        blocks.push(member_actions(&fronter_ids, member).into());
+    }
+
+    if enabled.len() > MEMBERS_SHOWN {
+        blocks.push(
+            SlackContextBlock::new(vec![md!(
+                "And {} more. Use `/members list` to see everyone.",
+                enabled.len() - MEMBERS_SHOWN
+            )])
+            .into(),
+        );
+    }
+
+    if !switches.is_empty() {
+        blocks.push(SlackDividerBlock::new().into());
+        blocks.extend(render::switch_history(switches, members));
+    }
+
+    SlackView::Home(SlackHomeView::new(blocks))
+}
+
+/// The buttons under a member. Members that can't be switched to only get the edit button
+fn member_actions(fronter_ids: &[member::Id<Trusted>], member: &Member) -> SlackActionsBlock {
+    let mut actions: Vec<SlackActionBlockElement> = Vec::new();
+
+    // Switching replaces everyone fronting, so it's still useful for a co-fronter
+    if member.proxy_enabled && fronter_ids != [member.id] {
+        actions.push(
+            SlackBlockButtonElement::new(SWITCH_ACTION.into(), pt!("Switch"))
+                .with_value(member.id.to_string())
+                .into(),
+        );
+    }
+
+    actions.push(
+        SlackBlockButtonElement::new(EDIT_ACTION.into(), pt!("Edit"))
+            .with_value(member.id.to_string())
+            .into(),
+    );
+
+    SlackActionsBlock::new(actions)
+}
+
+/// The member a home tab button is for, if it belongs to the user who pressed it
+async fn button_member(
+    user_id: &user::Id<Trusted>,
+    value: &str,
+    db: &SqlitePool,
+) -> Result<Option<member::Id<Trusted>>, Error> {
+    let Ok(member_id) = value.parse::<i64>().map(member::Id::new) else {
+        warn!(value, "Malformed member id on home tab button. Bailing");
+        return Ok(None);
+    };
+
+    let member_id = member_id
+        .validate_by_user(user_id, db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    if member_id.is_none() {
+        warn!(
+            value,
+            "Member doesn't belong to the user. Bailing in case this was a malicious call"
+        );
+    }
+
+    Ok(member_id)
+}
+
+/// Handles the switch buttons on the home tab. `value` is the member's ID, or [`None`] to switch to the base account
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn switch(
+    event: &SlackInteractionBlockActionsEvent,
+    value: Option<&str>,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user) = event.user.as_ref() else {
+        warn!("No user found on home tab switch. Bailing");
+        return Ok(());
+    };
+    let user_id = user::Id::<Trusted>::from(user.id.clone());
+
+    let member_id = match value {
+        Some(value) => {
+            let Some(member_id) = button_member(&user_id, value, &user_state.db).await? else {
+                return Ok(());
+            };
+
+            if !member_id
+                .proxy_enabled(&user_state.db)
+                .await
+                .change_context(Error::Sqlx)?
+            {
+                debug!("Member has proxying turned off");
+                return publish(&user.id, client, &user_state.db).await;
+            }
+
+            Some(member_id)
+        }
+        None => None,
+    };
+
+    let Some(system) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        return Ok(());
+    };
+
+    system
+        .id
+        .change_fronting_member(member_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    info!(?member_id, "Switched from home tab");
+
+    publish(&user.id, client, &user_state.db).await
+}
+
+/// Handles the edit buttons on the home tab by opening the member's edit modal
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn edit(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user) = event.user.as_ref() else {
+        warn!("No user found on home tab edit. Bailing");
+        return Ok(());
+    };
+    let user_id = user::Id::<Trusted>::from(user.id.clone());
+
+    let Some(member_id) = button_member(&user_id, value, &user_state.db).await? else {
+        return Ok(());
+    };
+
+    let member = Member::fetch_by_id(member_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    client
+        .open_session(&BOT_TOKEN)
+        .views_open(&SlackApiViewsOpenRequest::new(
+            event.trigger_id.clone(),
+            member::View::from(member).create_edit_view(member_id),
+        ))
+        .await
+        .attach_printable("Error opening view")
+        .change_context(Error::Slack)?;
+
+    Ok(())
+}
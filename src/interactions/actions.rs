@@ -0,0 +1,292 @@
+//! Routing block actions (buttons and menus in messages and the home tab) to the feature that sent them.
+//!
+//! Features register their action IDs in [`REGISTRY`], either exactly or as a prefix for actions that carry data in
+//! their ID. Actions nothing is registered for are logged and dropped.
+
+use std::{error::Error, sync::Arc};
+
+use futures::{FutureExt, future::BoxFuture};
+use slack_morphism::prelude::*;
+use tracing::warn;
+
+use super::{member, message, system};
+use crate::{
+    commands,
+    events::{hold_back, reaction, suggest},
+    home,
+    models::user,
+};
+
+type HandlerResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+/// Handles a single action. Plain functions so they can live in [`REGISTRY`]
+type Handler = for<'a> fn(Action<'a>) -> BoxFuture<'a, HandlerResult>;
+
+/// A single action from a block actions event
+pub struct Action<'a> {
+    pub event: &'a SlackInteractionBlockActionsEvent,
+    /// The button's value, or the value of the option picked from a menu. Empty if there's neither
+    pub value: &'a str,
+    pub client: &'a Arc<SlackHyperClient>,
+    /// For handlers that run commands, which fetch the user state themselves
+    pub states: &'a SlackClientEventsUserState,
+    pub user_state: &'a user::State,
+}
+
+/// Which action IDs a handler is for
+enum Matcher {
+    Exact(&'static str),
+    Prefix(&'static str),
+}
+
+impl Matcher {
+    fn matches(&self, action_id: &str) -> bool {
+        match self {
+            Self::Exact(id) => action_id == *id,
+            Self::Prefix(prefix) => action_id.starts_with(prefix),
+        }
+    }
+}
+
+/// Every block action the bot handles. The first match wins
+const REGISTRY: &[(Matcher, Handler)] = &[
+    (
+        Matcher::Exact("message_info_consent_approve"),
+        approve_info_consent,
+    ),
+    (
+        Matcher::Exact("message_info_consent_deny"),
+        deny_info_consent,
+    ),
+    (Matcher::Exact("switch_to_member"), switch_to_member),
+    (Matcher::Exact(member::PICK_SWITCH_ACTION), pick_switch),
+    (Matcher::Exact(member::PICK_INFO_ACTION), pick_info),
+    (Matcher::Exact("add_another_member"), add_another_member),
+    (Matcher::Exact("undo_member_disable"), undo_member_disable),
+    (Matcher::Exact(home::SWITCH_ACTION), home_switch),
+    (Matcher::Exact(home::SWITCH_BASE_ACTION), home_switch_base),
+    (Matcher::Exact(home::EDIT_ACTION), home_edit),
+    (Matcher::Exact(reaction::EDIT_ACTION), edit_from_reaction),
+    (Matcher::Prefix(suggest::ACTION_PREFIX), proxy_suggestion),
+    (Matcher::Exact(hold_back::CONFIRM_ACTION), confirm_hold_back),
+    (Matcher::Exact(hold_back::UNDO_ACTION), undo_hold_back),
+    (Matcher::Prefix(commands::PAGE_ACTION_PREFIX), turn_page),
+    (Matcher::Exact(system::REAUTH_ACTION), reauth),
+];
+
+/// Runs the registered handler for each action in the event
+#[tracing::instrument(skip_all)]
+pub async fn dispatch(
+    event: &SlackInteractionBlockActionsEvent,
+    client: &Arc<SlackHyperClient>,
+    states: &SlackClientEventsUserState,
+    user_state: &user::State,
+) -> HandlerResult {
+    for action in event.actions.iter().flatten() {
+        // Buttons carry a value, while menus carry the option that was picked
+        let value = action
+            .value
+            .as_deref()
+            .or_else(|| {
+                action
+                    .selected_option
+                    .as_ref()
+                    .map(|option| option.value.as_str())
+            })
+            .unwrap_or_default();
+
+        let id = &*action.action_id.0;
+
+        let Some((_, handler)) = REGISTRY.iter().find(|(matcher, _)| matcher.matches(id)) else {
+            warn!(id, "Unknown block action ID");
+            continue;
+        };
+
+        handler(Action {
+            event,
+            value,
+            client,
+            states,
+            user_state,
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+fn approve_info_consent(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        message::info_consent(
+            action.event,
+            action.value,
+            true,
+            action.client.clone(),
+            action.user_state,
+        )
+        .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn deny_info_consent(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        message::info_consent(
+            action.event,
+            action.value,
+            false,
+            action.client.clone(),
+            action.user_state,
+        )
+        .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn switch_to_member(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        member::switch_to_member(action.event, action.value, action.client, action.user_state)
+            .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn pick_switch(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        member::pick_switch(action.event, action.value, action.client, action.user_state).await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn pick_info(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        member::pick_info(action.event, action.value, action.client, action.user_state).await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn add_another_member(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        member::add_another_member(action.event, action.client).await;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn undo_member_disable(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        member::undo_disable(action.event, action.value, action.client, action.user_state).await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn home_switch(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        home::switch(
+            action.event,
+            Some(action.value),
+            action.client,
+            action.user_state,
+        )
+        .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn home_switch_base(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        home::switch(action.event, None, action.client, action.user_state).await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn home_edit(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        home::edit(action.event, action.value, action.client, action.user_state).await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn edit_from_reaction(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        message::start_edit_from_reaction(
+            action.event,
+            action.value,
+            action.client,
+            action.user_state,
+        )
+        .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn proxy_suggestion(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        message::proxy_suggestion(action.event, action.value, action.client, action.user_state)
+            .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn confirm_hold_back(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        message::finish_hold_back(
+            action.event,
+            action.value,
+            true,
+            action.client,
+            action.user_state,
+        )
+        .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn undo_hold_back(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        message::finish_hold_back(
+            action.event,
+            action.value,
+            false,
+            action.client,
+            action.user_state,
+        )
+        .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn turn_page(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        commands::turn_page(
+            action.event,
+            action.value,
+            action.client.clone(),
+            action.states.clone(),
+        )
+        .await?;
+        Ok(())
+    }
+    .boxed()
+}
+
+fn reauth(action: Action<'_>) -> BoxFuture<'_, HandlerResult> {
+    async move {
+        system::reauth(action.event, action.client, action.user_state).await?;
+        Ok(())
+    }
+    .boxed()
+}
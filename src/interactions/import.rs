@@ -0,0 +1,236 @@
+//! The form shown when an import has members that look like ones the system already has.
+//!
+//! The import is saved while the form is open, and finished once the user picks what to do with each conflict.
+
+use error_stack::{Result, ResultExt, bail};
+use slack_morphism::prelude::*;
+use tracing::{debug, info, warn};
+
+use crate::{
+    fields,
+    models::{
+        Member, System,
+        export::{self, Conflict, ExportedMember, ImportSummary, Resolution},
+        trust::Trusted,
+        user::{self, State},
+    },
+    render::MemberReference,
+};
+
+/// Prefix of the form's external ID, followed by the pending import's ID
+pub const EXTERNAL_ID_PREFIX: &str = "import_conflicts_";
+/// Prefix of each conflict's select, followed by the imported member's export ID
+const CONFLICT_ACTION_PREFIX: &str = "conflict_";
+/// How many conflicts fit in the form. A modal can have 100 blocks, and a few are used for the explanation
+const CONFLICTS_SHOWN: usize = 95;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while adding the imported members
+    Import,
+    /// No system found for the user
+    NoSystem,
+}
+
+/// The conflicts form, with every conflict set to be skipped
+pub fn conflicts_view(
+    pending_id: i64,
+    source: &str,
+    members: &[ExportedMember],
+    conflicts: &[Conflict],
+    existing: &[Member],
+) -> SlackView {
+    let options = Resolution::ALL.map(|resolution| {
+        SlackBlockChoiceItem::<SlackBlockPlainTextOnly>::new(
+            resolution.label().into(),
+            resolution.value().into(),
+        )
+    });
+
+    let mut blocks = slack_blocks![
+        some_into(SlackSectionBlock::new().with_text(md!(
+            "{} of the {} members from {source} look like members you already have. Choose what to do with each of them. Everyone else will be added as a new member.",
+            conflicts.len(),
+            members.len()
+        ))),
+        some_into(SlackContextBlock::new(vec![md!(
+            "Overwriting replaces the existing member's names, details and triggers with the imported ones."
+        )])),
+        some_into(SlackDividerBlock::new())
+    ];
+
+    for conflict in conflicts.iter().take(CONFLICTS_SHOWN) {
+        let Some(imported) = members.iter().find(|member| member.id == conflict.imported) else {
+            continue;
+        };
+        let Some(existing) = existing
+            .iter()
+            .find(|member| member.id == conflict.existing)
+        else {
+            continue;
+        };
+
+        blocks.push(
+            SlackSectionBlock::new()
+                .with_text(md!(
+                    "*{}* ({}) looks like {}",
+                    imported.display_name,
+                    imported.full_name,
+                    MemberReference::new(existing).with_full_name().mrkdwn()
+                ))
+                .with_accessory(
+                    SlackBlockStaticSelectElement::new(
+                        format!("{CONFLICT_ACTION_PREFIX}{}", imported.id).into(),
+                    )
+                    .with_options(options.to_vec())
+                    .with_initial_option(options[0].clone())
+                    .into(),
+                )
+                .into(),
+        );
+    }
+
+    if conflicts.len() > CONFLICTS_SHOWN {
+        blocks.push(
+            SlackContextBlock::new(vec![md!(
+                "{} more members that look like existing ones didn't fit here, and will be skipped.",
+                conflicts.len() - CONFLICTS_SHOWN
+            )])
+            .into(),
+        );
+    }
+
+    SlackView::Modal(
+        SlackModalView::new("Import conflicts".into(), blocks)
+            .with_submit("Import".into())
+            .with_close("Cancel".into())
+            .with_external_id(format!("{EXTERNAL_ID_PREFIX}{pending_id}")),
+    )
+}
+
+/// The resolution picked for each imported member, keyed by their export ID
+fn picked_resolutions(view_state: SlackViewState) -> Vec<(i64, Resolution)> {
+    let mut picked = Vec::new();
+
+    for (_id, values) in view_state.values {
+        for (id, content) in values {
+            let Some(imported) =
+                id.0.strip_prefix(CONFLICT_ACTION_PREFIX)
+                    .and_then(|imported| imported.parse::<i64>().ok())
+            else {
+                warn!(id = id.0, "Unknown field in import conflicts view");
+                continue;
+            };
+
+            if let Some(resolution) = content
+                .selected_option
+                .and_then(|option| Resolution::from_value(&option.value))
+            {
+                picked.push((imported, resolution));
+            }
+        }
+    }
+
+    picked
+}
+
+/// Finishes an import once its conflicts form is submitted
+#[tracing::instrument(skip(view_state, user_state), fields(system_id))]
+pub async fn resolve_conflicts(
+    view_state: SlackViewState,
+    pending_id: i64,
+    user_state: &State,
+    user_id: user::Id<Trusted>,
+) -> Result<SlackView, Error> {
+    let Some(system) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    fields!(system_id = %system.id);
+
+    let Some(members) = export::take_pending_import(pending_id, system.id, &user_state.db)
+        .await
+        .change_context(Error::Import)?
+    else {
+        debug!("Pending import not found. It was already finished, or expired");
+        return Ok(super::status_view(
+            "Import expired",
+            "This import has already finished, or was started over a day ago. Run the import again to start over.",
+        ));
+    };
+
+    let picked = picked_resolutions(view_state);
+
+    // Members may have changed since the form was opened, so conflicts are found again.
+    // Anything that wasn't in the form is skipped.
+    let resolutions = export::find_conflicts(&members, &system, &user_state.db)
+        .await
+        .change_context(Error::Import)?
+        .into_iter()
+        .map(|conflict| {
+            let resolution = picked
+                .iter()
+                .find(|(imported, _)| *imported == conflict.imported)
+                .map_or(Resolution::Skip, |(_, resolution)| *resolution);
+
+            (conflict, resolution)
+        })
+        .collect::<Vec<_>>();
+
+    let summary = export::import_members(&members, &resolutions, system.id, &user_state.db)
+        .await
+        .change_context(Error::Import)?;
+
+    info!(?summary, "Finished import after resolving conflicts");
+
+    Ok(super::status_view(
+        "Import finished",
+        &summary_text(summary),
+    ))
+}
+
+/// Describes what an import did, e.g. "Added 3 members, updated 1 and skipped 2."
+pub fn summary_text(summary: ImportSummary) -> String {
+    let ImportSummary {
+        added,
+        updated,
+        unchanged,
+        overwritten,
+        skipped,
+    } = summary;
+
+    let mut parts = vec![format!(
+        "Added {added} {}",
+        if added == 1 { "member" } else { "members" }
+    )];
+
+    if updated > 0 {
+        parts.push(format!(
+            "updated {updated} that changed since the last import"
+        ));
+    }
+
+    if unchanged > 0 {
+        parts.push(format!("left {unchanged} that didn't change"));
+    }
+
+    if overwritten > 0 {
+        parts.push(format!("overwrote {overwritten}"));
+    }
+
+    if skipped > 0 {
+        parts.push(format!("skipped {skipped}"));
+    }
+
+    let last = parts.pop().unwrap_or_default();
+    if parts.is_empty() {
+        format!("{last}.")
+    } else {
+        format!("{} and {last}.", parts.join(", "))
+    }
+}
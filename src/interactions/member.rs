@@ -1,15 +1,18 @@
 use error_stack::{Result, ResultExt, bail};
 use slack_morphism::prelude::*;
-use tracing::trace;
+use tracing::{debug, info, trace, warn};
 
 use crate::{
-    BOT_TOKEN, fields,
+    fields, home,
     models::{
-        member,
-        system::System,
-        trust::Trusted,
+        AuditLog, Member, MemberField, audit, member,
+        system::{self, System},
+        trust::{Trusted, Untrusted},
         user::{self, State},
+        viewer::Viewer,
     },
+    notify,
+    render::{self, MemberReference, reference},
 };
 
 #[derive(thiserror::Error, displaydoc::Display, Debug)]
@@ -22,6 +25,8 @@ pub enum Error {
     ParsingView,
     /// No system found for the user
     NoSystem,
+    /// Unable to write an edit into the confirmation screen
+    Metadata,
 }
 
 #[tracing::instrument(skip(view_state, client, user_state), fields(system_id))]
@@ -30,7 +35,7 @@ pub async fn create_member(
     client: &SlackHyperClient,
     user_state: &State,
     user_id: user::Id<Trusted>,
-) -> Result<(), Error> {
+) -> Result<SlackView, Error> {
     trace!("Creating member");
     let data = member::View::try_from(view_state).change_context(Error::ParsingView)?;
 
@@ -50,30 +55,43 @@ pub async fn create_member(
         .await
         .change_context(Error::Sqlx)?;
 
-    let session = client.open_session(&BOT_TOKEN);
-    let user: SlackUserId = user_id.into();
+    let command_name = register_command_name(&data.display_name, id, system_id, user_state).await?;
 
-    let conversation = session
-        .conversations_open(&SlackApiConversationsOpenRequest::new().with_users(vec![user.clone()]))
-        .await
-        .change_context(Error::Slack)?
-        .channel;
+    notify::send(
+        client,
+        &user_state.db,
+        &user_id,
+        notify::Notification::MemberAdded {
+            display_name: data.display_name.clone(),
+            member_id: id,
+        },
+    )
+    .await;
 
-    session
-        .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
-            conversation.id,
-            user,
-            SlackMessageContent::new().with_text(format!(
-                "Successfully added {}! Their ID is {}",
-                data.display_name, id
-            )),
-        ))
-        .await
-        .change_context(Error::Slack)?;
+    let summary = command_name.map_or_else(
+        || format!("Added *{}* to your system!", data.display_name),
+        |name| {
+            format!(
+                "Added *{}* to your system! Use `{name}` to refer to them in commands.",
+                data.display_name
+            )
+        },
+    );
 
-    Ok(())
+    Ok(result_view("Member added", summary, data, id, true))
 }
 
+/// Prefix of the confirmation screen's external ID, followed by the member's ID.
+///
+/// The edit itself is carried in the screen's private metadata, which Slack sends back when it's submitted.
+pub const CONFIRM_EDIT_PREFIX: &str = "confirm_edit_member_";
+/// The most private metadata Slack lets a view carry
+const MAX_PRIVATE_METADATA_LENGTH: usize = 3000;
+/// The longest a value is shown on the confirmation screen. Sections are capped at 3000 characters, and each change
+/// shows two values
+const CHANGE_PREVIEW_LENGTH: usize = 1000;
+
+/// Shows what submitting the edit member form would change, for the user to confirm before it's saved
 #[tracing::instrument(skip(view_state, client, user_state))]
 pub async fn edit_member(
     view_state: SlackViewState,
@@ -81,34 +99,705 @@ pub async fn edit_member(
     user_state: &State,
     user_id: user::Id<Trusted>,
     member_id: member::Id<Trusted>,
-) -> Result<(), Error> {
-    trace!("Editing member");
+) -> Result<SlackView, Error> {
+    trace!("Previewing member edit");
     let data = member::View::try_from(view_state).change_context(Error::ParsingView)?;
 
+    let current = member::View::from(
+        member_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(Error::Sqlx)?,
+    );
+
+    let changes = current.changes(&data);
+
+    if changes.is_empty() {
+        return Ok(super::status_view(
+            "Nothing changed",
+            &format!("*{}* is already set up like that.", data.display_name),
+        ));
+    }
+
+    let metadata = serde_json::to_string(&data).change_context(Error::Metadata)?;
+
+    if metadata.len() > MAX_PRIVATE_METADATA_LENGTH {
+        // Usually from a long description. The form was still submitted on purpose, so it's saved without confirming
+        debug!(
+            length = metadata.len(),
+            "Edit is too long to carry through the confirmation screen. Saving it straight away"
+        );
+        return save_edit(client, user_state, user_id, member_id, data).await;
+    }
+
+    Ok(confirm_edit_view(
+        &data.display_name,
+        member_id,
+        &changes,
+        metadata,
+    ))
+}
+
+/// Shows a value on the confirmation screen, cut short if it's long
+fn preview(value: Option<&str>) -> String {
+    match value {
+        None => "_Not set_".to_string(),
+        Some(value) if value.chars().count() > CHANGE_PREVIEW_LENGTH => {
+            format!(
+                "{}…",
+                value
+                    .chars()
+                    .take(CHANGE_PREVIEW_LENGTH)
+                    .collect::<String>()
+            )
+        }
+        Some(value) => value.to_string(),
+    }
+}
+
+/// The screen listing what an edit changes, old → new, with a button to save it
+fn confirm_edit_view(
+    display_name: &str,
+    member_id: member::Id<Trusted>,
+    changes: &[member::Change],
+    metadata: String,
+) -> SlackView {
+    let mut blocks = slack_blocks![some_into(SlackSectionBlock::new().with_text(md!(
+        "This will change {} of *{}*'s details. Check everything looks right before saving.",
+        changes.len(),
+        display_name
+    )))];
+
+    blocks.extend(changes.iter().map(|change| {
+        SlackSectionBlock::new()
+            .with_text(md!(
+                "*{}*\n{}\n→ {}",
+                change.field,
+                preview(change.old.as_deref()),
+                preview(change.new.as_deref())
+            ))
+            .into()
+    }));
+
+    SlackView::Modal(
+        SlackModalView::new("Confirm changes".into(), blocks)
+            .with_submit("Save".into())
+            .with_close("Cancel".into())
+            .with_external_id(format!("{CONFIRM_EDIT_PREFIX}{}", member_id.id))
+            .with_private_metadata(metadata),
+    )
+}
+
+/// Saves an edit the user confirmed on the confirmation screen, read back from the screen's private metadata
+#[tracing::instrument(skip(client, user_state, private_metadata))]
+pub async fn confirm_edit_member(
+    client: &SlackHyperClient,
+    user_state: &State,
+    user_id: user::Id<Trusted>,
+    member_id: member::Id<Trusted>,
+    private_metadata: Option<&str>,
+) -> Result<SlackView, Error> {
+    let Some(data) = private_metadata.and_then(|metadata| {
+        serde_json::from_str::<member::View>(metadata)
+            .inspect_err(|error| warn!(?error, "Malformed edit on confirmation screen"))
+            .ok()
+    }) else {
+        return Ok(super::status_view(
+            "Edit not saved",
+            "The changes couldn't be read back from this screen. Open the member's edit form and make your changes again.",
+        ));
+    };
+
+    save_edit(client, user_state, user_id, member_id, data).await
+}
+
+/// Saves an edit to a member
+async fn save_edit(
+    client: &SlackHyperClient,
+    user_state: &State,
+    user_id: user::Id<Trusted>,
+    member_id: member::Id<Trusted>,
+    data: member::View,
+) -> Result<SlackView, Error> {
+    trace!("Editing member");
+
     data.update(member_id, &user_state.db)
         .await
         .change_context(Error::Sqlx)?;
 
-    let session = client.open_session(&BOT_TOKEN);
-    let user: SlackUserId = user_id.into();
+    notify::send(
+        client,
+        &user_state.db,
+        &user_id,
+        notify::Notification::MemberEdited {
+            display_name: data.display_name.clone(),
+            member_id: member_id.id,
+        },
+    )
+    .await;
+
+    Ok(result_view(
+        "Member edited",
+        format!("Saved your changes to *{}*.", data.display_name),
+        data,
+        member_id.id,
+        false,
+    ))
+}
+
+/// Confirms deleting a member for good, and asks what to do with the logs of their messages
+pub fn delete_view(member: &Member) -> SlackView {
+    let anonymize = SlackBlockChoiceItem::<SlackBlockText>::new(
+        md!(
+            "*Keep them without the member*\nYou can still edit and delete the messages, but they won't be linked to anyone."
+        ),
+        "anonymize".into(),
+    );
+    let delete = SlackBlockChoiceItem::<SlackBlockText>::new(
+        md!(
+            "*Delete them*\nThe messages stay in Slack, but you won't be able to edit or delete them through the bot anymore."
+        ),
+        "delete".into(),
+    );
+
+    SlackView::Modal(
+        SlackModalView::new(
+            "Delete member".into(),
+            slack_blocks![
+                some_into(SlackSectionBlock::new().with_text(md!(
+                    "This permanently deletes {}, along with their triggers, aliases, fields and name recording. It can't be undone.\n\nIf you might want them back later, close this and use `/members disable` instead.",
+                    MemberReference::new(member).with_full_name().mrkdwn()
+                ))),
+                some_into(SlackInputBlock::new(
+                    "Logs of their proxied messages".into(),
+                    SlackBlockRadioButtonsElement::new(
+                        "message_logs".into(),
+                        vec![anonymize.clone(), delete]
+                    )
+                    .with_initial_option(anonymize)
+                    .into(),
+                ))
+            ],
+        )
+        .with_submit("Delete".into())
+        .with_close("Cancel".into())
+        .with_external_id(format!("delete_member_{}", member.id)),
+    )
+}
+
+/// Deletes a member once the deletion form is submitted
+#[tracing::instrument(skip(view_state, client, user_state), fields(system_id))]
+pub async fn delete_member(
+    view_state: SlackViewState,
+    client: &SlackHyperClient,
+    user_state: &State,
+    user_id: user::Id<Trusted>,
+    member_id: member::Id<Trusted>,
+) -> Result<SlackView, Error> {
+    trace!("Deleting member");
+
+    let keep_message_logs = view_state
+        .values
+        .into_values()
+        .flat_map(|values| values.into_iter())
+        .find(|(id, _)| id.0 == "message_logs")
+        .and_then(|(_, content)| content.selected_option)
+        .is_none_or(|option| option.value != "delete");
+
+    let Some(system_id) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+        .map(|system| system.id)
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    fields!(system_id = %system_id);
+
+    let member = member_id
+        .fetch(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    member_id
+        .delete(keep_message_logs, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    AuditLog::record(
+        system_id,
+        &user_id,
+        audit::Action::MemberDeleted,
+        Some(member_id.to_string()),
+        &user_state.db,
+    )
+    .await
+    .change_context(Error::Sqlx)?;
 
-    let conversation = session
-        .conversations_open(&SlackApiConversationsOpenRequest::new().with_users(vec![user.clone()]))
+    info!(%member_id, keep_message_logs, "Member deleted");
+
+    home::refresh(&user_id, client, &user_state.db).await;
+
+    Ok(super::status_view(
+        "Member deleted",
+        &format!("*{}* has been deleted.", member.display_name),
+    ))
+}
+
+/// Gives a new member a command name made from their display name, unless another member already has it as an alias
+async fn register_command_name(
+    display_name: &str,
+    member_id: i64,
+    system_id: system::Id<Trusted>,
+    user_state: &State,
+) -> Result<Option<String>, Error> {
+    let Some(name) = member::command_name_from(display_name) else {
+        return Ok(None);
+    };
+
+    if member::Id::fetch_by_alias(&name, system_id, &user_state.db)
         .await
-        .change_context(Error::Slack)?
-        .channel;
+        .change_context(Error::Sqlx)?
+        .is_some()
+    {
+        debug!(name, "Command name is already taken");
+        return Ok(None);
+    }
+
+    let Some(member_id) = member::Id::new(member_id)
+        .validate_by_system(system_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        return Ok(None);
+    };
 
-    session
-        .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
-            conversation.id,
-            user,
+    member_id
+        .set_command_name(system_id, Some(&name), &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    Ok(Some(name))
+}
+
+/// The screen shown once a member has been added or edited, summarizing them with buttons for what to do next
+fn result_view(
+    title: &str,
+    summary: String,
+    data: member::View,
+    member_id: i64,
+    created: bool,
+) -> SlackView {
+    let mut actions: Vec<SlackActionBlockElement> = vec![
+        SlackBlockButtonElement::new(
+            "switch_to_member".into(),
+            pt!("Switch to {}", data.display_name),
+        )
+        .with_value(member_id.to_string())
+        .into(),
+    ];
+
+    if created {
+        actions.push(
+            SlackBlockButtonElement::new("add_another_member".into(), pt!("Add another member"))
+                .into(),
+        );
+    }
+
+    let blocks = slack_blocks![
+        some_into(
+            SlackSectionBlock::new()
+                .with_text(md!(summary))
+                .opt_accessory(reference::avatar(data.profile_picture_url.as_deref()))
+        ),
+        some_into(SlackSectionBlock::new().with_fields(vec![
+            md!("*Full name*\n{}", data.full_name),
+            md!("*Pronouns*\n{}", data.pronouns.unwrap_or_else(|| "Not set".into())),
+            md!("*Member ID*\n{}", member_id),
+        ])),
+        optionally_into(created => SlackContextBlock::new(vec![md!(
+            "Next, add a trigger to send messages as them: `/triggers add {} prefix <text>`",
+            member_id
+        )])),
+        some_into(SlackActionsBlock::new(actions))
+    ];
+
+    SlackView::Modal(SlackModalView::new(title.into(), blocks).with_close("Done".into()))
+}
+
+/// Action ID of the member menu sent by `/members switch` without any members
+pub const PICK_SWITCH_ACTION: &str = "pick_switch_member";
+/// Action ID of the member menu sent by `/members info` without a member
+pub const PICK_INFO_ACTION: &str = "pick_member_info";
+
+/// The member picked from a member menu, if they belong to the user who picked them
+async fn picked_member(
+    user_id: &user::Id<Trusted>,
+    value: &str,
+    user_state: &State,
+) -> Result<Option<member::Id<Trusted>>, Error> {
+    let Ok(member_id) = value.parse::<i64>().map(member::Id::new) else {
+        warn!(value, "Malformed member id on member menu. Bailing");
+        return Ok(None);
+    };
+
+    let member_id = member_id
+        .validate_by_user(user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    if member_id.is_none() {
+        warn!(
+            value,
+            "Member doesn't belong to the user. Bailing in case this was a malicious call"
+        );
+    }
+
+    Ok(member_id)
+}
+
+/// Replaces the message with the member menu
+async fn replace_menu(
+    event: &SlackInteractionBlockActionsEvent,
+    client: &SlackHyperClient,
+    content: SlackMessageContent,
+) -> Result<(), Error> {
+    let Some(response_url) = &event.response_url else {
+        warn!("No response URL on member menu. Bailing");
+        return Ok(());
+    };
+
+    client
+        .respond_to_event(
+            response_url,
+            &SlackApiPostWebhookMessageRequest::new(content).with_replace_original(true),
+        )
+        .await
+        .change_context(Error::Slack)
+        .map(|_| ())
+}
+
+/// Handles picking who to switch to from the menu `/members switch` sends without any members
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn pick_switch(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user_id) = event
+        .user
+        .as_ref()
+        .map(|user| user::Id::<Trusted>::from(user.id.clone()))
+    else {
+        warn!("No user found on member menu. Bailing");
+        return Ok(());
+    };
+
+    let Some(system) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    let member = if value == render::BASE_ACCOUNT_OPTION {
+        None
+    } else {
+        let Some(member_id) = picked_member(&user_id, value, user_state).await? else {
+            return Ok(());
+        };
+
+        Some(
+            member_id
+                .fetch(&user_state.db)
+                .await
+                .change_context(Error::Sqlx)?,
+        )
+    };
+
+    if let Some(member) = &member
+        && !member.proxy_enabled
+    {
+        debug!("Member has proxying turned off");
+        return replace_menu(
+            event,
+            client,
             SlackMessageContent::new().with_text(format!(
-                "Successfully edited {} (ID {})",
-                data.display_name, member_id
+                "Messages can't be proxied as {}. Use `/members proxy {} on` to turn proxying on.",
+                MemberReference::new(member).mrkdwn(),
+                member.id
             )),
-        ))
+        )
+        .await;
+    }
+
+    let fronter_ids = member.iter().map(|member| member.id).collect::<Vec<_>>();
+
+    system
+        .id
+        .set_fronters(&fronter_ids, &user_state.db)
         .await
-        .change_context(Error::Slack)?;
+        .change_context(Error::Sqlx)?;
+
+    info!(member_ids = ?fronter_ids, "Switched from member menu");
+
+    home::refresh(&user_id, client, &user_state.db).await;
+
+    let response = member.map_or_else(
+        || "Switched to base account".to_string(),
+        |member| {
+            format!(
+                "Switched to {}",
+                MemberReference::new(&member).with_full_name().mrkdwn()
+            )
+        },
+    );
+
+    replace_menu(
+        event,
+        client,
+        SlackMessageContent::new().with_text(response),
+    )
+    .await
+}
+
+/// Handles picking a member from the menu `/members info` sends without a member
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn pick_info(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user_id) = event
+        .user
+        .as_ref()
+        .map(|user| user::Id::<Trusted>::from(user.id.clone()))
+    else {
+        warn!("No user found on member menu. Bailing");
+        return Ok(());
+    };
+
+    let Some(system) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    let Some(member_id) = picked_member(&user_id, value, user_state).await? else {
+        return Ok(());
+    };
+
+    let member = member_id
+        .fetch(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    let fronting = system
+        .id
+        .fronters(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+        .contains(&member.id);
+    let fields = MemberField::fetch_by_member_id(member.id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    replace_menu(
+        event,
+        client,
+        SlackMessageContent::new().with_blocks(render::member_card(
+            member,
+            &fields,
+            fronting,
+            Viewer::Owner,
+        )),
+    )
+    .await
+}
+
+/// Handles the "Switch to" button on a member result screen
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn switch_to_member(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user_id) = event
+        .user
+        .as_ref()
+        .map(|user| user::Id::<Trusted>::from(user.id.clone()))
+    else {
+        warn!("No user found on switch action. Bailing");
+        return Ok(());
+    };
+
+    let Ok(member_id) = value.parse::<i64>().map(member::Id::new) else {
+        warn!(value, "Malformed member id on switch action. Bailing");
+        return Ok(());
+    };
+
+    let Some(member_id) = member_id
+        .validate_by_user(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        warn!(
+            value,
+            "Member doesn't belong to the user. Bailing in case this was a malicious call"
+        );
+        return Ok(());
+    };
+
+    let Some(mut system) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    let member = system
+        .change_fronting_member(Some(member_id), &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+        .expect("Member to exist after validation");
+
+    home::refresh(&user_id, client, &user_state.db).await;
+
+    if let SlackInteractionActionContainer::View(container) = &event.container {
+        super::update_view(
+            client,
+            container.view_id.clone(),
+            super::status_view(
+                "Switched member",
+                &format!("Switched to {}.", MemberReference::new(&member).mrkdwn()),
+            ),
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+/// Handles the "Add another member" button on a member result screen
+#[tracing::instrument(skip(event, client))]
+pub async fn add_another_member(
+    event: &SlackInteractionBlockActionsEvent,
+    client: &SlackHyperClient,
+) {
+    if let SlackInteractionActionContainer::View(container) = &event.container {
+        super::update_view(
+            client,
+            container.view_id.clone(),
+            member::View::create_add_view(),
+        )
+        .await;
+    }
+}
+
+/// Handles the "Undo" button shown after disabling a member, re-enabling them if it's still within the undo window
+#[tracing::instrument(skip(event, client, user_state), fields(system_id))]
+pub async fn undo_disable(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user_id) = event
+        .user
+        .as_ref()
+        .map(|user| user::Id::<Trusted>::from(user.id.clone()))
+    else {
+        warn!("No user found on undo action. Bailing");
+        return Ok(());
+    };
+
+    let Ok(log_id) = value.parse::<audit::Id<Untrusted>>() else {
+        warn!(value, "Malformed audit log id on undo action. Bailing");
+        return Ok(());
+    };
+
+    let Some(system) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    fields!(system_id = %system.id);
+
+    let log = log_id
+        .fetch_undoable(system.id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+        .filter(|log| log.action == audit::Action::MemberDisabled);
+
+    let member_id = match log
+        .and_then(|log| log.details)
+        .and_then(|details| details.parse::<member::Id<Untrusted>>().ok())
+    {
+        Some(member_id) => member_id
+            .validate_by_system(system.id, &user_state.db)
+            .await
+            .change_context(Error::Sqlx)?,
+        None => None,
+    };
+
+    let response = if let Some(member_id) = member_id {
+        if member_id
+            .enabled(&user_state.db)
+            .await
+            .change_context(Error::Sqlx)?
+        {
+            "The member has already been enabled again.".to_string()
+        } else {
+            member_id
+                .set_enabled(true, &user_state.db)
+                .await
+                .change_context(Error::Sqlx)?;
+
+            AuditLog::record(
+                system.id,
+                &user_id,
+                audit::Action::MemberEnabled,
+                Some(member_id.to_string()),
+                &user_state.db,
+            )
+            .await
+            .change_context(Error::Sqlx)?;
+
+            info!(%member_id, "Undid disabling member");
+
+            let member = member_id
+                .fetch(&user_state.db)
+                .await
+                .change_context(Error::Sqlx)?;
+
+            format!("Re-enabled {}.", MemberReference::new(&member).mrkdwn())
+        }
+    } else {
+        debug!("Undo window has passed");
+        format!(
+            "This can only be undone for {} minutes. Use `/members enable` instead.",
+            audit::UNDO_WINDOW_MINUTES
+        )
+    };
+
+    if let Some(response_url) = &event.response_url {
+        client
+            .respond_to_event(
+                response_url,
+                &SlackApiPostWebhookMessageRequest::new(
+                    SlackMessageContent::new().with_text(response),
+                )
+                .with_replace_original(true),
+            )
+            .await
+            .change_context(Error::Slack)?;
+    }
 
     Ok(())
 }
@@ -1,16 +1,24 @@
-use error_stack::{Result, ResultExt};
+use error_stack::{Result, ResultExt, bail};
+use sqlx::SqlitePool;
 use std::sync::Arc;
 use tracing::{debug, warn};
 
-use slack_morphism::prelude::*;
+use slack_morphism::{errors::SlackClientError, prelude::*};
 
 use crate::{
-    BOT_TOKEN, fields,
+    BOT_TOKEN,
+    events::{hold_back, suggest},
+    fields,
     models::{
-        Member, MessageLog, System, member,
-        trust::Trusted,
+        AuditLog, Member, MemberField, MessageLog, System, audit, member,
+        trust::{Trusted, Untrusted},
         user::{self, State},
+        viewer::Viewer,
     },
+    notify,
+    proxy::{Identity, Original, ProxyOptions, ProxyRequest},
+    render::{self, MemberReference, mrkdwn},
+    slack_error,
 };
 
 #[derive(Debug, displaydoc::Display, thiserror::Error)]
@@ -21,6 +29,16 @@ pub enum Error {
     Sqlx,
     /// Unable to parse view
     ParsingView,
+    /// Error while working out who is viewing the system
+    Viewer,
+    /// Error while proxying the message
+    Proxy,
+    /// The message is in an archived channel, so it can't be changed
+    ChannelArchived,
+    /// The message no longer exists in Slack
+    MessageNotFound,
+    /// The message is too long. Messages can be up to 3000 characters
+    MessageTooLong,
 }
 
 #[tracing::instrument(skip_all, fields(trigger_id = ?event.trigger_id))]
@@ -58,10 +76,6 @@ pub async fn start_edit(
     };
 
     let system = log
-        .member_id
-        .fetch(&user_state.db)
-        .await
-        .change_context(Error::Sqlx)?
         .system_id
         .fetch(&user_state.db)
         .await
@@ -82,17 +96,40 @@ pub async fn start_edit(
         return Ok(());
     }
 
-    let message_content = message.content.text.unwrap_or_default();
+    open_edit_view(
+        &session,
+        event.trigger_id,
+        message,
+        &event.channel.unwrap().id,
+    )
+    .await
+}
+
+/// Opens the edit form for a proxied message
+async fn open_edit_view(
+    session: &SlackClientSession<'_, SlackClientHyperHttpsConnector>,
+    trigger_id: SlackTriggerId,
+    message: SlackHistoryMessage,
+    channel_id: &SlackChannelId,
+) -> Result<(), Error> {
+    // Editing happens in mrkdwn, so formatting from the rich text is kept
+    let message_content = message
+        .content
+        .blocks
+        .as_deref()
+        .and_then(mrkdwn::from_rich_text)
+        .or(message.content.text)
+        .unwrap_or_default();
 
     let view = EditMessageView {
         message: message_content,
     }
-    .create_view(&message.origin.ts, &event.channel.unwrap().id);
+    .create_view(&message.origin.ts, channel_id);
 
     fields!(view = ?&view);
 
     session
-        .views_open(&SlackApiViewsOpenRequest::new(event.trigger_id, view))
+        .views_open(&SlackApiViewsOpenRequest::new(trigger_id, view))
         .await
         .change_context(Error::Slack)?;
 
@@ -101,6 +138,57 @@ pub async fn start_edit(
     Ok(())
 }
 
+/// Opens the edit form from the button sent after reacting to a proxied message with ✏️.
+///
+/// Reactions don't come with a trigger ID, so the form can't be opened until the button is pressed.
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn start_edit_from_reaction(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let session = client.open_session(&BOT_TOKEN);
+
+    let Some(user) = event.user.as_ref() else {
+        warn!("No user found on edit button. Bailing");
+        return Ok(());
+    };
+
+    let Some((channel_id, message_id)) = value.split_once('_') else {
+        warn!(value, "Malformed message on edit button. Bailing");
+        return Ok(());
+    };
+    let channel_id = SlackChannelId::new(channel_id.to_owned());
+    let message_id = SlackTs::new(message_id.to_owned());
+
+    let Some(log) = MessageLog::fetch_by_message_id(&message_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        debug!("Message has been deleted since the button was sent");
+        return Ok(());
+    };
+
+    let system = log
+        .system_id
+        .fetch(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    if system.owner_id != user.id {
+        warn!("User is not the owner of the system. Bailing");
+        return Ok(());
+    }
+
+    let Some(message) = fetch_message(&session, &channel_id, &message_id).await? else {
+        warn!("Message not found");
+        return Ok(());
+    };
+
+    open_edit_view(&session, event.trigger_id.clone(), message, &channel_id).await
+}
+
 #[tracing::instrument(skip(client, user_state))]
 pub async fn edit(
     view_state: SlackViewState,
@@ -123,10 +211,6 @@ pub async fn edit(
     };
 
     let system = log
-        .member_id
-        .fetch(&user_state.db)
-        .await
-        .change_context(Error::Sqlx)?
         .system_id
         .fetch(&user_state.db)
         .await
@@ -141,36 +225,211 @@ pub async fn edit(
 
     fields!(view = ?&view);
 
-    session
+    // The input's max length is only a hint to the client, so check it here too
+    if view.message.chars().count() > EditMessageView::MAX_LENGTH {
+        bail!(Error::MessageTooLong);
+    }
+
+    let message = fetch_message(&session, &channel_id, &message_id).await?;
+    let thread_ts = message.as_ref().and_then(thread_of);
+
+    // Blocks replace the message's existing ones, so anything that isn't the text (like images) has to be sent again
+    let blocks = std::iter::once(
+        SlackSectionBlock::new()
+            .with_text(md!("{}", view.message))
+            .into(),
+    )
+    .chain(message.map(non_text_blocks).unwrap_or_default())
+    .collect();
+
+    let content = SlackMessageContent::new()
+        .with_text(view.message)
+        .with_blocks(blocks);
+
+    let update = session
         .chat_update(&SlackApiChatUpdateRequest::new(
-            channel_id,
-            SlackMessageContent::new().with_text(view.message),
-            message_id,
+            channel_id.clone(),
+            content.clone(),
+            message_id.clone(),
         ))
-        .await
-        .change_context(Error::Slack)?;
+        .await;
+
+    let strategy = match update {
+        Ok(_) => "updated in place",
+        Err(SlackClientError::ApiError(error))
+            if matches!(
+                error.code.as_str(),
+                "cant_update_message" | "edit_window_closed"
+            ) =>
+        {
+            debug!(
+                code = %error.code,
+                "Message can't be updated. Reposting instead"
+            );
+            repost(
+                client,
+                &system,
+                log,
+                &channel_id,
+                &message_id,
+                thread_ts,
+                content,
+                &user_state.db,
+            )
+            .await?;
+            "reposted"
+        }
+        Err(SlackClientError::ApiError(error)) if error.code == "is_archived" => {
+            bail!(Error::ChannelArchived)
+        }
+        Err(SlackClientError::ApiError(error)) if error.code == "message_not_found" => {
+            bail!(Error::MessageNotFound)
+        }
+        Err(error) => return Err(error).change_context(Error::Slack),
+    };
+
+    AuditLog::record(
+        system.id,
+        &user_id.into(),
+        audit::Action::MessageEdited,
+        Some(strategy.to_string()),
+        &user_state.db,
+    )
+    .await
+    .change_context(Error::Sqlx)?;
+
+    debug!(strategy, "Edited message");
+
+    Ok(())
+}
+
+/// Posts an edited message under the same identity as the original, then deletes the original.
+///
+/// Used when Slack won't let us update a message in place, e.g. because it's too old.
+#[tracing::instrument(skip(client, system, content, db))]
+async fn repost(
+    client: &SlackHyperClient,
+    system: &System,
+    log: MessageLog,
+    channel_id: &SlackChannelId,
+    message_id: &SlackTs,
+    thread_ts: Option<SlackTs>,
+    content: SlackMessageContent,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let identity = match log.member_id {
+        Some(member_id) => member_id
+            .fetch(db)
+            .await
+            .change_context(Error::Sqlx)?
+            .into(),
+        None => Identity::Anonymous,
+    };
 
-    debug!("Edited message");
+    ProxyRequest {
+        channel_id: channel_id.clone(),
+        content,
+        identity,
+        thread_ts,
+        files: Vec::new(),
+        options: ProxyOptions {
+            replaces: Some(Original::Proxied(message_id.clone())),
+            ..ProxyOptions::default()
+        },
+    }
+    .send(client, system, db)
+    .await
+    .change_context(Error::Proxy)?;
 
     Ok(())
 }
 
+/// Fetches a single message, whether or not it's in a thread
+async fn fetch_message(
+    session: &SlackClientSession<'_, SlackClientHyperHttpsConnector>,
+    channel_id: &SlackChannelId,
+    message_id: &SlackTs,
+) -> Result<Option<SlackHistoryMessage>, Error> {
+    // Unlike conversations.history, conversations.replies also finds messages inside threads
+    let request = SlackApiConversationsRepliesRequest::new(channel_id.clone(), message_id.clone());
+
+    Ok(slack_error::retry("conversations.replies", || {
+        session.conversations_replies(&request)
+    })
+    .await
+    .change_context(Error::Slack)?
+    .messages
+    .into_iter()
+    .find(|message| message.origin.ts == *message_id))
+}
+
+/// Whether a message is pinned in its channel
+async fn is_pinned(
+    session: &SlackClientSession<'_, SlackClientHyperHttpsConnector>,
+    channel_id: &SlackChannelId,
+    message_id: &SlackTs,
+) -> Result<bool, Error> {
+    Ok(session
+        .pins_list(&SlackApiPinsListRequest::new(channel_id.clone()))
+        .await
+        .change_context(Error::Slack)?
+        .items
+        .into_iter()
+        .filter_map(|pin| pin.message)
+        .any(|message| message.origin.ts == *message_id))
+}
+
+/// The blocks of a message other than its text, like images
+fn non_text_blocks(message: SlackHistoryMessage) -> Vec<SlackBlock> {
+    message
+        .content
+        .blocks
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|block| !matches!(block, SlackBlock::RichText(_) | SlackBlock::Section(_)))
+        .collect()
+}
+
+/// The thread a message was replied in, if it's a reply
+fn thread_of(message: &SlackHistoryMessage) -> Option<SlackTs> {
+    // Thread parents have their own timestamp as the thread timestamp
+    message
+        .origin
+        .thread_ts
+        .clone()
+        .filter(|thread_ts| *thread_ts != message.origin.ts)
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct EditMessageView {
     pub message: String,
 }
 
 impl EditMessageView {
+    /// The longest message that can be entered.
+    ///
+    /// Slack allows much longer messages, but plain text inputs and section blocks are capped at 3000 characters.
+    pub const MAX_LENGTH: usize = 3000;
+
     /// Due to the way the slack blocks are created, all fields are moved.
     /// Clone the whole struct if you need to keep the original.
     pub fn create_blocks(self) -> Vec<SlackBlock> {
-        slack_blocks![some_into(SlackInputBlock::new(
-            // https://github.com/abdolence/slack-morphism-rust/issues/327
-            "Message (No rich text support. Sorry!)".into(),
-            SlackBlockPlainTextInputElement::new("message".into())
-                .with_initial_value(self.message)
-                .into(),
-        ))]
+        slack_blocks![some_into(
+            SlackInputBlock::new(
+                // Rich text inputs can't be read back yet, so formatting is edited as mrkdwn
+                // https://github.com/abdolence/slack-morphism-rust/issues/327
+                "Message".into(),
+                SlackBlockPlainTextInputElement::new("message".into())
+                    .with_initial_value(self.message)
+                    .with_multiline(true)
+                    .with_max_length(Self::MAX_LENGTH as u64)
+                    .into(),
+            )
+            .with_hint(pt!(
+                "Up to {} characters. Format with Slack markup, like *bold*, _italic_, ~strikethrough~ and `code`",
+                Self::MAX_LENGTH
+            ))
+        )]
     }
 
     pub fn create_view(self, message_id: &SlackTs, channel_id: &SlackChannelId) -> SlackView {
@@ -286,11 +545,11 @@ async fn start_reproxy_user(
         .await
         .change_context(Error::Sqlx)?;
 
-    let view = ReproxyView { member: None }.create_view(
-        &members,
-        &message.origin.ts,
-        &event.channel.unwrap().id,
-    );
+    let view = ReproxyView {
+        member: None,
+        keep_pin: true,
+    }
+    .create_view(&members, &message.origin.ts, &event.channel.unwrap().id);
 
     fields!(view = ?&view);
 
@@ -314,10 +573,6 @@ async fn start_reproxy_log(
     let session = client.open_session(&BOT_TOKEN);
 
     let system = log
-        .member_id
-        .fetch(&user_state.db)
-        .await
-        .change_context(Error::Sqlx)?
         .system_id
         .fetch(&user_state.db)
         .await
@@ -344,7 +599,8 @@ async fn start_reproxy_log(
         .change_context(Error::Sqlx)?;
 
     let view = ReproxyView {
-        member: Some(log.member_id.id),
+        member: log.member_id.map(|member_id| member_id.id),
+        keep_pin: true,
     }
     .create_view(&members, &log.message_id, &event.channel.unwrap().id);
 
@@ -397,53 +653,73 @@ pub async fn reproxy(
 
     let member = id.fetch(&user_state.db).await.change_context(Error::Sqlx)?;
 
-    let Ok(messages) = session
-        .conversations_history(
-            &SlackApiConversationsHistoryRequest::new()
-                .with_channel(channel_id.clone())
-                .with_latest(message_id.clone())
-                .with_limit(1)
-                .with_inclusive(true),
-        )
-        .await
-    else {
-        warn!("Failed to fetch message history");
-        return Ok(());
-    };
-
-    let Some(message) = messages.messages.first() else {
-        warn!(?messages, "Message not found in history");
+    let Some(message) = fetch_message(&session, &channel_id, &message_id).await? else {
+        warn!("Message not found");
         return Ok(());
     };
 
-    let message_request =
-        SlackApiChatPostMessageRequest::new(channel_id.clone(), message.content.clone())
-            .with_username(member.display_name.clone())
-            .opt_icon_url(member.profile_picture_url.clone());
+    // The original's pin goes away when it's deleted, so it's checked for before then
+    let pinned = view.keep_pin
+        && match is_pinned(&session, &channel_id, &message_id).await {
+            Ok(pinned) => pinned,
+            Err(error) => {
+                warn!(?error, "Failed to check if the message is pinned");
+                false
+            }
+        };
 
-    session
-        .chat_post_message(&message_request)
+    // Messages we've already proxied are deleted by the bot, rather than with the system's token
+    let original = if MessageLog::fetch_by_message_id(&message_id, &user_state.db)
         .await
-        .change_context(Error::Slack)?;
-
-    let token = SlackApiToken::new(system.slack_oauth_token.expose().into())
-        .with_token_type(SlackApiTokenType::User);
-
-    let user_session = client.open_session(&token);
+        .change_context(Error::Sqlx)?
+        .is_some()
+    {
+        Original::Proxied(message_id)
+    } else {
+        Original::User(message_id)
+    };
 
-    user_session
-        .chat_delete(&SlackApiChatDeleteRequest::new(channel_id, message_id))
-        .await
-        .change_context(Error::Slack)?;
+    let thread_ts = thread_of(&message);
+    let mut content = message.content;
+
+    let ts = ProxyRequest {
+        channel_id: channel_id.clone(),
+        files: content.files.take().unwrap_or_default(),
+        content,
+        identity: member.into(),
+        thread_ts,
+        options: ProxyOptions {
+            replaces: Some(original),
+            ..ProxyOptions::default()
+        },
+    }
+    .send(client, &system, &user_state.db)
+    .await
+    .change_context(Error::Proxy)?;
 
     debug!("Reproxied message");
 
+    if pinned {
+        // The message is already reproxied, so losing the pin shouldn't fail it
+        if let Err(error) = session
+            .pins_add(&SlackApiPinsAddRequest::new(channel_id, ts))
+            .await
+        {
+            warn!(
+                ?error,
+                "Failed to carry the pin over to the reproxied message"
+            );
+        }
+    }
+
     Ok(())
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct ReproxyView {
     pub member: Option<i64>,
+    /// Whether to pin the reproxied message if the original was pinned
+    pub keep_pin: bool,
 }
 
 impl ReproxyView {
@@ -454,11 +730,11 @@ impl ReproxyView {
             .iter()
             .map(|member| {
                 SlackBlockChoiceItem::<SlackBlockPlainTextOnly>::new(
-                    format!(
-                        "{} ({}, ID: {})",
-                        member.display_name, member.full_name, member.id
-                    )
-                    .into(),
+                    MemberReference::new(member)
+                        .with_full_name()
+                        .with_id(true)
+                        .plain()
+                        .into(),
                     member.id.to_string(),
                 )
             })
@@ -470,26 +746,42 @@ impl ReproxyView {
                 .find(|member| member.id.id == member_id)
                 .map(|member| {
                     SlackBlockChoiceItem::<SlackBlockPlainTextOnly>::new(
-                        format!(
-                            "{} ({}, ID: {})",
-                            member.display_name, member.full_name, member.id
-                        )
-                        .into(),
+                        MemberReference::new(member)
+                            .with_full_name()
+                            .with_id(true)
+                            .plain()
+                            .into(),
                         member.id.to_string(),
                     )
                 })
         });
 
-        slack_blocks![some_into(
-            SlackSectionBlock::new()
-                .with_text(SlackBlockText::Plain("Member".into()))
-                .with_accessory(
-                    SlackBlockStaticSelectElement::new("member".into())
-                        .with_options(options)
-                        .opt_initial_option(value)
-                        .into()
-                )
-        )]
+        let keep_pin = SlackBlockChoiceItem::<SlackBlockText>::new(
+            pt!("Keep the message pinned").into(),
+            "keep_pin".into(),
+        );
+
+        slack_blocks![
+            some_into(
+                SlackSectionBlock::new()
+                    .with_text(SlackBlockText::Plain("Member".into()))
+                    .with_accessory(
+                        SlackBlockStaticSelectElement::new("member".into())
+                            .with_options(options)
+                            .opt_initial_option(value)
+                            .into()
+                    )
+            ),
+            some_into(
+                SlackSectionBlock::new()
+                    .with_text(md!("Pins"))
+                    .with_accessory(
+                        SlackBlockCheckboxesElement::new("keep_pin".into(), vec![keep_pin.clone()])
+                            .opt_initial_options(self.keep_pin.then(|| vec![keep_pin]))
+                            .into()
+                    )
+            )
+        ]
     }
     pub fn create_view(
         self,
@@ -519,6 +811,11 @@ impl TryFrom<SlackViewState> for ReproxyView {
                             .selected_option
                             .and_then(|option| option.value.parse::<i64>().ok());
                     }
+                    "keep_pin" => {
+                        view.keep_pin = content
+                            .selected_options
+                            .is_some_and(|options| !options.is_empty());
+                    }
                     other => {
                         warn!("Unknown field in view when parsing a member::View: {other}");
                     }
@@ -567,10 +864,6 @@ pub async fn delete(
     };
 
     let system = log
-        .member_id
-        .fetch(&user_state.db)
-        .await
-        .change_context(Error::Sqlx)?
         .system_id
         .fetch(&user_state.db)
         .await
@@ -603,6 +896,84 @@ pub async fn delete(
     Ok(())
 }
 
+/// Pins a proxied message, or unpins it if it's already pinned.
+///
+/// Some workspaces don't let people pin messages sent by apps, so the bot pins it instead. Like pinning any other
+/// message, anyone who can see it can pin it.
+#[tracing::instrument(skip(client, user_state))]
+pub async fn pin(
+    event: SlackInteractionMessageActionEvent,
+    client: Arc<SlackHyperClient>,
+    user_state: &State,
+) -> Result<(), Error> {
+    let session = client.open_session(&BOT_TOKEN);
+
+    let message = event
+        .message
+        .expect("Expected message to pin to, well, have a message");
+    let channel_id = event.channel.unwrap().id;
+
+    if MessageLog::fetch_by_message_id(&message.origin.ts, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+        .is_none()
+    {
+        debug!(
+            "Message not found in database. User is trying to pin a message that isn't sent by us."
+        );
+
+        session
+            .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
+                channel_id,
+                event.user.id,
+                SlackMessageContent::new().with_text(
+                    "A member didn't send this message. You can pin it yourself instead.".into(),
+                ),
+            ))
+            .await
+            .change_context(Error::Slack)?;
+
+        return Ok(());
+    }
+
+    let response = match session
+        .pins_add(&SlackApiPinsAddRequest::new(
+            channel_id.clone(),
+            message.origin.ts.clone(),
+        ))
+        .await
+    {
+        Ok(_) => {
+            debug!("Pinned message");
+            "Pinned the message."
+        }
+        Err(SlackClientError::ApiError(error)) if error.code == "already_pinned" => {
+            session
+                .pins_remove(&SlackApiPinsRemoveRequest::new(
+                    channel_id.clone(),
+                    message.origin.ts,
+                ))
+                .await
+                .change_context(Error::Slack)?;
+
+            debug!("Unpinned message");
+            "Unpinned the message."
+        }
+        Err(error) => return Err(error).change_context(Error::Slack),
+    };
+
+    session
+        .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
+            channel_id,
+            event.user.id,
+            SlackMessageContent::new().with_text(response.into()),
+        ))
+        .await
+        .change_context(Error::Slack)?;
+
+    Ok(())
+}
+
 #[tracing::instrument(skip(client, user_state))]
 pub async fn info(
     event: SlackInteractionMessageActionEvent,
@@ -635,54 +1006,415 @@ pub async fn info(
         return Ok(());
     };
 
-    let member = log
-        .member_id
+    let channel_id = event.channel.unwrap().id;
+    let content = info_content(
+        log,
+        &event.user.id,
+        &channel_id,
+        &message.origin.ts,
+        &client,
+        user_state,
+    )
+    .await?;
+
+    session
+        .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
+            channel_id,
+            event.user.id,
+            content,
+        ))
+        .await
+        .change_context(Error::Slack)?;
+
+    debug!("Sent message info");
+
+    Ok(())
+}
+
+/// Renders a proxied message's info as seen by `requester`.
+///
+/// If the system requires consent to reveal who owns it, the owner is asked for it and hidden until they approve.
+pub async fn info_content(
+    log: MessageLog,
+    requester: &SlackUserId,
+    channel_id: &SlackChannelId,
+    message_id: &SlackTs,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<SlackMessageContent, Error> {
+    let system = log
+        .system_id
+        .fetch(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    let Some(member_id) = log.member_id else {
+        debug!("Message was sent anonymously");
+
+        // Only the owner gets to know an anonymous message was theirs
+        let text = if system.owner_id == *requester {
+            "This message was sent anonymously by you."
+        } else {
+            "This message was sent anonymously."
+        };
+
+        return Ok(SlackMessageContent::new().with_text(text.into()));
+    };
+
+    let member = member_id
+        .fetch(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    let viewer = Viewer::resolve(&requester.clone().into(), &system, client, &user_state.db)
+        .await
+        .change_context(Error::Viewer)?;
+    let show_owner = !system.message_info_requires_consent || viewer == Viewer::Owner;
+
+    if !show_owner {
+        debug!("System requires consent to reveal the owner. Asking the owner");
+        notify::send(
+            client,
+            &user_state.db,
+            &system.owner_id,
+            notify::Notification::InfoConsentRequest {
+                requester: requester.clone(),
+                member_display_name: MemberReference::new(&member).mrkdwn(),
+                channel_id: channel_id.clone(),
+                message_id: message_id.clone(),
+            },
+        )
+        .await;
+    }
+
+    let fronting = system
+        .id
+        .fronters(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+        .contains(&member.id);
+    let fields = MemberField::fetch_by_member_id(member.id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    Ok(SlackMessageContent::new().with_blocks(info_blocks(
+        member, &fields, &system, fronting, viewer, show_owner,
+    )))
+}
+
+/// Renders the info card for a member that sent a message.
+///
+/// If `show_owner` is false, the system owner is hidden from the card.
+fn info_blocks(
+    member: Member,
+    fields: &[MemberField],
+    system: &System,
+    fronting: bool,
+    viewer: Viewer,
+    show_owner: bool,
+) -> Vec<SlackBlock> {
+    let owner = if show_owner {
+        system.owner_id.to_slack_format()
+    } else {
+        "_Hidden until the system owner approves_".to_string()
+    };
+
+    let mut blocks = render::member_card(member, fields, fronting, viewer);
+    blocks.push(
+        SlackSectionBlock::new()
+            .with_text(md!("*System*: {}", owner))
+            .into(),
+    );
+    blocks
+}
+
+/// Handles the system owner's answer to a message info consent prompt
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn info_consent(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    approved: bool,
+    client: Arc<SlackHyperClient>,
+    user_state: &State,
+) -> Result<(), Error> {
+    let session = client.open_session(&BOT_TOKEN);
+
+    let Some(owner) = event.user.as_ref().map(|user| user.id.clone()) else {
+        warn!("No user found on consent action. Bailing");
+        return Ok(());
+    };
+
+    let Some((requester, rest)) = value.split_once('_') else {
+        warn!(value, "Malformed consent action value. Bailing");
+        return Ok(());
+    };
+
+    let Some((channel_id, message_id)) = rest.split_once('_') else {
+        warn!(value, "Malformed consent action value. Bailing");
+        return Ok(());
+    };
+
+    let requester = SlackUserId::new(requester.to_owned());
+    let channel_id = SlackChannelId::new(channel_id.to_owned());
+    let message_id = SlackTs::new(message_id.to_owned());
+
+    let Some(log) = MessageLog::fetch_by_message_id(&message_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        debug!("Message was deleted before the owner answered");
+        return Ok(());
+    };
+
+    let Some(member_id) = log.member_id else {
+        warn!("Consent was asked for an anonymous message. This shouldn't happen. Bailing");
+        return Ok(());
+    };
+
+    let member = member_id
         .fetch(&user_state.db)
         .await
         .change_context(Error::Sqlx)?;
 
-    let system = member
+    let system = log
         .system_id
         .fetch(&user_state.db)
         .await
         .change_context(Error::Sqlx)?;
 
-    let blocks = slack_blocks![
-        some_into(SlackHeaderBlock::new(member.full_name.into())),
-        some_into(SlackDividerBlock::new()),
-        some_into(
-            SlackSectionBlock::new()
-                .with_text(md!(
-                    "*{}*\n{}{}\n*System*: {}",
-                    member.display_name,
-                    member.pronouns.unwrap_or_default(),
-                    member
-                        .name_pronunciation
-                        .map(|pronunciation| format!(" - {pronunciation}"))
-                        .unwrap_or_default(),
-                    system.owner_id.to_slack_format()
-                ))
-                .opt_accessory(member.profile_picture_url.and_then(|url| Some(
-                    SlackSectionBlockElement::Image(SlackBlockImageElement::new(
-                        url.parse().ok()?,
-                        "Profile picture".into()
-                    ))
-                )))
-        ),
-        optionally_into(system.currently_fronting_member_id.is_some_and(|id| id == member.id) => SlackSectionBlock::new().with_text(md!("*Fronting*")))
-        // TO-DO: fields
-    ];
+    if system.owner_id != owner {
+        warn!(
+            "User answering the consent prompt is not the owner of the system. Bailing in case this was a malicious call"
+        );
+        return Ok(());
+    }
+
+    let (requester_message, owner_message) = if approved {
+        let viewer = Viewer::resolve(&requester.clone().into(), &system, &client, &user_state.db)
+            .await
+            .change_context(Error::Viewer)?;
+        let fronting = system
+            .id
+            .fronters(&user_state.db)
+            .await
+            .change_context(Error::Sqlx)?
+            .contains(&member.id);
+        let fields = MemberField::fetch_by_member_id(member.id, &user_state.db)
+            .await
+            .change_context(Error::Sqlx)?;
+
+        (
+            SlackMessageContent::new().with_blocks(info_blocks(
+                member, &fields, &system, fronting, viewer, true,
+            )),
+            format!("Shared your account with {}", requester.to_slack_format()),
+        )
+    } else {
+        (
+            SlackMessageContent::new()
+                .with_text("The system owner chose not to share who sent this message.".into()),
+            format!(
+                "Kept your account hidden from {}",
+                requester.to_slack_format()
+            ),
+        )
+    };
 
     session
         .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
-            event.channel.unwrap().id,
-            event.user.id,
-            SlackMessageContent::new().with_blocks(blocks),
+            channel_id,
+            requester,
+            requester_message,
         ))
         .await
         .change_context(Error::Slack)?;
 
-    debug!("Deleted message");
+    if let Some(response_url) = &event.response_url {
+        client
+            .respond_to_event(
+                response_url,
+                &SlackApiPostWebhookMessageRequest::new(
+                    SlackMessageContent::new().with_text(owner_message),
+                )
+                .with_replace_original(true),
+            )
+            .await
+            .change_context(Error::Slack)?;
+    }
+
+    debug!(approved, "Answered message info consent prompt");
+
+    Ok(())
+}
+
+/// Proxies a message as the member the owner picked from a suggestion prompt
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn proxy_suggestion(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user_id) = event.user.as_ref().map(|user| user.id.clone()) else {
+        warn!("No user found on suggestion action. Bailing");
+        return Ok(());
+    };
+
+    let Some((member_id, rest)) = value.split_once('_') else {
+        warn!(value, "Malformed suggestion action value. Bailing");
+        return Ok(());
+    };
+
+    let Some((channel_id, message_id)) = rest.split_once('_') else {
+        warn!(value, "Malformed suggestion action value. Bailing");
+        return Ok(());
+    };
+
+    let Ok(member_id) = member_id.parse::<member::Id<Untrusted>>() else {
+        warn!(
+            value,
+            "Malformed member ID in suggestion action value. Bailing"
+        );
+        return Ok(());
+    };
+
+    let channel_id = SlackChannelId::new(channel_id.to_owned());
+    let message_id = SlackTs::new(message_id.to_owned());
+
+    let Some(system) = System::fetch_by_user_id(&user::Id::new(user_id), &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        debug!("User picking a suggestion has no system");
+        return Ok(());
+    };
+
+    let Some(member_id) = member_id
+        .validate_by_system(system.id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        warn!("Suggested member isn't part of the user's system. Bailing");
+        return Ok(());
+    };
+
+    let response = if let Some(stashed) = suggest::take(&channel_id, &message_id) {
+        let member = member_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(Error::Sqlx)?;
+        let name = MemberReference::new(&member).mrkdwn();
+        let mut content = stashed.content;
+
+        ProxyRequest {
+            channel_id,
+            files: content.files.take().unwrap_or_default(),
+            content,
+            identity: member.into(),
+            thread_ts: stashed.thread_ts,
+            options: ProxyOptions {
+                replaces: Some(Original::User(message_id)),
+                ..ProxyOptions::default()
+            },
+        }
+        .send(client, &system, &user_state.db)
+        .await
+        .change_context(Error::Proxy)?;
+
+        format!("Proxied as {name}.")
+    } else {
+        debug!("Suggested message expired");
+        "This suggestion has expired. Use a message action to reproxy the message instead."
+            .to_string()
+    };
+
+    if let Some(response_url) = &event.response_url {
+        client
+            .respond_to_event(
+                response_url,
+                &SlackApiPostWebhookMessageRequest::new(
+                    SlackMessageContent::new().with_text(response),
+                )
+                .with_replace_original(true),
+            )
+            .await
+            .change_context(Error::Slack)?;
+    }
+
+    Ok(())
+}
+
+/// Finishes a held back proxy from its prompt, deleting the original if `confirmed`, or the proxied message if not
+#[tracing::instrument(skip(event, client, user_state))]
+pub async fn finish_hold_back(
+    event: &SlackInteractionBlockActionsEvent,
+    value: &str,
+    confirmed: bool,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user_id) = event.user.as_ref().map(|user| user.id.clone()) else {
+        warn!("No user found on hold back action. Bailing");
+        return Ok(());
+    };
+
+    let Some((channel_id, original_ts, proxied_ts)) = hold_back::parse_value(value) else {
+        warn!(value, "Malformed hold back action value. Bailing");
+        return Ok(());
+    };
+
+    let Some(system) = System::fetch_by_user_id(&user::Id::new(user_id), &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        debug!("User finishing a held back proxy has no system");
+        return Ok(());
+    };
+
+    // Both messages have to be the system's, and proxied from each other, so the buttons can't delete anything else
+    let log = MessageLog::fetch_by_original_ts(&original_ts, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+        .filter(|log| log.system_id == system.id && log.message_id == proxied_ts);
+
+    let response = if log.is_none() {
+        debug!("Held back proxy was already finished, or its message was deleted");
+        "This message was already dealt with."
+    } else if confirmed {
+        let original = Original::User(original_ts);
+
+        // The original may have been up for a while, so it's more likely to have picked up reactions
+        original
+            .mirror_reactions(client, &channel_id, &proxied_ts)
+            .await;
+
+        original
+            .delete(client, &system, channel_id, &user_state.db)
+            .await
+            .change_context(Error::Proxy)?;
+
+        "Deleted your original message."
+    } else {
+        Original::Proxied(proxied_ts)
+            .delete(client, &system, channel_id, &user_state.db)
+            .await
+            .change_context(Error::Proxy)?;
+
+        "Deleted the proxied message. Your original is left as it was."
+    };
+
+    if let Some(response_url) = &event.response_url {
+        client
+            .respond_to_event(
+                response_url,
+                &SlackApiPostWebhookMessageRequest::new(
+                    SlackMessageContent::new().with_text(response.into()),
+                )
+                .with_replace_original(true),
+            )
+            .await
+            .change_context(Error::Slack)?;
+    }
 
     Ok(())
 }
@@ -1,30 +1,172 @@
-mod member;
-mod message;
+pub mod actions;
+pub mod import;
+pub mod member;
+pub mod message;
+pub mod system;
 use std::error::Error;
 use std::sync::Arc;
 
-use axum::Extension;
-use error_stack::Report;
-use member::{create_member, edit_member};
+use axum::{
+    Extension, Json,
+    response::{IntoResponse, Response},
+};
+use member::{create_member, delete_member, edit_member};
 use slack_morphism::prelude::*;
-use tracing::{debug, error, warn};
+use tracing::{Instrument, debug, error, warn};
 
 use crate::models::{self, trust::Trusted, user};
-use crate::{BOT_TOKEN, fields};
+use crate::{BOT_TOKEN, fields, ops, shutdown};
 
+/// Acknowledges an interaction and processes it in the background.
+///
+/// Slack shows an error to the user if an interaction isn't acknowledged within 3 seconds,
+/// so any database or Slack API work happens after the response is sent.
+/// Modal submissions are acknowledged with a placeholder screen, which is later updated with the result.
 #[tracing::instrument(skip(event, environment))]
 pub async fn process_interaction_event(
     Extension(environment): Extension<Arc<SlackHyperListenerEnvironment>>,
     Extension(event): Extension<SlackInteractionEvent>,
-) {
+) -> Response {
     let client = environment.client.clone();
     let states = environment.user_state.clone();
 
-    if let Err(error) = interaction_event(client, event, states).await {
-        error!(?error, "Error processing interaction event");
+    let response = match &event {
+        SlackInteractionEvent::ViewSubmission(submission)
+            if matches!(submission.view.view, SlackView::Modal(_)) =>
+        {
+            Json(SlackViewSubmissionResponse::Update(
+                SlackViewSubmissionUpdateResponse::new(status_view(
+                    "Working on it...",
+                    "Hang on, this should only take a moment.",
+                )),
+            ))
+            .into_response()
+        }
+        _ => ().into_response(),
+    };
+
+    handle_interaction_event(event, client, states);
+
+    response
+}
+
+/// Starts handling an interaction in the background, however it arrived, so Slack gets its acknowledgement in time.
+///
+/// Errors are reported, and shown to the user through the interaction's response URL if it has one.
+pub fn handle_interaction_event(
+    event: SlackInteractionEvent,
+    client: Arc<SlackHyperClient>,
+    states: SlackClientEventsUserState,
+) {
+    let response_url = match &event {
+        SlackInteractionEvent::MessageAction(event) => Some(event.response_url.clone()),
+        SlackInteractionEvent::BlockActions(event) => event.response_url.clone(),
+        _ => None,
+    };
+
+    let Some(in_flight) = shutdown::track() else {
+        warn!("Shutting down. Dropping interaction");
+        return;
+    };
+
+    tokio::spawn(
+        async move {
+            let _in_flight = in_flight;
+
+            if let Err(error) = interaction_event(client.clone(), event, states).await {
+                error!(?error, "Error processing interaction event");
+                ops::report("interaction", &error);
+
+                if let Some(response_url) = response_url {
+                    respond_with_error(&client, &response_url, &*error).await;
+                }
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+/// Tells the user an interaction failed through its response URL
+async fn respond_with_error(
+    client: &SlackHyperClient,
+    response_url: &SlackResponseUrl,
+    error: &(dyn Error + Send + Sync),
+) {
+    let response = client
+        .respond_to_event(
+            response_url,
+            // Response URL messages are ephemeral unless told otherwise
+            &SlackApiPostWebhookMessageRequest::new(
+                SlackMessageContent::new().with_text(format!("An error occured! {error}")),
+            ),
+        )
+        .await;
+
+    if let Err(error) = response {
+        error!(?error, "Failed to respond with error");
     }
 }
 
+/// A modal with a title and a single line of text, used for progress and result screens
+fn status_view(title: &str, text: &str) -> SlackView {
+    SlackView::Modal(
+        SlackModalView::new(
+            title.into(),
+            slack_blocks![some_into(SlackSectionBlock::new().with_text(md!(text)))],
+        )
+        .with_close("Close".into()),
+    )
+}
+
+/// Shown when a submitted modal can't be matched to anything the bot knows how to handle
+fn unprocessable_view() -> SlackView {
+    status_view(
+        "Something went wrong",
+        "This form couldn't be processed. Try opening it again.",
+    )
+}
+
+/// Replaces a submitted modal with the outcome of processing it
+#[tracing::instrument(skip(client, view))]
+async fn update_view(client: &SlackHyperClient, view_id: SlackViewId, view: SlackView) {
+    let session = client.open_session(&BOT_TOKEN);
+
+    if let Err(error) = session
+        .views_update(&SlackApiViewsUpdateRequest::new(view).with_view_id(view_id))
+        .await
+    {
+        error!(?error, "Failed to update view");
+    }
+}
+
+/// A generic confirmation screen for submissions that don't have their own result screen
+fn done_view() -> SlackView {
+    status_view("Done!", "All done. You can close this now.")
+}
+
+/// Shows a processing result on the submitted modal: the result screen on success, or the error otherwise
+async fn finish_view<E>(
+    result: error_stack::Result<SlackView, E>,
+    client: &SlackHyperClient,
+    view_id: SlackViewId,
+) where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let view = match result {
+        Ok(view) => view,
+        Err(error) => {
+            error!(?error);
+            ops::report("form", &error);
+            status_view(
+                "Something went wrong",
+                &format!("An error occured! {error}"),
+            )
+        }
+    };
+
+    update_view(client, view_id, view).await;
+}
+
 #[tracing::instrument(skip(client, event, states))]
 async fn interaction_event(
     client: Arc<SlackHyperClient>,
@@ -62,6 +204,14 @@ async fn interaction_event(
                     )
                     .await?;
                 }
+                "pin_message" => {
+                    message::pin(
+                        message_event,
+                        client,
+                        states.read().await.get_user_state().unwrap(),
+                    )
+                    .await?;
+                }
                 "message_info" => {
                     message::info(
                         message_event,
@@ -74,6 +224,16 @@ async fn interaction_event(
             }
             Ok(())
         }
+        SlackInteractionEvent::BlockActions(block_actions_event) => {
+            debug!(?block_actions_event, "Received block actions event");
+            actions::dispatch(
+                &block_actions_event,
+                &client,
+                &states,
+                states.read().await.get_user_state().unwrap(),
+            )
+            .await
+        }
         event => {
             debug!(?event, "Received interaction event",);
             Ok(())
@@ -88,6 +248,7 @@ async fn handle_view_submission(
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     match view_submission.view.view {
         SlackView::Home(view) => {
+            // The home tab has no submit button. Its buttons arrive as block actions, handled by `home`
             debug!(?view, "Received home view");
             Ok(())
         }
@@ -96,12 +257,14 @@ async fn handle_view_submission(
 
             let user_id: user::Id<Trusted> = view_submission.user.id.into();
 
+            let view_id = view_submission.view.state_params.id;
+
             let Some(view_state) = view_submission.view.state_params.state else {
                 error!("No state found in modal view submission");
                 return Ok(());
             };
 
-            handle_modal_view(client, view, view_state, states, user_id).await;
+            handle_modal_view(client, view, view_id, view_state, states, user_id).await;
 
             Ok(())
         }
@@ -112,6 +275,7 @@ async fn handle_view_submission(
 async fn handle_modal_view(
     client: Arc<SlackHyperClient>,
     view: SlackModalView,
+    view_id: SlackViewId,
     view_state: SlackViewState,
     states: SlackClientEventsUserState,
     user_id: user::Id<Trusted>,
@@ -127,15 +291,13 @@ async fn handle_modal_view(
             error!(
                 "No external id found in modal view. To the person that created the modal: How do you expect the bot to figure out what to do?"
             );
+            update_view(&client, view_id, unprocessable_view()).await;
         }
         Some("create_member") => {
             debug!("Received create member modal view");
 
-            if let Err(error) =
-                create_member(view_state, &client, user_state, user_id.clone()).await
-            {
-                handle_user_error(error, user_id.into(), client).await;
-            }
+            let result = create_member(view_state, &client, user_state, user_id).await;
+            finish_view(result, &client, view_id).await;
         }
         Some(id) if id.starts_with("edit_message_") => {
             debug!("Received edit message modal view");
@@ -146,18 +308,17 @@ async fn handle_modal_view(
             let message_id = SlackTs::new(message_id.to_owned());
             let channel_id = SlackChannelId::new(channel_id.to_owned());
 
-            if let Err(e) = message::edit(
+            let result = message::edit(
                 view_state,
                 &client,
                 user_state,
-                user_id.clone().into(),
+                user_id.into(),
                 message_id,
                 channel_id,
             )
             .await
-            {
-                handle_user_error(e, user_id.into(), client).await;
-            }
+            .map(|()| done_view());
+            finish_view(result, &client, view_id).await;
         }
         Some(id) if id.starts_with("reproxy_message_") => {
             debug!("Received reproxy message modal view");
@@ -168,18 +329,17 @@ async fn handle_modal_view(
             let message_id = SlackTs::new(message_id.to_owned());
             let channel_id = SlackChannelId::new(channel_id.to_owned());
 
-            if let Err(e) = message::reproxy(
+            let result = message::reproxy(
                 view_state,
                 &client,
                 user_state,
-                user_id.clone().into(),
+                user_id.into(),
                 message_id,
                 channel_id,
             )
             .await
-            {
-                handle_user_error(e, user_id.into(), client).await;
-            }
+            .map(|()| done_view());
+            finish_view(result, &client, view_id).await;
         }
         Some(id) if id.starts_with("edit_member_") => {
             debug!("Received edit member modal view");
@@ -194,6 +354,7 @@ async fn handle_modal_view(
                     id,
                     "Failed to parse member id from external id. Bailing in case this was a malicious call",
                 );
+                update_view(&client, view_id, unprocessable_view()).await;
                 return;
             };
 
@@ -205,50 +366,108 @@ async fn handle_modal_view(
                     id,
                     "Failed to validate member id from external id. Bailing in case this was a malicious call",
                 );
+                update_view(&client, view_id, unprocessable_view()).await;
                 return;
             };
 
-            if let Err(error) = edit_member(
-                view_state,
+            let result =
+                edit_member(view_state, &client, user_state, user_id, trusted_member_id).await;
+            finish_view(result, &client, view_id).await;
+        }
+        Some(id) if id.starts_with(member::CONFIRM_EDIT_PREFIX) => {
+            debug!("Received confirm member edit modal view");
+
+            let Ok(member_id) = id
+                .strip_prefix(member::CONFIRM_EDIT_PREFIX)
+                .expect("id starts with the confirm edit prefix")
+                .parse::<i64>()
+                .map(models::member::Id::new)
+            else {
+                error!(id, "Failed to parse member id from external id");
+                update_view(&client, view_id, unprocessable_view()).await;
+                return;
+            };
+
+            let Ok(Some(trusted_member_id)) =
+                member_id.validate_by_user(&user_id, &user_state.db).await
+            else {
+                error!(
+                    id,
+                    "Failed to validate member id from external id. The member may have been deleted",
+                );
+                update_view(&client, view_id, unprocessable_view()).await;
+                return;
+            };
+
+            let result = member::confirm_edit_member(
                 &client,
                 user_state,
-                user_id.clone(),
+                user_id,
                 trusted_member_id,
+                view.private_metadata.as_deref(),
             )
-            .await
-            {
-                handle_user_error(error, user_id.into(), client).await;
-            }
+            .await;
+            finish_view(result, &client, view_id).await;
         }
-        Some(id) => {
-            error!("receieved unknown external id: {id}");
+        Some(id) if id.starts_with("delete_member_") => {
+            debug!("Received delete member modal view");
+
+            let Ok(member_id) = id
+                .strip_prefix("delete_member_")
+                .expect("id starts with delete_member_")
+                .parse::<i64>()
+                .map(models::member::Id::new)
+            else {
+                error!(
+                    id,
+                    "Failed to parse member id from external id. Bailing in case this was a malicious call",
+                );
+                update_view(&client, view_id, unprocessable_view()).await;
+                return;
+            };
+
+            let Ok(Some(trusted_member_id)) =
+                member_id.validate_by_user(&user_id, &user_state.db).await
+            else {
+                error!(
+                    id,
+                    "Failed to validate member id from external id. The member may already be deleted",
+                );
+                update_view(&client, view_id, unprocessable_view()).await;
+                return;
+            };
+
+            let result =
+                delete_member(view_state, &client, user_state, user_id, trusted_member_id).await;
+            finish_view(result, &client, view_id).await;
         }
-    }
-}
+        Some(system::DELETE_EXTERNAL_ID) => {
+            debug!("Received delete system modal view");
 
-pub async fn handle_user_error<E>(
-    error: Report<E>,
-    user: SlackUserId,
-    client: Arc<SlackHyperClient>,
-) where
-    E: std::error::Error + Send + Sync + 'static,
-{
-    error!(?error);
+            let result = system::delete_system(view_state, &client, user_state, user_id).await;
+            finish_view(result, &client, view_id).await;
+        }
+        Some(id) if id.starts_with(import::EXTERNAL_ID_PREFIX) => {
+            debug!("Received import conflicts modal view");
 
-    let session = client.open_session(&BOT_TOKEN);
+            let Ok(pending_id) = id
+                .strip_prefix(import::EXTERNAL_ID_PREFIX)
+                .expect("id starts with the import prefix")
+                .parse::<i64>()
+            else {
+                error!(id, "Failed to parse pending import id from external id");
+                update_view(&client, view_id, unprocessable_view()).await;
+                return;
+            };
 
-    let conversation = session
-        .conversations_open(&SlackApiConversationsOpenRequest::new().with_users(vec![user.clone()]))
-        .await
-        .expect("Expected to be able to open conversation")
-        .channel;
-
-    session
-        .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
-            conversation.id,
-            user,
-            SlackMessageContent::new().with_text(format!("An error occured! {error}",)),
-        ))
-        .await
-        .expect("Expected to be able to post ephemeral message");
+            // Pending imports are looked up by the user's system, so another system's import can't be finished
+            let result =
+                import::resolve_conflicts(view_state, pending_id, user_state, user_id).await;
+            finish_view(result, &client, view_id).await;
+        }
+        Some(id) => {
+            error!("receieved unknown external id: {id}");
+            update_view(&client, view_id, unprocessable_view()).await;
+        }
+    }
 }
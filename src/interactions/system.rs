@@ -0,0 +1,212 @@
+//! The form for deleting a system, opened with `/system delete`, and the button for reauthenticating one.
+
+use error_stack::{Result, ResultExt, bail, report};
+use oauth2::reqwest;
+use serde::Deserialize;
+use slack_morphism::prelude::*;
+use tracing::{info, trace, warn};
+
+use crate::{
+    fields, home,
+    models::{
+        System,
+        trust::Trusted,
+        user::{self, State},
+    },
+    oauth,
+};
+
+/// The form's external ID
+pub const DELETE_EXTERNAL_ID: &str = "delete_system";
+/// What has to be typed into the form to delete the system
+const CONFIRMATION: &str = "delete";
+/// Action ID of the button sent with notifications about a system's authorization breaking
+pub const REAUTH_ACTION: &str = "reauth_system";
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    Slack,
+    /// No system found for the user
+    NoSystem,
+}
+
+/// Slack's response to revoking a token
+#[derive(Deserialize)]
+struct RevokeResponse {
+    ok: bool,
+    error: Option<String>,
+}
+
+/// Confirms deleting the user's system, and everything in it
+pub fn delete_view(member_count: usize) -> SlackView {
+    SlackView::Modal(
+        SlackModalView::new(
+            "Delete system".into(),
+            slack_blocks![
+                some_into(SlackSectionBlock::new().with_text(md!(
+                    "This permanently deletes your system and all {} of its members, along with their triggers, aliases, message logs, switch history and everything else the bot has stored about you. It can't be undone.\n\nThe bot will also sign out of your Slack account. Messages already sent stay in Slack.",
+                    member_count
+                ))),
+                some_into(SlackContextBlock::new(vec![md!(
+                    "Want a copy first? Close this and run `/system export`."
+                )])),
+                some_into(
+                    SlackInputBlock::new(
+                        pt!("Type \"{}\" to confirm", CONFIRMATION),
+                        SlackBlockPlainTextInputElement::new("confirmation".into()).into(),
+                    )
+                )
+            ],
+        )
+        .with_submit("Delete".into())
+        .with_close("Cancel".into())
+        .with_external_id(DELETE_EXTERNAL_ID.into()),
+    )
+}
+
+/// Revokes the system's user token, so the bot can no longer act as the user
+async fn revoke_token(system: &System) -> Result<(), Error> {
+    let response: RevokeResponse = reqwest::Client::new()
+        .post("https://slack.com/api/auth.revoke")
+        .bearer_auth(system.slack_oauth_token.expose())
+        .send()
+        .await
+        .change_context(Error::Slack)?
+        .text()
+        .await
+        .change_context(Error::Slack)
+        .and_then(|body| serde_json::from_str(&body).change_context(Error::Slack))?;
+
+    match response {
+        RevokeResponse { ok: true, .. } => Ok(()),
+        // Already signed out, e.g. by the user removing the app
+        RevokeResponse {
+            error: Some(error), ..
+        } if matches!(
+            &*error,
+            "invalid_auth" | "token_revoked" | "account_inactive"
+        ) =>
+        {
+            Ok(())
+        }
+        RevokeResponse { error, .. } => {
+            Err(report!(Error::Slack)).attach_printable(format!("auth.revoke failed: {error:?}"))
+        }
+    }
+}
+
+/// Deletes the user's system once the deletion form is submitted
+#[tracing::instrument(skip(view_state, client, user_state), fields(system_id))]
+pub async fn delete_system(
+    view_state: SlackViewState,
+    client: &SlackHyperClient,
+    user_state: &State,
+    user_id: user::Id<Trusted>,
+) -> Result<SlackView, Error> {
+    trace!("Deleting system");
+
+    let confirmed = view_state
+        .values
+        .into_values()
+        .flat_map(|values| values.into_iter())
+        .find(|(id, _)| id.0 == "confirmation")
+        .and_then(|(_, content)| content.value)
+        .is_some_and(|value| value.trim().eq_ignore_ascii_case(CONFIRMATION));
+
+    if !confirmed {
+        return Ok(super::status_view(
+            "System not deleted",
+            &format!(
+                "Your system wasn't deleted, as \"{CONFIRMATION}\" wasn't typed in. Run `/system delete` again if you meant to."
+            ),
+        ));
+    }
+
+    let Some(system) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    fields!(system_id = %system.id);
+
+    system
+        .id
+        .delete(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    info!("System deleted");
+
+    // Only revoked once the system is gone, so a failed delete leaves a working system.
+    // The token is no longer stored either way, so a failed revoke is only logged
+    if let Err(error) = revoke_token(&system).await {
+        warn!(?error, "Failed to revoke system token");
+    }
+
+    home::refresh(&user_id, client, &user_state.db).await;
+
+    Ok(super::status_view(
+        "System deleted",
+        "Your system and everything in it has been deleted. You can make a new one any time with `/system create`.",
+    ))
+}
+
+/// Replaces a notification's reauthenticate button with a fresh link to authorize the system with Slack.
+///
+/// The link is made on click rather than when the notification is sent, so it still works if the notification sat
+/// unread for a while, or if the user started authorizing from somewhere else since.
+#[tracing::instrument(skip_all, fields(system_id))]
+pub async fn reauth(
+    event: &SlackInteractionBlockActionsEvent,
+    client: &SlackHyperClient,
+    user_state: &State,
+) -> Result<(), Error> {
+    let Some(user_id) = event
+        .user
+        .as_ref()
+        .map(|user| user::Id::<Trusted>::from(user.id.clone()))
+    else {
+        warn!("No user found on reauthenticate button. Bailing");
+        return Ok(());
+    };
+
+    let Some(response_url) = &event.response_url else {
+        warn!("No response URL on reauthenticate button. Bailing");
+        return Ok(());
+    };
+
+    let Some(system) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    fields!(system_id = %system.id);
+    trace!("Reauthenticating system from notification");
+
+    let auth_url = oauth::authorize_url(&system.owner_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    client
+        .respond_to_event(
+            response_url,
+            &SlackApiPostWebhookMessageRequest::new(SlackMessageContent::new().with_blocks(
+                slack_blocks![some_into(SlackSectionBlock::new().with_text(md!(
+                    "<{}|Reauthenticate your system with Slack>. Messages will be proxied again once you're done.",
+                    auth_url
+                )))],
+            ))
+            .with_replace_original(true),
+        )
+        .await
+        .change_context(Error::Slack)?;
+
+    Ok(())
+}
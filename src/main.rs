@@ -2,22 +2,52 @@
 #![warn(clippy::pedantic, clippy::nursery, missing_docs, clippy::cargo)]
 #![allow(clippy::multiple_crate_versions)]
 
+mod api;
+mod backfill;
+mod blobs;
+mod blocks;
+mod cache;
 mod commands;
 mod env;
 mod events;
+mod health;
+mod home;
 mod interactions;
+mod metrics;
+mod migrate;
 mod models;
+mod notify;
 mod oauth;
+mod ops;
+mod pluralkit;
+mod preflight;
+mod proxy;
+mod rate_limit;
+mod reminders;
+mod render;
+mod retention;
+mod shutdown;
+mod slack_error;
+mod socket_mode;
+mod timeparse;
+mod upload;
 mod util;
 
-use crate::models::{system, trust::Trusted, user};
+use crate::models::{
+    CapturedEvent, captured_event, system,
+    trust::{Trusted, Untrusted},
+    user,
+};
 use std::{
+    path::PathBuf,
     process::ExitCode,
     str::FromStr,
     sync::{Arc, LazyLock},
 };
 
 use axum::{extract::MatchedPath, http::Request};
+use blobs::blob_handler;
+use clap::Parser;
 use commands::process_command_event;
 use error_stack::{ResultExt, report};
 use events::process_push_event;
@@ -26,10 +56,10 @@ use oauth::oauth_handler;
 use slack_morphism::prelude::*;
 use sqlx::{SqlitePool, sqlite::SqliteConnectOptions};
 use tower_http::trace::TraceLayer;
-use tracing::{debug, info, info_span, level_filters::LevelFilter};
+use tracing::{debug, info, info_span, level_filters::LevelFilter, warn};
 use tracing_subscriber::{EnvFilter, Layer, layer::SubscriberExt, util::SubscriberInitExt};
 
-/// The slack app token. Used for socket mode if we ever decide to use it.
+/// The slack app token. Used for Socket Mode
 pub static APP_TOKEN: LazyLock<SlackApiToken> =
     LazyLock::new(|| SlackApiToken::new(env::slack_app_token().into()));
 
@@ -43,12 +73,104 @@ enum Error {
     Env,
     /// Error during slack client initialization
     Initialization,
+    /// Error replaying a stored event
+    Replay,
+    /// Error migrating from another bot
+    Migrate,
+    /// The deployment failed its startup checks
+    Preflight,
+}
+
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum CliCommand {
+    /// Runs a stored event through message handling against DATABASE_URL, without proxying anything.
+    ///
+    /// Point DATABASE_URL at a staging copy of the database to reproduce a report without touching production.
+    Replay {
+        /// The ID of the stored event (see /plura-admin debug stored-events)
+        id: captured_event::Id<Untrusted>,
+        /// The database the event is stored in, if it isn't DATABASE_URL
+        #[arg(long)]
+        source: Option<String>,
+    },
+    /// Adds the systems in a dump from another Slack proxy bot to their owners' systems.
+    ///
+    /// The mapping file says where everything is in the dump (see the migrate module for the format).
+    /// Owners need to have made a system first. Running it again updates members that were already migrated.
+    Migrate {
+        /// The other bot's data, as JSON
+        dump: PathBuf,
+        /// Where each part of a system is in the dump
+        #[arg(long)]
+        mapping: PathBuf,
+        /// Print what would be migrated, without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Connection options for a database, decrypting it if there's an encryption key
+fn connect_options(url: &str) -> SqliteConnectOptions {
+    let options = SqliteConnectOptions::from_str(url)
+        .unwrap()
+        .optimize_on_close(true, None);
+
+    match env::encryption_key() {
+        Some(key) => options.pragma("key", key),
+        None => options,
+    }
+}
+
+/// Prints a dry run of a stored event, matched against `pool`
+async fn replay(
+    id: captured_event::Id<Untrusted>,
+    source: Option<String>,
+    pool: &SqlitePool,
+) -> error_stack::Result<ExitCode, Error> {
+    let source = match source {
+        Some(url) => SqlitePool::connect_with(connect_options(&url).read_only(true))
+            .await
+            .attach_printable("Error connecting to source database")
+            .change_context(Error::Replay)?,
+        None => pool.clone(),
+    };
+
+    let Some(captured) = CapturedEvent::fetch(id, &source)
+        .await
+        .change_context(Error::Replay)?
+    else {
+        warn!("That event doesn't exist, or has been pruned");
+        return Ok(ExitCode::FAILURE);
+    };
+
+    let event = events::capture::restore(&captured).change_context(Error::Replay)?;
+    let lines = events::replay::dry_run(event, pool)
+        .await
+        .change_context(Error::Replay)?;
+
+    println!(
+        "Stored event {} from {} UTC",
+        captured.id.id, captured.created_at
+    );
+    for line in lines {
+        println!("{line}");
+    }
+
+    Ok(ExitCode::SUCCESS)
 }
 
 #[dotenvy::load]
 #[tokio::main]
 #[tracing::instrument]
 async fn main() -> error_stack::Result<ExitCode, Error> {
+    let cli = Cli::parse();
     let console_subscriber = tracing_subscriber::fmt::layer().pretty();
     let error_subscriber = tracing_error::ErrorLayer::default();
     let env_subscriber = EnvFilter::builder()
@@ -74,14 +196,7 @@ async fn main() -> error_stack::Result<ExitCode, Error> {
         .map_err(|_| report!(Error::Initialization))
         .attach_printable("Error installing default ring crypto provider")?;
 
-    let mut options = SqliteConnectOptions::from_str(&env::database_url())
-        .unwrap()
-        .optimize_on_close(true, None)
-        .create_if_missing(true);
-
-    if let Some(key) = env::encryption_key() {
-        options = options.pragma("key", key);
-    }
+    let options = connect_options(&env::database_url()).create_if_missing(true);
 
     let pool = SqlitePool::connect_with(options)
         .await
@@ -109,12 +224,45 @@ async fn main() -> error_stack::Result<ExitCode, Error> {
     .attach_printable("Error fetching systems from database")
     .change_context(Error::Initialization)?;
 
+    match cli.command {
+        Some(CliCommand::Replay { id, source }) => return replay(id, source, &pool).await,
+        Some(CliCommand::Migrate {
+            dump,
+            mapping,
+            dry_run,
+        }) => {
+            return migrate::run(&dump, &mapping, dry_run, &pool)
+                .await
+                .change_context(Error::Migrate);
+        }
+        None => {}
+    }
+
+    preflight::run(&pool)
+        .await
+        .change_context(Error::Preflight)?;
+
     let client = Arc::new(SlackClient::new(
         SlackClientHyperConnector::new()
             .attach_printable("Error creating Slack hyper connector")
-            .change_context(Error::Initialization)?,
+            .change_context(Error::Initialization)?
+            .with_rate_control(slack_error::rate_control()),
     ));
 
+    ops::init(client.clone());
+
+    // Clean up after any proxying that was cut off by the bot stopping
+    if let Err(error) = proxy::recover(&client, &pool).await {
+        warn!(?error, "Error recovering interrupted proxy operations");
+    }
+
+    if let Err(error) = retention::vacuum(&pool).await {
+        warn!(?error, "Error vacuuming database");
+    }
+
+    retention::spawn(client.clone(), pool.clone());
+    reminders::spawn(client.clone(), pool.clone());
+
     let state = user::State { db: pool.clone() };
 
     let listener_environment: Arc<SlackHyperListenerEnvironment> = Arc::new(
@@ -126,10 +274,7 @@ async fn main() -> error_stack::Result<ExitCode, Error> {
     let listener: SlackEventsAxumListener<SlackHyperHttpsConnector> =
         SlackEventsAxumListener::new(listener_environment.clone());
 
-    let app = axum::routing::Router::new()
-        // Note: I do not use the slack-morphism oauth thing because it's a bit too much for me
-        .route("/auth", axum::routing::get(oauth_handler))
-        .with_state(state.clone())
+    let slack_routes = axum::routing::Router::new()
         .route(
             "/push",
             axum::routing::post(process_push_event).layer(
@@ -153,7 +298,56 @@ async fn main() -> error_stack::Result<ExitCode, Error> {
                     .events_layer(&signing_secret)
                     .with_event_extractor(SlackEventsExtractors::interaction_event()),
             ),
+        );
+
+    // Kept alive for as long as the server runs, as dropping it disconnects from Slack
+    let (socket_mode_listener, slack_routes) = if socket_mode::enabled() {
+        let socket_mode_listener = socket_mode::start(listener_environment.clone())
+            .await
+            .change_context(Error::Initialization)?;
+
+        (Some(socket_mode_listener), axum::routing::Router::new())
+    } else {
+        (None, slack_routes)
+    };
+
+    let api_routes = axum::routing::Router::new()
+        .route(
+            "/api/v1/systems/{id}/proxy",
+            axum::routing::post(api::proxy_handler).layer(axum::Extension(client.clone())),
+        )
+        .route(
+            "/api/v1/systems/{id}/members",
+            axum::routing::get(api::members_handler),
+        )
+        .route(
+            "/api/v1/systems/{id}/switch",
+            axum::routing::post(api::switch_handler).layer(axum::Extension(client.clone())),
+        )
+        .route(
+            "/api/v1/systems/{id}/export",
+            axum::routing::get(api::export_handler),
         )
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            api::authenticate,
+        ));
+
+    let app = axum::routing::Router::new()
+        // Note: I do not use the slack-morphism oauth thing because it's a bit too much for me
+        .route("/auth", axum::routing::get(oauth_handler))
+        .route("/blobs/{token}", axum::routing::get(blob_handler))
+        .merge(api_routes)
+        .route(
+            "/healthz",
+            axum::routing::get(health::health_handler).layer(axum::Extension(client.clone())),
+        )
+        .with_state(state.clone())
+        .route(
+            "/metrics",
+            axum::routing::get(|| async { metrics::render() }),
+        )
+        .merge(slack_routes)
         .layer(
             TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
                 // Log the matched route's path (with placeholders not filled in).
@@ -178,10 +372,28 @@ async fn main() -> error_stack::Result<ExitCode, Error> {
         .attach_printable("Failed to bind to address")
         .change_context(Error::Initialization)?;
 
-    axum::serve(listener, app)
-        .await
-        .attach_printable("Failed to start server")
-        .change_context(Error::Initialization)?;
+    preflight::spawn_reachability_check();
+
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        shutdown::signal().await;
+
+        if let Some(socket_mode_listener) = socket_mode_listener {
+            socket_mode_listener.shutdown().await;
+        }
+    });
+
+    tokio::select! {
+        result = server.into_future() => result
+            .attach_printable("Failed to start server")
+            .change_context(Error::Initialization)?,
+        // Requests that are stuck shouldn't hold up shutting down past the drain timeout
+        () = shutdown::drain() => {}
+    }
+
+    // Interactions keep running after their request is answered, so they may not have finished with the server
+    shutdown::drain().await;
+    pool.close().await;
+    info!("Shut down");
 
     Ok(ExitCode::SUCCESS)
 }
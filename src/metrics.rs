@@ -0,0 +1,192 @@
+//! In-memory counters for how proxy attempts turn out, and which errors Slack's API returns.
+//!
+//! Counters reset when the bot restarts. They are exported in the Prometheus text format on `/metrics`,
+//! and shown to operators through `/plura-admin stats`.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// How a proxy attempt turned out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The message was proxied and the original deleted
+    Success,
+    /// The sender doesn't have a system
+    NoSystem,
+    /// The sender has a system, but no member was triggered or fronting
+    NoTrigger,
+    /// Slack refused to post the proxied message
+    PostFailed,
+    /// The proxied message was posted, but the original couldn't be deleted
+    DeleteFailed,
+    /// The bot can't post in the channel (e.g. it isn't a member, or the channel is archived or restricted)
+    ChannelBlocked,
+    /// Something on our end went wrong, like the database
+    Internal,
+    /// The message was a duplicate of one that was just proxied, so it was deleted instead
+    Duplicate,
+}
+
+impl Outcome {
+    /// Every outcome, in the order they are reported
+    pub const ALL: [Self; 8] = [
+        Self::Success,
+        Self::NoSystem,
+        Self::NoTrigger,
+        Self::PostFailed,
+        Self::DeleteFailed,
+        Self::ChannelBlocked,
+        Self::Internal,
+        Self::Duplicate,
+    ];
+
+    /// The label used for this outcome in exported metrics
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::NoSystem => "no-system",
+            Self::NoTrigger => "no-trigger",
+            Self::PostFailed => "post-failed",
+            Self::DeleteFailed => "delete-failed",
+            Self::ChannelBlocked => "channel-blocked",
+            Self::Internal => "internal",
+            Self::Duplicate => "duplicate",
+        }
+    }
+}
+
+static PROXY_ATTEMPTS: [AtomicU64; Outcome::ALL.len()] =
+    [const { AtomicU64::new(0) }; Outcome::ALL.len()];
+
+/// Counts a proxy attempt
+pub fn record(outcome: Outcome) {
+    PROXY_ATTEMPTS[outcome as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// The number of proxy attempts per outcome since the bot started
+pub fn proxy_attempts() -> Vec<(Outcome, u64)> {
+    Outcome::ALL
+        .into_iter()
+        .map(|outcome| {
+            (
+                outcome,
+                PROXY_ATTEMPTS[outcome as usize].load(Ordering::Relaxed),
+            )
+        })
+        .collect()
+}
+
+/// How a proxy that failed after posting its message was cleaned up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cleanup {
+    /// The proxied message was deleted, leaving only the original
+    RolledBack,
+    /// The proxied message couldn't be deleted either, so both are shown until the bot next starts
+    Deferred,
+}
+
+impl Cleanup {
+    /// Every cleanup, in the order they are reported
+    pub const ALL: [Self; 2] = [Self::RolledBack, Self::Deferred];
+
+    /// The label used for this cleanup in exported metrics
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::RolledBack => "rolled-back",
+            Self::Deferred => "deferred",
+        }
+    }
+}
+
+static PARTIAL_FAILURES: [AtomicU64; Cleanup::ALL.len()] =
+    [const { AtomicU64::new(0) }; Cleanup::ALL.len()];
+
+/// Counts a proxy that failed after posting its message
+pub fn record_partial_failure(cleanup: Cleanup) {
+    PARTIAL_FAILURES[cleanup as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// The number of proxies that failed after posting, per cleanup, since the bot started
+pub fn partial_failures() -> Vec<(Cleanup, u64)> {
+    Cleanup::ALL
+        .into_iter()
+        .map(|cleanup| {
+            (
+                cleanup,
+                PARTIAL_FAILURES[cleanup as usize].load(Ordering::Relaxed),
+            )
+        })
+        .collect()
+}
+
+/// Slack API error codes, and how many times each was returned
+static SLACK_API_ERRORS: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+/// Counts an error returned by Slack's API. See [`crate::slack_error`]
+pub fn record_slack_error(code: &str) {
+    let mut errors = SLACK_API_ERRORS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    *errors.entry(code.to_string()).or_default() += 1;
+}
+
+/// The number of times Slack's API returned each error code since the bot started
+pub fn slack_api_errors() -> Vec<(String, u64)> {
+    SLACK_API_ERRORS
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .iter()
+        .map(|(code, count)| (code.clone(), *count))
+        .collect()
+}
+
+/// Renders all metrics in the Prometheus text format
+pub fn render() -> String {
+    let mut body = String::from(
+        "# HELP plura_proxy_attempts_total Proxy attempts by outcome\n\
+         # TYPE plura_proxy_attempts_total counter\n",
+    );
+
+    for (outcome, count) in proxy_attempts() {
+        // Writing to a string can't fail
+        let _ = writeln!(
+            body,
+            "plura_proxy_attempts_total{{outcome=\"{}\"}} {count}",
+            outcome.label()
+        );
+    }
+
+    body.push_str(
+        "# HELP plura_proxy_partial_failures_total Proxies that failed after posting, by how they were cleaned up\n\
+         # TYPE plura_proxy_partial_failures_total counter\n",
+    );
+
+    for (cleanup, count) in partial_failures() {
+        let _ = writeln!(
+            body,
+            "plura_proxy_partial_failures_total{{cleanup=\"{}\"}} {count}",
+            cleanup.label()
+        );
+    }
+
+    body.push_str(
+        "# HELP plura_slack_api_errors_total Errors returned by Slack's API by error code\n\
+         # TYPE plura_slack_api_errors_total counter\n",
+    );
+
+    for (code, count) in slack_api_errors() {
+        let _ = writeln!(
+            body,
+            "plura_slack_api_errors_total{{code=\"{code}\"}} {count}"
+        );
+    }
+
+    body
+}
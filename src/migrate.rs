@@ -0,0 +1,284 @@
+//! Migrating a community from another Slack proxy bot, run by an operator with `plura migrate`.
+//!
+//! Every bot stores its data differently, so the dump is read with a mapping file that says where each
+//! part of a system is, as [JSON pointers](https://datatracker.ietf.org/doc/html/rfc6901). For example:
+//!
+//! ```json
+//! {
+//!     "provider": "otherbot",
+//!     "systems": "/users",
+//!     "system": { "owner": "/slack_id", "members": "/alters" },
+//!     "member": {
+//!         "id": "/id",
+//!         "name": "/name",
+//!         "avatar": "/avatar",
+//!         "proxy_tags": { "list": "/brackets", "prefix": "/0", "suffix": "/1" }
+//!     }
+//! }
+//! ```
+//!
+//! Members are only added to systems that already exist, as a system needs its owner to sign in.
+//! Members are linked to their ID in the dump, so migrating again after more owners have made a system
+//! adds their members, and updates everyone else's instead of adding them twice.
+
+use std::{path::Path, process::ExitCode};
+
+use error_stack::{Result, ResultExt, bail, report};
+use serde::Deserialize;
+use serde_json::Value;
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{info, warn};
+
+use crate::{
+    interactions::import::summary_text,
+    models::{
+        System,
+        export::{self, ExportedExternalId, ExportedMember, Resolution},
+        user,
+    },
+    pluralkit::ProxyTag,
+};
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error reading the dump or mapping file
+    Read,
+    /// The mapping file isn't valid
+    Mapping,
+    /// The dump doesn't match the mapping: {0}
+    Dump(String),
+    /// Error while calling the database
+    Sqlx,
+    /// Error while adding the migrated members
+    Import,
+}
+
+/// Where each part of a system is in a dump
+#[derive(Deserialize, Debug)]
+struct Mapping {
+    /// What the other bot is called. Members are linked to their ID under this name
+    provider: String,
+    /// The list of systems. Leave it out if the whole dump is the list
+    #[serde(default)]
+    systems: String,
+    system: SystemMapping,
+    member: MemberMapping,
+}
+
+/// Where things are in each system, relative to the system
+#[derive(Deserialize, Debug)]
+struct SystemMapping {
+    /// The owner's Slack user ID
+    owner: String,
+    /// The list of the system's members
+    members: String,
+}
+
+/// Where things are in each member, relative to the member. Only the ID and name are required
+#[derive(Deserialize, Debug)]
+struct MemberMapping {
+    id: String,
+    name: String,
+    display_name: Option<String>,
+    avatar: Option<String>,
+    pronouns: Option<String>,
+    description: Option<String>,
+    proxy_tags: Option<ProxyTagsMapping>,
+}
+
+/// Where a member's proxy tags are
+#[derive(Deserialize, Debug)]
+struct ProxyTagsMapping {
+    /// The list of tags. Leave it out if the member only has one, with the prefix and suffix on the member
+    list: Option<String>,
+    /// The prefix, relative to the tag
+    prefix: Option<String>,
+    /// The suffix, relative to the tag
+    suffix: Option<String>,
+}
+
+/// A system found in the dump
+struct MigratedSystem {
+    owner: SlackUserId,
+    members: Vec<ExportedMember>,
+}
+
+/// The text at a pointer. Numbers are turned into text, as bots often use them for IDs
+fn text(value: &Value, pointer: &str) -> Option<String> {
+    match value.pointer(pointer)? {
+        Value::String(text) if !text.is_empty() => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+/// The list at a pointer
+fn list<'a>(value: &'a Value, pointer: &str, what: &str) -> Result<&'a [Value], Error> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .ok_or_else(|| report!(Error::Dump(format!("{what} at `{pointer}` isn't a list"))))
+}
+
+impl MemberMapping {
+    /// Converts a member, using its position in the system as its export ID
+    fn member(&self, value: &Value, index: usize, provider: &str) -> Result<ExportedMember, Error> {
+        let (Some(id), Some(name)) = (text(value, &self.id), text(value, &self.name)) else {
+            bail!(Error::Dump(format!(
+                "member {index} doesn't have an ID and a name"
+            )));
+        };
+        let optional =
+            |pointer: &Option<String>| pointer.as_deref().and_then(|pointer| text(value, pointer));
+
+        let triggers = match &self.proxy_tags {
+            Some(mapping) => {
+                let tags = match &mapping.list {
+                    Some(pointer) => list(value, pointer, "proxy tags")?,
+                    None => std::slice::from_ref(value),
+                };
+
+                tags.iter()
+                    .filter_map(|tag| {
+                        ProxyTag {
+                            prefix: mapping
+                                .prefix
+                                .as_deref()
+                                .and_then(|pointer| text(tag, pointer)),
+                            suffix: mapping
+                                .suffix
+                                .as_deref()
+                                .and_then(|pointer| text(tag, pointer)),
+                        }
+                        .to_exported()
+                    })
+                    .collect()
+            }
+            None => Vec::new(),
+        };
+
+        Ok(ExportedMember {
+            id: index.try_into().unwrap_or(i64::MAX),
+            display_name: optional(&self.display_name).unwrap_or_else(|| name.clone()),
+            full_name: name,
+            profile_picture_url: optional(&self.avatar),
+            proxy_avatar_url: None,
+            title: None,
+            pronouns: optional(&self.pronouns),
+            name_pronunciation: None,
+            name_recording_url: None,
+            description: optional(&self.description),
+            birthday: None,
+            emoji: None,
+            enabled: true,
+            proxy_enabled: true,
+            priority: 0,
+            message_count: 0,
+            triggers,
+            aliases: Vec::new(),
+            command_name: None,
+            fields: Vec::new(),
+            external_ids: vec![ExportedExternalId {
+                provider: provider.to_string(),
+                id,
+            }],
+        })
+    }
+}
+
+impl Mapping {
+    /// Finds every system in the dump
+    fn systems(&self, dump: &Value) -> Result<Vec<MigratedSystem>, Error> {
+        list(dump, &self.systems, "systems")?
+            .iter()
+            .enumerate()
+            .map(|(index, system)| {
+                let Some(owner) = text(system, &self.system.owner) else {
+                    bail!(Error::Dump(format!("system {index} doesn't have an owner")));
+                };
+
+                let members = list(system, &self.system.members, "members")?
+                    .iter()
+                    .enumerate()
+                    .map(|(index, member)| self.member.member(member, index, &self.provider))
+                    .collect::<Result<_, _>>()?;
+
+                Ok(MigratedSystem {
+                    owner: owner.into(),
+                    members,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Adds the members in a dump from another bot to their owners' systems, printing what happened to each.
+///
+/// With `dry_run`, prints what would happen without changing anything.
+#[tracing::instrument(skip(pool))]
+pub async fn run(
+    dump: &Path,
+    mapping: &Path,
+    dry_run: bool,
+    pool: &SqlitePool,
+) -> Result<ExitCode, Error> {
+    let mapping = std::fs::read_to_string(mapping)
+        .attach_printable("Error reading mapping file")
+        .change_context(Error::Read)?;
+    let mapping: Mapping = serde_json::from_str(&mapping).change_context(Error::Mapping)?;
+    let dump = std::fs::read_to_string(dump)
+        .attach_printable("Error reading dump")
+        .change_context(Error::Read)?;
+    let dump: Value = serde_json::from_str(&dump)
+        .change_context_lazy(|| Error::Dump("it isn't valid JSON".to_string()))?;
+
+    let systems = mapping.systems(&dump)?;
+    let mut missing = 0;
+
+    info!(systems = systems.len(), "Read dump");
+
+    for MigratedSystem { owner, members } in systems {
+        let Some(system) = System::fetch_by_user_id(&user::Id::new(owner.clone()), pool)
+            .await
+            .change_context(Error::Sqlx)?
+        else {
+            println!("{owner}: no system yet, skipped {} members", members.len());
+            missing += 1;
+            continue;
+        };
+
+        // Nobody's around to pick what to do with members that look like existing ones, so they're left alone
+        let resolutions = export::find_conflicts(&members, &system, pool)
+            .await
+            .change_context(Error::Import)?
+            .into_iter()
+            .map(|conflict| (conflict, Resolution::Skip))
+            .collect::<Vec<_>>();
+
+        if dry_run {
+            println!(
+                "{owner}: {} members, {} already look like existing members",
+                members.len(),
+                resolutions.len()
+            );
+            continue;
+        }
+
+        let summary = export::import_members(&members, &resolutions, system.id, pool)
+            .await
+            .change_context(Error::Import)?;
+
+        println!("{owner}: {}", summary_text(summary));
+    }
+
+    if missing > 0 {
+        warn!(
+            missing,
+            "Some owners don't have a system yet. Run the migration again once they've made one"
+        );
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
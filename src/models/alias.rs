@@ -1,10 +1,10 @@
-use crate::id;
+use crate::{cache, id};
 
 use super::{
-    member, system,
+    is_unique_violation, member, system,
     trust::{Trusted, Untrusted},
 };
-use error_stack::{Result, ResultExt};
+use error_stack::{Report, Result, ResultExt};
 use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
 
 id!(
@@ -15,6 +15,49 @@ id!(
     => Alias
 );
 
+/// Why an alias couldn't be added or changed
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// That alias is already used by {0}
+    Taken(String),
+}
+
+/// Turns a failed alias write into [`Error::Taken`] if another member has the alias, looking up who
+async fn write_error(
+    error: Report<sqlx::Error>,
+    system_id: system::Id<Trusted>,
+    alias: &str,
+    db: &SqlitePool,
+) -> Report<Error> {
+    if !is_unique_violation(error.current_context()) {
+        return error.change_context(Error::Sqlx);
+    }
+
+    let owner = sqlx::query!(
+        r#"
+        SELECT members.display_name
+        FROM aliases
+        JOIN members ON members.id = aliases.member_id
+        WHERE aliases.system_id = $1 AND aliases.alias = $2
+        "#,
+        system_id,
+        alias
+    )
+    .fetch_optional(db)
+    .await;
+
+    match owner {
+        Ok(owner) => Report::new(Error::Taken(
+            owner.map_or_else(|| "another member".to_string(), |owner| owner.display_name),
+        )),
+        Err(lookup_error) => error
+            .change_context(Error::Sqlx)
+            .attach_printable(format!("Failed to look up alias owner: {lookup_error}")),
+    }
+}
+
 impl Id<Untrusted> {
     #[tracing::instrument(skip(db))]
     pub async fn validate_by_system(
@@ -55,10 +98,11 @@ impl Id<Trusted> {
     #[tracing::instrument(skip(db))]
     pub async fn change_alias(
         self,
+        system_id: system::Id<Trusted>,
         new_alias: String,
         db: &SqlitePool,
-    ) -> Result<SqliteQueryResult, sqlx::Error> {
-        sqlx::query!(
+    ) -> Result<SqliteQueryResult, Error> {
+        let result = sqlx::query!(
             r#"
                 UPDATE aliases
                 SET alias = $2
@@ -69,7 +113,12 @@ impl Id<Trusted> {
         )
         .execute(db)
         .await
-        .attach_printable("Failed to change alias in database")
+        .attach_printable("Failed to change alias in database");
+
+        match result {
+            Ok(result) => Ok(result),
+            Err(error) => Err(write_error(error, system_id, &new_alias, db).await),
+        }
     }
 }
 
@@ -81,6 +130,8 @@ pub struct Alias {
     pub system_id: system::Id<Trusted>,
     #[allow(clippy::struct_field_names)]
     pub alias: String,
+    /// Whether this is the member's command name
+    pub is_primary: bool,
 }
 
 impl Alias {
@@ -96,7 +147,8 @@ impl Alias {
                     id as "id: Id<Trusted>",
                     member_id as "member_id: member::Id<Trusted>",
                     system_id as "system_id: system::Id<Trusted>",
-                    alias
+                    alias,
+                    is_primary as "is_primary: bool"
                 FROM
                     aliases
                 WHERE
@@ -121,7 +173,8 @@ impl Alias {
                 id as "id: Id<Trusted>",
                 member_id as "member_id: member::Id<Trusted>",
                 system_id as "system_id: system::Id<Trusted>",
-                alias
+                alias,
+                is_primary as "is_primary: bool"
             FROM
                 aliases
             WHERE member_id = $1
@@ -139,8 +192,8 @@ impl Alias {
         system_id: system::Id<Trusted>,
         alias: String,
         db: &SqlitePool,
-    ) -> error_stack::Result<Self, sqlx::Error> {
-        sqlx::query_as!(
+    ) -> error_stack::Result<Self, Error> {
+        let result = sqlx::query_as!(
             Self,
             r#"
             INSERT INTO aliases (member_id, system_id, alias)
@@ -149,7 +202,8 @@ impl Alias {
                 id as "id: Id<Trusted>",
                 member_id as "member_id: member::Id<Trusted>",
                 system_id as "system_id: system::Id<Trusted>",
-                alias
+                alias,
+                is_primary as "is_primary: bool"
             "#,
             member_id,
             system_id,
@@ -157,6 +211,14 @@ impl Alias {
         )
         .fetch_one(db)
         .await
-        .attach_printable("Failed to insert alias into database")
+        .attach_printable("Failed to insert alias into database");
+
+        match result {
+            Ok(inserted) => {
+                cache::invalidate(system_id);
+                Ok(inserted)
+            }
+            Err(error) => Err(write_error(error, system_id, &alias, db).await),
+        }
     }
 }
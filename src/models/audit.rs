@@ -0,0 +1,160 @@
+use crate::id;
+
+use super::{
+    system,
+    trust::{Trusted, Untrusted},
+    user,
+};
+use error_stack::{Result, ResultExt};
+use sqlx::{SqlitePool, prelude::*};
+
+id!(
+    /// For an ID to be trusted, it must
+    ///
+    /// - Be a valid ID in the database
+    /// - Be associated with a valid system (constrained at database level; no validation needed)
+    => AuditLog
+);
+
+/// How long after an action it can be undone, in minutes
+pub const UNDO_WINDOW_MINUTES: i64 = 10;
+
+impl Id<Untrusted> {
+    /// Fetches an entry from the system's audit log, if it was recorded recently enough to be undone
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_undoable(
+        self,
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Option<AuditLog>, sqlx::Error> {
+        let window = format!("-{UNDO_WINDOW_MINUTES} minutes");
+
+        sqlx::query_as!(
+            AuditLog,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                actor_id as "actor_id: user::Id<Trusted>",
+                action as "action: Action",
+                details,
+                created_at as "created_at: time::PrimitiveDateTime"
+            FROM audit_logs
+            WHERE id = $1 AND system_id = $2 AND created_at > datetime('now', $3)
+            "#,
+            self.id,
+            system_id,
+            window
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch audit log")
+    }
+}
+
+#[derive(Debug, sqlx::Type, displaydoc::Display, PartialEq, Eq, Clone, Copy)]
+#[repr(i64)]
+/// An action taken on a system that is recorded in the audit log.
+#[ignore_extra_doc_attributes]
+pub enum Action {
+    /// Edited a message
+    MessageEdited = 0,
+    /// Disabled a member
+    ///
+    /// The details are the member's ID
+    MemberDisabled = 1,
+    /// Enabled a member
+    ///
+    /// The details are the member's ID
+    MemberEnabled = 2,
+    /// Permanently deleted a member
+    ///
+    /// The details are the deleted member's ID
+    MemberDeleted = 3,
+}
+
+impl From<i64> for Action {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::MessageEdited,
+            1 => Self::MemberDisabled,
+            2 => Self::MemberEnabled,
+            3 => Self::MemberDeleted,
+            _ => unreachable!(
+                "Invalid action value. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+/// A single entry in a system's audit log
+pub struct AuditLog {
+    pub id: Id<Trusted>,
+    pub system_id: system::Id<Trusted>,
+    /// The user that performed the action
+    pub actor_id: user::Id<Trusted>,
+    pub action: Action,
+    /// Extra information about the action, like how it was carried out
+    pub details: Option<String>,
+    pub created_at: time::PrimitiveDateTime,
+}
+
+impl AuditLog {
+    /// Records an action in the system's audit log
+    #[tracing::instrument(skip(db))]
+    pub async fn record(
+        system_id: system::Id<Trusted>,
+        actor_id: &user::Id<Trusted>,
+        action: Action,
+        details: Option<String>,
+        db: &SqlitePool,
+    ) -> Result<Id<Trusted>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_logs (system_id, actor_id, action, details)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id as "id: Id<Trusted>"
+            "#,
+            system_id,
+            actor_id,
+            action,
+            details
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to record audit log")
+        .map(|record| record.id)
+    }
+
+    /// Fetches the system's most recent audit log entries, newest first
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_recent(
+        system_id: system::Id<Trusted>,
+        limit: u16,
+        db: &SqlitePool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            AuditLog,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                actor_id as "actor_id: user::Id<Trusted>",
+                action as "action: Action",
+                details,
+                created_at as "created_at: time::PrimitiveDateTime"
+            FROM audit_logs
+            WHERE system_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+            "#,
+            system_id,
+            limit
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch recent audit logs")
+    }
+}
@@ -0,0 +1,67 @@
+use crate::env;
+
+use super::{system, trust::Trusted};
+use error_stack::{Result, ResultExt};
+use oauth2::CsrfToken;
+use sqlx::{SqlitePool, prelude::*};
+
+/// A file hosted by the bot, served publicly at [`Blob::url`]
+///
+/// Blobs are looked up by a random token rather than their ID, so the URL can't be guessed from another one.
+#[derive(FromRow, Debug)]
+pub struct Blob {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Blob {
+    /// Stores a file for a system, returning the token it's served at
+    #[tracing::instrument(skip(data, db), fields(size = data.len()))]
+    pub async fn insert(
+        system_id: system::Id<Trusted>,
+        content_type: &str,
+        data: &[u8],
+        db: &SqlitePool,
+    ) -> Result<String, sqlx::Error> {
+        // 128 random bits, base64url encoded
+        let token = CsrfToken::new_random().into_secret();
+
+        sqlx::query!(
+            "
+            INSERT INTO blobs (system_id, content_type, data, token)
+            VALUES ($1, $2, $3, $4)
+            ",
+            system_id,
+            content_type,
+            data,
+            token
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to store blob")?;
+
+        Ok(token)
+    }
+
+    /// Fetches a file by the token from its URL
+    #[tracing::instrument(skip_all)]
+    pub async fn fetch(token: &str, db: &SqlitePool) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Blob,
+            r#"
+            SELECT content_type, data
+            FROM blobs
+            WHERE token = $1
+            "#,
+            token
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch blob")
+    }
+
+    /// The public URL a file with the given token is served at
+    pub fn url(token: &str) -> String {
+        format!("{}/blobs/{token}", env::base_url())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::id;
+
+use super::trust::{Trusted, Untrusted};
+use error_stack::{Result, ResultExt};
+use sqlx::{SqlitePool, prelude::*};
+
+id!(
+    /// For an ID to be trusted, it must
+    ///
+    /// - Be a valid ID in the database
+    => CapturedEvent
+);
+
+/// The longest captured events can be kept for, however high the retention is set
+pub const MAX_RETENTION_MINUTES: u32 = 24 * 60;
+
+#[derive(FromRow, Debug)]
+/// A redacted message event payload, kept so reports of messages not being proxied can be reproduced
+pub struct CapturedEvent {
+    pub id: Id<Trusted>,
+    /// The event, serialized as JSON
+    pub payload: String,
+    pub created_at: time::PrimitiveDateTime,
+}
+
+impl CapturedEvent {
+    /// Stores an event payload, and removes any that are older than `retention_minutes`
+    #[tracing::instrument(skip(payload, db))]
+    pub async fn insert(
+        payload: &str,
+        retention_minutes: u32,
+        db: &SqlitePool,
+    ) -> Result<Id<Trusted>, sqlx::Error> {
+        let window = format!("-{} minutes", retention_minutes.min(MAX_RETENTION_MINUTES));
+
+        sqlx::query!(
+            r#"
+            DELETE FROM captured_events
+            WHERE created_at <= datetime('now', $1)
+            "#,
+            window
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to prune captured events")?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO captured_events (payload)
+            VALUES ($1)
+            RETURNING id as "id: Id<Trusted>"
+            "#,
+            payload
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to store captured event")
+        .map(|record| record.id)
+    }
+
+    /// Fetches a captured event, if it hasn't been pruned
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch(id: Id<Untrusted>, db: &SqlitePool) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            CapturedEvent,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                payload,
+                created_at as "created_at: time::PrimitiveDateTime"
+            FROM captured_events
+            WHERE id = $1
+            "#,
+            id.id
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch captured event")
+    }
+
+    /// Fetches the most recently captured events, newest first
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_recent(limit: u16, db: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            CapturedEvent,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                payload,
+                created_at as "created_at: time::PrimitiveDateTime"
+            FROM captured_events
+            ORDER BY id DESC
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch captured events")
+    }
+}
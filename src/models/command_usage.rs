@@ -0,0 +1,56 @@
+//! Daily counts of which commands are run, for operators to see which features get used.
+//!
+//! Only the command is counted, never who ran it or what they passed to it.
+
+use error_stack::{Result, ResultExt};
+use sqlx::SqlitePool;
+
+/// How many times a command was run
+#[derive(Debug)]
+pub struct CommandUsage {
+    /// The subcommand path, e.g. `members add`
+    pub command: String,
+    pub count: i64,
+}
+
+impl CommandUsage {
+    /// Counts a run of `command` towards today
+    #[tracing::instrument(skip(db))]
+    pub async fn record(command: &str, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO command_usage (day, command, count)
+            VALUES (date('now'), $1, 1)
+            ON CONFLICT (day, command) DO UPDATE SET count = count + 1
+            "#,
+            command
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to record command usage")
+        .map(|_| ())
+    }
+
+    /// How many times each command was run over the last `days` days, most used first
+    #[tracing::instrument(skip(db))]
+    pub async fn totals(days: u16, db: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        let since = format!("-{days} days");
+
+        sqlx::query_as!(
+            CommandUsage,
+            r#"
+            SELECT
+                command,
+                SUM(count) as "count!: i64"
+            FROM command_usage
+            WHERE day > date('now', $1)
+            GROUP BY command
+            ORDER BY SUM(count) DESC, command
+            "#,
+            since
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch command usage")
+    }
+}
@@ -0,0 +1,991 @@
+//! Versioned snapshots of a system, for backups and moving a system between instances of the bot.
+//!
+//! Every export is written with a top-level `version` field. When the format changes:
+//!
+//! 1. Move the current structs into a module for the old version (e.g. `v1`)
+//! 2. Change the structs here and bump [`CURRENT_VERSION`]
+//! 3. Add an arm to [`Export::from_json`] that parses the old version and converts it with a `From` impl
+//!
+//! This way an export from any earlier version can still be imported.
+//!
+//! IDs in an export only identify members within that export. Importing creates new members with new IDs.
+
+use std::collections::HashMap;
+
+use error_stack::{Result, ResultExt, bail};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqliteExecutor, SqlitePool};
+
+use crate::cache;
+
+use super::{
+    Alias, ExternalId, MemberField, System, member,
+    system::{self, NotificationPreference},
+    trigger::{self, Trigger},
+    trust::Trusted,
+};
+
+/// The version new exports are written as
+pub const CURRENT_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// The export isn't valid JSON, or doesn't match the format of its version
+    Format,
+    /// Export version {0} isn't supported by this version of the bot
+    UnsupportedVersion(u32),
+    /// The fronting member isn't one of the exported members
+    UnknownFrontingMember,
+}
+
+/// What to do with an imported member that looks like one the system already has
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Leave the existing member alone, and don't import this one
+    Skip,
+    /// Update the existing member with the imported one's details and triggers
+    Overwrite,
+    /// Import the member anyway, alongside the existing one
+    Duplicate,
+}
+
+impl Resolution {
+    pub const ALL: [Self; 3] = [Self::Skip, Self::Overwrite, Self::Duplicate];
+
+    /// The value of the resolution's option in the conflicts form
+    pub const fn value(self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Overwrite => "overwrite",
+            Self::Duplicate => "duplicate",
+        }
+    }
+
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Skip => "Skip",
+            Self::Overwrite => "Overwrite existing",
+            Self::Duplicate => "Add as a new member",
+        }
+    }
+
+    pub fn from_value(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|resolution| resolution.value() == value)
+    }
+}
+
+/// An imported member that looks like one the system already has
+#[derive(Debug, Clone, Copy)]
+pub struct Conflict {
+    /// The imported member's export ID
+    pub imported: i64,
+    pub existing: member::Id<Trusted>,
+}
+
+/// What an import ended up doing
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ImportSummary {
+    pub added: usize,
+    /// Members imported from the same place before, that changed since
+    pub updated: usize,
+    /// Members imported from the same place before, that haven't changed
+    pub unchanged: usize,
+    pub overwritten: usize,
+    pub skipped: usize,
+}
+
+/// A full snapshot of a system, in the current version's format
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Export {
+    pub settings: Settings,
+    /// The export ID of the fronting member, if any. With co-fronters, this is the primary fronter
+    pub fronting_member: Option<i64>,
+    /// The export IDs of the other members fronting, in order. Missing from exports made before co-fronting
+    #[serde(default)]
+    pub co_fronting_members: Vec<i64>,
+    /// The system's own IDs in other services. Missing from exports made before they were tracked
+    #[serde(default)]
+    pub external_ids: Vec<ExportedExternalId>,
+    pub members: Vec<ExportedMember>,
+}
+
+/// A system's settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Settings {
+    pub auto_switch_on_trigger: bool,
+    pub message_info_requires_consent: bool,
+    pub anonymous_prefix: Option<String>,
+    pub timezone: Option<String>,
+    /// Start and end hour
+    pub quiet_hours: Option<(u8, u8)>,
+    pub notifications: NotificationPreference,
+    /// Missing from exports made before system tags
+    #[serde(default)]
+    pub system_tag: Option<String>,
+    /// Missing from exports made before name formats
+    #[serde(default)]
+    pub name_format: Option<String>,
+}
+
+/// A member, along with their triggers and aliases
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedMember {
+    /// Identifies the member within this export only
+    pub id: i64,
+    pub full_name: String,
+    pub display_name: String,
+    pub profile_picture_url: Option<String>,
+    /// Missing from exports made before members had proxy avatars
+    #[serde(default)]
+    pub proxy_avatar_url: Option<String>,
+    pub title: Option<String>,
+    pub pronouns: Option<String>,
+    pub name_pronunciation: Option<String>,
+    /// Recordings hosted by the bot link back to the instance they were exported from
+    pub name_recording_url: Option<String>,
+    /// Missing from exports made before member descriptions
+    #[serde(default)]
+    pub description: Option<String>,
+    /// As `YYYY-MM-DD`, or `MM-DD` without a year. Missing from exports made before birthdays
+    #[serde(default)]
+    pub birthday: Option<String>,
+    pub emoji: Option<String>,
+    pub enabled: bool,
+    /// Missing from exports made before proxying could be turned off per member
+    #[serde(default = "proxy_enabled_default")]
+    pub proxy_enabled: bool,
+    /// Missing from exports made before member priorities
+    #[serde(default)]
+    pub priority: i64,
+    /// How many messages the member has sent. Kept for reference, and not restored
+    #[serde(default)]
+    pub message_count: i64,
+    pub triggers: Vec<ExportedTrigger>,
+    pub aliases: Vec<String>,
+    /// Which of the aliases is the member's command name. Missing from exports made before command names
+    #[serde(default)]
+    pub command_name: Option<String>,
+    /// Missing from exports made before custom fields
+    #[serde(default)]
+    pub fields: Vec<ExportedField>,
+    /// Where the member was imported from, so importing from there again updates them.
+    /// Missing from exports made before imports could be synced
+    #[serde(default)]
+    pub external_ids: Vec<ExportedExternalId>,
+}
+
+const fn proxy_enabled_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedTrigger {
+    pub typ: trigger::Type,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedField {
+    pub name: String,
+    pub value: String,
+}
+
+/// A system's or member's ID in another service. See [`ExternalId`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedExternalId {
+    /// Exports made before system IDs were tracked call this the source
+    #[serde(alias = "source")]
+    pub provider: String,
+    pub id: String,
+}
+
+impl From<ExternalId> for ExportedExternalId {
+    fn from(value: ExternalId) -> Self {
+        Self {
+            provider: value.provider,
+            id: value.external_id,
+        }
+    }
+}
+
+/// An export as written to a file: the export with its version alongside it
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    version: u32,
+    #[serde(flatten)]
+    export: T,
+}
+
+/// Just the version of an export, to work out how to parse the rest
+#[derive(Deserialize)]
+struct VersionOnly {
+    version: u32,
+}
+
+impl Export {
+    /// Takes a snapshot of a system
+    #[tracing::instrument(skip_all, fields(system_id = %system.id))]
+    pub async fn collect(system: &System, db: &SqlitePool) -> Result<Self, Error> {
+        let members = system.members(db).await.change_context(Error::Sqlx)?;
+        let aliases = Alias::fetch_by_system_id(system.id, db)
+            .await
+            .change_context(Error::Sqlx)?;
+        let triggers = Trigger::fetch_by_system_id(system.id, db)
+            .await
+            .change_context(Error::Sqlx)?;
+        let fields = MemberField::fetch_by_system_id(system.id, db)
+            .await
+            .change_context(Error::Sqlx)?;
+        let (system_external_ids, member_external_ids): (Vec<_>, Vec<_>) =
+            ExternalId::fetch_by_system_id(system.id, db)
+                .await
+                .change_context(Error::Sqlx)?
+                .into_iter()
+                .partition(|external_id| external_id.member_id.is_none());
+        let message_counts = system
+            .id
+            .message_counts(db)
+            .await
+            .change_context(Error::Sqlx)?;
+        let fronters = system.id.fronters(db).await.change_context(Error::Sqlx)?;
+
+        let members = members
+            .into_iter()
+            .map(|member| ExportedMember {
+                id: member.id.id,
+                message_count: message_counts
+                    .get(&member.id.id)
+                    .copied()
+                    .unwrap_or_default(),
+                triggers: triggers
+                    .iter()
+                    .filter(|trigger| trigger.member_id == member.id)
+                    .map(|trigger| ExportedTrigger {
+                        typ: trigger.typ,
+                        text: trigger.text.clone(),
+                    })
+                    .collect(),
+                aliases: aliases
+                    .iter()
+                    .filter(|alias| alias.member_id == member.id)
+                    .map(|alias| alias.alias.clone())
+                    .collect(),
+                external_ids: member_external_ids
+                    .iter()
+                    .filter(|external_id| external_id.member_id == Some(member.id))
+                    .cloned()
+                    .map(ExportedExternalId::from)
+                    .collect(),
+                fields: fields
+                    .iter()
+                    .filter(|field| field.member_id == member.id)
+                    .map(|field| ExportedField {
+                        name: field.name.clone(),
+                        value: field.value.clone(),
+                    })
+                    .collect(),
+                full_name: member.full_name,
+                display_name: member.display_name,
+                profile_picture_url: member.profile_picture_url,
+                proxy_avatar_url: member.proxy_avatar_url,
+                title: member.title,
+                pronouns: member.pronouns,
+                name_pronunciation: member.name_pronunciation,
+                name_recording_url: member.name_recording_url,
+                description: member.description,
+                birthday: member.birthday,
+                emoji: member.emoji,
+                enabled: member.enabled,
+                proxy_enabled: member.proxy_enabled,
+                priority: member.priority,
+                command_name: member.command_name,
+            })
+            .collect();
+
+        Ok(Self {
+            settings: Settings {
+                auto_switch_on_trigger: system.auto_switch_on_trigger,
+                message_info_requires_consent: system.message_info_requires_consent,
+                anonymous_prefix: system.anonymous_prefix.clone(),
+                timezone: system.timezone.clone(),
+                quiet_hours: system.quiet_hours(),
+                notifications: system.notifications,
+                system_tag: system.system_tag.clone(),
+                name_format: system.name_format.clone(),
+            },
+            fronting_member: fronters.first().map(|id| id.id),
+            co_fronting_members: fronters.iter().skip(1).map(|id| id.id).collect(),
+            external_ids: system_external_ids
+                .into_iter()
+                .map(ExportedExternalId::from)
+                .collect(),
+            members,
+        })
+    }
+
+    /// Adds the export's members to a system and replaces its settings with the export's.
+    ///
+    /// Existing members are left alone. Nothing is changed if any part of the import fails.
+    #[tracing::instrument(skip_all, fields(system_id = %system_id))]
+    pub async fn restore(
+        &self,
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), Error> {
+        let mut transaction = db.begin().await.change_context(Error::Sqlx)?;
+
+        let member_ids = insert_members(&self.members, system_id, &mut transaction).await?;
+        let fronting_member = self
+            .fronting_member
+            .and_then(|id| member_ids.get(&id).copied());
+
+        if self.fronting_member.is_some() && fronting_member.is_none() {
+            bail!(Error::UnknownFrontingMember);
+        }
+
+        let Some(co_fronting_members) = self
+            .co_fronting_members
+            .iter()
+            .map(|id| member_ids.get(id).copied())
+            .collect::<Option<Vec<_>>>()
+        else {
+            bail!(Error::UnknownFrontingMember);
+        };
+
+        let settings = &self.settings;
+        let (quiet_hours_start, quiet_hours_end) = settings.quiet_hours.unzip();
+
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET
+                auto_switch_on_trigger = $1,
+                message_info_requires_consent = $2,
+                anonymous_prefix = $3,
+                timezone = $4,
+                quiet_hours_start = $5,
+                quiet_hours_end = $6,
+                notifications = $7,
+                currently_fronting_member_id = coalesce($8, currently_fronting_member_id),
+                system_tag = $10,
+                name_format = $11
+            WHERE id = $9
+            "#,
+            settings.auto_switch_on_trigger,
+            settings.message_info_requires_consent,
+            settings.anonymous_prefix,
+            settings.timezone,
+            quiet_hours_start,
+            quiet_hours_end,
+            settings.notifications,
+            fronting_member,
+            system_id,
+            settings.system_tag,
+            settings.name_format,
+        )
+        .execute(&mut *transaction)
+        .await
+        .change_context(Error::Sqlx)?;
+
+        // Like the primary fronter, who is fronting is only replaced if the export has someone fronting
+        if let Some(fronting_member) = fronting_member {
+            sqlx::query!("DELETE FROM fronters WHERE system_id = $1", system_id)
+                .execute(&mut *transaction)
+                .await
+                .change_context(Error::Sqlx)?;
+
+            let fronters = std::iter::once(fronting_member).chain(co_fronting_members);
+            for (position, member_id) in (0_i64..).zip(fronters) {
+                sqlx::query!(
+                    "INSERT OR IGNORE INTO fronters (system_id, member_id, position) VALUES ($1, $2, $3)",
+                    system_id,
+                    member_id,
+                    position
+                )
+                .execute(&mut *transaction)
+                .await
+                .change_context(Error::Sqlx)?;
+            }
+        }
+
+        for external_id in &self.external_ids {
+            ExternalId::link(
+                system_id,
+                None,
+                &external_id.provider,
+                &external_id.id,
+                &mut *transaction,
+            )
+            .await
+            .change_context(Error::Sqlx)?;
+        }
+
+        transaction.commit().await.change_context(Error::Sqlx)?;
+        cache::invalidate(system_id);
+
+        Ok(())
+    }
+
+    /// Writes the export as JSON, tagged with [`CURRENT_VERSION`]
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(&Versioned {
+            version: CURRENT_VERSION,
+            export: self,
+        })
+        .change_context(Error::Format)
+    }
+
+    /// Reads an export of any supported version, upgrading it to the current format
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let VersionOnly { version } = serde_json::from_str(json).change_context(Error::Format)?;
+
+        match version {
+            CURRENT_VERSION => serde_json::from_str::<Versioned<Self>>(json)
+                .change_context(Error::Format)
+                .map(|versioned| versioned.export),
+            _ => bail!(Error::UnsupportedVersion(version)),
+        }
+    }
+}
+
+/// Finds imported members that look like members the system already has.
+///
+/// A member looks the same if their full or display name matches (ignoring case), or if they share an alias.
+/// Each imported member conflicts with at most one existing member.
+/// Members imported from the same place before aren't conflicts, as importing updates them.
+#[tracing::instrument(skip_all, fields(system_id = %system.id))]
+pub async fn find_conflicts(
+    members: &[ExportedMember],
+    system: &System,
+    db: &SqlitePool,
+) -> Result<Vec<Conflict>, Error> {
+    let existing = system.members(db).await.change_context(Error::Sqlx)?;
+    let aliases = Alias::fetch_by_system_id(system.id, db)
+        .await
+        .change_context(Error::Sqlx)?;
+    let linked = linked_members(members, system.id, db).await?;
+
+    let conflicts = members
+        .iter()
+        .filter(|member| !linked.contains_key(&member.id))
+        .filter_map(|member| {
+            let same_name = |name: &str| {
+                name.eq_ignore_ascii_case(&member.full_name)
+                    || name.eq_ignore_ascii_case(&member.display_name)
+            };
+
+            existing
+                .iter()
+                .find(|existing| {
+                    same_name(&existing.full_name) || same_name(&existing.display_name)
+                })
+                .map(|existing| existing.id)
+                .or_else(|| {
+                    aliases
+                        .iter()
+                        .find(|alias| member.aliases.contains(&alias.alias))
+                        .map(|alias| alias.member_id)
+                })
+                .map(|existing| Conflict {
+                    imported: member.id,
+                    existing,
+                })
+        })
+        .collect();
+
+    Ok(conflicts)
+}
+
+/// Adds members to a system, e.g. from another bot's export.
+///
+/// Members imported from the same place before are updated with any changes. Members with a conflict are
+/// handled by its resolution, and everyone else is added as a new member.
+/// Nothing is changed if any part of the import fails.
+#[tracing::instrument(skip_all, fields(system_id = %system_id))]
+pub async fn import_members(
+    members: &[ExportedMember],
+    resolutions: &[(Conflict, Resolution)],
+    system_id: system::Id<Trusted>,
+    db: &SqlitePool,
+) -> Result<ImportSummary, Error> {
+    let mut transaction = db.begin().await.change_context(Error::Sqlx)?;
+    let mut summary = ImportSummary::default();
+    let mut new_members = Vec::with_capacity(members.len());
+    let linked = linked_members(members, system_id, &mut *transaction).await?;
+
+    for member in members {
+        if let Some(&member_id) = linked.get(&member.id) {
+            if sync_member(member, member_id, system_id, &mut transaction).await? {
+                summary.updated += 1;
+            } else {
+                summary.unchanged += 1;
+            }
+            continue;
+        }
+
+        let resolution = resolutions
+            .iter()
+            .find(|(conflict, _)| conflict.imported == member.id);
+
+        match resolution {
+            Some((_, Resolution::Skip)) => summary.skipped += 1,
+            Some((conflict, Resolution::Overwrite)) => {
+                overwrite_member(member, conflict.existing, system_id, &mut transaction).await?;
+                summary.overwritten += 1;
+            }
+            Some((_, Resolution::Duplicate)) | None => new_members.push(member),
+        }
+    }
+
+    summary.added = new_members.len();
+    insert_members(new_members, system_id, &mut transaction).await?;
+    transaction.commit().await.change_context(Error::Sqlx)?;
+    cache::invalidate(system_id);
+
+    Ok(summary)
+}
+
+/// Keeps an import around while the user decides what to do with its conflicts.
+///
+/// Imports left waiting for over a day are cleared out. Returns the ID to finish the import with.
+#[tracing::instrument(skip_all, fields(system_id = %system_id))]
+pub async fn save_pending_import(
+    members: &[ExportedMember],
+    system_id: system::Id<Trusted>,
+    db: &SqlitePool,
+) -> Result<i64, Error> {
+    let json = serde_json::to_string(members).change_context(Error::Format)?;
+
+    sqlx::query!("DELETE FROM pending_imports WHERE created_at < datetime('now', '-1 day')")
+        .execute(db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    sqlx::query!(
+        "INSERT INTO pending_imports (system_id, members) VALUES ($1, $2) RETURNING id",
+        system_id,
+        json
+    )
+    .fetch_one(db)
+    .await
+    .change_context(Error::Sqlx)
+    .map(|record| record.id)
+}
+
+/// Removes a pending import, returning its members. [`None`] if it doesn't exist or belongs to another system
+#[tracing::instrument(skip(db))]
+pub async fn take_pending_import(
+    id: i64,
+    system_id: system::Id<Trusted>,
+    db: &SqlitePool,
+) -> Result<Option<Vec<ExportedMember>>, Error> {
+    let Some(record) = sqlx::query!(
+        "DELETE FROM pending_imports WHERE id = $1 AND system_id = $2 RETURNING members",
+        id,
+        system_id
+    )
+    .fetch_optional(db)
+    .await
+    .change_context(Error::Sqlx)?
+    else {
+        return Ok(None);
+    };
+
+    serde_json::from_str(&record.members)
+        .change_context(Error::Format)
+        .map(Some)
+}
+
+/// The existing member each imported member was imported as before, keyed by export ID
+async fn linked_members(
+    members: &[ExportedMember],
+    system_id: system::Id<Trusted>,
+    db: impl SqliteExecutor<'_>,
+) -> Result<HashMap<i64, member::Id<Trusted>>, Error> {
+    let links = ExternalId::fetch_by_system_id(system_id, db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    Ok(members
+        .iter()
+        .filter_map(|member| {
+            links
+                .iter()
+                .find(|link| {
+                    member.external_ids.iter().any(|external_id| {
+                        link.provider == external_id.provider && link.external_id == external_id.id
+                    })
+                })
+                .and_then(|link| link.member_id)
+                .map(|member_id| (member.id, member_id))
+        })
+        .collect())
+}
+
+/// Brings a member imported before up to date with the place they were imported from.
+///
+/// The names, pronouns, description, birthday and avatars are replaced, since that place is the source of truth
+/// for them. Details it doesn't have, like titles, are left alone, and new triggers are added.
+/// Returns whether any of the replaced details changed.
+async fn sync_member(
+    member: &ExportedMember,
+    member_id: member::Id<Trusted>,
+    system_id: system::Id<Trusted>,
+    connection: &mut SqliteConnection,
+) -> Result<bool, Error> {
+    let changed = sqlx::query!(
+        r#"
+        UPDATE members
+        SET
+            full_name = $1,
+            display_name = $2,
+            profile_picture_url = $3,
+            proxy_avatar_url = $4,
+            pronouns = $5,
+            description = $6,
+            birthday = $7
+        WHERE id = $8 AND system_id = $9 AND NOT (
+            full_name IS $1 AND
+            display_name IS $2 AND
+            profile_picture_url IS $3 AND
+            proxy_avatar_url IS $4 AND
+            pronouns IS $5 AND
+            description IS $6 AND
+            birthday IS $7
+        )
+        "#,
+        member.full_name,
+        member.display_name,
+        member.profile_picture_url,
+        member.proxy_avatar_url,
+        member.pronouns,
+        member.description,
+        member.birthday,
+        member_id,
+        system_id,
+    )
+    .execute(&mut *connection)
+    .await
+    .change_context(Error::Sqlx)?
+    .rows_affected()
+        > 0;
+
+    insert_related(member, member_id, system_id, connection).await?;
+
+    Ok(changed)
+}
+
+/// Updates an existing member with an imported member's details.
+///
+/// Details the import leaves empty keep their current value, and the member's settings (like whether they're
+/// enabled) are left alone. If the imported member has triggers, they replace the existing member's triggers.
+async fn overwrite_member(
+    member: &ExportedMember,
+    member_id: member::Id<Trusted>,
+    system_id: system::Id<Trusted>,
+    connection: &mut SqliteConnection,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"
+        UPDATE members
+        SET
+            full_name = $1,
+            display_name = $2,
+            profile_picture_url = coalesce($3, profile_picture_url),
+            proxy_avatar_url = coalesce($4, proxy_avatar_url),
+            title = coalesce($5, title),
+            pronouns = coalesce($6, pronouns),
+            name_pronunciation = coalesce($7, name_pronunciation),
+            name_recording_url = coalesce($8, name_recording_url),
+            description = coalesce($9, description),
+            birthday = coalesce($10, birthday),
+            emoji = coalesce($11, emoji)
+        WHERE id = $12 AND system_id = $13
+        "#,
+        member.full_name,
+        member.display_name,
+        member.profile_picture_url,
+        member.proxy_avatar_url,
+        member.title,
+        member.pronouns,
+        member.name_pronunciation,
+        member.name_recording_url,
+        member.description,
+        member.birthday,
+        member.emoji,
+        member_id,
+        system_id,
+    )
+    .execute(&mut *connection)
+    .await
+    .change_context(Error::Sqlx)?;
+
+    if !member.triggers.is_empty() {
+        sqlx::query!("DELETE FROM triggers WHERE member_id = $1", member_id)
+            .execute(&mut *connection)
+            .await
+            .change_context(Error::Sqlx)?;
+    }
+
+    insert_related(member, member_id, system_id, connection).await
+}
+
+/// Inserts members with their triggers and aliases.
+///
+/// Triggers and aliases the system already uses are skipped rather than failing the import.
+/// Returns the new ID of each member, keyed by their export ID.
+async fn insert_members<'a>(
+    members: impl IntoIterator<Item = &'a ExportedMember>,
+    system_id: system::Id<Trusted>,
+    connection: &mut SqliteConnection,
+) -> Result<HashMap<i64, i64>, Error> {
+    let mut member_ids = HashMap::new();
+
+    for member in members {
+        let member_id = sqlx::query!(
+            r#"
+            INSERT INTO members (full_name, display_name, profile_picture_url, proxy_avatar_url, title, pronouns, name_pronunciation, name_recording_url, description, birthday, emoji, enabled, proxy_enabled, priority, system_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            RETURNING id as "id: member::Id<Trusted>"
+            "#,
+            member.full_name,
+            member.display_name,
+            member.profile_picture_url,
+            member.proxy_avatar_url,
+            member.title,
+            member.pronouns,
+            member.name_pronunciation,
+            member.name_recording_url,
+            member.description,
+            member.birthday,
+            member.emoji,
+            member.enabled,
+            member.proxy_enabled,
+            member.priority,
+            system_id,
+        )
+        .fetch_one(&mut *connection)
+        .await
+        .change_context(Error::Sqlx)?
+        .id;
+
+        member_ids.insert(member.id, member_id.id);
+
+        insert_related(member, member_id, system_id, connection).await?;
+    }
+
+    Ok(member_ids)
+}
+
+/// Adds an imported member's triggers, aliases and fields to a member, and links them to where they were imported from.
+///
+/// Triggers and aliases the system already uses are skipped, and fields the member already has are replaced.
+async fn insert_related(
+    member: &ExportedMember,
+    member_id: member::Id<Trusted>,
+    system_id: system::Id<Trusted>,
+    connection: &mut SqliteConnection,
+) -> Result<(), Error> {
+    for trigger in &member.triggers {
+        sqlx::query!(
+                "INSERT INTO triggers (member_id, system_id, typ, text) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+                member_id,
+                system_id,
+                trigger.typ,
+                trigger.text,
+            )
+            .execute(&mut *connection)
+            .await
+            .change_context(Error::Sqlx)?;
+    }
+
+    for alias in &member.aliases {
+        let is_primary = member.command_name.as_ref() == Some(alias);
+
+        sqlx::query!(
+                "INSERT INTO aliases (member_id, system_id, alias, is_primary) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+                member_id,
+                system_id,
+                alias,
+                is_primary,
+            )
+            .execute(&mut *connection)
+            .await
+            .change_context(Error::Sqlx)?;
+    }
+
+    for external_id in &member.external_ids {
+        ExternalId::link(
+            system_id,
+            Some(member_id),
+            &external_id.provider,
+            &external_id.id,
+            &mut *connection,
+        )
+        .await
+        .change_context(Error::Sqlx)?;
+    }
+
+    for field in &member.fields {
+        sqlx::query!(
+            r#"
+            INSERT INTO member_fields (member_id, name, value)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (member_id, name) DO UPDATE SET value = excluded.value
+            "#,
+            member_id,
+            field.name,
+            field.value,
+        )
+        .execute(&mut *connection)
+        .await
+        .change_context(Error::Sqlx)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: i64, name: &str) -> ExportedMember {
+        ExportedMember {
+            id,
+            full_name: format!("{name} Example"),
+            display_name: name.to_string(),
+            profile_picture_url: Some("https://example.com/avatar.png".into()),
+            proxy_avatar_url: None,
+            title: None,
+            pronouns: Some("they/them".into()),
+            name_pronunciation: None,
+            name_recording_url: None,
+            description: Some("Likes tea".into()),
+            birthday: Some("05-01".into()),
+            emoji: Some(":tea:".into()),
+            enabled: true,
+            proxy_enabled: true,
+            priority: 1,
+            message_count: 12,
+            triggers: vec![ExportedTrigger {
+                typ: trigger::Type::Prefix,
+                text: format!("{name}:"),
+            }],
+            aliases: vec![name.to_lowercase()],
+            command_name: Some(name.to_lowercase()),
+            fields: vec![ExportedField {
+                name: "Role".into(),
+                value: "Host".into(),
+            }],
+            external_ids: vec![ExportedExternalId {
+                provider: "pluralkit".into(),
+                id: "abcde".into(),
+            }],
+        }
+    }
+
+    fn export() -> Export {
+        Export {
+            settings: Settings {
+                auto_switch_on_trigger: true,
+                message_info_requires_consent: false,
+                anonymous_prefix: Some("anon:".into()),
+                timezone: Some("Europe/London".into()),
+                quiet_hours: Some((22, 7)),
+                notifications: NotificationPreference::Errors,
+                system_tag: Some("| Example".into()),
+                name_format: None,
+            },
+            fronting_member: Some(1),
+            co_fronting_members: vec![2],
+            external_ids: vec![ExportedExternalId {
+                provider: "pluralkit".into(),
+                id: "vwxyz".into(),
+            }],
+            members: vec![member(1, "Ash"), member(2, "Birch")],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let export = export();
+        let json = export.to_json().unwrap();
+
+        assert_eq!(Export::from_json(&json).unwrap(), export);
+    }
+
+    #[test]
+    fn written_with_version() {
+        let json = export().to_json().unwrap();
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+
+        assert_eq!(value["version"], CURRENT_VERSION);
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let error = Export::from_json(r#"{ "version": 999 }"#).unwrap_err();
+
+        assert!(matches!(
+            error.current_context(),
+            Error::UnsupportedVersion(999)
+        ));
+    }
+
+    #[test]
+    fn invalid() {
+        for json in ["", "{}", r#"{ "version": "1" }"#, r#"{ "version": 1 }"#] {
+            let error = Export::from_json(json).unwrap_err();
+
+            assert!(
+                matches!(error.current_context(), Error::Format),
+                "{json} should be a format error"
+            );
+        }
+    }
+
+    #[test]
+    fn defaults_for_older_exports() {
+        let json = r#"{
+            "version": 1,
+            "settings": {
+                "auto_switch_on_trigger": false,
+                "message_info_requires_consent": true,
+                "anonymous_prefix": null,
+                "timezone": null,
+                "quiet_hours": null,
+                "notifications": "always"
+            },
+            "fronting_member": null,
+            "members": [{
+                "id": 1,
+                "full_name": "Ash Example",
+                "display_name": "Ash",
+                "profile_picture_url": null,
+                "title": null,
+                "pronouns": null,
+                "name_pronunciation": null,
+                "name_recording_url": null,
+                "emoji": null,
+                "enabled": true,
+                "triggers": [],
+                "aliases": [],
+                "external_ids": [{ "source": "pluralkit", "id": "abcde" }]
+            }]
+        }"#;
+
+        let export = Export::from_json(json).unwrap();
+        let member = &export.members[0];
+
+        assert!(export.co_fronting_members.is_empty());
+        assert_eq!(export.settings.system_tag, None);
+        assert!(member.proxy_enabled);
+        assert_eq!(member.priority, 0);
+        assert!(member.fields.is_empty());
+        assert_eq!(member.external_ids[0].provider, "pluralkit");
+    }
+}
@@ -0,0 +1,73 @@
+//! IDs that systems and members have in other services.
+//!
+//! Imports, exports and other integrations record these so they can find the same system or member again
+//! later, instead of guessing by name.
+
+use error_stack::{Result, ResultExt};
+use sqlx::{SqliteExecutor, prelude::*};
+
+use super::{member, system, trust::Trusted};
+
+#[derive(FromRow, Debug, Clone)]
+pub struct ExternalId {
+    pub system_id: system::Id<Trusted>,
+    /// [`None`] if the ID is the system's own
+    pub member_id: Option<member::Id<Trusted>>,
+    /// The service the ID is from, e.g. `pluralkit`
+    pub provider: String,
+    pub external_id: String,
+}
+
+impl ExternalId {
+    /// Fetches every external ID of a system and its members
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_system_id(
+        system_id: system::Id<Trusted>,
+        db: impl SqliteExecutor<'_>,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"
+            SELECT
+                system_id as "system_id: system::Id<Trusted>",
+                member_id as "member_id: member::Id<Trusted>",
+                provider,
+                external_id
+            FROM external_ids
+            WHERE system_id = $1
+            "#,
+            system_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch external IDs from database")
+    }
+
+    /// Records a member's ID in a provider, or the system's own if `member_id` is [`None`].
+    ///
+    /// Nothing changes if the member or system already has an ID there, or the ID is already used.
+    #[tracing::instrument(skip(db))]
+    pub async fn link(
+        system_id: system::Id<Trusted>,
+        member_id: Option<member::Id<Trusted>>,
+        provider: &str,
+        external_id: &str,
+        db: impl SqliteExecutor<'_>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO external_ids (system_id, member_id, provider, external_id)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+            system_id,
+            member_id,
+            provider,
+            external_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to link external ID")
+        .map(|_| ())
+    }
+}
@@ -0,0 +1,127 @@
+//! Responses to API requests sent with an `Idempotency-Key` header.
+//!
+//! Scripts retry requests that time out, even when the first try went through. A key is claimed before the request is
+//! carried out and its response is stored afterwards, so a retry with the same key gets the stored response back
+//! instead of posting or switching again. Keys are kept for [`TTL`].
+
+use error_stack::{Result, ResultExt};
+use sqlx::SqlitePool;
+
+use super::{system, trust::Trusted};
+
+/// How long a key is kept, as an SQLite date modifier
+const TTL: &str = "-1 day";
+
+/// What happened when claiming a key
+#[derive(Debug)]
+pub enum Claim {
+    /// The key is new, and is now held by this request
+    Claimed,
+    /// The key was already used
+    Used {
+        /// The endpoint it was used for
+        endpoint: String,
+        /// The JSON response. [`None`] while the first request is still being handled
+        response: Option<String>,
+    },
+}
+
+pub struct IdempotencyKey;
+
+impl IdempotencyKey {
+    /// Claims a key for a request to `endpoint`, or returns what it was already used for
+    #[tracing::instrument(skip(db))]
+    pub async fn claim(
+        system_id: system::Id<Trusted>,
+        key: &str,
+        endpoint: &str,
+        db: &SqlitePool,
+    ) -> Result<Claim, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM api_idempotency_keys WHERE created_at < datetime('now', $1)",
+            TTL
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to delete expired idempotency keys")?;
+
+        let claimed = sqlx::query!(
+            "
+            INSERT INTO api_idempotency_keys (system_id, key, endpoint)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (system_id, key) DO NOTHING
+            ",
+            system_id,
+            key,
+            endpoint
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to claim idempotency key")?
+        .rows_affected()
+            == 1;
+
+        if claimed {
+            return Ok(Claim::Claimed);
+        }
+
+        sqlx::query!(
+            "
+            SELECT endpoint, response
+            FROM api_idempotency_keys
+            WHERE system_id = $1 AND key = $2
+            ",
+            system_id,
+            key
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to fetch idempotency key")
+        .map(|row| Claim::Used {
+            endpoint: row.endpoint,
+            response: row.response,
+        })
+    }
+
+    /// Stores the response for a claimed key
+    #[tracing::instrument(skip(response, db))]
+    pub async fn complete(
+        system_id: system::Id<Trusted>,
+        key: &str,
+        response: &str,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "
+            UPDATE api_idempotency_keys
+            SET response = $3
+            WHERE system_id = $1 AND key = $2
+            ",
+            system_id,
+            key,
+            response
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to store idempotency key response")
+        .map(|_| ())
+    }
+
+    /// Lets go of a claimed key after the request failed, so it can be retried with the same key
+    #[tracing::instrument(skip(db))]
+    pub async fn release(
+        system_id: system::Id<Trusted>,
+        key: &str,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM api_idempotency_keys WHERE system_id = $1 AND key = $2",
+            system_id,
+            key
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to release idempotency key")
+        .map(|_| ())
+    }
+}
@@ -3,9 +3,10 @@ use std::{convert::Infallible, str::FromStr};
 use error_stack::{Result, ResultExt};
 use slack_morphism::prelude::*;
 use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
+use time::{Date, macros::format_description};
 use tracing::{debug, warn};
 
-use crate::id;
+use crate::{cache, env, id};
 
 use super::{
     system,
@@ -94,6 +95,26 @@ impl Id<Untrusted> {
     }
 }
 
+/// Turns a name into a command name: lowercased, with no spaces.
+///
+/// Returns [`None`] if the name has spaces in it, or could be mistaken for a member ID.
+pub fn normalize_command_name(name: &str) -> Option<String> {
+    let name = name.trim().to_lowercase();
+
+    (!name.is_empty() && !name.contains(char::is_whitespace) && name.parse::<i64>().is_err())
+        .then_some(name)
+}
+
+/// A command name made from a display name, with the spaces and any punctuation taken out
+pub fn command_name_from(display_name: &str) -> Option<String> {
+    normalize_command_name(
+        &display_name
+            .chars()
+            .filter(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+            .collect::<String>(),
+    )
+}
+
 impl Id<Trusted> {
     #[tracing::instrument(skip(db))]
     pub async fn fetch_triggers(self, db: &SqlitePool) -> Result<Vec<Trigger>, sqlx::Error> {
@@ -127,6 +148,224 @@ impl Id<Trusted> {
         .execute(db)
         .await
         .attach_printable("Failed to update member enabled status")
+        .inspect(|_| cache::invalidate_member(self))
+    }
+
+    /// Makes `name` the member's command name, or removes their command name if it's [`None`].
+    ///
+    /// The old command name is kept as a regular alias, so anything already using it still works.
+    /// The name should already be [normalized](normalize_command_name), and not be an alias of another member.
+    #[tracing::instrument(skip(db))]
+    pub async fn set_command_name(
+        self,
+        system_id: system::Id<Trusted>,
+        name: Option<&str>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        sqlx::query!(
+            "UPDATE aliases SET is_primary = FALSE WHERE member_id = $1 AND is_primary",
+            self
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to clear old command name")?;
+
+        if let Some(name) = name {
+            // Promotes the alias if the member already has it
+            sqlx::query!(
+                r#"
+                INSERT INTO aliases (member_id, system_id, alias, is_primary)
+                VALUES ($1, $2, $3, TRUE)
+                ON CONFLICT (member_id, alias) DO UPDATE SET is_primary = TRUE
+                "#,
+                self,
+                system_id,
+                name
+            )
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to set command name")?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit command name")?;
+
+        cache::invalidate(system_id);
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn proxy_enabled(self, db: &SqlitePool) -> Result<bool, sqlx::Error> {
+        sqlx::query!("SELECT proxy_enabled FROM members WHERE id = $1", self)
+            .fetch_one(db)
+            .await
+            .attach_printable("Failed to fetch member proxy status")
+            .map(|res| res.proxy_enabled)
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_proxy_enabled(
+        self,
+        proxy_enabled: bool,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE members SET proxy_enabled = $1 WHERE id = $2",
+            proxy_enabled,
+            self
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update member proxy status")
+        .inspect(|_| cache::invalidate_member(self))
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_name_recording_url(
+        self,
+        url: &str,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE members SET name_recording_url = $1 WHERE id = $2",
+            url,
+            self
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update member name recording")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_emoji(
+        self,
+        emoji: Option<&str>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!("UPDATE members SET emoji = $1 WHERE id = $2", emoji, self)
+            .execute(db)
+            .await
+            .attach_printable("Failed to update member emoji")
+            .inspect(|_| cache::invalidate_member(self))
+    }
+
+    /// Permanently deletes the member and everything stored about them.
+    ///
+    /// Their proxied messages stay in Slack. If `keep_message_logs` is set, the logs of those messages are kept
+    /// without saying who sent them, so the messages can still be managed. Otherwise they're deleted too.
+    #[tracing::instrument(skip(db))]
+    pub async fn delete(self, keep_message_logs: bool, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+        let recording_base_url = format!("{}/blobs/", env::base_url());
+
+        sqlx::query!(
+            "
+            UPDATE systems SET
+                currently_fronting_member_id = iif(currently_fronting_member_id = $1, NULL, currently_fronting_member_id),
+                autoproxy_member_id = iif(autoproxy_member_id = $1, NULL, autoproxy_member_id),
+                latched_member_id = iif(latched_member_id = $1, NULL, latched_member_id)
+            WHERE $1 IN (currently_fronting_member_id, autoproxy_member_id, latched_member_id)
+            ",
+            self
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to unset member from system")?;
+
+        // Recordings uploaded through Slack are hosted by the bot, so they go too
+        sqlx::query!(
+            "
+            DELETE FROM blobs
+            WHERE id IN (
+                SELECT blobs.id FROM blobs
+                JOIN members ON members.system_id = blobs.system_id
+                WHERE members.id = $1 AND members.name_recording_url = $2 || blobs.token
+            )
+            ",
+            self,
+            recording_base_url
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to delete member recording")?;
+
+        if keep_message_logs {
+            sqlx::query!(
+                "UPDATE message_logs SET member_id = NULL WHERE member_id = $1",
+                self
+            )
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to anonymize member message logs")?;
+        } else {
+            sqlx::query!("DELETE FROM message_logs WHERE member_id = $1", self)
+                .execute(&mut *transaction)
+                .await
+                .attach_printable("Failed to delete member message logs")?;
+        }
+
+        sqlx::query!("DELETE FROM fronters WHERE member_id = $1", self)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to remove member from fronters")?;
+
+        sqlx::query!("DELETE FROM switches WHERE member_id = $1", self)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete member switches")?;
+
+        sqlx::query!("DELETE FROM triggers WHERE member_id = $1", self)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete member triggers")?;
+
+        sqlx::query!("DELETE FROM aliases WHERE member_id = $1", self)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete member aliases")?;
+
+        sqlx::query!("DELETE FROM member_fields WHERE member_id = $1", self)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete member fields")?;
+
+        sqlx::query!("DELETE FROM external_ids WHERE member_id = $1", self)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete member external IDs")?;
+
+        sqlx::query!(
+            "DELETE FROM member_recording_process WHERE member_id = $1",
+            self
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to cancel member recording")?;
+
+        sqlx::query!("DELETE FROM members WHERE id = $1", self)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete member")?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit member deletion")?;
+
+        cache::invalidate_member(self);
+
+        Ok(())
     }
 }
 
@@ -179,15 +418,29 @@ pub struct Member {
     pub display_name: String,
     /// The full name of the member
     pub full_name: String,
-    /// Profile picture to use on messages
+    /// Profile picture shown on the member's info card, and on messages if there's no proxy avatar
     pub profile_picture_url: Option<String>,
+    /// Avatar to use on messages instead of the profile picture
+    pub proxy_avatar_url: Option<String>,
     pub title: Option<String>,
     pub pronouns: Option<String>,
     pub name_pronunciation: Option<String>,
     pub name_recording_url: Option<String>,
+    /// A long-form bio, in Slack mrkdwn
+    pub description: Option<String>,
+    /// As `YYYY-MM-DD`, or `MM-DD` if the year is left out. See [`parse_birthday`]
+    pub birthday: Option<String>,
+    /// Custom workspace emoji shown when switching to the member, without colons
+    pub emoji: Option<String>,
     pub created_at: time::PrimitiveDateTime,
     /// A deleted member is effectively a disabled member. They exist in the database, but you cannot interact with them in many ways.
     pub enabled: bool,
+    /// Whether messages can be proxied as the member. Unlike [`Member::enabled`], the member is otherwise left as-is
+    pub proxy_enabled: bool,
+    /// The member's primary alias, a short name for using in commands
+    pub command_name: Option<String>,
+    /// Breaks ties when triggers from several members match a message equally well. Higher wins
+    pub priority: i64,
 }
 
 impl Member {
@@ -203,11 +456,18 @@ impl Member {
                 full_name,
                 display_name,
                 profile_picture_url,
+                proxy_avatar_url,
                 title,
                 pronouns,
                 name_pronunciation,
                 name_recording_url,
+                description,
+                birthday,
+                emoji,
                 enabled,
+                proxy_enabled,
+                priority,
+                (SELECT alias FROM aliases WHERE aliases.member_id = members.id AND aliases.is_primary) as "command_name?: String",
                 created_at as "created_at: time::PrimitiveDateTime"
             FROM members
             WHERE id = $1
@@ -227,12 +487,16 @@ pub struct DetectedMember {
     pub id: Id<Trusted>,
     /// The display name of the member
     pub display_name: String,
-    /// Profile picture to use on messages
+    /// Profile picture shown on the member's info card
     pub profile_picture_url: Option<String>,
+    /// Avatar to use on messages instead of the profile picture
+    pub proxy_avatar_url: Option<String>,
     /// The trigger text that was matched
     pub trigger_text: String,
     /// The type of trigger
     pub typ: Type,
+    /// See [`Member::priority`]
+    pub priority: i64,
 }
 
 impl From<Member> for DetectedMember {
@@ -241,24 +505,42 @@ impl From<Member> for DetectedMember {
             id: value.id,
             display_name: value.display_name,
             profile_picture_url: value.profile_picture_url,
+            proxy_avatar_url: value.proxy_avatar_url,
             trigger_text: String::new(),
             typ: Type::Prefix,
+            priority: value.priority,
         }
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 pub struct View {
     pub full_name: String,
     pub display_name: String,
     pub profile_picture_url: Option<String>,
+    pub proxy_avatar_url: Option<String>,
     pub title: Option<String>,
     pub pronouns: Option<String>,
     pub name_pronunciation: Option<String>,
     pub name_recording_url: Option<String>,
+    pub description: Option<String>,
+    pub birthday: Option<String>,
+    pub priority: i64,
+}
+
+/// A field that's different between two [`View`]s
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// The field's name, as shown in the member form
+    pub field: &'static str,
+    pub old: Option<String>,
+    pub new: Option<String>,
 }
 
 impl View {
+    /// The longest description that can be entered. Descriptions are shown in a section, which is capped at 3000 characters
+    pub const MAX_DESCRIPTION_LENGTH: u64 = 3000;
+
     /// Due to the way the slack blocks are created, all fields are moved.
     /// Clone the whole struct if you need to keep the original.
     pub fn create_blocks(self) -> Vec<SlackBlock> {
@@ -282,6 +564,30 @@ impl View {
                 )
                 .with_optional(true)
             ),
+            some_into(
+                SlackInputBlock::new(
+                    "Proxy avatar URL".into(),
+                    SlackBlockPlainTextInputElement::new("proxy_avatar_url".into())
+                        .with_initial_value(self.proxy_avatar_url.unwrap_or_default())
+                        .into(),
+                )
+                .with_optional(true)
+                .with_hint(pt!(
+                    "Used on proxied messages instead of the profile picture. Leave empty to use the profile picture"
+                ))
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Trigger priority".into(),
+                    SlackBlockPlainTextInputElement::new("priority".into())
+                        .with_initial_value(self.priority.to_string())
+                        .into(),
+                )
+                .with_optional(true)
+                .with_hint(pt!(
+                    "When the same message matches triggers from several members, the longest trigger wins. If they're just as long, the member with the higher priority wins"
+                ))
+            ),
             // personal info
             some_into(SlackDividerBlock::new()),
             some_into(
@@ -328,6 +634,30 @@ impl View {
                         .into(),
                 )
                 .with_optional(true)
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Birthday".into(),
+                    SlackBlockPlainTextInputElement::new("birthday".into())
+                        .with_initial_value(self.birthday.unwrap_or_default())
+                        .into(),
+                )
+                .with_optional(true)
+                .with_hint(pt!("As YYYY-MM-DD, or MM-DD to leave out the year"))
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Description".into(),
+                    SlackBlockPlainTextInputElement::new("description".into())
+                        .with_initial_value(self.description.unwrap_or_default())
+                        .with_multiline(true)
+                        .with_max_length(Self::MAX_DESCRIPTION_LENGTH)
+                        .into(),
+                )
+                .with_optional(true)
+                .with_hint(pt!(
+                    "Shown on the member's info card. Format with Slack markup, like *bold*, _italic_ and <https://example.com|links>"
+                ))
             )
         ]
     }
@@ -359,23 +689,28 @@ impl View {
     ) -> error_stack::Result<i64, sqlx::Error> {
         debug!("Adding member {} to database", self.display_name);
         sqlx::query!("
-            INSERT INTO members (full_name, display_name, profile_picture_url, title, pronouns, name_pronunciation, name_recording_url, system_id)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO members (full_name, display_name, profile_picture_url, proxy_avatar_url, title, pronouns, name_pronunciation, name_recording_url, description, birthday, priority, system_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             RETURNING id
         ",
             self.full_name,
             self.display_name,
             self.profile_picture_url,
+            self.proxy_avatar_url,
             self.title,
             self.pronouns,
             self.name_pronunciation,
             self.name_recording_url,
+            self.description,
+            self.birthday,
+            self.priority,
             system_id.id,
         )
         .fetch_one(db)
         .await
         .attach_printable("Error adding member to database")
         .map(|row| row.id)
+        .inspect(|_| cache::invalidate(system_id))
     }
 
     /// Update a member in the database to match this view
@@ -389,19 +724,81 @@ impl View {
     ) -> error_stack::Result<SqliteQueryResult, sqlx::Error> {
         sqlx::query!("
             UPDATE members
-            SET full_name = $1, display_name = $2, profile_picture_url = $3, title = $4, pronouns = $5, name_pronunciation = $6, name_recording_url = $7
-            WHERE id = $8
+            SET full_name = $1, display_name = $2, profile_picture_url = $3, proxy_avatar_url = $4, title = $5, pronouns = $6, name_pronunciation = $7, name_recording_url = $8, description = $9, birthday = $10, priority = $11
+            WHERE id = $12
         ",
             self.full_name,
             self.display_name,
             self.profile_picture_url,
+            self.proxy_avatar_url,
             self.title,
             self.pronouns,
             self.name_pronunciation,
             self.name_recording_url,
+            self.description,
+            self.birthday,
+            self.priority,
             member_id,
         ).execute(db).await
         .attach_printable("Error editing member in database")
+        .inspect(|_| cache::invalidate_member(member_id))
+    }
+
+    /// The fields that differ in `edited`, in the order they're shown in the member form.
+    ///
+    /// Blank text counts as not set, as the form doesn't tell the two apart.
+    pub fn changes(&self, edited: &Self) -> Vec<Change> {
+        let text = |value: &Option<String>| value.clone().filter(|value| !value.trim().is_empty());
+
+        [
+            (
+                "Display name",
+                Some(self.display_name.clone()),
+                Some(edited.display_name.clone()),
+            ),
+            (
+                "Profile picture URL",
+                text(&self.profile_picture_url),
+                text(&edited.profile_picture_url),
+            ),
+            (
+                "Proxy avatar URL",
+                text(&self.proxy_avatar_url),
+                text(&edited.proxy_avatar_url),
+            ),
+            (
+                "Trigger priority",
+                Some(self.priority.to_string()),
+                Some(edited.priority.to_string()),
+            ),
+            (
+                "Full name",
+                Some(self.full_name.clone()),
+                Some(edited.full_name.clone()),
+            ),
+            ("Pronouns", text(&self.pronouns), text(&edited.pronouns)),
+            ("Title", text(&self.title), text(&edited.title)),
+            (
+                "Name pronunciation",
+                text(&self.name_pronunciation),
+                text(&edited.name_pronunciation),
+            ),
+            (
+                "Name recording URL",
+                text(&self.name_recording_url),
+                text(&edited.name_recording_url),
+            ),
+            ("Birthday", text(&self.birthday), text(&edited.birthday)),
+            (
+                "Description",
+                text(&self.description),
+                text(&edited.description),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, old, new)| Change { field, old, new })
+        .collect()
     }
 }
 
@@ -428,10 +825,30 @@ impl TryFrom<SlackViewState> for View {
                             .ok_or_else(|| MissingFieldError("display_name".to_string()))?;
                     }
                     "profile_picture_url" => view.profile_picture_url = content.value,
+                    "proxy_avatar_url" => view.proxy_avatar_url = content.value,
                     "title" => view.title = content.value,
                     "pronouns" => view.pronouns = content.value,
                     "name_pronunciation" => view.name_pronunciation = content.value,
                     "name_recording_url" => view.name_recording_url = content.value,
+                    "description" => view.description = content.value,
+                    "birthday" => {
+                        view.birthday = content
+                            .value
+                            .filter(|birthday| !birthday.trim().is_empty())
+                            .map(|birthday| {
+                                parse_birthday(&birthday)
+                                    .ok_or_else(|| MissingFieldError("birthday".to_string()))
+                            })
+                            .transpose()?;
+                    }
+                    "priority" => {
+                        view.priority = match content.value.as_deref().map(str::trim) {
+                            None | Some("") => 0,
+                            Some(priority) => priority
+                                .parse()
+                                .map_err(|_| MissingFieldError("priority".to_string()))?,
+                        };
+                    }
                     other => {
                         warn!("Unknown field in view when parsing a member::View: {other}");
                     }
@@ -457,10 +874,35 @@ impl From<Member> for View {
             full_name: value.full_name,
             display_name: value.display_name,
             profile_picture_url: value.profile_picture_url,
+            proxy_avatar_url: value.proxy_avatar_url,
             title: value.title,
             pronouns: value.pronouns,
             name_pronunciation: value.name_pronunciation,
             name_recording_url: value.name_recording_url,
+            description: value.description,
+            birthday: value.birthday,
+            priority: value.priority,
         }
     }
 }
+
+/// Reads a birthday typed as `YYYY-MM-DD`, or `MM-DD` to leave out the year.
+///
+/// Returns the birthday in the same form, with any missing zeroes added. [`None`] if it isn't a real date.
+pub fn parse_birthday(input: &str) -> Option<String> {
+    let format = format_description!("[year]-[month padding:none]-[day padding:none]");
+    let input = input.trim();
+
+    if let Ok(date) = Date::parse(input, format) {
+        return Some(format!(
+            "{:04}-{:02}-{:02}",
+            date.year(),
+            u8::from(date.month()),
+            date.day()
+        ));
+    }
+
+    // 2000 is a leap year, so birthdays on the 29th of February are allowed
+    let date = Date::parse(&format!("2000-{input}"), format).ok()?;
+    Some(format!("{:02}-{:02}", u8::from(date.month()), date.day()))
+}
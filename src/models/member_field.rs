@@ -0,0 +1,109 @@
+use error_stack::{Result, ResultExt};
+use sqlx::{SqlitePool, prelude::*};
+
+use super::{member, system, trust::Trusted};
+
+/// A labelled field on a member's profile, like their age or role
+#[derive(FromRow, Debug, Clone)]
+pub struct MemberField {
+    pub member_id: member::Id<Trusted>,
+    /// Unique per member, ignoring case
+    pub name: String,
+    pub value: String,
+}
+
+impl MemberField {
+    /// The longest field name that can be set
+    pub const MAX_NAME_LENGTH: usize = 50;
+    /// The longest field value that can be set. Slack caps each field of a section at 2000 characters
+    pub const MAX_VALUE_LENGTH: usize = 1000;
+
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_member_id(
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"
+            SELECT
+                member_id as "member_id: member::Id<Trusted>",
+                name,
+                value
+            FROM member_fields
+            WHERE member_id = $1
+            ORDER BY id
+            "#,
+            member_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch member fields from database")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_system_id(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Self,
+            r#"
+            SELECT
+                member_fields.member_id as "member_id: member::Id<Trusted>",
+                member_fields.name,
+                member_fields.value
+            FROM member_fields
+            JOIN members ON members.id = member_fields.member_id
+            WHERE members.system_id = $1
+            ORDER BY member_fields.id
+            "#,
+            system_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch member fields from database")
+    }
+
+    /// Sets a field on a member, replacing the value of an existing field with the same name
+    #[tracing::instrument(skip(db))]
+    pub async fn set(
+        member_id: member::Id<Trusted>,
+        name: &str,
+        value: &str,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO member_fields (member_id, name, value)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (member_id, name) DO UPDATE SET name = excluded.name, value = excluded.value
+            "#,
+            member_id,
+            name,
+            value
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to set member field")
+        .map(|_| ())
+    }
+
+    /// Removes a field from a member. Returns whether the member had the field
+    #[tracing::instrument(skip(db))]
+    pub async fn remove(
+        member_id: member::Id<Trusted>,
+        name: &str,
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM member_fields WHERE member_id = $1 AND name = $2",
+            member_id,
+            name
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to remove member field")
+        .map(|result| result.rows_affected() > 0)
+    }
+}
@@ -1,8 +1,8 @@
 use crate::id;
 
-use super::{member, trust::Trusted};
+use super::{member, system, trust::Trusted};
 use error_stack::{Result, ResultExt};
-use slack_morphism::SlackTs;
+use slack_morphism::{SlackChannelId, SlackMessageContent, SlackTs};
 use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
 
 id!(
@@ -10,7 +10,7 @@ id!(
     ///
     /// For an ID to be valid (trusted), it must
     ///
-    /// - Be associated with a valid user (constrained at database level; no validation needed)
+    /// - Be associated with a valid system (constrained at database level; no validation needed)
     /// - Have a message ID that exists in Slack and the bot can access (there shouldn't be a message without a message ID; no validation needed)
     => Message
 );
@@ -19,11 +19,21 @@ id!(
 #[allow(dead_code)]
 pub struct MessageLog {
     pub id: Id<Trusted>,
-    pub member_id: member::Id<Trusted>,
+    /// The member that sent the message. None if the message was sent anonymously
+    pub member_id: Option<member::Id<Trusted>>,
+    pub system_id: system::Id<Trusted>,
     #[sqlx(try_from = "String")]
     pub message_id: SlackTs,
 }
 
+/// A proxied message to delete, as it's past its channel's retention
+#[derive(Debug)]
+pub struct ExpiredMessage {
+    pub system_id: system::Id<Trusted>,
+    pub channel_id: SlackChannelId,
+    pub message_id: SlackTs,
+}
+
 impl MessageLog {
     /// Deletes a message log by the message ID.
     pub async fn delete_by_message_id(
@@ -54,6 +64,7 @@ impl MessageLog {
             SELECT
                 id as "id: Id<Trusted>",
                 member_id as "member_id: member::Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
                 message_id
             FROM
                 message_logs
@@ -66,6 +77,31 @@ impl MessageLog {
         .attach_printable("Failed to fetch message log")
     }
 
+    /// Fetches the log of the message proxied from a user's message, if it was proxied.
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_original_ts(
+        original_ts: &SlackTs,
+        db: &SqlitePool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            MessageLog,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                member_id as "member_id: member::Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                message_id
+            FROM
+                message_logs
+            WHERE original_ts = $1
+            "#,
+            original_ts.0,
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch message log")
+    }
+
     /// Fetches all message logs by the member ID.
     #[tracing::instrument(skip(db))]
     pub async fn fetch_all_by_member_id(
@@ -78,6 +114,7 @@ impl MessageLog {
                 SELECT
                     id as "id: Id<Trusted>",
                     member_id as "member_id: member::Id<Trusted>",
+                    system_id as "system_id: system::Id<Trusted>",
                     message_id
                 FROM
                     message_logs
@@ -91,27 +128,223 @@ impl MessageLog {
         .attach_printable("Failed to fetch message logs")
     }
 
+    /// Logs a proxied message.
+    ///
+    /// `original_ts` is the user's message it was proxied from, and `content_hash` that message's [`content_hash`], if any.
+    /// `channel_id` is where the bot posted the message, so it can be deleted in an ephemeral channel.
     #[tracing::instrument(skip(db))]
     pub async fn insert(
-        member_id: member::Id<Trusted>,
+        member_id: Option<member::Id<Trusted>>,
+        system_id: system::Id<Trusted>,
         message_id: &SlackTs,
+        channel_id: Option<&SlackChannelId>,
+        original_ts: Option<&SlackTs>,
+        content_hash: Option<&str>,
         db: &SqlitePool,
     ) -> Result<Self, sqlx::Error> {
+        let original_ts = original_ts.map(|ts| ts.0.as_str());
+        let channel_id = channel_id.map(|channel_id| channel_id.0.as_str());
+
         sqlx::query_as!(
             MessageLog,
             r#"
-                INSERT INTO message_logs (member_id, message_id)
-                VALUES ($1, $2)
+                INSERT INTO message_logs (member_id, system_id, message_id, channel_id, original_ts, content_hash, sent_at)
+                VALUES ($1, $2, $3, $4, $5, $6, datetime(CAST($3 AS REAL), 'unixepoch'))
                 RETURNING
                     id as "id: Id<Trusted>",
                     member_id as "member_id: member::Id<Trusted>",
+                    system_id as "system_id: system::Id<Trusted>",
                     message_id
             "#,
             member_id,
-            message_id.0
+            system_id,
+            message_id.0,
+            channel_id,
+            original_ts,
+            content_hash
         )
         .fetch_one(db)
         .await
         .attach_printable("Failed to insert message log")
     }
+
+    /// Proxied messages in ephemeral channels that are past the channel's retention, oldest first
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_expired(
+        limit: u32,
+        db: &SqlitePool,
+    ) -> Result<Vec<ExpiredMessage>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT
+                message_logs.system_id as "system_id: system::Id<Trusted>",
+                message_logs.channel_id as "channel_id!",
+                message_logs.message_id
+            FROM message_logs
+            JOIN ephemeral_channels
+                ON ephemeral_channels.system_id = message_logs.system_id
+                AND ephemeral_channels.channel_id = message_logs.channel_id
+            WHERE message_logs.sent_at <= datetime('now', '-' || ephemeral_channels.hours || ' hours')
+            ORDER BY message_logs.sent_at
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch expired messages")
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| ExpiredMessage {
+                    system_id: row.system_id,
+                    channel_id: SlackChannelId::new(row.channel_id),
+                    message_id: SlackTs::new(row.message_id),
+                })
+                .collect()
+        })
+    }
+
+    /// Stops a message from being deleted in its ephemeral channel, for messages the bot can't delete
+    #[tracing::instrument(skip(db))]
+    pub async fn forget_channel(
+        message_id: &SlackTs,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE message_logs SET channel_id = NULL WHERE message_id = $1",
+            message_id.0
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to forget message channel")
+    }
+
+    /// Deletes message logs older than their system's log retention, returning how many were deleted.
+    ///
+    /// Logs in ephemeral channels are left for the ephemeral job, which needs them to delete the messages from Slack.
+    #[tracing::instrument(skip(db))]
+    pub async fn prune(limit: u32, db: &SqlitePool) -> Result<u64, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM message_logs
+            WHERE id IN (
+                SELECT message_logs.id
+                FROM message_logs
+                JOIN systems ON systems.id = message_logs.system_id
+                WHERE
+                    systems.log_retention_days IS NOT NULL AND
+                    message_logs.sent_at <= datetime('now', '-' || systems.log_retention_days || ' days') AND
+                    NOT EXISTS (
+                        SELECT 1 FROM ephemeral_channels
+                        WHERE
+                            ephemeral_channels.system_id = message_logs.system_id AND
+                            ephemeral_channels.channel_id = message_logs.channel_id
+                    )
+                LIMIT $1
+            )
+            "#,
+            limit
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to prune message logs")
+        .map(|result| result.rows_affected())
+    }
+
+    /// Whether the system had a message with the same content proxied within [`DUPLICATE_WINDOW_SECONDS`] of `ts`.
+    ///
+    /// Slack clients sometimes send a message twice, and both copies shouldn't be proxied.
+    #[tracing::instrument(skip(db))]
+    pub async fn is_recent_duplicate(
+        system_id: system::Id<Trusted>,
+        content_hash: &str,
+        ts: &SlackTs,
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        let Ok(ts) = ts.0.parse::<f64>() else {
+            return Ok(false);
+        };
+
+        sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM message_logs
+                WHERE system_id = $1
+                    AND content_hash = $2
+                    AND ABS(CAST(message_id AS REAL) - $3) <= $4
+            ) as "duplicate: bool"
+            "#,
+            system_id,
+            content_hash,
+            ts,
+            DUPLICATE_WINDOW_SECONDS
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to check for duplicate messages")
+        .map(|record| record.duplicate)
+    }
+
+    /// Counts the messages proxied as each of the system's members, most messages first.
+    ///
+    /// Anonymous messages are counted under no member. Pass a member to only count their messages.
+    #[tracing::instrument(skip(db))]
+    pub async fn stats(
+        system_id: system::Id<Trusted>,
+        member_id: Option<member::Id<Trusted>>,
+        db: &SqlitePool,
+    ) -> Result<Vec<MessageStats>, sqlx::Error> {
+        sqlx::query_as!(
+            MessageStats,
+            r#"
+            SELECT
+                member_id as "member_id: member::Id<Trusted>",
+                COUNT(*) FILTER (WHERE sent_at >= datetime('now', '-1 day')) as "day!: i64",
+                COUNT(*) FILTER (WHERE sent_at >= datetime('now', '-7 days')) as "week!: i64",
+                COUNT(*) FILTER (WHERE sent_at >= datetime('now', '-30 days')) as "month!: i64",
+                COUNT(*) as "all_time!: i64"
+            FROM message_logs
+            WHERE system_id = $1
+                AND ($2 IS NULL OR member_id = $2)
+            GROUP BY member_id
+            ORDER BY all_time DESC
+            "#,
+            system_id,
+            member_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch message stats")
+    }
+}
+
+#[derive(FromRow, Debug)]
+/// How many messages were proxied as a member, over the past day, 7 days, 30 days, and all time
+pub struct MessageStats {
+    /// None for anonymous messages
+    pub member_id: Option<member::Id<Trusted>>,
+    pub day: i64,
+    pub week: i64,
+    pub month: i64,
+    pub all_time: i64,
+}
+
+/// How close together two messages with the same content must be to count as duplicates
+pub const DUPLICATE_WINDOW_SECONDS: f64 = 2.0;
+
+/// A short hash of a message's text, for spotting duplicates.
+///
+/// Messages without text (e.g. only files) aren't hashed, since their content can't be compared.
+pub fn content_hash(content: &SlackMessageContent) -> Option<String> {
+    // FNV-1a, which is stable across Rust versions unlike the standard library's hasher
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    let text = content.text.as_deref().filter(|text| !text.is_empty())?;
+
+    let hash = text.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    });
+
+    Some(format!("{hash:016x}"))
 }
@@ -1,13 +1,40 @@
 pub mod alias;
+pub mod audit;
+pub mod blob;
+pub mod captured_event;
+pub mod command_usage;
+pub mod export;
+pub mod external_id;
+pub mod idempotency_key;
 pub mod member;
+pub mod member_field;
 pub mod message;
+pub mod outbox;
+pub mod switch;
 pub mod system;
 pub mod trigger;
 pub mod trust;
 pub mod user;
+pub mod viewer;
+pub mod workspace;
 
 pub use alias::Alias;
+pub use audit::AuditLog;
+pub use blob::Blob;
+pub use captured_event::CapturedEvent;
+pub use command_usage::CommandUsage;
+pub use external_id::ExternalId;
+pub use idempotency_key::IdempotencyKey;
 pub use member::{DetectedMember, Member};
+pub use member_field::MemberField;
 pub use message::MessageLog;
+pub use switch::Switch;
 pub use system::System;
 pub use trigger::Trigger;
+
+/// Whether a query failed because it would have broken a `UNIQUE` constraint
+pub fn is_unique_violation(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .is_some_and(sqlx::error::DatabaseError::is_unique_violation)
+}
@@ -0,0 +1,140 @@
+use crate::id;
+
+use super::{system, trust::Trusted};
+use error_stack::{Result, ResultExt};
+use slack_morphism::{SlackChannelId, SlackTs};
+use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
+
+id!(
+    /// For an ID to be trusted, it must
+    ///
+    /// - Be a valid ID in the database
+    /// - Be associated with a valid system (constrained at database level; no validation needed)
+    => PendingProxy
+);
+
+#[derive(Debug, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[repr(i64)]
+/// Who sent the message a proxy operation is replacing
+pub enum OriginalKind {
+    /// The system owner
+    User = 0,
+    /// The bot, as a previously proxied message
+    Proxied = 1,
+}
+
+impl From<i64> for OriginalKind {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::User,
+            1 => Self::Proxied,
+            _ => unreachable!(
+                "Invalid original kind. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
+/// A proxy operation that replaces a message, recorded so it can be resolved if the bot crashes halfway through.
+///
+/// Proxying posts a new message and then deletes the original. If the bot stops between the two,
+/// both messages are left in the channel.
+#[derive(FromRow, Debug)]
+pub struct PendingProxy {
+    pub id: Id<Trusted>,
+    pub system_id: system::Id<Trusted>,
+    #[sqlx(try_from = "String")]
+    pub channel_id: SlackChannelId,
+    #[sqlx(try_from = "String")]
+    pub original_ts: SlackTs,
+    pub original_kind: OriginalKind,
+    /// The proxied message, once it has been posted
+    pub posted_ts: Option<String>,
+}
+
+impl PendingProxy {
+    /// Records that a proxy operation is about to start
+    #[tracing::instrument(skip(db))]
+    pub async fn start(
+        system_id: system::Id<Trusted>,
+        channel_id: &SlackChannelId,
+        original_ts: &SlackTs,
+        original_kind: OriginalKind,
+        db: &SqlitePool,
+    ) -> Result<Id<Trusted>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO proxy_outbox (system_id, channel_id, original_ts, original_kind)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id as "id: Id<Trusted>"
+            "#,
+            system_id,
+            channel_id.0,
+            original_ts.0,
+            original_kind
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to record pending proxy")
+        .map(|record| record.id)
+    }
+
+    /// Fetches every proxy operation that didn't finish
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_all(db: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            PendingProxy,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                channel_id,
+                original_ts,
+                original_kind,
+                posted_ts
+            FROM proxy_outbox
+            "#
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch pending proxies")
+    }
+}
+
+impl Id<Trusted> {
+    /// Records that the proxied message has been posted
+    #[tracing::instrument(skip(db))]
+    pub async fn mark_posted(
+        self,
+        posted_ts: &SlackTs,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE proxy_outbox
+            SET posted_ts = $1
+            WHERE id = $2
+            "#,
+            posted_ts.0,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to mark pending proxy as posted")
+    }
+
+    /// Removes the record once the original message has been deleted
+    #[tracing::instrument(skip(db))]
+    pub async fn complete(self, db: &SqlitePool) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM proxy_outbox
+            WHERE id = $1
+            "#,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to complete pending proxy")
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{cache, id};
+
+use super::{member, system, trust::Trusted, user};
+use error_stack::{Result, ResultExt};
+use sqlx::{SqlitePool, prelude::*};
+
+id!(
+    /// For an ID to be trusted, it must
+    ///
+    /// - Be a valid ID in the database
+    /// - Be associated with a valid system (constrained at database level; no validation needed)
+    => Switch
+);
+
+/// The most switches that can be shown at once
+pub const MAX_HISTORY: u16 = 50;
+
+/// A system that has gone longer than its reminder interval without a switch
+#[derive(Debug)]
+pub struct DueReminder {
+    pub system_id: system::Id<Trusted>,
+    pub owner_id: user::Id<Trusted>,
+    /// The system's reminder interval
+    pub hours: i64,
+}
+
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+/// A change of fronting member, in a system's switch history
+pub struct Switch {
+    pub id: Id<Trusted>,
+    pub system_id: system::Id<Trusted>,
+    /// The member that started fronting. None if the system switched to the base account
+    pub member_id: Option<member::Id<Trusted>>,
+    pub created_at: time::PrimitiveDateTime,
+}
+
+impl Switch {
+    /// Adds a switch to the system's history
+    #[tracing::instrument(skip(db))]
+    pub async fn record(
+        system_id: system::Id<Trusted>,
+        member_id: Option<member::Id<Trusted>>,
+        db: &SqlitePool,
+    ) -> Result<Id<Trusted>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO switches (system_id, member_id)
+            VALUES ($1, $2)
+            RETURNING id as "id: Id<Trusted>"
+            "#,
+            system_id,
+            member_id
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to record switch")
+        .map(|record| record.id)
+    }
+
+    /// Adds a switch to the system's history that happened at an earlier time
+    #[tracing::instrument(skip(db))]
+    pub async fn record_at(
+        system_id: system::Id<Trusted>,
+        member_id: Option<member::Id<Trusted>>,
+        at: time::OffsetDateTime,
+        db: &SqlitePool,
+    ) -> Result<Id<Trusted>, sqlx::Error> {
+        // Formatted like SQLite's CURRENT_TIMESTAMP, so it sorts alongside switches recorded as they happened
+        let created_at = at
+            .to_offset(time::UtcOffset::UTC)
+            .format(time::macros::format_description!(
+                "[year]-[month]-[day] [hour]:[minute]:[second]"
+            ))
+            .attach_printable("Failed to format switch time")
+            .change_context(sqlx::Error::Protocol("Invalid switch time".into()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO switches (system_id, member_id, created_at)
+            VALUES ($1, $2, $3)
+            RETURNING id as "id: Id<Trusted>"
+            "#,
+            system_id,
+            member_id,
+            created_at
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to record backdated switch")
+        .map(|record| record.id)
+        .inspect(|_| cache::invalidate(system_id))
+    }
+
+    /// Fetches the system's most recent switches, newest first
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_recent(
+        system_id: system::Id<Trusted>,
+        limit: u16,
+        db: &SqlitePool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Switch,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                member_id as "member_id: member::Id<Trusted>",
+                created_at as "created_at: time::PrimitiveDateTime"
+            FROM switches
+            WHERE system_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+            "#,
+            system_id,
+            limit
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch switch history")
+    }
+
+    /// Systems due a reminder to log a switch: neither a switch nor a reminder within their interval
+    #[tracing::instrument(skip(db))]
+    pub async fn due_reminders(
+        limit: u32,
+        db: &SqlitePool,
+    ) -> Result<Vec<DueReminder>, sqlx::Error> {
+        sqlx::query_as!(
+            DueReminder,
+            r#"
+            SELECT
+                id as "system_id: system::Id<Trusted>",
+                owner_id as "owner_id: user::Id<Trusted>",
+                switch_reminder_hours as "hours!"
+            FROM systems
+            WHERE
+                switch_reminder_hours IS NOT NULL AND
+                max(
+                    coalesce((SELECT max(created_at) FROM switches WHERE switches.system_id = systems.id), ''),
+                    coalesce(switch_reminded_at, '')
+                ) <= datetime('now', '-' || switch_reminder_hours || ' hours')
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch systems due a switch reminder")
+    }
+}
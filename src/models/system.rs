@@ -1,91 +1,998 @@
+use std::{cmp::Reverse, collections::HashMap, time::Duration};
+
 use crate::{
-    fields, id,
+    cache, fields, id,
     models::member::{DetectedMember, Member},
 };
 
-use super::{
-    member::{self},
-    trigger::Trigger,
-    trust::{Trustability, Trusted},
-    user,
-};
-use error_stack::{Result, ResultExt};
-use redact::Secret;
-use sqlx::{SqlitePool, prelude::*};
-use tracing::debug;
+use super::{
+    member::{self},
+    switch::Switch,
+    trigger::Trigger,
+    trust::{Trustability, Trusted},
+    user,
+};
+use error_stack::{Result, ResultExt};
+use redact::Secret;
+use sha2::{Digest, Sha256};
+use slack_morphism::{SlackChannelId, SlackTeamId};
+use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
+use time::OffsetDateTime;
+use time_tz::{OffsetDateTimeExt, Tz, timezones};
+use tracing::debug;
+
+id!(
+    /// An ID for a [`System`].
+    ///
+    /// For an ID to be trusted, it must:
+    ///
+    /// - Be a valid ID in the database
+    /// - Be associated with a valid user
+    => System
+);
+
+impl Id<Trusted> {
+    #[tracing::instrument(skip(db))]
+    pub async fn list_triggers(self, db: &SqlitePool) -> Result<Vec<Trigger>, sqlx::Error> {
+        Trigger::fetch_by_system_id(self, db).await
+    }
+
+    /// Changes who is fronting to a single member, or no one. See [`Self::set_fronters`]
+    #[tracing::instrument(skip(db))]
+    pub async fn change_fronting_member(
+        self,
+        new_active_member_id: Option<member::Id<Trusted>>,
+        db: &SqlitePool,
+    ) -> Result<Option<Member>, sqlx::Error> {
+        let new_active_member = match new_active_member_id {
+            Some(new_active_member_id) => Some(
+                Member::fetch_by_id(new_active_member_id, db)
+                    .await
+                    .attach_printable("Failed to fetch member")?,
+            ),
+            None => None,
+        };
+
+        fields!(new_active_member = ?&new_active_member);
+
+        self.set_fronters(new_active_member_id.as_slice(), db)
+            .await?;
+
+        Ok(new_active_member)
+    }
+
+    /// Changes who is fronting. Returns whether anything changed.
+    ///
+    /// The first member is the primary fronter, who autoproxy proxies as. Every change is added to the switch history,
+    /// including co-fronters joining or leaving.
+    #[tracing::instrument(skip(db))]
+    pub async fn set_fronters(
+        self,
+        member_ids: &[member::Id<Trusted>],
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        debug!("Changing fronters for {} to {:?}", self, member_ids);
+
+        let current = self.fronters(db).await?;
+
+        if current == member_ids {
+            return Ok(false);
+        }
+
+        let primary = member_ids.first().copied();
+
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        sqlx::query!("DELETE FROM fronters WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to clear fronters")?;
+
+        for (position, member_id) in (0_i64..).zip(member_ids) {
+            sqlx::query!(
+                "INSERT INTO fronters (system_id, member_id, position) VALUES ($1, $2, $3)",
+                self.id,
+                member_id,
+                position
+            )
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to add fronter")?;
+        }
+
+        sqlx::query!(
+            "UPDATE systems SET currently_fronting_member_id = $1 WHERE id = $2",
+            primary,
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to update system primary fronter")?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit fronters")?;
+
+        cache::invalidate(self);
+
+        // Auto-switching to whoever is already fronting returns early above, so triggered messages don't flood the history
+        Switch::record(self, primary, db).await?;
+
+        Ok(true)
+    }
+
+    /// Everyone fronting, primary fronter first
+    #[tracing::instrument(skip(db))]
+    pub async fn fronters(self, db: &SqlitePool) -> Result<Vec<member::Id<Trusted>>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT member_id as "member_id: member::Id<Trusted>"
+            FROM fronters
+            WHERE system_id = $1
+            ORDER BY position
+            "#,
+            self.id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch fronters")
+        .map(|rows| rows.into_iter().map(|row| row.member_id).collect())
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_message_info_consent(
+        self,
+        required: bool,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET message_info_requires_consent = $1
+            WHERE id = $2
+            "#,
+            required,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system message info consent setting")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_anonymous_prefix(
+        self,
+        prefix: Option<String>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET anonymous_prefix = $1
+            WHERE id = $2
+            "#,
+            prefix,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system anonymous prefix")
+    }
+
+    /// Finds the system an API token belongs to
+    #[tracing::instrument(skip_all)]
+    pub async fn fetch_by_api_token(
+        token: &str,
+        db: &SqlitePool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let hash = hash_api_token(token);
+
+        sqlx::query!(
+            r#"
+            SELECT id as "id: Id<Trusted>"
+            FROM systems
+            WHERE api_token_hash = $1
+            "#,
+            hash
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch system by API token")
+        .map(|row| row.map(|row| row.id))
+    }
+
+    /// The Slack workspace the system was authorized from.
+    /// [`None`] for systems authorized before this was recorded, which are in the workspace the bot was installed in
+    #[tracing::instrument(skip(db))]
+    pub async fn team_id(self, db: &SqlitePool) -> Result<Option<SlackTeamId>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT team_id
+            FROM systems
+            WHERE id = $1
+            "#,
+            self.id
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to fetch system team ID")
+        .map(|row| row.team_id.map(SlackTeamId::new))
+    }
+
+    /// Replaces the system's API token. [`None`] revokes it
+    ///
+    /// Only a hash of the token is stored, so it can't be shown again afterwards.
+    #[tracing::instrument(skip_all)]
+    pub async fn set_api_token(
+        self,
+        token: Option<&str>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        let hash = token.map(hash_api_token);
+
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET api_token_hash = $1
+            WHERE id = $2
+            "#,
+            hash,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system API token")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_system_tag(
+        self,
+        tag: Option<String>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET system_tag = $1
+            WHERE id = $2
+            "#,
+            tag,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system tag")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_name_format(
+        self,
+        format: Option<String>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET name_format = $1
+            WHERE id = $2
+            "#,
+            format,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system name format")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_suggest_members(
+        self,
+        enabled: bool,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET suggest_members = $1
+            WHERE id = $2
+            "#,
+            enabled,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system member suggestions setting")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_hold_back(
+        self,
+        enabled: bool,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET hold_back = $1
+            WHERE id = $2
+            "#,
+            enabled,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system hold back setting")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_visibility(
+        self,
+        visibility: Visibility,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET visibility = $1
+            WHERE id = $2
+            "#,
+            visibility,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system visibility")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_delete_delay(
+        self,
+        delete_delay_ms: u16,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET delete_delay_ms = $1
+            WHERE id = $2
+            "#,
+            delete_delay_ms,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system delete delay")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_proxy_enabled(
+        self,
+        enabled: bool,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET proxy_enabled = $1
+            WHERE id = $2
+            "#,
+            enabled,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system proxy status")
+    }
+
+    /// Changes how many days message logs are kept for. [`None`] keeps them forever
+    #[tracing::instrument(skip(db))]
+    pub async fn set_log_retention(
+        self,
+        days: Option<u16>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET log_retention_days = $1
+            WHERE id = $2
+            "#,
+            days,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system log retention")
+    }
+
+    /// Changes how many hours without a switch the owner is reminded after. [`None`] turns reminders off.
+    ///
+    /// The interval starts over from now, so turning reminders on doesn't remind straight away.
+    #[tracing::instrument(skip(db))]
+    pub async fn set_switch_reminder(
+        self,
+        hours: Option<u16>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET switch_reminder_hours = $1, switch_reminded_at = CURRENT_TIMESTAMP
+            WHERE id = $2
+            "#,
+            hours,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system switch reminder")
+    }
+
+    /// Records that the owner was just reminded to log a switch, so the next reminder waits a full interval
+    #[tracing::instrument(skip(db))]
+    pub async fn mark_switch_reminded(
+        self,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE systems SET switch_reminded_at = CURRENT_TIMESTAMP WHERE id = $1",
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to record switch reminder")
+    }
+
+    /// Changes the autoproxy mode. `member_id` is the member to proxy as in [`AutoproxyMode::Member`]
+    #[tracing::instrument(skip(db))]
+    pub async fn set_autoproxy(
+        self,
+        mode: AutoproxyMode,
+        member_id: Option<member::Id<Trusted>>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET autoproxy_mode = $1, autoproxy_member_id = $2
+            WHERE id = $3
+            "#,
+            mode,
+            member_id,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system autoproxy mode")
+    }
+
+    /// Remembers the member whose trigger was used last, for [`AutoproxyMode::Latch`]
+    #[tracing::instrument(skip(db))]
+    pub async fn latch(
+        self,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE systems SET latched_member_id = $1 WHERE id = $2",
+            member_id,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system latched member")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_timezone(
+        self,
+        timezone: Option<String>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET timezone = $1
+            WHERE id = $2
+            "#,
+            timezone,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system timezone")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_quiet_hours(
+        self,
+        hours: Option<(u8, u8)>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        let start = hours.map(|(start, _)| start);
+        let end = hours.map(|(_, end)| end);
+
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET quiet_hours_start = $1, quiet_hours_end = $2
+            WHERE id = $3
+            "#,
+            start,
+            end,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system quiet hours")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn set_notification_preference(
+        self,
+        preference: NotificationPreference,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET notifications = $1
+            WHERE id = $2
+            "#,
+            preference,
+            self.id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system notification preference")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn add_manager(
+        self,
+        user_id: &user::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO system_managers (system_id, user_id)
+            VALUES ($1, $2)
+            ON CONFLICT (system_id, user_id) DO NOTHING
+            "#,
+            self.id,
+            user_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to add system manager")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn remove_manager(
+        self,
+        user_id: &user::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM system_managers
+            WHERE system_id = $1 AND user_id = $2
+            "#,
+            self.id,
+            user_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to remove system manager")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn managers(self, db: &SqlitePool) -> Result<Vec<user::Id<Trusted>>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT user_id as "user_id: user::Id<Trusted>"
+            FROM system_managers
+            WHERE system_id = $1
+            "#,
+            self.id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch system managers")
+        .map(|rows| rows.into_iter().map(|row| row.user_id).collect())
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn is_manager(
+        self,
+        user_id: &user::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM system_managers
+                WHERE system_id = $1 AND user_id = $2
+            ) as "is_manager!: bool"
+            "#,
+            self.id,
+            user_id
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to check if user is a system manager")
+        .map(|row| row.is_manager)
+    }
 
-id!(
-    /// An ID for a [`System`].
-    ///
-    /// For an ID to be trusted, it must:
-    ///
-    /// - Be a valid ID in the database
-    /// - Be associated with a valid user
-    => System
-);
+    /// Stops the system's messages from being proxied in a channel
+    #[tracing::instrument(skip(db))]
+    pub async fn blacklist_channel(
+        self,
+        channel_id: &SlackChannelId,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO proxy_blacklist (system_id, channel_id)
+            VALUES ($1, $2)
+            ON CONFLICT (system_id, channel_id) DO NOTHING
+            "#,
+            self.id,
+            channel_id.0
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to blacklist channel")
+    }
 
-impl Id<Trusted> {
     #[tracing::instrument(skip(db))]
-    pub async fn list_triggers(self, db: &SqlitePool) -> Result<Vec<Trigger>, sqlx::Error> {
-        Trigger::fetch_by_system_id(self, db).await
+    pub async fn unblacklist_channel(
+        self,
+        channel_id: &SlackChannelId,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM proxy_blacklist
+            WHERE system_id = $1 AND channel_id = $2
+            "#,
+            self.id,
+            channel_id.0
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to remove channel from blacklist")
     }
 
     #[tracing::instrument(skip(db))]
-    pub async fn change_fronting_member(
+    pub async fn blacklisted_channels(
         self,
-        new_active_member_id: Option<member::Id<Trusted>>,
         db: &SqlitePool,
-    ) -> Result<Option<Member>, sqlx::Error> {
-        debug!(
-            "Changing active member for {} to {:?}",
-            self, new_active_member_id
-        );
+    ) -> Result<Vec<SlackChannelId>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT channel_id
+            FROM proxy_blacklist
+            WHERE system_id = $1
+            "#,
+            self.id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch blacklisted channels")
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| SlackChannelId::new(row.channel_id))
+                .collect()
+        })
+    }
 
-        let mut new_active_member = None;
+    #[tracing::instrument(skip(db))]
+    pub async fn is_blacklisted(
+        self,
+        channel_id: &SlackChannelId,
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM proxy_blacklist
+                WHERE system_id = $1 AND channel_id = $2
+            ) as "is_blacklisted!: bool"
+            "#,
+            self.id,
+            channel_id.0
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to check if channel is blacklisted")
+        .map(|row| row.is_blacklisted)
+    }
 
-        if let Some(new_active_member_id) = new_active_member_id {
-            new_active_member = Some(
-                Member::fetch_by_id(new_active_member_id, db)
-                    .await
-                    .attach_printable("Failed to fetch member")?,
-            );
-        }
+    /// Deletes the system's proxied messages in a channel once they're `hours` old.
+    ///
+    /// Replaces the channel's previous retention, if it had one.
+    #[tracing::instrument(skip(db))]
+    pub async fn set_ephemeral_channel(
+        self,
+        channel_id: &SlackChannelId,
+        hours: u32,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO ephemeral_channels (system_id, channel_id, hours)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (system_id, channel_id) DO UPDATE SET hours = $3
+            "#,
+            self.id,
+            channel_id.0,
+            hours
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to make channel ephemeral")
+    }
 
-        fields!(new_active_member = ?&new_active_member);
+    #[tracing::instrument(skip(db))]
+    pub async fn remove_ephemeral_channel(
+        self,
+        channel_id: &SlackChannelId,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM ephemeral_channels
+            WHERE system_id = $1 AND channel_id = $2
+            "#,
+            self.id,
+            channel_id.0
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to remove ephemeral channel")
+    }
 
+    /// The system's ephemeral channels, with how many hours messages are kept for in each
+    #[tracing::instrument(skip(db))]
+    pub async fn ephemeral_channels(
+        self,
+        db: &SqlitePool,
+    ) -> Result<Vec<(SlackChannelId, i64)>, sqlx::Error> {
         sqlx::query!(
             r#"
-            UPDATE systems
-            SET currently_fronting_member_id = $1
-            WHERE id = $2
+            SELECT channel_id, hours
+            FROM ephemeral_channels
+            WHERE system_id = $1
             "#,
-            new_active_member_id,
             self.id
         )
-        .execute(db)
+        .fetch_all(db)
         .await
-        .attach_printable("Failed to update system active member")?;
+        .attach_printable("Failed to fetch ephemeral channels")
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| (SlackChannelId::new(row.channel_id), row.hours))
+                .collect()
+        })
+    }
 
-        Ok(new_active_member)
+    /// Starts waiting for the system to upload a name recording for a member.
+    ///
+    /// Replaces any recording the system was already waiting on.
+    #[tracing::instrument(skip(db))]
+    pub async fn start_recording(
+        self,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO member_recording_process (system_id, member_id)
+            VALUES ($1, $2)
+            ON CONFLICT (system_id) DO UPDATE SET member_id = $2, started_at = CURRENT_TIMESTAMP
+            "#,
+            self.id,
+            member_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to start member recording")
     }
 
+    /// Stops waiting for a name recording, returning the member it was for.
+    ///
+    /// Recordings started more than 10 minutes ago have expired and return None.
     #[tracing::instrument(skip(db))]
-    pub async fn currently_fronting_member_id(
-        &self,
+    pub async fn take_recording(
+        self,
         db: &SqlitePool,
     ) -> Result<Option<member::Id<Trusted>>, sqlx::Error> {
         sqlx::query!(
             r#"
-            SELECT currently_fronting_member_id as "id: member::Id<Trusted>"
-            FROM systems
-            WHERE id = $1
+            DELETE FROM member_recording_process
+            WHERE system_id = $1
+            RETURNING
+                member_id as "member_id: member::Id<Trusted>",
+                started_at > datetime('now', '-10 minutes') as "active!: bool"
             "#,
             self.id
         )
-        .fetch_one(db)
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to take member recording")
+        .map(|row| row.filter(|row| row.active).map(|row| row.member_id))
+    }
+
+    /// Permanently deletes the system, its members, and everything else stored about it.
+    ///
+    /// Messages already proxied stay in Slack, but nothing links them to the system anymore.
+    /// The system's token isn't revoked here, so revoke it with Slack once this succeeds.
+    #[tracing::instrument(skip(db))]
+    pub async fn delete(self, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        // Members and the system point at each other, so the system lets go of its members first
+        sqlx::query!(
+            "
+            UPDATE systems SET
+                currently_fronting_member_id = NULL,
+                autoproxy_member_id = NULL,
+                latched_member_id = NULL
+            WHERE id = $1
+            ",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to unset system members")?;
+
+        sqlx::query!(
+            "DELETE FROM member_fields WHERE member_id IN (SELECT id FROM members WHERE system_id = $1)",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to delete member fields")?;
+
+        sqlx::query!("DELETE FROM fronters WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete fronters")?;
+
+        sqlx::query!("DELETE FROM switches WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete switches")?;
+
+        sqlx::query!("DELETE FROM triggers WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete triggers")?;
+
+        sqlx::query!("DELETE FROM aliases WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete aliases")?;
+
+        sqlx::query!("DELETE FROM external_ids WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete external IDs")?;
+
+        sqlx::query!(
+            "DELETE FROM member_recording_process WHERE system_id = $1",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to cancel recording")?;
+
+        sqlx::query!("DELETE FROM message_logs WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete message logs")?;
+
+        sqlx::query!("DELETE FROM proxy_outbox WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete pending proxy operations")?;
+
+        sqlx::query!("DELETE FROM blobs WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete hosted files")?;
+
+        sqlx::query!("DELETE FROM pending_imports WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete pending imports")?;
+
+        sqlx::query!("DELETE FROM proxy_blacklist WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete blacklisted channels")?;
+
+        sqlx::query!(
+            "DELETE FROM ephemeral_channels WHERE system_id = $1",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to delete ephemeral channels")?;
+
+        sqlx::query!("DELETE FROM system_managers WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete managers")?;
+
+        sqlx::query!("DELETE FROM audit_logs WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete audit log")?;
+
+        sqlx::query!(
+            "DELETE FROM api_idempotency_keys WHERE system_id = $1",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to delete API idempotency keys")?;
+
+        sqlx::query!("DELETE FROM members WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete members")?;
+
+        sqlx::query!(
+            "
+            DELETE FROM system_oauth_process
+            WHERE owner_id = (SELECT owner_id FROM systems WHERE id = $1)
+            ",
+            self.id
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to delete sign in process")?;
+
+        sqlx::query!("DELETE FROM systems WHERE id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to delete system")?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit system deletion")
+    }
+
+    /// How many messages each member has sent, by member ID
+    #[tracing::instrument(skip(db))]
+    pub async fn message_counts(self, db: &SqlitePool) -> Result<HashMap<i64, i64>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT member_id as "member_id!", count(*) as "count!: i64"
+            FROM message_logs
+            WHERE system_id = $1 AND member_id IS NOT NULL
+            GROUP BY member_id
+            "#,
+            self
+        )
+        .fetch_all(db)
         .await
-        .attach_printable("Failed to fetch system currently fronting member id")
-        .map(|row| row.id)
+        .attach_printable("Failed to count messages")
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| (row.member_id, row.count))
+                .collect()
+        })
     }
 
     #[tracing::instrument(skip(db))]
@@ -99,6 +1006,22 @@ impl Id<Trusted> {
                 currently_fronting_member_id as "currently_fronting_member_id: member::Id<Trusted>",
                 auto_switch_on_trigger,
                 slack_oauth_token,
+                message_info_requires_consent,
+                anonymous_prefix,
+                system_tag,
+                name_format,
+                timezone,
+                quiet_hours_start as "quiet_hours_start: u8",
+                quiet_hours_end as "quiet_hours_end: u8",
+                notifications as "notifications: NotificationPreference",
+                suggest_members,
+                hold_back,
+                delete_delay_ms as "delete_delay_ms: u16",
+                visibility as "visibility: Visibility",
+                proxy_enabled,
+                autoproxy_mode as "autoproxy_mode: AutoproxyMode",
+                autoproxy_member_id as "autoproxy_member_id: member::Id<Trusted>",
+                latched_member_id as "latched_member_id: member::Id<Trusted>",
                 created_at as "created_at: time::PrimitiveDateTime"
             FROM systems
             WHERE id = $1
@@ -111,6 +1034,111 @@ impl Id<Trusted> {
     }
 }
 
+/// The hex encoded SHA-256 hash of an API token, which is what's stored in place of the token.
+/// Tokens are long and random, so a plain hash is enough without a salt or a slow hash
+fn hash_api_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[derive(
+    Debug,
+    sqlx::Type,
+    displaydoc::Display,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[repr(i64)]
+/// Which notifications the bot DMs a system owner
+pub enum NotificationPreference {
+    /// Every notification
+    Always = 0,
+    /// Only errors
+    Errors = 1,
+    /// No notifications
+    Never = 2,
+}
+
+impl From<i64> for NotificationPreference {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::Always,
+            1 => Self::Errors,
+            2 => Self::Never,
+            _ => unreachable!(
+                "Invalid notification preference. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    sqlx::Type,
+    displaydoc::Display,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+#[repr(i64)]
+/// Who can look a system up with `/system info` and `/members list`
+pub enum Visibility {
+    /// Anyone in the workspace
+    Public = 0,
+    /// Only the owner
+    Private = 1,
+}
+
+impl From<i64> for Visibility {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::Public,
+            1 => Self::Private,
+            _ => unreachable!(
+                "Invalid system visibility. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, sqlx::Type, displaydoc::Display, PartialEq, Eq, Clone, Copy)]
+#[repr(i64)]
+/// How messages without a trigger are proxied
+pub enum AutoproxyMode {
+    /// Off: only messages with a trigger are proxied
+    Off = 0,
+    /// Front: messages are proxied as the fronting member
+    Front = 1,
+    /// Latch: messages are proxied as the member whose trigger was used last
+    Latch = 2,
+    /// Member: messages are proxied as a chosen member
+    Member = 3,
+}
+
+impl From<i64> for AutoproxyMode {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::Off,
+            1 => Self::Front,
+            2 => Self::Latch,
+            3 => Self::Member,
+            _ => unreachable!(
+                "Invalid autoproxy mode. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
 #[derive(Debug, FromRow, PartialEq, Eq, Clone)]
 #[sqlx(transparent)]
 pub struct SlackOauthToken(Secret<String>);
@@ -127,7 +1155,7 @@ impl From<String> for SlackOauthToken {
     }
 }
 
-#[derive(FromRow, Debug)]
+#[derive(FromRow, Debug, Clone)]
 #[allow(dead_code)]
 /// A plural system
 ///
@@ -142,16 +1170,114 @@ pub struct System {
     pub id: Id<Trusted>,
     /// The owner of the system.
     pub owner_id: user::Id<Trusted>,
-    /// The currently fronting member, if any
+    /// The primary fronter, if anyone is fronting. Everyone fronting is in [`Id::fronters`]
     pub currently_fronting_member_id: Option<member::Id<Trusted>>,
     /// Whether a [`trigger::Trigger`] activation changes the active member to the member the trigger is associated with
     pub auto_switch_on_trigger: bool,
     /// The Slack OAuth token for the system
     pub slack_oauth_token: SlackOauthToken,
+    /// Whether other users need the owner's approval before message info reveals who owns a member's message
+    pub message_info_requires_consent: bool,
+    /// Messages starting with this prefix are sent anonymously, without being linked to a member
+    pub anonymous_prefix: Option<String>,
+    /// Added to the names of proxied messages, e.g. to show which system they're from
+    pub system_tag: Option<String>,
+    /// How the names of proxied messages are built. See [`System::proxy_name`]
+    pub name_format: Option<String>,
+    /// The IANA name of the system's timezone. Defaults to UTC
+    pub timezone: Option<String>,
+    /// The hour quiet hours start at, in the system's timezone
+    pub quiet_hours_start: Option<u8>,
+    /// The hour quiet hours end at, in the system's timezone
+    pub quiet_hours_end: Option<u8>,
+    /// Which notifications the bot sends the owner
+    pub notifications: NotificationPreference,
+    /// Whether to suggest recently used members for untriggered messages while no one is fronting
+    pub suggest_members: bool,
+    /// Whether to keep the original of a proxied message until the owner confirms the proxy looks right
+    pub hold_back: bool,
+    /// How long to wait after posting a proxied message before deleting the original. See [`System::delete_delay`]
+    pub delete_delay_ms: u16,
+    /// Who else can look the system up
+    pub visibility: Visibility,
+    /// Whether any of the system's messages are proxied. Turned off to pause proxying without changing anything else
+    pub proxy_enabled: bool,
+    /// How messages without a trigger are proxied
+    pub autoproxy_mode: AutoproxyMode,
+    /// The member to proxy as in [`AutoproxyMode::Member`]
+    pub autoproxy_member_id: Option<member::Id<Trusted>>,
+    /// The member whose trigger was used last, for [`AutoproxyMode::Latch`]
+    pub latched_member_id: Option<member::Id<Trusted>>,
     pub created_at: time::PrimitiveDateTime,
 }
 
 impl System {
+    /// The name format used when the system hasn't set one
+    pub const DEFAULT_NAME_FORMAT: &str = "{name} {system_tag}";
+
+    /// The name a member's messages are proxied under.
+    ///
+    /// `{name}` in the system's name format is replaced with the member's display name, and `{system_tag}` with the
+    /// system's tag (or nothing, if it doesn't have one).
+    pub fn proxy_name(&self, display_name: &str) -> String {
+        let format = self
+            .name_format
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_NAME_FORMAT);
+        let tag = self.system_tag.as_deref().unwrap_or_default();
+
+        // Split on {name} first, so a display name that happens to contain {system_tag} is left as-is
+        format
+            .split("{name}")
+            .map(|part| part.replace("{system_tag}", tag))
+            .collect::<Vec<_>>()
+            .join(display_name)
+            .trim()
+            .to_string()
+    }
+
+    /// The system's timezone, falling back to UTC if it isn't set or is no longer valid
+    pub fn timezone(&self) -> &'static Tz {
+        self.timezone
+            .as_deref()
+            .and_then(timezones::get_by_name)
+            .unwrap_or(timezones::db::UTC)
+    }
+
+    /// How long to wait after posting a proxied message before deleting the original.
+    ///
+    /// Some Slack clients briefly show both messages, or the proxied message above the original, when they're
+    /// swapped at once. A short delay makes the swap less jarring at the cost of proxying feeling slower.
+    pub fn delete_delay(&self) -> Duration {
+        Duration::from_millis(self.delete_delay_ms.into())
+    }
+
+    /// The quiet hours of the system as `(start, end)`, if set
+    pub const fn quiet_hours(&self) -> Option<(u8, u8)> {
+        match (self.quiet_hours_start, self.quiet_hours_end) {
+            (Some(start), Some(end)) => Some((start, end)),
+            _ => None,
+        }
+    }
+
+    /// Whether proxying is currently paused because of the system's quiet hours
+    pub fn in_quiet_hours(&self) -> bool {
+        let Some((start, end)) = self.quiet_hours() else {
+            return false;
+        };
+
+        let hour = OffsetDateTime::now_utc()
+            .to_timezone(self.timezone())
+            .hour();
+
+        if start <= end {
+            (start..end).contains(&hour)
+        } else {
+            // The window wraps around midnight (e.g. 22-6)
+            hour >= start || hour < end
+        }
+    }
+
     #[tracing::instrument(skip(db))]
     pub async fn fetch_by_user_id<T>(
         user_id: &user::Id<T>,
@@ -169,6 +1295,22 @@ impl System {
                 currently_fronting_member_id as "currently_fronting_member_id: member::Id<Trusted>",
                 auto_switch_on_trigger,
                 slack_oauth_token,
+                message_info_requires_consent,
+                anonymous_prefix,
+                system_tag,
+                name_format,
+                timezone,
+                quiet_hours_start as "quiet_hours_start: u8",
+                quiet_hours_end as "quiet_hours_end: u8",
+                notifications as "notifications: NotificationPreference",
+                suggest_members,
+                hold_back,
+                delete_delay_ms as "delete_delay_ms: u16",
+                visibility as "visibility: Visibility",
+                proxy_enabled,
+                autoproxy_mode as "autoproxy_mode: AutoproxyMode",
+                autoproxy_member_id as "autoproxy_member_id: member::Id<Trusted>",
+                latched_member_id as "latched_member_id: member::Id<Trusted>",
                 created_at as "created_at: time::PrimitiveDateTime"
             FROM
                 systems
@@ -183,11 +1325,15 @@ impl System {
     }
 
     #[tracing::instrument(skip(db))]
-    pub async fn active_member(&self, db: &SqlitePool) -> Result<Option<Member>, sqlx::Error> {
-        match self.currently_fronting_member_id {
-            Some(id) => Ok(Some(Member::fetch_by_id(id, db).await?)),
-            None => Ok(None),
+    /// The members fronting, fetched in full, primary fronter first
+    pub async fn fronting_members(&self, db: &SqlitePool) -> Result<Vec<Member>, sqlx::Error> {
+        let mut members = Vec::new();
+
+        for member_id in self.id.fronters(db).await? {
+            members.push(Member::fetch_by_id(member_id, db).await?);
         }
+
+        Ok(members)
     }
 
     #[tracing::instrument(skip(db))]
@@ -215,11 +1361,18 @@ impl System {
                 full_name,
                 display_name,
                 profile_picture_url,
+                proxy_avatar_url,
                 title,
                 pronouns,
                 name_pronunciation,
                 name_recording_url,
+                description,
+                birthday,
+                emoji,
                 enabled,
+                proxy_enabled,
+                priority,
+                (SELECT alias FROM aliases WHERE aliases.member_id = members.id AND aliases.is_primary) as "command_name?: String",
                 created_at as "created_at: time::PrimitiveDateTime"
             FROM
                 members
@@ -232,35 +1385,94 @@ impl System {
         .attach_printable("Failed to fetch members")
     }
 
+    /// The enabled members that most recently sent a message, most recent first
+    #[tracing::instrument(skip(db))]
+    pub async fn recently_used_members(
+        &self,
+        limit: i64,
+        db: &SqlitePool,
+    ) -> Result<Vec<Member>, sqlx::Error> {
+        sqlx::query_as!(
+            Member,
+            r#"
+            SELECT
+                members.id as "id: member::Id<Trusted>",
+                members.system_id as "system_id: Id<Trusted>",
+                full_name,
+                display_name,
+                profile_picture_url,
+                proxy_avatar_url,
+                title,
+                pronouns,
+                name_pronunciation,
+                name_recording_url,
+                description,
+                birthday,
+                emoji,
+                enabled,
+                proxy_enabled,
+                priority,
+                (SELECT alias FROM aliases WHERE aliases.member_id = members.id AND aliases.is_primary) as "command_name?: String",
+                members.created_at as "created_at: time::PrimitiveDateTime"
+            FROM
+                message_logs
+            JOIN members ON members.id = message_logs.member_id
+            WHERE message_logs.system_id = $1 AND members.enabled = TRUE AND members.proxy_enabled = TRUE
+            GROUP BY members.id
+            ORDER BY max(message_logs.id) DESC
+            LIMIT $2
+            "#,
+            self.id,
+            limit
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch recently used members")
+    }
+
     pub async fn find_member_by_trigger_rules(
         &self,
         db: &SqlitePool,
         message: &str,
     ) -> Result<Option<DetectedMember>, sqlx::Error> {
         debug!(message, "Finding detected member if there is a match");
-        sqlx::query_as!(
+        // Regex triggers can't be matched in SQLite, so every trigger is checked here instead
+        let candidates = sqlx::query_as!(
             DetectedMember,
             r#"
                 SELECT
                     members.id as "id: member::Id<Trusted>",
                     display_name,
                     profile_picture_url,
+                    proxy_avatar_url,
                     triggers.text as trigger_text,
-                    triggers.typ
+                    triggers.typ,
+                    members.priority
                 FROM
                     members
                 JOIN
                     triggers ON members.id = triggers.member_id
                 WHERE
-                    -- See trigger.rs file for all types and names
                     members.enabled = TRUE AND
-                    ((triggers.typ = 0 AND $1 LIKE '%' || triggers.text) OR
-                    (triggers.typ = 1 AND $1 LIKE triggers.text || '%'))
+                    members.proxy_enabled = TRUE AND
+                    triggers.system_id = $1
+                ORDER BY triggers.id
             "#,
-            message
+            self.id
         )
-        .fetch_optional(db)
+        .fetch_all(db)
         .await
-        .attach_printable("Failed to fetch triggered member")
+        .attach_printable("Failed to fetch triggered member")?;
+
+        // The longest trigger wins, then the member with the highest priority, then the oldest trigger
+        Ok(candidates
+            .into_iter()
+            .filter(|candidate| candidate.typ.matches(&candidate.trigger_text, message))
+            .min_by_key(|candidate| {
+                (
+                    Reverse(candidate.trigger_text.chars().count()),
+                    Reverse(candidate.priority),
+                )
+            }))
     }
 }
@@ -1,14 +1,24 @@
-use std::str::FromStr;
+use std::{borrow::Cow, str::FromStr};
 
 use crate::id;
 
 use super::{
-    member, system,
+    is_unique_violation, member, system,
     trust::{Trusted, Untrusted},
 };
-use error_stack::{Result, ResultExt};
+use error_stack::{Report, Result, ResultExt};
+use regex::{Regex, RegexBuilder};
 use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
 
+/// Where the message goes in a wraps trigger, e.g. `[text]`
+pub const WRAPS_PLACEHOLDER: &str = "text";
+
+/// The capture group a regex trigger can use to pick out the message to send
+pub const REGEX_MESSAGE_GROUP: &str = "text";
+
+/// The most memory a compiled regex trigger can use, so one trigger can't slow down every message
+const REGEX_SIZE_LIMIT: usize = 256 * 1024;
+
 id!(
     /// For an ID to be trusted, it must
     ///
@@ -17,6 +27,37 @@ id!(
     => Trigger
 );
 
+/// Why a trigger couldn't be added or changed
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// That trigger is already used by {0}
+    Taken(String),
+}
+
+impl Error {
+    /// Turns a failed trigger write into [`Self::Taken`] if it clashed with another trigger, using `owner` to look up
+    /// who has it
+    async fn from_write<F>(error: Report<sqlx::Error>, owner: F) -> Report<Self>
+    where
+        F: Future<Output = std::result::Result<Option<String>, sqlx::Error>> + Send,
+    {
+        if !is_unique_violation(error.current_context()) {
+            return error.change_context(Self::Sqlx);
+        }
+
+        match owner.await {
+            Ok(owner) => Report::new(Self::Taken(
+                owner.unwrap_or_else(|| "another member".to_string()),
+            )),
+            Err(lookup_error) => error
+                .change_context(Self::Sqlx)
+                .attach_printable(format!("Failed to look up trigger owner: {lookup_error}")),
+        }
+    }
+}
+
 impl Id<Untrusted> {
     #[tracing::instrument(skip(db))]
     pub async fn validate_by_system(
@@ -40,6 +81,28 @@ impl Id<Untrusted> {
 }
 
 impl Id<Trusted> {
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch(self, db: &SqlitePool) -> Result<Trigger, sqlx::Error> {
+        sqlx::query_as!(
+            Trigger,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                member_id as "member_id: member::Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                text,
+                typ
+            FROM
+                triggers
+            WHERE id = $1
+            "#,
+            self,
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Error fetching trigger")
+    }
+
     #[tracing::instrument(skip(db))]
     pub async fn delete(self, db: &SqlitePool) -> Result<SqliteQueryResult, sqlx::Error> {
         sqlx::query!(
@@ -60,8 +123,8 @@ impl Id<Trusted> {
         typ: Option<Type>,
         content: Option<String>,
         db: &SqlitePool,
-    ) -> error_stack::Result<Self, sqlx::Error> {
-        sqlx::query!(
+    ) -> error_stack::Result<Self, Error> {
+        let result = sqlx::query!(
             r#"
             UPDATE triggers
             SET
@@ -78,11 +141,49 @@ impl Id<Trusted> {
         .fetch_one(db)
         .await
         .attach_printable("Failed to update trigger")
-        .map(|record| record.id)
+        .map(|record| record.id);
+
+        match result {
+            Ok(id) => Ok(id),
+            // The trigger keeps whichever of its type and text aren't changed, so the clash is found the same way
+            Err(error) => Err(Error::from_write(
+                error,
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT members.display_name
+                    FROM triggers AS current
+                    JOIN triggers AS other ON
+                        other.system_id = current.system_id AND
+                        other.id != current.id AND
+                        other.typ = coalesce($2, current.typ) AND
+                        other.text = coalesce($3, current.text)
+                    JOIN members ON members.id = other.member_id
+                    WHERE current.id = $1
+                    "#,
+                    self,
+                    typ,
+                    content
+                )
+                .fetch_optional(db),
+            )
+            .await),
+        }
     }
 }
 
-#[derive(Debug, sqlx::Type, displaydoc::Display, PartialEq, Eq, clap::ValueEnum, Clone, Copy)]
+#[derive(
+    Debug,
+    sqlx::Type,
+    displaydoc::Display,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    Clone,
+    Copy,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
 #[repr(i64)]
 /// The type of trigger.
 ///
@@ -97,6 +198,78 @@ pub enum Type {
     ///
     /// Matches the beginning of a message (e.g. "]J" would match "]J hello")
     Prefix = 1,
+    /// Wraps
+    ///
+    /// Matches a message surrounded by the text on either side of "text" (e.g. "[text]" would match "[hello]")
+    Wraps = 2,
+    /// Regex
+    ///
+    /// Matches a message against a regular expression. If it has a group named "text", only that part of the message is sent (e.g. "^(?<text>.*) -j$" would match "hello -j")
+    Regex = 3,
+}
+
+impl Type {
+    /// Checks the trigger text can be used with this type, returning the problem to show the user if not
+    pub fn validate(self, text: &str) -> std::result::Result<(), String> {
+        if text.is_empty() {
+            return Err("Triggers can't be empty.".to_string());
+        }
+
+        match self {
+            Self::Prefix | Self::Suffix => Ok(()),
+            Self::Wraps => match text.split_once(WRAPS_PLACEHOLDER) {
+                Some((prefix, suffix)) if !prefix.is_empty() || !suffix.is_empty() => Ok(()),
+                Some(_) => {
+                    Err("Wraps triggers need text around `text`, e.g. `[text]`.".to_string())
+                }
+                None => Err(
+                    "Wraps triggers need `text` where the message goes, e.g. `[text]`.".to_string(),
+                ),
+            },
+            Self::Regex => build_regex(text)
+                .map(|_| ())
+                .map_err(|error| format!("That isn't a valid regex: {error}")),
+        }
+    }
+
+    /// The text a trigger of this type matches at the start and end of a message.
+    ///
+    /// [`None`] for regex triggers, which can match anywhere.
+    pub fn edges(self, text: &str) -> Option<(&str, &str)> {
+        match self {
+            Self::Prefix => Some((text, "")),
+            Self::Suffix => Some(("", text)),
+            Self::Wraps => text.split_once(WRAPS_PLACEHOLDER),
+            Self::Regex => None,
+        }
+    }
+
+    /// The message with the trigger removed, or [`None`] if the trigger doesn't match it
+    pub fn strip<'a>(self, text: &str, message: &'a str) -> Option<Cow<'a, str>> {
+        if let Some((prefix, suffix)) = self.edges(text) {
+            return message
+                .strip_prefix(prefix)?
+                .strip_suffix(suffix)
+                .map(Cow::Borrowed);
+        }
+
+        let captures = build_regex(text).ok()?.captures(message)?;
+
+        Some(Cow::Borrowed(
+            captures
+                .name(REGEX_MESSAGE_GROUP)
+                .map_or(message, |group| group.as_str()),
+        ))
+    }
+
+    /// Whether a trigger of this type activates on the message
+    pub fn matches(self, text: &str, message: &str) -> bool {
+        self.strip(text, message).is_some()
+    }
+}
+
+fn build_regex(text: &str) -> std::result::Result<Regex, regex::Error> {
+    RegexBuilder::new(text).size_limit(REGEX_SIZE_LIMIT).build()
 }
 
 impl From<i64> for Type {
@@ -104,6 +277,8 @@ impl From<i64> for Type {
         match value {
             0 => Self::Suffix,
             1 => Self::Prefix,
+            2 => Self::Wraps,
+            3 => Self::Regex,
             _ => unreachable!(
                 "Invalid type value. This means the database and rust struct are out of sync"
             ),
@@ -122,6 +297,8 @@ impl FromStr for Type {
         match s {
             "suffix" => Ok(Self::Suffix),
             "prefix" => Ok(Self::Prefix),
+            "wraps" => Ok(Self::Wraps),
+            "regex" => Ok(Self::Regex),
             _ => Err(UnknownType(s.to_string())),
         }
     }
@@ -138,6 +315,23 @@ pub struct Trigger {
 }
 
 impl Trigger {
+    /// Whether this trigger and another belonging to a different member can both match the same message
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        if self.member_id == other.member_id || self.typ != other.typ {
+            return false;
+        }
+
+        // Whether two regexes can match the same message can't be worked out cheaply, so they're never flagged
+        let (Some((prefix, suffix)), Some((other_prefix, other_suffix))) =
+            (self.typ.edges(&self.text), other.typ.edges(&other.text))
+        else {
+            return false;
+        };
+
+        (prefix.starts_with(other_prefix) || other_prefix.starts_with(prefix))
+            && (suffix.ends_with(other_suffix) || other_suffix.ends_with(suffix))
+    }
+
     #[tracing::instrument(skip(db))]
     pub async fn fetch_by_system_id(
         system_id: system::Id<Trusted>,
@@ -196,8 +390,8 @@ impl Trigger {
         typ: Type,
         content: String,
         db: &SqlitePool,
-    ) -> error_stack::Result<Self, sqlx::Error> {
-        sqlx::query_as!(
+    ) -> error_stack::Result<Self, Error> {
+        let result = sqlx::query_as!(
             Self,
             r#"
             INSERT INTO triggers (member_id, system_id, typ, text)
@@ -216,6 +410,26 @@ impl Trigger {
         )
         .fetch_one(db)
         .await
-        .attach_printable("Failed to insert trigger into database")
+        .attach_printable("Failed to insert trigger into database");
+
+        match result {
+            Ok(trigger) => Ok(trigger),
+            Err(error) => Err(Error::from_write(
+                error,
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT members.display_name
+                    FROM triggers
+                    JOIN members ON members.id = triggers.member_id
+                    WHERE triggers.system_id = $1 AND triggers.typ = $2 AND triggers.text = $3
+                    "#,
+                    system_id,
+                    typ,
+                    content
+                )
+                .fetch_optional(db),
+            )
+            .await),
+        }
     }
 }
@@ -118,7 +118,8 @@ pub fn parse_slack_user_id(escaped: &str) -> Option<Id<Untrusted>> {
         .and_then(|s| s.strip_suffix('>'))
         .and_then(|s| s.split('|').next())
         .filter(|s| !s.is_empty())
-        .filter(|s| s.starts_with('U'))
+        // Enterprise Grid users have IDs starting with W
+        .filter(|s| s.starts_with(['U', 'W']))
         .map(|s| SlackUserId::new(s.to_string()))
         .map(|s| Id {
             id: Text(s),
@@ -126,6 +127,27 @@ pub fn parse_slack_user_id(escaped: &str) -> Option<Id<Untrusted>> {
         })
 }
 
+/// Parses an escaped Slack user mention and trusts it by checking the user exists.
+///
+/// Returns [`None`] if the mention is malformed or the user can't be found.
+pub async fn parse_and_trust<SCHC>(escaped: &str, client: &SlackClient<SCHC>) -> Option<Id<Trusted>>
+where
+    SCHC: SlackClientHttpConnector + Send + Sync,
+{
+    trust_parsed(escaped, |user_id| user_id.trust(client)).await
+}
+
+/// Parses an escaped Slack user mention and awaits `trust` on it, so the check never blocks the runtime
+async fn trust_parsed<F, E>(
+    escaped: &str,
+    trust: impl FnOnce(Id<Untrusted>) -> F,
+) -> Option<Id<Trusted>>
+where
+    F: Future<Output = Result<Id<Trusted>, E>>,
+{
+    trust(parse_slack_user_id(escaped)?).await.ok()
+}
+
 impl From<Id<Trusted>> for SlackUserId {
     fn from(value: Id<Trusted>) -> Self {
         value.id.0
@@ -157,3 +179,62 @@ impl<T> PartialEq<Id<T>> for SlackUserId {
 pub struct State {
     pub db: SqlitePool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(escaped: &str) -> Option<SlackUserId> {
+        parse_slack_user_id(escaped).map(|id| id.id.0)
+    }
+
+    #[test]
+    fn mentions() {
+        let user_id = Some(SlackUserId::new("U0123456".into()));
+
+        assert_eq!(parse("<@U0123456>"), user_id);
+        assert_eq!(parse("<@U0123456|ash>"), user_id);
+    }
+
+    #[test]
+    fn enterprise_grid_mentions() {
+        let user_id = Some(SlackUserId::new("W0123456".into()));
+
+        assert_eq!(parse("<@W0123456>"), user_id);
+        assert_eq!(parse("<@W0123456|ash>"), user_id);
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn trusts_mentions_without_blocking_the_runtime() {
+        // Every lookup shares one thread, so one blocking on the runtime would panic instead of letting the others run
+        let mentions = (0..64).map(|i| format!("<@U{i:07}>")).collect::<Vec<_>>();
+
+        let lookups = mentions.iter().map(|mention| {
+            trust_parsed(mention, |user_id| async move {
+                tokio::task::yield_now().await;
+                Ok::<_, ()>(Id::<Trusted>::from(user_id.id.0))
+            })
+        });
+
+        let trusted = futures::future::join_all(lookups).await;
+
+        assert!(trusted.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn not_mentions() {
+        for escaped in [
+            "",
+            "U0123456",
+            "@U0123456",
+            "<@U0123456",
+            "<@>",
+            "<@|ash>",
+            "<#C0123456>",
+            "<@A0123456>",
+            "<!here>",
+        ] {
+            assert_eq!(parse(escaped), None, "{escaped} shouldn't parse");
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Who is looking at a system, and how much of it they get to see.
+//!
+//! Commands and interactions that show a system to someone other than its owner
+//! resolve a [`Viewer`] first, then hand it to the renderers in [`crate::render`].
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+
+use crate::{BOT_TOKEN, slack_error};
+
+use super::{System, system::Visibility, trust::Trusted, user};
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    Slack,
+}
+
+/// The relationship between a user and the system they are looking at.
+///
+/// Variants are ordered from most to least access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Viewer {
+    /// The owner of the system
+    Owner,
+    /// A user the system has added as a manager
+    Manager,
+    /// A workspace admin or owner
+    Admin,
+    /// Anyone else
+    Public,
+}
+
+impl Viewer {
+    /// Works out how `viewer_id` relates to `system`.
+    ///
+    /// Admin status is checked last, as it requires a Slack API call.
+    #[tracing::instrument(skip(system, client, db), fields(system_id = %system.id))]
+    pub async fn resolve(
+        viewer_id: &user::Id<Trusted>,
+        system: &System,
+        client: &SlackHyperClient,
+        db: &SqlitePool,
+    ) -> Result<Self, Error> {
+        if system.owner_id == *viewer_id {
+            return Ok(Self::Owner);
+        }
+
+        if system
+            .id
+            .is_manager(viewer_id, db)
+            .await
+            .change_context(Error::Sqlx)?
+        {
+            return Ok(Self::Manager);
+        }
+
+        let session = client.open_session(&BOT_TOKEN);
+
+        let request = SlackApiUsersInfoRequest::new(viewer_id.id.0.clone());
+        let user = slack_error::retry("users.info", || session.users_info(&request))
+            .await
+            .attach_printable("Failed to fetch viewer info")
+            .change_context(Error::Slack)?
+            .user;
+
+        if user.flags.is_admin.unwrap_or_default() || user.flags.is_owner.unwrap_or_default() {
+            return Ok(Self::Admin);
+        }
+
+        Ok(Self::Public)
+    }
+
+    /// Whether the viewer can see member IDs
+    pub const fn sees_ids(self) -> bool {
+        matches!(self, Self::Owner | Self::Manager | Self::Admin)
+    }
+
+    /// Whether the viewer can see disabled members
+    pub const fn sees_disabled(self) -> bool {
+        matches!(self, Self::Owner | Self::Manager | Self::Admin)
+    }
+
+    /// Whether the viewer can look the system up at all, given its [visibility](System::visibility)
+    pub fn sees_system(self, system: &System) -> bool {
+        self.sees_visibility(system.visibility)
+    }
+
+    /// Whether the viewer can look up a system with `visibility`. Private systems are only shown to their owner
+    fn sees_visibility(self, visibility: Visibility) -> bool {
+        visibility == Visibility::Public || self == Self::Owner
+    }
+
+    /// Whether the viewer can see details only useful for managing members, like aliases
+    pub const fn sees_management_details(self) -> bool {
+        matches!(self, Self::Owner | Self::Manager)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_systems_are_only_shown_to_their_owner() {
+        assert!(Viewer::Owner.sees_visibility(Visibility::Private));
+        assert!(!Viewer::Manager.sees_visibility(Visibility::Private));
+        assert!(!Viewer::Admin.sees_visibility(Visibility::Private));
+        assert!(!Viewer::Public.sees_visibility(Visibility::Private));
+    }
+
+    #[test]
+    fn public_systems_are_shown_to_everyone() {
+        assert!(Viewer::Public.sees_visibility(Visibility::Public));
+        assert!(Viewer::Manager.sees_visibility(Visibility::Public));
+    }
+}
@@ -0,0 +1,61 @@
+//! Settings for the workspace the bot runs in, which operators change with `/plura-admin branding`.
+
+use error_stack::{Result, ResultExt};
+use sqlx::SqlitePool;
+
+/// How the bot presents its own messages, like notifications and channel announcements.
+///
+/// Proxied messages are posted under the member's profile, so this doesn't apply to them.
+#[derive(Debug, Clone, Default)]
+pub struct Branding {
+    /// The name to post under, instead of the app's name
+    pub display_name: Option<String>,
+    /// The picture to post with, instead of the app's icon
+    pub icon_url: Option<String>,
+    /// Text shown under each message
+    pub footer: Option<String>,
+}
+
+impl Branding {
+    /// The workspace's branding. Anything that hasn't been set is left as the app's own
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch(db: &SqlitePool) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(
+            Branding,
+            r#"
+            SELECT
+                display_name,
+                icon_url,
+                footer
+            FROM workspace_settings
+            WHERE id = 1
+            "#
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch workspace branding")
+        .map(Option::unwrap_or_default)
+    }
+
+    /// Replaces the workspace's branding
+    #[tracing::instrument(skip(db))]
+    pub async fn save(&self, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO workspace_settings (id, display_name, icon_url, footer)
+            VALUES (1, $1, $2, $3)
+            ON CONFLICT (id) DO UPDATE SET
+                display_name = excluded.display_name,
+                icon_url = excluded.icon_url,
+                footer = excluded.footer
+            "#,
+            self.display_name,
+            self.icon_url,
+            self.footer
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to save workspace branding")
+        .map(|_| ())
+    }
+}
@@ -0,0 +1,387 @@
+//! Notifications the bot DMs to users, outside of the interaction that caused them.
+//!
+//! Anything that messages a user out of band should go through [`send`] with a [`Notification`],
+//! so the system owner's [`NotificationPreference`] and rate limits are respected in one place.
+//! Notifications are posted with the workspace's [`Branding`].
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{debug, error, warn};
+
+use crate::{
+    BOT_TOKEN, backfill, interactions,
+    models::{System, system::NotificationPreference, trust::Trusted, user, workspace::Branding},
+};
+
+/// How many notifications a user can receive within [`RATE_LIMIT_WINDOW`]
+const RATE_LIMIT: usize = 5;
+/// How many backfill progress updates a user can receive within [`RATE_LIMIT_WINDOW`], on top of [`RATE_LIMIT`]
+const PROGRESS_RATE_LIMIT: usize = 2;
+/// The window the rate limits apply to
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// When each user was last sent a notification under each quota, most recent last.
+/// Users are removed once they haven't been sent anything within [`RATE_LIMIT_WINDOW`]
+static SENT: LazyLock<Mutex<HashMap<(SlackUserId, Quota), VecDeque<Instant>>>> =
+    LazyLock::new(Mutex::default);
+
+/// How many users have been sent a notification recently enough to be tracked for rate limiting
+pub fn tracked_users() -> usize {
+    SENT.lock()
+        .expect("Notification rate limit lock to not be poisoned")
+        .keys()
+        .map(|(user, _)| user)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Which rate limit a notification counts towards
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Quota {
+    General,
+    /// Backfill progress, which comes in steadily during a long backfill and shouldn't crowd out anything else
+    Progress,
+}
+
+impl Quota {
+    const fn limit(self) -> usize {
+        match self {
+            Self::General => RATE_LIMIT,
+            Self::Progress => PROGRESS_RATE_LIMIT,
+        }
+    }
+}
+
+/// How important a notification is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Confirmation that something worked
+    Info,
+    /// Something went wrong and the user should know about it
+    Error,
+    /// The user needs to respond. Always sent, regardless of preference
+    Prompt,
+    /// The result of something the user asked for. Always sent, regardless of preference
+    Requested,
+}
+
+impl NotificationPreference {
+    /// Whether a notification of `level` should be sent
+    pub const fn allows(self, level: Level) -> bool {
+        match (self, level) {
+            (_, Level::Prompt | Level::Requested) | (Self::Always, _) => true,
+            (Self::Errors, level) => matches!(level, Level::Error),
+            (Self::Never, _) => false,
+        }
+    }
+}
+
+/// A notification that can be sent to a user
+#[derive(Debug)]
+pub enum Notification {
+    /// A member was added through the add member modal
+    MemberAdded {
+        display_name: String,
+        member_id: i64,
+    },
+    /// A member was edited through the edit member modal
+    MemberEdited {
+        display_name: String,
+        member_id: i64,
+    },
+    /// A message couldn't be proxied
+    ProxyFailed {
+        /// Why the message couldn't be proxied
+        reason: String,
+        /// Whether it was because the system's authorization broke, so the owner should reauthenticate
+        reauth: bool,
+    },
+    /// Someone asked who sent a message, and the system requires the owner's consent to show them
+    InfoConsentRequest {
+        requester: SlackUserId,
+        member_display_name: String,
+        channel_id: SlackChannelId,
+        message_id: SlackTs,
+    },
+    /// A backfill has scanned another batch of messages
+    BackfillProgress {
+        channel_id: SlackChannelId,
+        summary: backfill::Summary,
+    },
+    /// A backfill has scanned the whole range
+    BackfillFinished {
+        channel_id: SlackChannelId,
+        mode: backfill::Mode,
+        summary: backfill::Summary,
+    },
+    /// A backfill stopped partway through
+    BackfillFailed { channel_id: SlackChannelId },
+    /// The system hasn't logged a switch within its reminder interval
+    SwitchReminder { hours: i64 },
+}
+
+impl Notification {
+    pub const fn level(&self) -> Level {
+        match self {
+            Self::MemberAdded { .. } | Self::MemberEdited { .. } => Level::Info,
+            Self::ProxyFailed { .. } => Level::Error,
+            Self::InfoConsentRequest { .. } => Level::Prompt,
+            Self::BackfillProgress { .. }
+            | Self::BackfillFinished { .. }
+            | Self::BackfillFailed { .. }
+            | Self::SwitchReminder { .. } => Level::Requested,
+        }
+    }
+
+    /// Which rate limit the notification counts towards
+    const fn quota(&self) -> Quota {
+        match self {
+            Self::BackfillProgress { .. } => Quota::Progress,
+            _ => Quota::General,
+        }
+    }
+
+    /// Renders the notification into a message
+    fn render(self) -> SlackMessageContent {
+        match self {
+            Self::MemberAdded {
+                display_name,
+                member_id,
+            } => SlackMessageContent::new().with_text(format!(
+                "Successfully added {display_name}! Their ID is {member_id}"
+            )),
+            Self::MemberEdited {
+                display_name,
+                member_id,
+            } => SlackMessageContent::new().with_text(format!(
+                "Successfully edited {display_name} (ID {member_id})"
+            )),
+            Self::ProxyFailed {
+                reason,
+                reauth: false,
+            } => SlackMessageContent::new()
+                .with_text(format!("Your message couldn't be proxied! {reason}")),
+            Self::ProxyFailed {
+                reason,
+                reauth: true,
+            } => SlackMessageContent::new().with_blocks(slack_blocks![
+                some_into(SlackSectionBlock::new().with_text(md!(
+                    "Your message couldn't be proxied! {}",
+                    reason
+                ))),
+                some_into(SlackActionsBlock::new(vec![
+                    SlackBlockButtonElement::new(
+                        interactions::system::REAUTH_ACTION.into(),
+                        pt!("Reauthenticate")
+                    )
+                    .into(),
+                ]))
+            ]),
+            Self::InfoConsentRequest {
+                requester,
+                member_display_name,
+                channel_id,
+                message_id,
+            } => {
+                // requester, channel and message, in the same `_` separated style as modal external ids
+                let value = format!("{}_{}_{}", requester.0, channel_id.0, message_id.0);
+
+                SlackMessageContent::new().with_blocks(slack_blocks![
+                    some_into(SlackSectionBlock::new().with_text(md!(
+                        "{} wants to know who sent a message from {} in {}. Do you want to show them your account?",
+                        requester.to_slack_format(),
+                        member_display_name,
+                        channel_id.to_slack_format()
+                    ))),
+                    some_into(SlackActionsBlock::new(vec![
+                        SlackBlockButtonElement::new(
+                            "message_info_consent_approve".into(),
+                            pt!("Show my account")
+                        )
+                        .with_value(value.clone())
+                        .into(),
+                        SlackBlockButtonElement::new(
+                            "message_info_consent_deny".into(),
+                            pt!("Keep hidden")
+                        )
+                        .with_value(value)
+                        .into(),
+                    ]))
+                ])
+            }
+            Self::BackfillProgress {
+                channel_id,
+                summary,
+            } => SlackMessageContent::new().with_text(format!(
+                "Backfilling {}: scanned {} messages so far, {} matched your triggers.",
+                channel_id.to_slack_format(),
+                summary.scanned,
+                summary.matched
+            )),
+            Self::BackfillFinished {
+                channel_id,
+                mode,
+                summary,
+            } => {
+                let action = match mode {
+                    backfill::Mode::Index => "indexed",
+                    backfill::Mode::Proxy => "proxied",
+                };
+
+                SlackMessageContent::new().with_text(format!(
+                    "Finished backfilling {}! Scanned {} messages and {action} {} of them.{}",
+                    channel_id.to_slack_format(),
+                    summary.scanned,
+                    summary.matched - summary.failed,
+                    if summary.failed > 0 {
+                        format!(" {} couldn't be {action}.", summary.failed)
+                    } else {
+                        String::new()
+                    }
+                ))
+            }
+            Self::BackfillFailed { channel_id } => SlackMessageContent::new().with_text(format!(
+                "Backfilling {} stopped partway through. Messages already handled have been kept.",
+                channel_id.to_slack_format()
+            )),
+            Self::SwitchReminder { hours } => SlackMessageContent::new().with_text(format!(
+                "You haven't logged a switch in {hours} hours. Who's fronting? Log it with `/members switch`, or turn these reminders off with `/system remind off`."
+            )),
+        }
+    }
+}
+
+/// The workspace's branding, or the defaults if it can't be fetched
+pub async fn branding(db: &SqlitePool) -> Branding {
+    Branding::fetch(db).await.unwrap_or_else(|error| {
+        warn!(
+            ?error,
+            "Failed to fetch workspace branding. Using the defaults"
+        );
+        Branding::default()
+    })
+}
+
+/// Adds the workspace's footer under a message from the bot, if it has one
+pub fn with_footer(branding: &Branding, content: SlackMessageContent) -> SlackMessageContent {
+    let Some(footer) = &branding.footer else {
+        return content;
+    };
+
+    let footer: SlackBlock = SlackContextBlock::new(vec![md!("{}", footer)]).into();
+
+    // The text is kept as-is, as it's what shows up in notifications
+    let blocks = match content.blocks.clone() {
+        Some(mut blocks) => {
+            blocks.push(footer);
+            blocks
+        }
+        None => vec![
+            SlackSectionBlock::new()
+                .with_text(md!("{}", content.text.clone().unwrap_or_default()))
+                .into(),
+            footer,
+        ],
+    };
+
+    content.with_blocks(blocks)
+}
+
+/// Records a notification for `user`, returning false if they've hit the quota's rate limit
+fn check_rate_limit(user: &SlackUserId, quota: Quota) -> bool {
+    let now = Instant::now();
+    let mut sent = SENT
+        .lock()
+        .expect("Notification rate limit lock to not be poisoned");
+
+    sent.retain(|_, times| {
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) > RATE_LIMIT_WINDOW)
+        {
+            times.pop_front();
+        }
+
+        !times.is_empty()
+    });
+
+    let times = sent.entry((user.clone(), quota)).or_default();
+
+    if times.len() >= quota.limit() {
+        return false;
+    }
+
+    times.push_back(now);
+    true
+}
+
+/// DMs `user_id` a notification, if their system's preference and the rate limit allow it.
+///
+/// Users without a system get errors and prompts, since there's no preference to go by.
+/// Failing to notify is logged rather than returned, as it shouldn't fail whatever caused the notification.
+#[tracing::instrument(skip(client, db))]
+pub async fn send(
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+    user_id: &user::Id<Trusted>,
+    notification: Notification,
+) {
+    let level = notification.level();
+
+    let preference = match System::fetch_by_user_id(user_id, db).await {
+        Ok(system) => system.map_or(NotificationPreference::Errors, |system| {
+            system.notifications
+        }),
+        Err(error) => {
+            error!(?error, "Failed to fetch notification preference");
+            return;
+        }
+    };
+
+    if !preference.allows(level) {
+        debug!(?preference, "Notification not sent due to preference");
+        return;
+    }
+
+    let user: SlackUserId = user_id.clone().into();
+
+    if !check_rate_limit(&user, notification.quota()) {
+        warn!("User has been sent too many notifications. Dropping notification");
+        return;
+    }
+
+    let session = client.open_session(&BOT_TOKEN);
+    let branding = branding(db).await;
+
+    let conversation = match session
+        .conversations_open(&SlackApiConversationsOpenRequest::new().with_users(vec![user.clone()]))
+        .await
+    {
+        Ok(response) => response.channel,
+        Err(error) => {
+            error!(?error, "Failed to open conversation for notification");
+            return;
+        }
+    };
+
+    if let Err(error) = session
+        .chat_post_ephemeral(
+            &SlackApiChatPostEphemeralRequest::new(
+                conversation.id,
+                user,
+                with_footer(&branding, notification.render()),
+            )
+            .opt_username(branding.display_name.clone())
+            .opt_icon_url(branding.icon_url.clone()),
+        )
+        .await
+    {
+        error!(?error, "Failed to send notification");
+    }
+}
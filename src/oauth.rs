@@ -3,11 +3,12 @@ use axum::{
     http::{self, StatusCode, request::Parts},
 };
 use oauth2::{
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, EndpointNotSet, EndpointSet, RedirectUrl,
-    TokenUrl, reqwest,
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointNotSet, EndpointSet,
+    RedirectUrl, TokenUrl, reqwest, url::Url,
 };
 use serde::{Deserialize, Serialize};
 use slack_morphism::SlackUserId;
+use sqlx::SqlitePool;
 use tracing::error;
 
 use crate::{
@@ -15,6 +16,11 @@ use crate::{
     models::{trust::Trusted, user},
 };
 
+/// The scopes a system's user token is requested with
+///
+/// `files:read` is needed to download name recordings sent to the bot.
+pub const USER_SCOPES: &[&str] = &["users.profile:read", "chat:write", "files:read"];
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SlackAuthedUser {
     pub id: String,
@@ -23,9 +29,18 @@ pub struct SlackAuthedUser {
     pub token_type: String,
 }
 
+/// The workspace the app was authorized in
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SlackAuthedTeam {
+    pub id: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SlackTokenFields {
     pub authed_user: SlackAuthedUser,
+    /// Missing for org-wide installs in Enterprise Grid
+    #[serde(default)]
+    pub team: Option<SlackAuthedTeam>,
 }
 impl oauth2::ExtraTokenFields for SlackTokenFields {}
 
@@ -56,6 +71,35 @@ pub fn create_oauth_client() -> SlackOauthClient {
         .set_redirect_uri(RedirectUrl::new(format!("{}/auth", env::base_url())).unwrap())
 }
 
+/// Starts authorizing `owner_id`'s system with Slack, returning the URL for them to open.
+///
+/// Any authorization they started before stops working, as its CSRF token is replaced.
+pub async fn authorize_url<T>(owner_id: &user::Id<T>, db: &SqlitePool) -> Result<Url, sqlx::Error> {
+    // Note: we aren't doing PKCE since this is only ran on a trusted server
+    let (auth_url, csrf_token) = create_oauth_client()
+        .authorize_url(CsrfToken::new_random)
+        // So we get a regular token as well. Required by oauth2 for some reason
+        .add_extra_param("scope", "commands")
+        .add_extra_param("user_scope", USER_SCOPES.join(","))
+        .url();
+
+    let secret = csrf_token.secret();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO system_oauth_process (owner_id, csrf)
+        VALUES ($1, $2)
+        ON CONFLICT (owner_id) DO UPDATE SET csrf = $2
+        "#,
+        owner_id.id,
+        secret
+    )
+    .execute(db)
+    .await?;
+
+    Ok(auth_url)
+}
+
 #[derive(Deserialize)]
 pub struct OauthCode {
     pub code: String,
@@ -111,6 +155,11 @@ pub async fn oauth_handler(
             let user_token = response.extra_fields().authed_user.access_token.clone();
             let user_id = response.extra_fields().authed_user.id.clone();
             let user_id: SlackUserId = user_id.into();
+            let team_id = response
+                .extra_fields()
+                .team
+                .as_ref()
+                .map(|team| team.id.clone());
 
             if user_id != record.owner_id {
                 return "CSRF token doesn't match the user".to_owned();
@@ -118,12 +167,13 @@ pub async fn oauth_handler(
 
             let user = sqlx::query!(
                 r#"
-                  INSERT INTO systems (owner_id, slack_oauth_token)
-                  VALUES ($1, $2)
-                  ON CONFLICT (owner_id) DO UPDATE SET slack_oauth_token = $2
+                  INSERT INTO systems (owner_id, slack_oauth_token, team_id)
+                  VALUES ($1, $2, $3)
+                  ON CONFLICT (owner_id) DO UPDATE SET slack_oauth_token = $2, team_id = coalesce($3, team_id)
                 "#,
                 record.owner_id.id,
                 user_token,
+                team_id,
             )
             .execute(db)
             .await;
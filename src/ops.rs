@@ -0,0 +1,184 @@
+//! Forwarding error reports to the people running the bot.
+//!
+//! When `ERROR_REPORT_CHANNEL` or `ERROR_REPORT_WEBHOOK` is set, errors users run into are posted there with their
+//! error chain and the spans they happened in, so they don't have to be dug out of the logs.
+//! Reports are rate limited, and anything that looks like a token or secret is redacted before it leaves the bot.
+
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::{
+        Arc, LazyLock, Mutex, OnceLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use oauth2::reqwest;
+use regex::Regex;
+use slack_morphism::prelude::*;
+use tracing::{Instrument, debug, info_span, warn};
+use tracing_error::SpanTrace;
+
+use crate::{BOT_TOKEN, env};
+
+/// How many reports can be sent within [`RATE_LIMIT_WINDOW`]. Reports past this are counted, then dropped
+const RATE_LIMIT: usize = 10;
+/// The window [`RATE_LIMIT`] applies to
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// The longest a report can be. Slack cuts off section text at 3000 characters, which has to fit the code fences too
+const MAX_REPORT_LENGTH: usize = 2900;
+
+/// The client reports are posted to the error channel with, set once the bot starts
+static CLIENT: OnceLock<Arc<SlackHyperClient>> = OnceLock::new();
+
+/// When each recent report was sent, most recent last
+static SENT: LazyLock<Mutex<VecDeque<Instant>>> = LazyLock::new(Mutex::default);
+
+/// How many reports were dropped by the rate limit since the last one was sent
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Slack tokens: bot, user, app, refresh and so on
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(xox[a-z]|xapp)-[A-Za-z0-9-]+").expect("Token pattern to be valid")
+});
+
+/// Sets the client reports are posted with. Until this is called, reports only go to the webhook
+pub fn init(client: Arc<SlackHyperClient>) {
+    if CLIENT.set(client).is_err() {
+        warn!("Error reporting was already initialized");
+    }
+}
+
+/// Reports an error to the configured channel and webhook, if any.
+///
+/// `source` is a short description of what was going on, like "command" or "interaction".
+/// The report is sent in the background, so this returns straight away and never fails.
+pub fn report(source: &'static str, error: &dyn Debug) {
+    let channel = env::error_report_channel().map(SlackChannelId::new);
+    let webhook = env::error_report_webhook();
+
+    if channel.is_none() && webhook.is_none() {
+        return;
+    }
+
+    let Some(dropped) = check_rate_limit() else {
+        debug!("Too many error reports. Dropping report");
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+        return;
+    };
+
+    let details = truncate(redact(format!(
+        "{error:?}\n\nSpan trace:\n{}",
+        SpanTrace::capture()
+    )));
+
+    let mut summary = format!("*Error in {source}*");
+    if dropped > 0 {
+        summary.push_str(&format!(
+            " ({dropped} earlier reports dropped by the rate limit)"
+        ));
+    }
+
+    tokio::spawn(
+        async move {
+            if let Some(channel) = channel {
+                post_to_channel(channel, &summary, &details).await;
+            }
+
+            if let Some(webhook) = webhook {
+                post_to_webhook(&webhook, &summary, &details).await;
+            }
+        }
+        .instrument(info_span!("error_report")),
+    );
+}
+
+/// Records a report, returning how many were dropped since the last one, or [`None`] if the rate limit was hit
+fn check_rate_limit() -> Option<usize> {
+    let now = Instant::now();
+    let mut sent = SENT
+        .lock()
+        .expect("Error report rate limit lock to not be poisoned");
+
+    while sent
+        .front()
+        .is_some_and(|time| now.duration_since(*time) > RATE_LIMIT_WINDOW)
+    {
+        sent.pop_front();
+    }
+
+    if sent.len() >= RATE_LIMIT {
+        return None;
+    }
+
+    sent.push_back(now);
+    Some(DROPPED.swap(0, Ordering::Relaxed))
+}
+
+/// Removes Slack tokens and the bot's own secrets from a report
+fn redact(report: String) -> String {
+    let secrets = [env::slack_client_secret(), env::slack_signing_secret()];
+
+    let report = secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(report, |report, secret| {
+            report.replace(secret.as_str(), "[redacted]")
+        });
+
+    TOKEN.replace_all(&report, "[redacted token]").into_owned()
+}
+
+/// Cuts a report down to [`MAX_REPORT_LENGTH`], keeping the start where the error itself is
+fn truncate(mut report: String) -> String {
+    if report.len() > MAX_REPORT_LENGTH {
+        let mut end = MAX_REPORT_LENGTH;
+        while !report.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        report.truncate(end);
+        report.push_str("\n[truncated]");
+    }
+
+    report
+}
+
+async fn post_to_channel(channel: SlackChannelId, summary: &str, details: &str) {
+    let Some(client) = CLIENT.get() else {
+        warn!("Error reporting isn't initialized. Not posting report to channel");
+        return;
+    };
+
+    let content = SlackMessageContent::new()
+        .with_text(summary.to_string())
+        .with_blocks(slack_blocks![
+            some_into(SlackSectionBlock::new().with_text(md!("{}", summary))),
+            some_into(SlackSectionBlock::new().with_text(md!("```{}```", details)))
+        ]);
+
+    if let Err(error) = client
+        .open_session(&BOT_TOKEN)
+        .chat_post_message(&SlackApiChatPostMessageRequest::new(channel, content))
+        .await
+    {
+        warn!(?error, "Failed to post error report to channel");
+    }
+}
+
+async fn post_to_webhook(webhook: &str, summary: &str, details: &str) {
+    let body = serde_json::json!({ "text": format!("{summary}\n```{details}```") });
+
+    let response = reqwest::Client::new()
+        .post(webhook)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status);
+
+    if let Err(error) = response {
+        warn!(?error, "Failed to post error report to webhook");
+    }
+}
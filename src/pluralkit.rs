@@ -0,0 +1,243 @@
+//! Importing members from PluralKit, either from a `pk;export` file or with a PluralKit token.
+//!
+//! PluralKit's members are converted into [`ExportedMember`]s, so they're added the same way as members from our own exports.
+
+use std::time::Duration;
+
+use error_stack::{Result, ResultExt, bail};
+use oauth2::reqwest;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    models::{
+        export::{ExportedExternalId, ExportedMember, ExportedTrigger},
+        member,
+        trigger::{self, WRAPS_PLACEHOLDER},
+    },
+    render::mrkdwn,
+};
+
+/// The provider of the external IDs PluralKit members are linked with, so importing again updates them
+pub const SOURCE: &str = "pluralkit";
+
+/// The year PluralKit gives birthdays that don't show the year
+const HIDDEN_BIRTH_YEAR: &str = "0004-";
+
+/// PluralKit's API, used when importing with a token
+const API_URL: &str = "https://api.pluralkit.me/v2";
+
+/// PluralKit asks API clients to identify themselves
+const USER_AGENT: &str = "plura (https://github.com/Suya1671/plura)";
+
+/// Where `pk;export` files are hosted. Exports are only fetched from here, so the bot can't be pointed at arbitrary URLs
+const EXPORT_HOSTS: &[&str] = &["cdn.discordapp.com", "media.discordapp.net"];
+
+/// How long to wait for PluralKit or Discord to respond
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling PluralKit or downloading the export
+    Request,
+    /// PluralKit didn't accept the token
+    InvalidToken,
+    /// The export isn't a PluralKit export
+    Format,
+}
+
+/// Where to import from
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// The link to a `pk;export` file
+    Export(Url),
+    /// A PluralKit token, from `pk;token`
+    Token(String),
+}
+
+impl Source {
+    /// Works out whether the user gave us an export link or a token.
+    ///
+    /// Returns [`None`] for links that aren't to a `pk;export` file.
+    pub fn parse(source: &str) -> Option<Self> {
+        let Ok(url) = Url::parse(source) else {
+            return Some(Self::Token(source.to_string()));
+        };
+
+        (url.scheme() == "https"
+            && url
+                .host_str()
+                .is_some_and(|host| EXPORT_HOSTS.contains(&host)))
+        .then_some(Self::Export(url))
+    }
+}
+
+/// The parts of a `pk;export` file we import
+#[derive(Deserialize)]
+struct PkExport {
+    members: Vec<PkMember>,
+}
+
+/// A PluralKit member, as returned by the API and in exports
+#[derive(Deserialize)]
+struct PkMember {
+    /// PluralKit's short ID, e.g. `abcde`
+    id: String,
+    /// Unlike the short ID, this never changes
+    uuid: String,
+    name: String,
+    display_name: Option<String>,
+    pronouns: Option<String>,
+    /// In Discord's flavour of Markdown
+    description: Option<String>,
+    /// As `YYYY-MM-DD`, with the year 0004 when it's hidden
+    birthday: Option<String>,
+    avatar_url: Option<String>,
+    webhook_avatar_url: Option<String>,
+    #[serde(default)]
+    proxy_tags: Vec<ProxyTag>,
+}
+
+/// Text around a message that marks it as sent by a member. Other bots mostly describe proxying the same way
+#[derive(Deserialize)]
+pub struct ProxyTag {
+    pub prefix: Option<String>,
+    pub suffix: Option<String>,
+}
+
+impl ProxyTag {
+    /// The trigger that matches the same messages as this proxy tag
+    fn to_trigger(&self) -> Option<trigger::Type> {
+        let prefix = self.prefix.as_deref().filter(|prefix| !prefix.is_empty());
+        let suffix = self.suffix.as_deref().filter(|suffix| !suffix.is_empty());
+
+        match (prefix, suffix) {
+            (None, None) => None,
+            (Some(_), None) => Some(trigger::Type::Prefix),
+            (None, Some(_)) => Some(trigger::Type::Suffix),
+            // Wraps triggers split on the first placeholder, so a prefix containing it needs a regex instead
+            (Some(prefix), Some(_)) if prefix.contains(WRAPS_PLACEHOLDER) => {
+                Some(trigger::Type::Regex)
+            }
+            (Some(_), Some(_)) => Some(trigger::Type::Wraps),
+        }
+    }
+
+    /// The trigger that matches the same messages, if the tag has any text
+    pub fn to_exported(&self) -> Option<ExportedTrigger> {
+        let typ = self.to_trigger()?;
+        let prefix = self.prefix.clone().unwrap_or_default();
+        let suffix = self.suffix.clone().unwrap_or_default();
+
+        let text = match typ {
+            trigger::Type::Prefix => prefix,
+            trigger::Type::Suffix => suffix,
+            trigger::Type::Wraps => format!("{prefix}{WRAPS_PLACEHOLDER}{suffix}"),
+            trigger::Type::Regex => format!(
+                "(?s)^{}(?<text>.*){}$",
+                regex::escape(&prefix),
+                regex::escape(&suffix)
+            ),
+        };
+
+        Some(ExportedTrigger { typ, text })
+    }
+}
+
+impl PkMember {
+    /// Converts the member, using its position in the import as its export ID
+    fn into_exported(self, index: usize) -> ExportedMember {
+        ExportedMember {
+            id: index.try_into().unwrap_or(i64::MAX),
+            triggers: self
+                .proxy_tags
+                .iter()
+                .filter_map(ProxyTag::to_exported)
+                .collect(),
+            // So members can still be referred to by the IDs the system is used to
+            aliases: vec![self.id],
+            command_name: None,
+            display_name: self.display_name.unwrap_or_else(|| self.name.clone()),
+            full_name: self.name,
+            profile_picture_url: self.avatar_url,
+            // The webhook avatar is the one PluralKit proxies with
+            proxy_avatar_url: self.webhook_avatar_url,
+            title: None,
+            pronouns: self.pronouns,
+            name_pronunciation: None,
+            name_recording_url: None,
+            description: self.description.as_deref().map(mrkdwn::from_markdown),
+            birthday: self.birthday.as_deref().and_then(|birthday| {
+                member::parse_birthday(birthday.strip_prefix(HIDDEN_BIRTH_YEAR).unwrap_or(birthday))
+            }),
+            fields: Vec::new(),
+            external_ids: vec![ExportedExternalId {
+                provider: SOURCE.to_string(),
+                id: self.uuid,
+            }],
+            emoji: None,
+            enabled: true,
+            proxy_enabled: true,
+            priority: 0,
+            message_count: 0,
+        }
+    }
+}
+
+/// Fetches a system's members from PluralKit
+#[tracing::instrument(skip_all)]
+pub async fn fetch_members(source: &Source) -> Result<Vec<ExportedMember>, Error> {
+    let http = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .user_agent(USER_AGENT)
+        .build()
+        .change_context(Error::Request)?;
+
+    let members = match source {
+        Source::Export(url) => {
+            let body = http
+                .get(url.clone())
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .change_context(Error::Request)?
+                .text()
+                .await
+                .change_context(Error::Request)?;
+
+            serde_json::from_str::<PkExport>(&body)
+                .change_context(Error::Format)?
+                .members
+        }
+        Source::Token(token) => {
+            let response = http
+                .get(format!("{API_URL}/systems/@me/members"))
+                .header("Authorization", token)
+                .send()
+                .await
+                .change_context(Error::Request)?;
+
+            if matches!(
+                response.status(),
+                reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN
+            ) {
+                bail!(Error::InvalidToken);
+            }
+
+            let body = response
+                .error_for_status()
+                .change_context(Error::Request)?
+                .text()
+                .await
+                .change_context(Error::Request)?;
+
+            serde_json::from_str::<Vec<PkMember>>(&body).change_context(Error::Format)?
+        }
+    };
+
+    Ok(members
+        .into_iter()
+        .enumerate()
+        .map(|(index, member)| member.into_exported(index))
+        .collect())
+}
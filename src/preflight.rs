@@ -0,0 +1,273 @@
+//! Checks run when the bot starts, so a misconfigured deployment shows up in the logs straight away instead of on the
+//! first message someone sends.
+//!
+//! Problems that stop the bot working at all, like a bot token Slack rejects, stop it from starting. Problems that only
+//! break some features are logged as warnings, and the bot starts without them.
+
+use std::time::Duration;
+
+use error_stack::{Result, report};
+use oauth2::{reqwest, url::Url};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use tracing::{debug, info, warn};
+
+use crate::{env, socket_mode};
+
+/// Scopes the bot can't proxy without
+const REQUIRED_SCOPES: &[&str] = &[
+    "chat:write",
+    "chat:write.customize",
+    "users:read",
+    "commands",
+];
+
+/// Scopes for individual features, and what breaks without them
+const FEATURE_SCOPES: &[(&str, &str)] = &[
+    (
+        "channels:history",
+        "messages in public channels won't be proxied",
+    ),
+    (
+        "groups:history",
+        "messages in private channels won't be proxied",
+    ),
+    ("mpim:history", "messages in group DMs won't be proxied"),
+    (
+        "im:history",
+        "name recordings sent to the bot won't be saved",
+    ),
+    ("im:write", "notifications can't be sent"),
+    ("channels:read", "/system diagnose can't check channels"),
+    (
+        "reactions:read",
+        "reacting to proxied messages won't do anything",
+    ),
+    (
+        "reactions:write",
+        "reactions on originals won't be carried over to proxied messages",
+    ),
+    ("pins:write", "proxied messages can't be pinned"),
+    ("files:write", "files on proxied messages can't be shared"),
+];
+
+/// How long to wait for the server to respond to the reachability check
+const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// The deployment is misconfigured. See the logs for what's wrong
+    Misconfigured,
+}
+
+/// What the checks found
+#[derive(Default)]
+struct Findings {
+    /// Stop the bot from starting
+    problems: Vec<String>,
+    /// Only break some features
+    warnings: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct AuthTestResponse {
+    ok: bool,
+    error: Option<String>,
+}
+
+/// Runs the checks that have to pass before the bot starts
+#[tracing::instrument(skip_all)]
+pub async fn run(db: &SqlitePool) -> Result<(), Error> {
+    let mut findings = Findings::default();
+
+    check_env(&mut findings);
+    check_bot_token(&mut findings).await;
+    check_schema(db, &mut findings).await;
+
+    for warning in &findings.warnings {
+        warn!("Preflight: {warning}");
+    }
+
+    if findings.problems.is_empty() {
+        info!(
+            warnings = findings.warnings.len(),
+            "Preflight checks passed"
+        );
+        return Ok(());
+    }
+
+    let mut error = report!(Error::Misconfigured);
+    for problem in findings.problems {
+        warn!("Preflight: {problem}");
+        error = error.attach_printable(problem);
+    }
+
+    Err(error)
+}
+
+/// Checks environment variables that are set, but can't work as they are
+fn check_env(findings: &mut Findings) {
+    match Url::parse(&env::base_url()) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        _ => findings
+            .problems
+            .push("BASE_URL isn't an http(s) URL, so signing in with Slack won't work".to_string()),
+    }
+
+    if socket_mode::enabled() && !env::slack_app_token().starts_with("xapp-") {
+        findings.problems.push(
+            "SOCKET_MODE is on, but SLACK_APP_TOKEN isn't an app-level token (xapp-...)"
+                .to_string(),
+        );
+    }
+
+    if !env::slack_bot_token().starts_with("xoxb-") {
+        findings
+            .warnings
+            .push("SLACK_BOT_TOKEN doesn't look like a bot token (xoxb-...)".to_string());
+    }
+}
+
+/// Checks the bot token works, and has the scopes the bot uses
+async fn check_bot_token(findings: &mut Findings) {
+    // slack-morphism doesn't expose response headers, which is where Slack lists a token's scopes
+    let response = match reqwest::Client::new()
+        .post("https://slack.com/api/auth.test")
+        .bearer_auth(env::slack_bot_token())
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(error) => {
+            debug!(?error, "Failed to call auth.test");
+            findings
+                .warnings
+                .push("Couldn't reach Slack to check the bot token. Starting anyway".to_string());
+            return;
+        }
+    };
+
+    let scopes = response
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|scopes| scopes.to_str().ok())
+        .unwrap_or_default()
+        .split(',')
+        .map(|scope| scope.trim().to_string())
+        .collect::<Vec<_>>();
+
+    let body = response
+        .text()
+        .await
+        .ok()
+        .and_then(|body| serde_json::from_str::<AuthTestResponse>(&body).ok());
+
+    match body {
+        Some(AuthTestResponse { ok: true, .. }) => {}
+        Some(AuthTestResponse { error, .. }) => {
+            findings.problems.push(format!(
+                "Slack rejected SLACK_BOT_TOKEN (`{}`)",
+                error.as_deref().unwrap_or("unknown error")
+            ));
+            return;
+        }
+        None => {
+            findings.warnings.push(
+                "Slack gave an unexpected response when checking the bot token. Starting anyway"
+                    .to_string(),
+            );
+            return;
+        }
+    }
+
+    let granted = |scope: &str| scopes.iter().any(|granted| granted == scope);
+
+    let missing = REQUIRED_SCOPES
+        .iter()
+        .filter(|scope| !granted(scope))
+        .copied()
+        .collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        findings.problems.push(format!(
+            "The bot token is missing the {} scope(s)",
+            missing.join(", ")
+        ));
+    }
+
+    for (scope, breaks) in FEATURE_SCOPES {
+        if !granted(scope) {
+            findings
+                .warnings
+                .push(format!("The bot token is missing {scope}, so {breaks}"));
+        }
+    }
+}
+
+/// Checks the database isn't from a newer version of the bot, whose schema this version doesn't know about
+async fn check_schema(db: &SqlitePool, findings: &mut Findings) {
+    let known = sqlx::migrate!()
+        .migrations
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or_default();
+
+    let applied = match sqlx::query_scalar!("SELECT MAX(version) FROM _sqlx_migrations")
+        .fetch_one(db)
+        .await
+    {
+        Ok(applied) => applied.unwrap_or_default(),
+        Err(error) => {
+            findings.problems.push(format!(
+                "Couldn't read the database's schema version: {error}"
+            ));
+            return;
+        }
+    };
+
+    info!(applied, known, "Checked database schema version");
+
+    if applied > known {
+        findings.problems.push(format!(
+            "The database's schema ({applied}) is newer than this version of the bot knows about ({known}). Update the bot, or restore an older backup"
+        ));
+    }
+}
+
+/// Checks Slack can reach the HTTP endpoints, by requesting one through BASE_URL.
+///
+/// Only run when `PREFLIGHT_REACHABILITY` is set, as some networks can't reach their own public address. Any response
+/// at all means the server is reachable, so only failing to connect is reported.
+pub fn spawn_reachability_check() {
+    if socket_mode::enabled()
+        || !env::preflight_reachability().is_some_and(|value| {
+            matches!(
+                value.trim().to_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+    {
+        return;
+    }
+
+    tokio::spawn(async {
+        let url = format!("{}/push", env::base_url().trim_end_matches('/'));
+
+        match reqwest::Client::new()
+            .get(&url)
+            .timeout(REACHABILITY_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(response) => {
+                debug!(status = %response.status(), "Event endpoint is reachable");
+            }
+            Err(error) => warn!(
+                ?error,
+                %url,
+                "Preflight: Couldn't reach the event endpoint through BASE_URL. Slack probably can't either"
+            ),
+        }
+    });
+}
@@ -0,0 +1,941 @@
+//! The proxy engine: posting a message under a member's (or an anonymous) profile.
+//!
+//! Everything that sends a message on behalf of a system builds a [`ProxyRequest`] and sends it through here,
+//! so message logging, attachments and cleanup of the original message behave the same everywhere.
+
+use error_stack::{Result, ResultExt, report};
+use oauth2::reqwest;
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{Instrument, debug, info, warn};
+
+use crate::{
+    BOT_TOKEN,
+    blocks::{Block, PostMessageRequest, SlackFileImageBlock, UpdateMessageRequest},
+    metrics::{self, Cleanup, Outcome},
+    models::{
+        self, Member, member,
+        outbox::{self, OriginalKind, PendingProxy},
+        trust::Trusted,
+    },
+    slack_error, upload,
+};
+
+/// Files larger than this are linked in the thread instead of being re-uploaded
+const MAX_FOLLOW_UP_SIZE: u64 = 100 * 1024 * 1024;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while posting a message to Slack
+    PostMessage,
+    /// Error while updating a proxied message
+    UpdateMessage,
+    /// Error while deleting a message from Slack
+    DeleteMessage,
+    /// Error while saving message log to database
+    MessageLog,
+    /// Error while recording the operation in the outbox
+    Outbox,
+    /// Error while sharing a file in the message's thread
+    FollowUp,
+}
+
+/// The profile a message is posted under
+#[derive(Debug, Clone)]
+pub enum Identity {
+    /// A member of the system
+    Member {
+        id: member::Id<Trusted>,
+        display_name: String,
+        /// The member's proxy avatar, or their profile picture if they don't have one
+        avatar_url: Option<String>,
+    },
+    /// A neutral profile that isn't linked to any member
+    Anonymous,
+}
+
+impl Identity {
+    /// The display name used for anonymous messages
+    pub const ANONYMOUS_NAME: &str = "Anonymous";
+
+    pub const fn member_id(&self) -> Option<member::Id<Trusted>> {
+        match self {
+            Self::Member { id, .. } => Some(*id),
+            Self::Anonymous => None,
+        }
+    }
+
+    /// The name to post under. Anonymous messages don't get the system's tag, as it would give away who sent them
+    fn username(&self, system: &models::System) -> String {
+        match self {
+            Self::Member { display_name, .. } => system.proxy_name(display_name),
+            Self::Anonymous => Self::ANONYMOUS_NAME.to_string(),
+        }
+    }
+
+    fn icon_url(&self) -> Option<String> {
+        match self {
+            Self::Member { avatar_url, .. } => avatar_url.clone(),
+            // Leaving the icon out uses the bot's own avatar
+            Self::Anonymous => None,
+        }
+    }
+}
+
+impl From<Member> for Identity {
+    fn from(member: Member) -> Self {
+        Self::Member {
+            id: member.id,
+            display_name: member.display_name,
+            avatar_url: member.proxy_avatar_url.or(member.profile_picture_url),
+        }
+    }
+}
+
+impl From<models::DetectedMember> for Identity {
+    fn from(member: models::DetectedMember) -> Self {
+        Self::Member {
+            id: member.id,
+            display_name: member.display_name,
+            avatar_url: member.proxy_avatar_url.or(member.profile_picture_url),
+        }
+    }
+}
+
+/// A message that is being replaced by the proxied message
+#[derive(Debug, Clone)]
+pub enum Original {
+    /// A message the system owner sent themselves. Deleted with the system's token
+    User(SlackTs),
+    /// A message the bot already proxied. Deleted with the bot's token, along with its message log
+    Proxied(SlackTs),
+}
+
+impl Original {
+    const fn new(kind: OriginalKind, ts: SlackTs) -> Self {
+        match kind {
+            OriginalKind::User => Self::User(ts),
+            OriginalKind::Proxied => Self::Proxied(ts),
+        }
+    }
+
+    const fn ts(&self) -> &SlackTs {
+        match self {
+            Self::User(ts) | Self::Proxied(ts) => ts,
+        }
+    }
+
+    const fn kind(&self) -> OriginalKind {
+        match self {
+            Self::User(_) => OriginalKind::User,
+            Self::Proxied(_) => OriginalKind::Proxied,
+        }
+    }
+
+    /// Deletes the original message from Slack, along with its message log if it was proxied
+    pub async fn delete(
+        &self,
+        client: &SlackHyperClient,
+        system: &models::System,
+        channel_id: SlackChannelId,
+        db: &SqlitePool,
+    ) -> Result<(), Error> {
+        match self {
+            Self::User(ts) => {
+                let token = SlackApiToken::new(system.slack_oauth_token.expose().into())
+                    .with_token_type(SlackApiTokenType::User);
+
+                let session = client.open_session(&token);
+                let request =
+                    SlackApiChatDeleteRequest::new(channel_id, ts.clone()).with_as_user(true);
+
+                ignore_missing(
+                    slack_error::retry("chat.delete", || session.chat_delete(&request)).await,
+                )
+                .change_context(Error::DeleteMessage)?;
+            }
+            Self::Proxied(ts) => {
+                let session = client.open_session(&BOT_TOKEN);
+                let request = SlackApiChatDeleteRequest::new(channel_id, ts.clone());
+
+                ignore_missing(
+                    slack_error::retry("chat.delete", || session.chat_delete(&request)).await,
+                )
+                .change_context(Error::DeleteMessage)?;
+
+                models::MessageLog::delete_by_message_id(ts, db)
+                    .await
+                    .change_context(Error::MessageLog)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the reactions on the original message to the message replacing it, so reactions people left before the
+    /// original was deleted aren't lost.
+    ///
+    /// The bot can only react once with each emoji, so who reacted and how many times isn't carried over. Failing to
+    /// mirror a reaction is logged rather than returned, as it shouldn't stop the original being replaced.
+    pub async fn mirror_reactions(
+        &self,
+        client: &SlackHyperClient,
+        channel_id: &SlackChannelId,
+        to: &SlackTs,
+    ) {
+        let session = client.open_session(&BOT_TOKEN);
+
+        let response = slack_error::traced(
+            "reactions.get",
+            session.reactions_get(
+                &SlackApiReactionsGetRequest::new()
+                    .with_channel(channel_id.clone())
+                    .with_timestamp(self.ts().clone()),
+            ),
+        )
+        .await;
+
+        let reactions = match response {
+            Ok(SlackApiReactionsGetResponse::Message(response)) => {
+                response.message.content.reactions.unwrap_or_default()
+            }
+            Ok(SlackApiReactionsGetResponse::File(_)) => return,
+            Err(error) => {
+                warn!(%error, "Couldn't fetch reactions on the original message");
+                return;
+            }
+        };
+
+        for reaction in reactions {
+            let result = slack_error::traced(
+                "reactions.add",
+                session.reactions_add(&SlackApiReactionsAddRequest::new(
+                    channel_id.clone(),
+                    reaction.name.clone(),
+                    to.clone(),
+                )),
+            )
+            .await;
+
+            match result {
+                Ok(_) => debug!(reaction = %reaction.name.0, "Mirrored reaction"),
+                Err(SlackClientError::ApiError(error)) if error.code == "already_reacted" => {}
+                Err(error) => {
+                    warn!(%error, reaction = %reaction.name.0, "Couldn't mirror reaction");
+                }
+            }
+        }
+    }
+}
+
+/// Treats deleting a message that's already gone as a success.
+///
+/// This happens when a delete is retried after the first attempt went through, or someone else deleted it first.
+fn ignore_missing<T>(
+    result: std::result::Result<T, SlackClientError>,
+) -> std::result::Result<(), SlackClientError> {
+    match result {
+        Err(SlackClientError::ApiError(error)) if error.code == "message_not_found" => Ok(()),
+        result => result.map(|_| ()),
+    }
+}
+
+/// Extra behaviour for a [`ProxyRequest`]
+#[derive(Debug, Clone, Default)]
+pub struct ProxyOptions {
+    /// The message to delete once the proxied message is posted
+    pub replaces: Option<Original>,
+    /// Leave the message in [`replaces`](Self::replaces) in the channel, for the owner to delete once they've
+    /// checked the proxied message. It's still logged as the original, so edits to it update the proxied message
+    pub hold_back: bool,
+    /// Whether to ask Slack to preview links in the message. Otherwise only media links are previewed
+    pub unfurl: bool,
+    /// The [content hash](models::message::content_hash) of the message being proxied, logged to spot duplicates
+    pub content_hash: Option<String>,
+}
+
+/// How far sending a [`ProxyRequest`] got after the message was posted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    /// The proxied message is in the channel, but not logged yet
+    Posted,
+    /// The proxied message is logged, but the original (if any) is still in the channel
+    Logged,
+    /// The original is deleted, so the message only shows up once
+    Replaced,
+    /// The original will be deleted in the background, after the system's [delete delay](models::System::delete_delay)
+    Deferred,
+}
+
+/// A message to post under a system's profile
+#[derive(Debug)]
+pub struct ProxyRequest {
+    pub channel_id: SlackChannelId,
+    /// The content to post. Any files on the content are ignored; use `files` instead
+    pub content: SlackMessageContent,
+    pub identity: Identity,
+    /// The thread to post in, if any
+    pub thread_ts: Option<SlackTs>,
+    /// Files to attach to the message. Images are shown in the message; anything else is shared in its thread
+    pub files: Vec<SlackFile>,
+    pub options: ProxyOptions,
+}
+
+impl ProxyRequest {
+    /// Posts the message, logs it, and deletes the message it replaces (if any).
+    ///
+    /// Replacing a message is recorded in the outbox first, so that if the bot stops before the original is deleted,
+    /// [`recover`] can finish the job on the next startup. If the original can't be deleted, the proxied message is
+    /// deleted again instead.
+    ///
+    /// Returns the timestamp of the posted message.
+    #[tracing::instrument(skip(self, client, system, db), fields(system_id = %system.id, channel_id = %self.channel_id, slack_error))]
+    pub async fn send(
+        self,
+        client: &SlackHyperClient,
+        system: &models::System,
+        db: &SqlitePool,
+    ) -> Result<SlackTs, Error> {
+        let result = self.send_inner(client, system, db).await;
+
+        if let Err(error) = &result {
+            slack_error::record(error);
+        }
+
+        metrics::record(outcome(&result));
+        result
+    }
+
+    async fn send_inner(
+        self,
+        client: &SlackHyperClient,
+        system: &models::System,
+        db: &SqlitePool,
+    ) -> Result<SlackTs, Error> {
+        info!("Proxying message");
+        let bot_session = client.open_session(&BOT_TOKEN);
+
+        let mut content = self.content;
+        content.files = None;
+
+        // Tags the message, so if posting fails partway we can check whether it went through before retrying
+        let attempt = attempt_key(system.id);
+        content.metadata = Some(
+            SlackMessageMetadata::new(ATTEMPT_METADATA_EVENT.to_string()).with_event_payload(
+                std::collections::HashMap::from([(
+                    ATTEMPT_METADATA_KEY.to_string(),
+                    attempt.clone(),
+                )]),
+            ),
+        );
+
+        let (image_blocks, follow_ups) = split_files(self.files);
+
+        let request = PostMessageRequest::new(
+            SlackApiChatPostMessageRequest::new(self.channel_id.clone(), content)
+                .opt_thread_ts(self.thread_ts.clone())
+                .opt_unfurl_links(self.options.unfurl.then_some(true))
+                .with_username(self.identity.username(system))
+                .opt_icon_url(self.identity.icon_url()),
+            image_blocks,
+        );
+
+        let pending = match &self.options.replaces {
+            Some(original) if !self.options.hold_back => Some(
+                PendingProxy::start(
+                    system.id,
+                    &self.channel_id,
+                    original.ts(),
+                    original.kind(),
+                    db,
+                )
+                .await
+                .change_context(Error::Outbox)?,
+            ),
+            _ => None,
+        };
+
+        let posted_after = now_ts();
+
+        let ts = match post(&bot_session, &request).await {
+            Err(error) if slack_error::is_transient(&error) => {
+                warn!(%error, "Transient error while posting. Checking if it went through before retrying");
+                slack_error::backoff(0).await;
+
+                match find_attempt(
+                    &bot_session,
+                    &self.channel_id,
+                    self.thread_ts.as_ref(),
+                    &posted_after,
+                    &attempt,
+                )
+                .await
+                {
+                    Some(ts) => {
+                        debug!("Message was posted despite the error");
+                        Ok(ts)
+                    }
+                    None => post(&bot_session, &request).await,
+                }
+            }
+            result => result,
+        };
+
+        let ts = match ts {
+            Ok(ts) => ts,
+            Err(error) => {
+                // Nothing was posted, so there's nothing to recover
+                if let Some(pending) = pending
+                    && let Err(error) = pending.complete(db).await
+                {
+                    warn!(?error, "Failed to clear outbox entry for failed proxy");
+                }
+
+                return Err(error).change_context(Error::PostMessage);
+            }
+        };
+
+        if let Some(pending) = pending {
+            pending
+                .mark_posted(&ts, db)
+                .await
+                .change_context(Error::Outbox)?;
+        }
+
+        let mut stage = Stage::Posted;
+        let finished = loop {
+            stage = match stage {
+                Stage::Posted => match self.log(system, &ts, db).await {
+                    Ok(()) => Stage::Logged,
+                    Err(error) => break Err(error),
+                },
+                Stage::Logged => match (&self.options.replaces, pending) {
+                    (Some(original), Some(pending)) => {
+                        if system.delete_delay().is_zero() {
+                            match replace(client, system, &self.channel_id, original, &ts, db).await
+                            {
+                                Ok(()) => Stage::Replaced,
+                                Err(error) => break Err(error),
+                            }
+                        } else {
+                            replace_later(
+                                client.clone(),
+                                system.clone(),
+                                self.channel_id.clone(),
+                                original.clone(),
+                                ts.clone(),
+                                pending,
+                                db.clone(),
+                            );
+                            Stage::Deferred
+                        }
+                    }
+                    _ => Stage::Replaced,
+                },
+                Stage::Replaced | Stage::Deferred => break Ok(()),
+            };
+        };
+
+        if let Err(error) = finished {
+            // Without an original there's nothing to show twice, so the posted message is kept
+            if let Some(pending) = pending {
+                warn!(
+                    ?error,
+                    ?stage,
+                    "Failed to replace original message. Rolling back"
+                );
+                roll_back(client, system, &self.channel_id, ts, pending, db).await;
+            }
+
+            return Err(error);
+        }
+
+        // A deferred replacement clears the outbox entry itself once the original is deleted
+        if let Some(pending) = pending
+            && stage != Stage::Deferred
+        {
+            pending.complete(db).await.change_context(Error::Outbox)?;
+        }
+
+        // Replies to a thread keep their files in that thread, otherwise they start one under the proxied message
+        let thread_ts = self.thread_ts.unwrap_or_else(|| ts.clone());
+
+        for file in follow_ups {
+            // The message is already posted, so a file that can't be shared shouldn't fail the proxy
+            if let Err(error) = follow_up(
+                client,
+                system,
+                &self.channel_id,
+                &thread_ts,
+                &self.identity,
+                file,
+            )
+            .await
+            {
+                warn!(
+                    ?error,
+                    "Failed to share file in the proxied message's thread"
+                );
+            }
+        }
+
+        Ok(ts)
+    }
+
+    async fn log(
+        &self,
+        system: &models::System,
+        ts: &SlackTs,
+        db: &SqlitePool,
+    ) -> Result<(), Error> {
+        let original_ts = match &self.options.replaces {
+            Some(Original::User(ts)) => Some(ts),
+            _ => None,
+        };
+
+        models::MessageLog::insert(
+            self.identity.member_id(),
+            system.id,
+            ts,
+            Some(&self.channel_id),
+            original_ts,
+            self.options.content_hash.as_deref(),
+            db,
+        )
+        .await
+        .change_context(Error::MessageLog)
+        .map(|_| ())
+    }
+}
+
+/// Carries a message's reactions over to the proxied message at `ts`, then deletes the original
+async fn replace(
+    client: &SlackHyperClient,
+    system: &models::System,
+    channel_id: &SlackChannelId,
+    original: &Original,
+    ts: &SlackTs,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    original.mirror_reactions(client, channel_id, ts).await;
+    original
+        .delete(client, system, channel_id.clone(), db)
+        .await
+}
+
+/// [Replaces](replace) the original after the system's [delete delay](models::System::delete_delay), without holding up
+/// the event that's being proxied.
+///
+/// The outbox entry is only cleared once the original is deleted, so if the bot stops first, [`recover`] deletes it on
+/// the next startup. If the original can't be deleted, the proxied message is [rolled back](roll_back).
+fn replace_later(
+    client: SlackHyperClient,
+    system: models::System,
+    channel_id: SlackChannelId,
+    original: Original,
+    ts: SlackTs,
+    pending: outbox::Id<Trusted>,
+    db: SqlitePool,
+) {
+    let delay = system.delete_delay();
+    debug!(?delay, "Deleting original message after a delay");
+
+    tokio::spawn(
+        async move {
+            tokio::time::sleep(delay).await;
+
+            match replace(&client, &system, &channel_id, &original, &ts, &db).await {
+                Ok(()) => {
+                    if let Err(error) = pending.complete(&db).await {
+                        warn!(?error, "Failed to clear outbox entry for delayed delete");
+                    }
+                }
+                Err(error) => {
+                    warn!(
+                        ?error,
+                        "Failed to delete original message after a delay. Rolling back"
+                    );
+                    roll_back(&client, &system, &channel_id, ts, pending, &db).await;
+                }
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+/// Deletes a proxied message whose original couldn't be deleted, so the channel isn't left with the message twice.
+///
+/// If the proxied message can't be deleted either, the outbox entry is kept for [`recover`] to try again on the
+/// next startup.
+async fn roll_back(
+    client: &SlackHyperClient,
+    system: &models::System,
+    channel_id: &SlackChannelId,
+    ts: SlackTs,
+    pending: outbox::Id<Trusted>,
+    db: &SqlitePool,
+) {
+    if let Err(error) = Original::Proxied(ts)
+        .delete(client, system, channel_id.clone(), db)
+        .await
+    {
+        warn!(
+            ?error,
+            "Failed to delete proxied message. Leaving it for recovery"
+        );
+        metrics::record_partial_failure(Cleanup::Deferred);
+        return;
+    }
+
+    metrics::record_partial_failure(Cleanup::RolledBack);
+
+    if let Err(error) = pending.complete(db).await {
+        warn!(?error, "Failed to clear outbox entry for rolled back proxy");
+    }
+}
+
+/// The metadata event type proxied messages are tagged with
+const ATTEMPT_METADATA_EVENT: &str = "plura_proxy";
+/// The metadata field holding a proxy attempt's key
+const ATTEMPT_METADATA_KEY: &str = "attempt";
+
+/// A key unique to this attempt at proxying a message
+fn attempt_key(system_id: models::system::Id<Trusted>) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+
+    format!("{system_id}-{nanos}")
+}
+
+/// The current time as a Slack timestamp
+fn now_ts() -> SlackTs {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    SlackTs(format!("{}.{:06}", now.as_secs(), now.subsec_micros()))
+}
+
+async fn post(
+    session: &SlackClientSession<'_, SlackClientHyperHttpsConnector>,
+    request: &PostMessageRequest,
+) -> std::result::Result<SlackTs, SlackClientError> {
+    slack_error::traced(
+        "chat.postMessage",
+        session
+            .http_session_api
+            .http_post::<_, SlackApiChatPostMessageResponse>(
+                "chat.postMessage",
+                request,
+                Some(&CHAT_POST_MESSAGE_SPECIAL_LIMIT_RATE_CTL),
+            ),
+    )
+    .await
+    .map(|response| response.ts)
+}
+
+/// Looks for a message posted by a proxy attempt since `since`, returning its timestamp if it went through
+async fn find_attempt(
+    session: &SlackClientSession<'_, SlackClientHyperHttpsConnector>,
+    channel_id: &SlackChannelId,
+    thread_ts: Option<&SlackTs>,
+    since: &SlackTs,
+    attempt: &str,
+) -> Option<SlackTs> {
+    let messages = match thread_ts {
+        Some(thread_ts) => slack_error::traced(
+            "conversations.replies",
+            session.conversations_replies(
+                &SlackApiConversationsRepliesRequest::new(channel_id.clone(), thread_ts.clone())
+                    .with_oldest(since.clone()),
+            ),
+        )
+        .await
+        .map(|response| response.messages),
+        None => slack_error::traced(
+            "conversations.history",
+            session.conversations_history(
+                &SlackApiConversationsHistoryRequest::new()
+                    .with_channel(channel_id.clone())
+                    .with_oldest(since.clone())
+                    .with_include_all_metadata(true),
+            ),
+        )
+        .await
+        .map(|response| response.messages),
+    };
+
+    let messages = match messages {
+        Ok(messages) => messages,
+        Err(error) => {
+            warn!(%error, "Couldn't check if the message was posted");
+            return None;
+        }
+    };
+
+    messages
+        .into_iter()
+        .find(|message| {
+            message
+                .content
+                .metadata
+                .as_ref()
+                .filter(|metadata| metadata.event_type == ATTEMPT_METADATA_EVENT)
+                .and_then(|metadata| metadata.event_payload.as_ref())
+                .and_then(|payload| payload.get(ATTEMPT_METADATA_KEY))
+                .is_some_and(|key| key == attempt)
+        })
+        .map(|message| message.origin.ts)
+}
+
+/// Updates a message the bot already proxied, e.g. after the user edited the message it was proxied from.
+///
+/// The message keeps the profile it was posted under, since Slack doesn't let a message's name or icon change.
+/// Files that aren't images were shared in the message's thread when it was first proxied, so they're left alone.
+#[tracing::instrument(skip(client, content, files), fields(slack_error))]
+pub async fn update(
+    client: &SlackHyperClient,
+    channel_id: &SlackChannelId,
+    ts: &SlackTs,
+    mut content: SlackMessageContent,
+    files: Vec<SlackFile>,
+) -> Result<(), Error> {
+    info!("Updating proxied message");
+    content.files = None;
+
+    let (image_blocks, _) = split_files(files);
+
+    let request = UpdateMessageRequest::new(
+        SlackApiChatUpdateRequest::new(channel_id.clone(), content, ts.clone()),
+        image_blocks,
+    );
+
+    let session = client.open_session(&BOT_TOKEN);
+
+    // Updating to the same content twice is harmless, so this can be retried
+    let res: std::result::Result<SlackApiChatUpdateResponse, _> =
+        slack_error::retry("chat.update", || {
+            session.http_session_api.http_post(
+                "chat.update",
+                &request,
+                Some(&SLACK_TIER3_METHOD_CONFIG),
+            )
+        })
+        .await;
+
+    res.change_context(Error::UpdateMessage)
+        .inspect_err(|error| {
+            slack_error::record(error);
+        })
+        .map(|_| ())
+}
+
+/// Shares a file from the original message in the proxied message's thread.
+///
+/// The file is downloaded with the system's token and re-uploaded by the bot. Files too large to re-upload
+/// are linked instead.
+#[tracing::instrument(skip(client, system, identity, file), fields(file_id = %file.id))]
+async fn follow_up(
+    client: &SlackHyperClient,
+    system: &models::System,
+    channel_id: &SlackChannelId,
+    thread_ts: &SlackTs,
+    identity: &Identity,
+    file: SlackFile,
+) -> Result<(), Error> {
+    let name = file.name.clone().unwrap_or_else(|| file.id.to_string());
+
+    let url = file
+        .url_private_download
+        .clone()
+        .ok_or_else(|| report!(Error::FollowUp))
+        .attach_printable("File has no download URL")?;
+
+    let response = reqwest::Client::new()
+        .get(url)
+        .bearer_auth(system.slack_oauth_token.expose())
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .change_context(Error::FollowUp)?;
+
+    if response
+        .content_length()
+        .is_none_or(|size| size > MAX_FOLLOW_UP_SIZE)
+    {
+        debug!("File is too large to re-upload, linking it instead");
+
+        let permalink = file
+            .permalink
+            .ok_or_else(|| report!(Error::FollowUp))
+            .attach_printable("File has no permalink")?;
+
+        slack_error::traced(
+            "chat.postMessage",
+            client.open_session(&BOT_TOKEN).chat_post_message(
+                &SlackApiChatPostMessageRequest::new(
+                    channel_id.clone(),
+                    SlackMessageContent::new().with_text(format!("File: <{permalink}|{name}>")),
+                )
+                .with_thread_ts(thread_ts.clone())
+                .with_username(identity.username(system))
+                .opt_icon_url(identity.icon_url()),
+            ),
+        )
+        .await
+        .change_context(Error::FollowUp)?;
+
+        return Ok(());
+    }
+
+    let data = response.bytes().await.change_context(Error::FollowUp)?;
+    let content_type = file.mimetype.map_or_else(
+        || "application/octet-stream".to_string(),
+        |mimetype| mimetype.0,
+    );
+
+    upload::share(
+        client,
+        channel_id.clone(),
+        Some(thread_ts.clone()),
+        upload::File {
+            filename: &name,
+            content_type: &content_type,
+            data: data.to_vec(),
+        },
+        None,
+    )
+    .await
+    .change_context(Error::FollowUp)
+}
+
+/// Slack errors from posting that mean the bot isn't allowed to post in the channel at all
+const CHANNEL_BLOCKED_CODES: &[&str] = &[
+    "channel_not_found",
+    "not_in_channel",
+    "is_archived",
+    "restricted_action",
+    "restricted_action_read_only_channel",
+    "restricted_action_thread_only_channel",
+    "team_access_not_granted",
+];
+
+/// Classifies the result of sending a proxy request for metrics
+fn outcome<T>(result: &Result<T, Error>) -> Outcome {
+    let Err(error) = result else {
+        return Outcome::Success;
+    };
+
+    match error.current_context() {
+        Error::PostMessage => match slack_error::code(error) {
+            Some(code) if CHANNEL_BLOCKED_CODES.contains(&code) => Outcome::ChannelBlocked,
+            _ => Outcome::PostFailed,
+        },
+        Error::UpdateMessage => Outcome::PostFailed,
+        Error::DeleteMessage => Outcome::DeleteFailed,
+        Error::MessageLog | Error::Outbox | Error::FollowUp => Outcome::Internal,
+    }
+}
+
+/// Resolves proxy operations that were interrupted before the original message was deleted.
+///
+/// Operations that never posted are dropped, as the original message is untouched.
+/// Otherwise the delete is retried; if the original can't be deleted, the proxied message is deleted instead
+/// so the channel isn't left with the message twice.
+#[tracing::instrument(skip_all)]
+pub async fn recover(client: &SlackHyperClient, db: &SqlitePool) -> Result<(), Error> {
+    let pending = PendingProxy::fetch_all(db)
+        .await
+        .change_context(Error::Outbox)?;
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    info!(
+        count = pending.len(),
+        "Recovering interrupted proxy operations"
+    );
+
+    for entry in pending {
+        if let Err(error) = recover_one(client, &entry, db).await {
+            warn!(?error, id = %entry.id, "Failed to recover proxy operation. Will retry on next startup");
+            continue;
+        }
+
+        entry.id.complete(db).await.change_context(Error::Outbox)?;
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(client, db))]
+async fn recover_one(
+    client: &SlackHyperClient,
+    entry: &PendingProxy,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let Some(posted_ts) = entry.posted_ts.clone().map(SlackTs) else {
+        debug!("Proxied message was never posted. Dropping");
+        return Ok(());
+    };
+
+    let system = entry
+        .system_id
+        .fetch(db)
+        .await
+        .change_context(Error::Outbox)?;
+
+    let original = Original::new(entry.original_kind, entry.original_ts.clone());
+
+    let Err(error) = original
+        .delete(client, &system, entry.channel_id.clone(), db)
+        .await
+    else {
+        debug!("Deleted original message");
+        return Ok(());
+    };
+
+    if let Some(SlackClientError::ApiError(api_error)) = error.downcast_ref::<SlackClientError>()
+        && api_error.code == "message_not_found"
+    {
+        debug!("Original message is already gone");
+        return Ok(());
+    }
+
+    warn!(
+        ?error,
+        "Couldn't delete original message. Deleting the proxied message instead"
+    );
+
+    Original::Proxied(posted_ts)
+        .delete(client, &system, entry.channel_id.clone(), db)
+        .await
+}
+
+/// Splits a message's files into image blocks to show in the message, and files to share in its thread.
+///
+/// Images can't be represented by slack-morphism's image block yet, so they are sent with [`PostMessageRequest`].
+/// Videos and other files can't be embedded in a message at all.
+fn split_files(files: Vec<SlackFile>) -> (Vec<Block>, Vec<SlackFile>) {
+    let mut image_blocks = Vec::new();
+    let mut follow_ups = Vec::new();
+
+    for file in files {
+        match file.filetype.as_ref().map(|f| f.0.as_str()) {
+            Some("png" | "jpg" | "jpeg" | "gif" | "webp") => {
+                // https://github.com/abdolence/slack-morphism-rust/issues/320
+                // Some(SlackImageBlock::new(file.permalink?, String::new()).into())
+                image_blocks.push(SlackFileImageBlock::new(file.id).into());
+            }
+            typ => {
+                debug!(?typ, "Can't embed file, sharing it in the thread");
+                follow_ups.push(file);
+            }
+        }
+    }
+
+    (image_blocks, follow_ups)
+}
@@ -0,0 +1,119 @@
+//! Rate limiting for the HTTP API, so one misbehaving script can't slow the bot down for everyone.
+//!
+//! Each system gets `API_RATE_LIMIT` requests per [`WINDOW`]. Counts are kept in memory, so they reset when the bot
+//! restarts.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use tracing::warn;
+
+use crate::env;
+
+/// How many requests a system can make per [`WINDOW`] when `API_RATE_LIMIT` isn't set
+const DEFAULT_LIMIT: u32 = 60;
+/// How long a system's request count lasts before it's reset
+pub const WINDOW: Duration = Duration::from_secs(60);
+
+/// The limiter for API requests, keyed by system ID. [`None`] if `API_RATE_LIMIT` is 0
+pub static API: LazyLock<Option<Limiter<i64>>> = LazyLock::new(|| {
+    let limit = env::api_rate_limit().map_or(DEFAULT_LIMIT, |value| {
+        value.trim().parse().unwrap_or_else(|_| {
+            warn!(value, "Ignoring API_RATE_LIMIT, as it isn't a number");
+            DEFAULT_LIMIT
+        })
+    });
+
+    (limit != 0).then(|| Limiter::new(limit, WINDOW))
+});
+
+/// Counts requests per key in fixed windows
+pub struct Limiter<K> {
+    limit: u32,
+    window: Duration,
+    /// When each key's current window started, and how many requests it has made in it
+    windows: Mutex<HashMap<K, (Instant, u32)>>,
+}
+
+/// Where a key stands after making a request
+#[derive(Debug, PartialEq, Eq)]
+pub struct Usage {
+    /// Whether the request is within the limit
+    pub allowed: bool,
+    /// How many more requests can be made in the current window
+    pub remaining: u32,
+    /// How long until the current window ends
+    pub reset: Duration,
+}
+
+impl<K: Eq + Hash> Limiter<K> {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            windows: Mutex::default(),
+        }
+    }
+
+    pub const fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Counts a request from `key`.
+    ///
+    /// Windows that have ended are forgotten first, so keys that stop making requests don't pile up.
+    pub fn hit(&self, key: K) -> Usage {
+        let mut windows = self
+            .windows
+            .lock()
+            .expect("rate limiter lock to not be poisoned");
+        windows.retain(|_, (started, _)| started.elapsed() < self.window);
+
+        let (started, count) = windows.entry(key).or_insert((Instant::now(), 0));
+        *count = count.saturating_add(1);
+
+        Usage {
+            allowed: *count <= self.limit,
+            remaining: self.limit.saturating_sub(*count),
+            reset: self.window.saturating_sub(started.elapsed()),
+        }
+    }
+
+    /// How many keys are being counted
+    #[cfg(test)]
+    fn tracked(&self) -> usize {
+        self.windows
+            .lock()
+            .expect("rate limiter lock to not be poisoned")
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_each_key_separately() {
+        let limiter = Limiter::new(2, Duration::from_secs(60));
+
+        assert!(limiter.hit(1).allowed);
+        assert_eq!(limiter.hit(1).remaining, 0);
+        assert!(!limiter.hit(1).allowed);
+        assert!(limiter.hit(2).allowed);
+    }
+
+    #[test]
+    fn forgets_ended_windows() {
+        let limiter = Limiter::new(1, Duration::ZERO);
+
+        assert!(limiter.hit(1).allowed);
+        assert!(limiter.hit(2).allowed);
+        assert!(limiter.hit(1).allowed);
+        assert_eq!(limiter.tracked(), 1);
+    }
+}
@@ -0,0 +1,78 @@
+//! Reminding systems to log switches.
+//!
+//! Systems that use switch logging as a journal can turn on reminders with `/system remind`. A background job
+//! regularly looks for systems that haven't switched within their interval, and DMs the owner. After a reminder, the
+//! next one waits another full interval, so a system that doesn't switch for a while is reminded once per interval.
+
+use std::{sync::Arc, time::Duration};
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use crate::{
+    models::Switch,
+    notify::{self, Notification},
+};
+
+/// How often to look for systems due a reminder
+const CHECK_EVERY: Duration = Duration::from_secs(10 * 60);
+/// The most reminders to send per check. Anyone left over is reminded on the next one
+const BATCH_SIZE: u32 = 100;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+}
+
+/// Starts the background job that sends switch reminders
+pub fn spawn(client: Arc<SlackHyperClient>, db: SqlitePool) {
+    tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(CHECK_EVERY);
+
+            loop {
+                interval.tick().await;
+
+                match remind(&client, &db).await {
+                    Ok(0) => {}
+                    Ok(reminded) => info!(reminded, "Sent switch reminders"),
+                    Err(error) => warn!(?error, "Failed to send switch reminders"),
+                }
+            }
+        }
+        .instrument(info_span!("switch_reminders")),
+    );
+}
+
+/// Reminds a batch of systems that are due, returning how many were reminded
+async fn remind(client: &SlackHyperClient, db: &SqlitePool) -> Result<usize, Error> {
+    let due = Switch::due_reminders(BATCH_SIZE, db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    for reminder in &due {
+        debug!(system_id = %reminder.system_id, "Reminding system to log a switch");
+
+        // Marked first, so a system whose DMs keep failing isn't retried on every check
+        reminder
+            .system_id
+            .mark_switch_reminded(db)
+            .await
+            .change_context(Error::Sqlx)?;
+
+        notify::send(
+            client,
+            db,
+            &reminder.owner_id,
+            Notification::SwitchReminder {
+                hours: reminder.hours,
+            },
+        )
+        .await;
+    }
+
+    Ok(due.len())
+}
@@ -0,0 +1,506 @@
+//! Rendering of system data into Slack blocks.
+//!
+//! Renderers take a [`Viewer`] so the same data can be shown to the owner, managers, admins and everyone else
+//! without each command or interaction deciding what to hide on its own.
+
+pub mod json;
+pub mod mrkdwn;
+pub mod reference;
+
+use slack_morphism::prelude::*;
+
+use crate::models::{
+    Alias, AuditLog, Member, MemberField, Switch, System, Trigger, message::MessageStats,
+    viewer::Viewer,
+};
+pub use reference::MemberReference;
+
+/// Flattens blocks into their text, one line per piece of text. Buttons, images and other interactive parts are dropped
+pub fn plain_text(blocks: &[SlackBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| serde_json::to_value(block).ok())
+        .filter(|block| block["type"] != "actions")
+        .flat_map(|block| {
+            // Looked up by name so sections keep their text before their fields
+            let text = block.get("text").cloned();
+            let rest = ["fields", "elements"]
+                .into_iter()
+                .filter_map(|key| {
+                    block
+                        .get(key)
+                        .and_then(serde_json::Value::as_array)
+                        .cloned()
+                })
+                .flatten();
+
+            text.into_iter().chain(rest).collect::<Vec<_>>()
+        })
+        .filter_map(|text| {
+            text.get("text")
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_owned)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The most options Slack allows in a select menu
+const MAX_SELECT_OPTIONS: usize = 100;
+/// The value of the base account option in [`member_picker`]
+pub const BASE_ACCOUNT_OPTION: &str = "base";
+
+/// A prompt with a menu of members, for commands run without saying which member.
+///
+/// Only members that are enabled are offered. `base_account` adds an option for the base account first,
+/// with the value [`BASE_ACCOUNT_OPTION`].
+pub fn member_picker(
+    prompt: &str,
+    action_id: &str,
+    members: &[Member],
+    base_account: bool,
+) -> Vec<SlackBlock> {
+    let base = base_account.then(|| {
+        SlackBlockChoiceItem::<SlackBlockPlainTextOnly>::new(
+            pt!("Base account"),
+            BASE_ACCOUNT_OPTION.to_string(),
+        )
+    });
+
+    let options = base
+        .into_iter()
+        .chain(
+            members
+                .iter()
+                .filter(|member| member.enabled)
+                .map(|member| {
+                    SlackBlockChoiceItem::<SlackBlockPlainTextOnly>::new(
+                        MemberReference::new(member)
+                            .with_full_name()
+                            .with_id(true)
+                            .plain()
+                            .into(),
+                        member.id.to_string(),
+                    )
+                }),
+        )
+        .take(MAX_SELECT_OPTIONS)
+        .collect();
+
+    slack_blocks![some_into(
+        SlackSectionBlock::new()
+            .with_text(md!("{}", prompt))
+            .with_accessory(
+                SlackBlockStaticSelectElement::new(action_id.into())
+                    .with_placeholder(pt!("Choose a member"))
+                    .with_options(options)
+                    .into()
+            )
+    )]
+}
+
+/// Renders a list of members, one entry per member with its section.
+///
+/// Disabled members are left out for viewers that can't see them.
+pub fn member_list(
+    members: Vec<Member>,
+    aliases: &[Alias],
+    viewer: Viewer,
+) -> Vec<Vec<SlackBlock>> {
+    members
+        .into_iter()
+        .filter(|member| member.enabled || viewer.sees_disabled())
+        .map(|member| {
+            let member_aliases = aliases
+                .iter()
+                .filter(|alias| alias.member_id == member.id)
+                .map(|alias| alias.alias.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            let fields = [
+                Some(md!("*Aliases*: {}", member_aliases))
+                    .filter(|_| viewer.sees_management_details() && !member_aliases.is_empty()),
+                Some(md!("*Disabled*")).filter(|_| !member.enabled),
+                Some(md!("*Not proxied*")).filter(|_| member.enabled && !member.proxy_enabled),
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+            // Slack rejects sections with an empty list of fields
+            let section = MemberReference::new(&member)
+                .with_full_name()
+                .with_id(viewer.sees_ids())
+                .section();
+
+            if fields.is_empty() {
+                vec![section.into()]
+            } else {
+                vec![section.with_fields(fields).into()]
+            }
+        })
+        .collect()
+}
+
+/// Renders the profile card of a single member, with their birthday and custom fields.
+///
+/// Due to the way the slack blocks are created, the member is moved.
+pub fn member_card(
+    member: Member,
+    fields: &[MemberField],
+    fronting: bool,
+    viewer: Viewer,
+) -> Vec<SlackBlock> {
+    let section = MemberReference::new(&member).section();
+    let name = MemberReference::new(&member).mrkdwn();
+    let description = member.description.unwrap_or_default();
+
+    let mut blocks = slack_blocks![
+        some_into(SlackHeaderBlock::new(member.full_name.into())),
+        some_into(SlackDividerBlock::new()),
+        some_into(section.with_text(md!(
+            "{}\n{}{}",
+            name,
+            member.pronouns.unwrap_or_default(),
+            member
+                .name_pronunciation
+                .map(|pronunciation| format!(" - {pronunciation}"))
+                .unwrap_or_default()
+        ))),
+        optionally_into(!description.trim().is_empty() => SlackSectionBlock::new().with_text(md!(description)))
+    ];
+
+    let fields = member
+        .birthday
+        .map(|date| md!("*Birthday*\n{}", birthday(&date)))
+        .into_iter()
+        .chain(
+            fields
+                .iter()
+                .map(|field| md!("*{}*\n{}", field.name, field.value)),
+        )
+        .collect::<Vec<SlackBlockText>>();
+
+    // Sections can only have 10 fields each
+    blocks.extend(
+        fields
+            .chunks(10)
+            .map(|fields| SlackSectionBlock::new().with_fields(fields.to_vec()).into()),
+    );
+
+    blocks.extend(slack_blocks![
+        optionally_into(fronting => SlackSectionBlock::new().with_text(md!("*Fronting*"))),
+        optionally_into(viewer.sees_ids() => SlackContextBlock::new(vec![md!("Member ID: {}", member.id)]))
+    ]);
+
+    blocks
+}
+
+/// A birthday as written in [`Member::birthday`], like "May 1, 2000", or "May 1" without a year
+fn birthday(birthday: &str) -> String {
+    let mut parts = birthday.split('-').rev();
+    let day = parts.next().and_then(|day| day.parse::<u8>().ok());
+    let month = parts
+        .next()
+        .and_then(|month| month.parse::<u8>().ok())
+        .and_then(|month| time::Month::try_from(month).ok());
+    let year = parts.next();
+
+    match (day, month, year) {
+        (Some(day), Some(month), Some(year)) => format!("{month} {day}, {year}"),
+        (Some(day), Some(month), None) => format!("{month} {day}"),
+        // Only valid birthdays are saved, so this shouldn't happen
+        _ => birthday.to_string(),
+    }
+}
+
+/// Renders an overview of a system: who it belongs to, how many members it has and who is fronting.
+///
+/// Fronting members are shown with [`member_card`], so they follow the same rules as the rest of the viewer's tier.
+/// `fronting` should have the primary fronter first.
+pub fn system_card(
+    system: &System,
+    members: &[Member],
+    fronting: Vec<Member>,
+    viewer: Viewer,
+) -> Vec<SlackBlock> {
+    let member_count = members
+        .iter()
+        .filter(|member| member.enabled || viewer.sees_disabled())
+        .count();
+
+    let mut blocks = slack_blocks![
+        some_into(SlackSectionBlock::new().with_text(md!(
+            "*System of {}*\n{} {}",
+            system.owner_id.to_slack_format(),
+            member_count,
+            if member_count == 1 { "member" } else { "members" }
+        ))),
+        optionally_into(viewer.sees_ids() => SlackContextBlock::new(vec![md!("System ID: {}", system.id)]))
+    ];
+
+    if fronting.is_empty() {
+        blocks.push(
+            SlackSectionBlock::new()
+                .with_text(md!("No fronting member"))
+                .into(),
+        );
+    }
+
+    for member in fronting {
+        // Custom fields are left out to keep the card short
+        blocks.extend(member_card(member, &[], true, viewer));
+    }
+
+    blocks
+}
+
+/// How many of each thing a system has, for [`system_details`]
+pub struct SystemCounts {
+    pub triggers: usize,
+    pub aliases: usize,
+    pub managers: usize,
+}
+
+/// Renders the details of a system only its owner sees: when it was made, what it has, and how it's set up.
+///
+/// `token_health` is a line describing whether the system's Slack authorization works.
+pub fn system_details(
+    system: &System,
+    members: &[Member],
+    counts: &SystemCounts,
+    token_health: &str,
+) -> Vec<SlackBlock> {
+    let created_at = system.created_at.assume_utc();
+    let enabled = members.iter().filter(|member| member.enabled).count();
+    let disabled = members.len() - enabled;
+
+    slack_blocks![
+        some_into(SlackDividerBlock::new()),
+        some_into(SlackSectionBlock::new().with_fields(vec![
+            md!(
+                "*Created*\n<!date^{}^{{date_long}}|{}>",
+                created_at.unix_timestamp(),
+                created_at.date()
+            ),
+            md!(
+                "*Members*\n{enabled} enabled{}",
+                if disabled > 0 {
+                    format!(", {disabled} disabled")
+                } else {
+                    String::new()
+                }
+            ),
+            md!("*Triggers*\n{}", counts.triggers),
+            md!("*Aliases*\n{}", counts.aliases),
+            md!("*Managers*\n{}", counts.managers),
+            md!("*Autoproxy*\n{}", system.autoproxy_mode),
+        ])),
+        some_into(SlackSectionBlock::new().with_text(md!("*Integrations*\n{}", token_health)))
+    ]
+}
+
+/// Switches per section, to stay under Slack's limit on section text length
+const SWITCHES_PER_SECTION: usize = 10;
+
+/// Renders a system's switch history, one line per switch.
+///
+/// `members` should include disabled members, so switches to them still show a name.
+pub fn switch_history(switches: &[Switch], members: &[Member]) -> Vec<SlackBlock> {
+    let lines = switches
+        .iter()
+        .map(|switch| {
+            let who = switch
+                .member_id
+                .and_then(|member_id| members.iter().find(|member| member.id == member_id))
+                .map_or_else(
+                    || "Base account".to_string(),
+                    |member| MemberReference::new(member).mrkdwn(),
+                );
+
+            let created_at = switch.created_at.assume_utc();
+
+            // Slack shows the date in the viewer's own timezone
+            format!(
+                "• <!date^{}^{{date_short_pretty}} at {{time}}|{} {:02}:{:02} UTC>: {who}",
+                created_at.unix_timestamp(),
+                created_at.date(),
+                created_at.hour(),
+                created_at.minute()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    std::iter::once(SlackHeaderBlock::new("Switch history".into()).into())
+        .chain(lines.chunks(SWITCHES_PER_SECTION).map(|chunk| {
+            SlackSectionBlock::new()
+                .with_text(md!("{}", chunk.join("\n")))
+                .into()
+        }))
+        .collect()
+}
+
+/// Members per section in message stats, to stay under Slack's limit on section text length
+const STATS_PER_SECTION: usize = 10;
+
+/// Renders how many messages were proxied as each member, one line per member.
+///
+/// `members` should include disabled members, so their messages still show a name.
+pub fn message_stats(stats: &[MessageStats], members: &[Member]) -> Vec<SlackBlock> {
+    let lines = stats
+        .iter()
+        .map(|stats| {
+            let who = stats.member_id.map_or_else(
+                || "Anonymous".to_string(),
+                |member_id| {
+                    members
+                        .iter()
+                        .find(|member| member.id == member_id)
+                        .map_or_else(
+                            || "Deleted member".to_string(),
+                            |member| MemberReference::new(member).mrkdwn(),
+                        )
+                },
+            );
+
+            format!(
+                "• {who}: *{}* today, *{}* this week, *{}* this month, *{}* in total",
+                stats.day, stats.week, stats.month, stats.all_time
+            )
+        })
+        .collect::<Vec<_>>();
+
+    std::iter::once(SlackHeaderBlock::new("Message stats".into()).into())
+        .chain(lines.chunks(STATS_PER_SECTION).map(|chunk| {
+            SlackSectionBlock::new()
+                .with_text(md!("{}", chunk.join("\n")))
+                .into()
+        }))
+        .chain(std::iter::once(
+            SlackContextBlock::new(vec![md!(
+                "Today, this week and this month are the past 24 hours, 7 days and 30 days."
+            )])
+            .into(),
+        ))
+        .collect()
+}
+
+/// Lines per section when inspecting a system, to stay under Slack's limit on section text length
+const INSPECT_LINES_PER_SECTION: usize = 10;
+/// The most members or triggers listed when inspecting a system, so the message stays under Slack's 50 block limit
+const INSPECT_MAX_LINES: usize = 100;
+
+/// Renders everything a moderator can see about a system: its members, triggers and recent audit log.
+///
+/// This is read-only, so nothing in it can be acted on. `members` should include disabled members.
+pub fn inspection(
+    system: &System,
+    members: &[Member],
+    triggers: &[Trigger],
+    audit_logs: &[AuditLog],
+) -> Vec<SlackBlock> {
+    let name = |member_id| {
+        members
+            .iter()
+            .find(|member: &&Member| member.id == member_id)
+            .map_or_else(
+                || "Deleted member".to_string(),
+                |member| MemberReference::new(member).mrkdwn(),
+            )
+    };
+
+    let member_lines = members
+        .iter()
+        .map(|member| {
+            let status = match (member.enabled, member.proxy_enabled) {
+                (false, _) => " _(disabled)_",
+                (true, false) => " _(not proxied)_",
+                (true, true) => "",
+            };
+
+            format!(
+                "• {} ({}){status}",
+                MemberReference::new(member).with_full_name().mrkdwn(),
+                member.id
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let trigger_lines = triggers
+        .iter()
+        .map(|trigger| {
+            format!(
+                "• {} ({}): {} `{}`",
+                name(trigger.member_id),
+                trigger.id,
+                trigger.typ,
+                trigger.text
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let audit_lines = audit_logs
+        .iter()
+        .map(|log| {
+            let created_at = log.created_at.assume_utc();
+            let details = log
+                .details
+                .as_deref()
+                .map(|details| format!(" ({details})"))
+                .unwrap_or_default();
+
+            format!(
+                "• <!date^{}^{{date_short_pretty}} at {{time}}|{} {:02}:{:02} UTC>: {} {}{details}",
+                created_at.unix_timestamp(),
+                created_at.date(),
+                created_at.hour(),
+                created_at.minute(),
+                log.actor_id.to_slack_format(),
+                log.action.to_string().to_lowercase()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut blocks = slack_blocks![
+        some_into(SlackSectionBlock::new().with_text(md!(
+            "*System of {}*\nSystem ID: {}",
+            system.owner_id.to_slack_format(),
+            system.id
+        ))),
+        some_into(SlackContextBlock::new(vec![md!(
+            "You're inspecting this system as a moderator. Only you can see this, and nothing here can be changed."
+        )]))
+    ];
+
+    for (title, lines, empty) in [
+        ("Members", member_lines, "No members"),
+        ("Triggers", trigger_lines, "No triggers"),
+        ("Recent activity", audit_lines, "Nothing recorded"),
+    ] {
+        blocks.push(SlackHeaderBlock::new(title.into()).into());
+
+        if lines.is_empty() {
+            blocks.push(SlackSectionBlock::new().with_text(md!("{}", empty)).into());
+            continue;
+        }
+
+        let hidden = lines.len().saturating_sub(INSPECT_MAX_LINES);
+
+        blocks.extend(
+            lines[..lines.len() - hidden]
+                .chunks(INSPECT_LINES_PER_SECTION)
+                .map(|chunk| {
+                    SlackSectionBlock::new()
+                        .with_text(md!("{}", chunk.join("\n")))
+                        .into()
+                }),
+        );
+
+        if hidden > 0 {
+            blocks.push(SlackContextBlock::new(vec![md!("...and {} more", hidden)]).into());
+        }
+    }
+
+    blocks
+}
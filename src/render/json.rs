@@ -0,0 +1,121 @@
+//! JSON versions of what list and info commands show, for `--format json`.
+//!
+//! These follow the same [`Viewer`] rules as the block renderers, so scripting against the bot doesn't reveal anything
+//! the blocks would have hidden.
+
+use serde_json::{Value, json};
+
+use crate::models::{Alias, Member, MemberField, System, Trigger, viewer::Viewer};
+
+/// A member, with the details every viewer can see
+fn member(member: &Member, viewer: Viewer) -> Value {
+    let mut value = json!({
+        "display_name": member.display_name,
+        "full_name": member.full_name,
+        "pronouns": member.pronouns,
+        "title": member.title,
+        "emoji": member.emoji,
+        "avatar_url": member.profile_picture_url,
+        "enabled": member.enabled,
+    });
+
+    if viewer.sees_ids() {
+        value["id"] = json!(member.id.id);
+        value["command_name"] = json!(member.command_name);
+    }
+
+    value
+}
+
+/// See [`super::member_list`]
+pub fn member_list(members: &[Member], aliases: &[Alias], viewer: Viewer) -> Value {
+    members
+        .iter()
+        .filter(|member| member.enabled || viewer.sees_disabled())
+        .map(|listed| {
+            let mut value = member(listed, viewer);
+            value["proxied"] = json!(listed.proxy_enabled);
+
+            if viewer.sees_management_details() {
+                value["aliases"] = aliases
+                    .iter()
+                    .filter(|alias| alias.member_id == listed.id)
+                    .map(|alias| alias.alias.as_str())
+                    .collect();
+            }
+
+            value
+        })
+        .collect()
+}
+
+/// See [`super::member_card`]
+pub fn member_card(card: &Member, fields: &[MemberField], fronting: bool, viewer: Viewer) -> Value {
+    let mut value = member(card, viewer);
+    value["description"] = json!(card.description);
+    value["birthday"] = json!(card.birthday);
+    value["name_pronunciation"] = json!(card.name_pronunciation);
+    value["fronting"] = json!(fronting);
+    value["fields"] = fields
+        .iter()
+        .map(|field| json!({ "name": field.name, "value": field.value }))
+        .collect();
+
+    value
+}
+
+/// See [`super::system_card`]
+pub fn system_card(
+    system: &System,
+    members: &[Member],
+    fronting: &[Member],
+    viewer: Viewer,
+) -> Value {
+    let mut value = json!({
+        "owner_id": system.owner_id.id.0,
+        "member_count": members
+            .iter()
+            .filter(|member| member.enabled || viewer.sees_disabled())
+            .count(),
+        "fronting": fronting
+            .iter()
+            .map(|fronter| member(fronter, viewer))
+            .collect::<Vec<_>>(),
+    });
+
+    if viewer.sees_ids() {
+        value["id"] = json!(system.id.id);
+    }
+
+    value
+}
+
+/// A system's triggers
+pub fn triggers(triggers: &[Trigger]) -> Value {
+    triggers
+        .iter()
+        .map(|trigger| {
+            json!({
+                "id": trigger.id.id,
+                "member_id": trigger.member_id.id,
+                "type": trigger.typ,
+                "text": trigger.text,
+            })
+        })
+        .collect()
+}
+
+/// A system's aliases
+pub fn aliases(aliases: &[Alias]) -> Value {
+    aliases
+        .iter()
+        .map(|alias| {
+            json!({
+                "id": alias.id.id,
+                "member_id": alias.member_id.id,
+                "alias": alias.alias,
+                "command_name": alias.is_primary,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,207 @@
+//! Converting a message's rich text, or Markdown from other apps, into Slack's mrkdwn markup.
+//!
+//! Modals can't take rich text back from the user, so editing a message goes through mrkdwn instead
+//! (see <https://github.com/abdolence/slack-morphism-rust/issues/327>). Formatting, links, mentions, lists, quotes
+//! and code blocks all have a mrkdwn equivalent, so they survive the round trip.
+
+use serde_json::Value;
+use slack_morphism::prelude::*;
+
+/// The message's rich text as mrkdwn, or [`None`] if it doesn't have any rich text
+pub fn from_rich_text(blocks: &[SlackBlock]) -> Option<String> {
+    let sections = blocks
+        .iter()
+        .filter_map(|block| match block {
+            SlackBlock::RichText(richtext) => richtext["elements"].as_array(),
+            _ => None,
+        })
+        .flatten()
+        .map(section)
+        .collect::<Vec<_>>();
+
+    if sections.is_empty() {
+        return None;
+    }
+
+    Some(
+        sections
+            .iter()
+            .map(|section| section.trim_end_matches('\n'))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// A top-level part of the rich text, like a paragraph or a list
+fn section(section: &Value) -> String {
+    let elements = section["elements"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice);
+
+    match section["type"].as_str().unwrap_or_default() {
+        "rich_text_preformatted" => {
+            let code = elements.iter().map(plain).collect::<String>();
+            format!("```{code}```")
+        }
+        "rich_text_quote" => inline(elements)
+            .lines()
+            .map(|line| format!("> {line}"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "rich_text_list" => {
+            let indent = "    "
+                .repeat(usize::try_from(section["indent"].as_u64().unwrap_or(0)).unwrap_or(0));
+            let ordered = section["style"] == "ordered";
+            let offset = section["offset"].as_u64().unwrap_or(0);
+
+            elements
+                .iter()
+                .zip(1..)
+                .map(|(item, number)| {
+                    let bullet = if ordered {
+                        format!("{}.", offset + number)
+                    } else {
+                        "•".to_string()
+                    };
+                    let item = item["elements"].as_array().map_or(&[][..], Vec::as_slice);
+
+                    format!("{indent}{bullet} {}", inline(item))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        _ => inline(elements),
+    }
+}
+
+/// Inline elements, like text and links, with their styling
+fn inline(elements: &[Value]) -> String {
+    elements
+        .iter()
+        .map(|element| styled(&plain(element), &element["style"]))
+        .collect()
+}
+
+/// An inline element without its styling, as used inside code blocks
+fn plain(element: &Value) -> String {
+    let field = |name: &str| element[name].as_str().unwrap_or_default().to_string();
+
+    match element["type"].as_str().unwrap_or_default() {
+        "text" => escape(&field("text")),
+        "link" => match element["text"].as_str() {
+            Some(text) => format!("<{}|{}>", field("url"), escape(text)),
+            None => format!("<{}>", field("url")),
+        },
+        "emoji" => format!(":{}:", field("name")),
+        "user" => format!("<@{}>", field("user_id")),
+        "channel" => format!("<#{}>", field("channel_id")),
+        "usergroup" => format!("<!subteam^{}>", field("usergroup_id")),
+        "broadcast" => format!("<!{}>", field("range")),
+        _ => String::new(),
+    }
+}
+
+/// Wraps text in the markers for its style.
+///
+/// Markers only work right next to the text, so surrounding whitespace is kept outside them.
+fn styled(text: &str, style: &Value) -> String {
+    let trimmed = text.trim();
+
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+
+    let markers = [
+        ("code", "`"),
+        ("strike", "~"),
+        ("italic", "_"),
+        ("bold", "*"),
+    ]
+    .into_iter()
+    .filter(|(name, _)| style[name].as_bool().unwrap_or(false))
+    .map(|(_, marker)| marker)
+    .collect::<String>();
+
+    if markers.is_empty() {
+        return text.to_string();
+    }
+
+    let start = text.len() - text.trim_start().len();
+    let end = start + trimmed.len();
+    let closing = markers.chars().rev().collect::<String>();
+
+    format!(
+        "{}{markers}{trimmed}{closing}{}",
+        &text[..start],
+        &text[end..]
+    )
+}
+
+/// Escapes the characters mrkdwn uses for links and mentions
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Converts Markdown, as written on Discord and PluralKit, into mrkdwn.
+///
+/// Bold, italics, strikethrough and links are converted. Underlines have no mrkdwn equivalent, so they become italics.
+/// Code is left as it is, since both use backticks.
+pub fn from_markdown(markdown: &str) -> String {
+    let mut converted = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+
+    while let Some(next) = rest.chars().next() {
+        let (output, length) = if let Some(code) = code_span(rest) {
+            (code, code.len())
+        } else if rest.starts_with("**") {
+            ("*", 2)
+        } else if rest.starts_with("__") {
+            ("_", 2)
+        } else if rest.starts_with("~~") {
+            ("~", 2)
+        } else if next == '*' && !rest[1..].starts_with(char::is_whitespace) {
+            // A `*` followed by a space is a list item, not italics
+            ("_", 1)
+        } else if let Some((text, url, length)) = link(rest) {
+            converted.push_str(&format!("<{url}|{text}>"));
+            rest = &rest[length..];
+            continue;
+        } else {
+            (&rest[..next.len_utf8()], next.len_utf8())
+        };
+
+        converted.push_str(output);
+        rest = &rest[length..];
+    }
+
+    converted
+}
+
+/// The code span or block at the start of the text, including its backticks
+fn code_span(text: &str) -> Option<&str> {
+    let fence = if text.starts_with("```") {
+        "```"
+    } else if text.starts_with('`') {
+        "`"
+    } else {
+        return None;
+    };
+
+    let end = text[fence.len()..].find(fence)? + fence.len() * 2;
+    Some(&text[..end])
+}
+
+/// The text and URL of a `[text](url)` link at the start of the text, and how long the link is
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let inner = text.strip_prefix('[')?;
+    let (label, after) = inner.split_once("](")?;
+    let (url, _) = after.split_once(')')?;
+
+    if label.contains(['\n', '[']) || url.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some((label, url, label.len() + url.len() + 4))
+}
@@ -0,0 +1,92 @@
+//! References to members, formatted the same way wherever a member is mentioned.
+//!
+//! Anything that names a member (buttons, select options, replies and sections) should go through [`MemberReference`],
+//! so a new member detail only needs adding here to show up everywhere.
+
+use slack_morphism::prelude::*;
+
+use crate::models::Member;
+
+/// How a member is referred to in a message or block
+#[derive(Debug, Clone, Copy)]
+pub struct MemberReference<'a> {
+    member: &'a Member,
+    full_name: bool,
+    id: bool,
+}
+
+impl<'a> MemberReference<'a> {
+    /// A reference with just the member's emoji and display name
+    pub const fn new(member: &'a Member) -> Self {
+        Self {
+            member,
+            full_name: false,
+            id: false,
+        }
+    }
+
+    /// Also show the member's full name
+    pub const fn with_full_name(mut self) -> Self {
+        self.full_name = true;
+        self
+    }
+
+    /// Also show the member's ID and command name, if `show` is true. Pass [`Viewer::sees_ids`](crate::models::viewer::Viewer::sees_ids) here
+    pub const fn with_id(mut self, show: bool) -> Self {
+        self.id = show;
+        self
+    }
+
+    /// The member's display name, with their emoji in front if they have one
+    pub fn name(&self) -> String {
+        self.member.emoji.as_ref().map_or_else(
+            || self.member.display_name.clone(),
+            |emoji| format!(":{emoji}: {}", self.member.display_name),
+        )
+    }
+
+    /// The full name, and the ID and command name if asked for, e.g. `Alex Smith, ID: 4, alex`
+    fn details(&self) -> Option<String> {
+        let details = [
+            Some(self.member.full_name.clone())
+                .filter(|full_name| self.full_name && *full_name != self.member.display_name),
+            Some(format!("ID: {}", self.member.id)).filter(|_| self.id),
+            self.member.command_name.clone().filter(|_| self.id),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        (!details.is_empty()).then(|| details.join(", "))
+    }
+
+    /// The reference as plain text, for buttons and select options
+    pub fn plain(&self) -> String {
+        match self.details() {
+            Some(details) => format!("{} ({details})", self.name()),
+            None => self.name(),
+        }
+    }
+
+    /// The reference as mrkdwn, with the name in bold
+    pub fn mrkdwn(&self) -> String {
+        match self.details() {
+            Some(details) => format!("*{}* ({details})", self.name()),
+            None => format!("*{}*", self.name()),
+        }
+    }
+
+    /// A section with the reference and the member's profile picture as a thumbnail
+    pub fn section(&self) -> SlackSectionBlock {
+        SlackSectionBlock::new()
+            .with_text(md!(self.mrkdwn()))
+            .opt_accessory(avatar(self.member.profile_picture_url.as_deref()))
+    }
+}
+
+/// A profile picture thumbnail for a section's accessory, if the URL is set and valid
+pub fn avatar(url: Option<&str>) -> Option<SlackSectionBlockElement> {
+    Some(SlackSectionBlockElement::Image(
+        SlackBlockImageElement::new(url?.parse().ok()?, "Profile picture".into()),
+    ))
+}
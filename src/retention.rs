@@ -0,0 +1,156 @@
+//! Deleting old messages and message logs.
+//!
+//! Systems can mark a channel as ephemeral with `/system ephemeral add`, e.g. for a venting channel.
+//! A background job regularly looks for proxied messages there that are past the channel's retention, and deletes them
+//! from Slack along with their message logs.
+//!
+//! Systems can also choose how long their message logs are kept with `/system settings retention`. Another job prunes
+//! logs past that, leaving the messages themselves in Slack. The database is vacuumed on startup to hand the space
+//! pruning frees back to the filesystem.
+
+use std::{sync::Arc, time::Duration};
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use crate::{
+    models::{MessageLog, message::ExpiredMessage},
+    proxy::Original,
+};
+
+/// How often to look for expired messages
+const CHECK_EVERY: Duration = Duration::from_secs(10 * 60);
+/// The most messages to delete per check. Anything left over is deleted on the next one
+const BATCH_SIZE: u32 = 200;
+/// How long to wait between deletes, to stay well within Slack's rate limits
+const DELETE_DELAY: Duration = Duration::from_secs(1);
+/// How often to prune message logs
+const PRUNE_EVERY: Duration = Duration::from_secs(60 * 60);
+/// The most message logs to prune at once, so pruning doesn't hold the database for long
+const PRUNE_BATCH_SIZE: u32 = 1000;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error deleting the message
+    Delete,
+}
+
+/// Starts the background jobs that delete expired messages and prune old message logs
+pub fn spawn(client: Arc<SlackHyperClient>, db: SqlitePool) {
+    let prune_db = db.clone();
+
+    tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(CHECK_EVERY);
+
+            loop {
+                interval.tick().await;
+
+                match delete_expired(&client, &db).await {
+                    Ok(0) => {}
+                    Ok(deleted) => info!(deleted, "Deleted expired messages"),
+                    Err(error) => warn!(?error, "Failed to delete expired messages"),
+                }
+            }
+        }
+        .instrument(info_span!("retention")),
+    );
+
+    tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(PRUNE_EVERY);
+
+            loop {
+                interval.tick().await;
+
+                match prune_logs(&prune_db).await {
+                    Ok(0) => {}
+                    Ok(pruned) => info!(pruned, "Pruned message logs"),
+                    Err(error) => warn!(?error, "Failed to prune message logs"),
+                }
+            }
+        }
+        .instrument(info_span!("log_retention")),
+    );
+}
+
+/// Rebuilds the database file, so space freed by pruning is handed back to the filesystem.
+///
+/// SQLite otherwise keeps freed pages around for reuse, so the file never shrinks.
+#[tracing::instrument(skip_all)]
+pub async fn vacuum(db: &SqlitePool) -> Result<(), Error> {
+    debug!("Vacuuming database");
+
+    sqlx::query!("VACUUM")
+        .execute(db)
+        .await
+        .attach_printable("Failed to vacuum database")
+        .change_context(Error::Sqlx)
+        .map(|_| ())
+}
+
+/// Prunes message logs past their system's retention in batches, returning how many were pruned
+async fn prune_logs(db: &SqlitePool) -> Result<u64, Error> {
+    let mut pruned = 0;
+
+    loop {
+        let batch = MessageLog::prune(PRUNE_BATCH_SIZE, db)
+            .await
+            .change_context(Error::Sqlx)?;
+        pruned += batch;
+
+        if batch < u64::from(PRUNE_BATCH_SIZE) {
+            return Ok(pruned);
+        }
+    }
+}
+
+/// Deletes a batch of expired messages, returning how many were deleted
+async fn delete_expired(client: &SlackHyperClient, db: &SqlitePool) -> Result<usize, Error> {
+    let expired = MessageLog::fetch_expired(BATCH_SIZE, db)
+        .await
+        .change_context(Error::Sqlx)?;
+    let mut deleted = 0;
+
+    for message in expired {
+        match delete(client, &message, db).await {
+            Ok(()) => deleted += 1,
+            Err(error) => {
+                warn!(?error, ?message, "Failed to delete expired message");
+
+                // Deletes are already retried, so this is likely to keep failing, e.g. because the bot left the channel.
+                // It's forgotten so it doesn't hold up the messages behind it
+                MessageLog::forget_channel(&message.message_id, db)
+                    .await
+                    .change_context(Error::Sqlx)?;
+            }
+        }
+
+        tokio::time::sleep(DELETE_DELAY).await;
+    }
+
+    Ok(deleted)
+}
+
+async fn delete(
+    client: &SlackHyperClient,
+    message: &ExpiredMessage,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    debug!(?message, "Deleting expired message");
+
+    let system = message
+        .system_id
+        .fetch(db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    Original::Proxied(message.message_id.clone())
+        .delete(client, &system, message.channel_id.clone(), db)
+        .await
+        .change_context(Error::Delete)
+}
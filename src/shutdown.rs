@@ -0,0 +1,100 @@
+//! Shutting down without cutting off messages partway through being proxied.
+//!
+//! On SIGTERM or SIGINT the bot stops taking new events, then waits up to [`DRAIN_TIMEOUT`] for the events it's
+//! already handling before the database is closed. Events hold an [`InFlight`] guard while they're handled, which is
+//! what's waited on. Anything still cut off after the timeout is picked up by [`proxy::recover`](crate::proxy::recover)
+//! on the next startup.
+
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// How long to wait for in-flight events once shutdown starts. Kept under the usual 30 second grace period
+/// container platforms give before killing the process
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// When shutdown started, if it has
+static STARTED: LazyLock<watch::Sender<Option<Instant>>> =
+    LazyLock::new(|| watch::Sender::new(None));
+/// How many events are being handled
+static IN_FLIGHT: LazyLock<watch::Sender<usize>> = LazyLock::new(|| watch::Sender::new(0));
+
+/// An event that's being handled. Dropping it marks the event as finished
+pub struct InFlight(());
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        IN_FLIGHT.send_modify(|count| *count -= 1);
+    }
+}
+
+/// Starts handling an event, or returns [`None`] if the bot is shutting down and shouldn't start anything new
+pub fn track() -> Option<InFlight> {
+    if STARTED.borrow().is_some() {
+        return None;
+    }
+
+    IN_FLIGHT.send_modify(|count| *count += 1);
+    Some(InFlight(()))
+}
+
+/// Waits for SIGTERM or SIGINT, then starts shutting down
+pub async fn signal() {
+    let interrupt = async {
+        if let Err(error) = tokio::signal::ctrl_c().await {
+            warn!(%error, "Failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(error) => {
+                warn!(%error, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => {},
+        () = terminate => {},
+    }
+
+    info!("Shutting down");
+    STARTED.send_replace(Some(Instant::now()));
+}
+
+/// Once shutdown has started, waits for in-flight events to finish, or for [`DRAIN_TIMEOUT`] to pass since it started
+pub async fn drain() {
+    let mut shutdown = STARTED.subscribe();
+    let Ok(Some(started)) = shutdown
+        .wait_for(Option::is_some)
+        .await
+        .map(|started| *started)
+    else {
+        return;
+    };
+
+    let deadline = tokio::time::Instant::from_std(started + DRAIN_TIMEOUT);
+    let mut in_flight = IN_FLIGHT.subscribe();
+
+    match tokio::time::timeout_at(deadline, in_flight.wait_for(|count| *count == 0)).await {
+        Ok(_) => info!("Finished handling in-flight events"),
+        Err(_) => warn!(
+            remaining = *IN_FLIGHT.borrow(),
+            "Timed out waiting for in-flight events"
+        ),
+    }
+}
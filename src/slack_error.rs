@@ -0,0 +1,225 @@
+//! Getting Slack's error codes out of error reports.
+//!
+//! When a Slack API call fails, slack-morphism keeps the `error` string from Slack's response (e.g. `message_not_found`)
+//! deep in the report chain. Pulling it out lets it be logged, counted, and explained to users.
+//!
+//! Some failures are Slack having a bad moment rather than something wrong with the request, and are worth retrying.
+//! Rate limits are handled for every call by the client's [rate control](rate_control), which waits as long as Slack's
+//! `Retry-After` asks. Other passing failures are only retried where it's safe to, with [`retry`].
+//!
+//! Calls wrapped in [`traced`] get a `slack_api` span of their own, with the method, how long it took, and the error
+//! code if it failed, so a slow or failing proxy can be pinned on a single call.
+
+use std::{
+    future::Future,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use error_stack::Report;
+use slack_morphism::prelude::*;
+use tracing::{Instrument, Span, debug, field, info_span};
+
+use crate::{fields, metrics};
+
+/// The error code Slack responded with, if the report was caused by a Slack API error
+pub fn code<C>(report: &Report<C>) -> Option<&str> {
+    match report.downcast_ref::<SlackClientError>()? {
+        SlackClientError::ApiError(error) => Some(&error.code),
+        SlackClientError::RateLimitError(_) => Some("ratelimited"),
+        _ => None,
+    }
+}
+
+/// Records the Slack error code behind a report in the current span and in metrics.
+///
+/// The span needs a `slack_error` field for the code to show up on it.
+pub fn record<C>(report: &Report<C>) -> Option<&str> {
+    let code = code(report)?;
+
+    fields!(slack_error = %code);
+    debug!(slack_error = code, "Slack API call failed");
+    metrics::record_slack_error(code);
+
+    Some(code)
+}
+
+/// Explains a Slack error code to the user, for the codes they can do something about
+pub fn describe(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "not_in_channel" | "channel_not_found" => {
+            "The bot isn't in this channel. Add it with `/invite`."
+        }
+        "is_archived" => "This channel is archived.",
+        "restricted_action" | "restricted_action_read_only_channel" => {
+            "This channel doesn't let the bot post."
+        }
+        "restricted_action_thread_only_channel" => "This channel only allows replies in threads.",
+        "cant_delete_message" => {
+            "Slack didn't let the bot delete your original message. Your workspace may not allow deleting messages."
+        }
+        "message_not_found" => "The message was deleted before the bot could get to it.",
+        "token_revoked" | "token_expired" | "invalid_auth" | "not_authed" | "account_inactive" => {
+            "Your system's Slack authorization has stopped working. Reauthenticate to fix this."
+        }
+        "missing_scope" => {
+            "Your system's Slack authorization is missing a permission. Reauthenticate to fix this."
+        }
+        "msg_too_long" => "The message is too long.",
+        "ratelimited" => "Slack is rate limiting the bot. Try again in a minute.",
+        _ => return None,
+    })
+}
+
+/// Whether a Slack error code means the bot isn't in the conversation it tried to use
+pub fn is_missing_access(code: &str) -> bool {
+    matches!(code, "not_in_channel" | "channel_not_found")
+}
+
+/// Whether a Slack error code means the system's user token needs replacing, by reauthenticating with `/system reauth`
+pub fn needs_reauth(code: &str) -> bool {
+    matches!(
+        code,
+        "token_revoked"
+            | "token_expired"
+            | "invalid_auth"
+            | "not_authed"
+            | "account_inactive"
+            | "missing_scope"
+    )
+}
+
+/// Describes why a report happened for users, including Slack's error code if there is one
+pub fn user_message<C>(report: &Report<C>) -> String
+where
+    C: std::fmt::Display,
+{
+    let context = report.current_context();
+
+    match code(report) {
+        Some(code) => match describe(code) {
+            Some(description) => format!("{context}: {description} (`{code}`)"),
+            None => format!("{context} (`{code}`)"),
+        },
+        None => context.to_string(),
+    }
+}
+
+/// The code to record for a failed call, matching what [`code`] gets out of a report
+fn error_code(error: &SlackClientError) -> &str {
+    match error {
+        SlackClientError::ApiError(error) => &error.code,
+        SlackClientError::RateLimitError(_) => "ratelimited",
+        SlackClientError::HttpError(_) => "http_error",
+        SlackClientError::HttpProtocolError(_) | SlackClientError::EndOfStream(_) => {
+            "connection_error"
+        }
+        _ => "client_error",
+    }
+}
+
+/// Makes a Slack API call in a `slack_api` span, recording its `method`, latency, and error code
+pub async fn traced<T, Fut>(method: &'static str, call: Fut) -> Result<T, SlackClientError>
+where
+    Fut: Future<Output = Result<T, SlackClientError>> + Send,
+{
+    let span = info_span!(
+        "slack_api",
+        method,
+        latency_ms = field::Empty,
+        slack_error = field::Empty
+    );
+
+    let started = Instant::now();
+    let result = call.instrument(span.clone()).await;
+    let latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    record_call(&span, latency_ms, result.as_ref().err());
+
+    result
+}
+
+/// Records how a traced call went on its span
+fn record_call(span: &Span, latency_ms: u64, error: Option<&SlackClientError>) {
+    span.record("latency_ms", latency_ms);
+
+    let _entered = span.enter();
+    match error {
+        Some(error) => {
+            let code = error_code(error);
+            span.record("slack_error", code);
+            debug!(latency_ms, slack_error = code, "Slack API call failed");
+        }
+        None => debug!(latency_ms, "Slack API call succeeded"),
+    }
+}
+
+/// Slack error codes that mean Slack itself had a problem, rather than the request
+const TRANSIENT_CODES: &[&str] = &[
+    "internal_error",
+    "fatal_error",
+    "service_unavailable",
+    "request_timeout",
+];
+
+/// How many times a call is retried, whether for rate limits or transient errors
+const MAX_RETRIES: u32 = 3;
+/// How long to wait before the first retry, before jitter is added. Each retry after waits twice as long
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+/// The longest to wait for Slack's rate limits before giving up on a call
+const MAX_RATE_LIMIT_DELAY: Duration = Duration::from_secs(60);
+/// The most jitter added to the retry delay, so retries from many messages don't all land at once
+const MAX_JITTER_MILLIS: u32 = 500;
+
+/// Whether the error was likely a passing problem on Slack's end, like a 5xx or a dropped connection
+pub fn is_transient(error: &SlackClientError) -> bool {
+    match error {
+        SlackClientError::HttpError(error) => error.status_code.is_server_error(),
+        SlackClientError::HttpProtocolError(_) | SlackClientError::EndOfStream(_) => true,
+        SlackClientError::ApiError(error) => TRANSIENT_CODES.contains(&error.code.as_str()),
+        _ => false,
+    }
+}
+
+/// The rate control the bot's Slack client is set up with.
+///
+/// Calls are spaced out to stay within each method's rate limit tier, and calls that get rate limited anyway are
+/// retried once Slack's `Retry-After` has passed.
+pub fn rate_control() -> SlackApiRateControlConfig {
+    SlackApiRateControlConfig::new()
+        .with_max_retries(MAX_RETRIES as usize)
+        .with_max_delay_timeout(MAX_RATE_LIMIT_DELAY)
+}
+
+/// Waits a slightly random amount of time before a retry, doubling with each `attempt` (starting at 0)
+pub async fn backoff(attempt: u32) {
+    // Good enough randomness for spreading out retries, without pulling in a crate for it
+    let jitter = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos() % MAX_JITTER_MILLIS);
+
+    let delay = RETRY_DELAY * 2_u32.saturating_pow(attempt);
+
+    tokio::time::sleep(delay + Duration::from_millis(jitter.into())).await;
+}
+
+/// Makes a Slack API call, retrying it with [`backoff`] while it fails with a [transient](is_transient) error.
+///
+/// Each attempt is [traced](traced) separately. Only use this for calls that are safe to repeat.
+pub async fn retry<T, F, Fut>(method: &'static str, mut call: F) -> Result<T, SlackClientError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, SlackClientError>> + Send,
+{
+    let mut attempt = 0;
+
+    loop {
+        match traced(method, call()).await {
+            Err(error) if attempt < MAX_RETRIES && is_transient(&error) => {
+                debug!(%error, attempt, "Transient Slack error. Retrying");
+                backoff(attempt).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
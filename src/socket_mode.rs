@@ -0,0 +1,85 @@
+//! Receiving events from Slack over Socket Mode, for deployments that can't expose the `/push`, `/command` and
+//! `/interaction` endpoints.
+//!
+//! Turned on with `SOCKET_MODE`. The bot connects out to Slack with the app token, and events are handed to the same
+//! handlers the HTTP endpoints use. The rest of the HTTP server (OAuth, blobs, the API and metrics) still runs.
+//!
+//! Socket Mode acknowledges an interaction without a response, so submitted forms close straight away instead of
+//! showing the "Working on it..." screen and their result.
+
+use std::sync::Arc;
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::info;
+
+use crate::{APP_TOKEN, commands, env, events, interactions};
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error connecting to Slack
+    Connect,
+}
+
+/// Whether `SOCKET_MODE` is turned on
+pub fn enabled() -> bool {
+    env::socket_mode().is_some_and(|value| {
+        matches!(
+            value.trim().to_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    })
+}
+
+/// Connects to Slack and starts handling events. The listener stops when it's dropped, so keep it around
+pub async fn start(
+    environment: Arc<SlackHyperListenerEnvironment>,
+) -> Result<SlackClientSocketModeListener<SlackClientHyperHttpsConnector>, Error> {
+    let callbacks = SlackSocketModeListenerCallbacks::new()
+        .with_push_events(push_event)
+        .with_command_events(command_event)
+        .with_interaction_events(interaction_event);
+
+    let listener = SlackClientSocketModeListener::new(
+        &SlackClientSocketModeConfig::new(),
+        environment,
+        callbacks,
+    );
+
+    listener
+        .listen_for(&APP_TOKEN)
+        .await
+        .attach_printable("Error registering the app token for Socket Mode")
+        .change_context(Error::Connect)?;
+
+    listener.start().await;
+    info!("Listening for events over Socket Mode");
+
+    Ok(listener)
+}
+
+async fn push_event(
+    event: SlackPushEventCallback,
+    client: Arc<SlackHyperClient>,
+    states: SlackClientEventsUserState,
+) -> UserCallbackResult<()> {
+    events::handle_push_event(event, client, states).await;
+    Ok(())
+}
+
+async fn command_event(
+    event: SlackCommandEvent,
+    client: Arc<SlackHyperClient>,
+    states: SlackClientEventsUserState,
+) -> UserCallbackResult<SlackCommandEventResponse> {
+    Ok(commands::handle_command_event(event, client, states).await)
+}
+
+async fn interaction_event(
+    event: SlackInteractionEvent,
+    client: Arc<SlackHyperClient>,
+    states: SlackClientEventsUserState,
+) -> UserCallbackResult<()> {
+    interactions::handle_interaction_event(event, client, states);
+    Ok(())
+}
@@ -0,0 +1,323 @@
+//! Parsing times typed into commands.
+//!
+//! Accepts times relative to now (`now`, `in 2h`, `90m ago`, `1h 30m ago`), a day with an optional time of day
+//! (`yesterday 22:00`, `tomorrow 9am`, `2024-05-01 14:00`), or just a time of day (`22:00`, `10:30pm`), which is taken as
+//! today. Days and times of day are in the system's timezone, so `yesterday 22:00` means 22:00 where the system is.
+
+use time::{Date, Duration, OffsetDateTime, PrimitiveDateTime, Time, macros::format_description};
+use time_tz::{OffsetDateTimeExt, PrimitiveDateTimeExt, Tz};
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum ParseError {
+    /// No time was given
+    Empty,
+    /// Couldn't read `{0}` as a time. Try something like `yesterday 22:00`, `in 2h`, or `2024-05-01 14:00`
+    Unrecognized(String),
+    /// That time is too far away
+    OutOfRange,
+    /// That time doesn't exist in your timezone, as the clocks skip over it
+    Skipped,
+}
+
+/// Parses a time, using `now` for relative times and `tz` for days and times of day
+pub fn parse(input: &str, now: OffsetDateTime, tz: &Tz) -> Result<OffsetDateTime, ParseError> {
+    let input = input.trim().to_lowercase();
+    let words = input.split_whitespace().collect::<Vec<_>>();
+    let unrecognized = || ParseError::Unrecognized(input.clone());
+
+    match words.as_slice() {
+        [] => Err(ParseError::Empty),
+        ["now"] => Ok(now),
+        ["in", duration @ ..] => {
+            let duration = parse_duration(duration).ok_or_else(unrecognized)?;
+            now.checked_add(duration).ok_or(ParseError::OutOfRange)
+        }
+        [duration @ .., "ago"] => {
+            let duration = parse_duration(duration).ok_or_else(unrecognized)?;
+            now.checked_sub(duration).ok_or(ParseError::OutOfRange)
+        }
+        [day, time @ ..] => {
+            let today = now.to_timezone(tz).date();
+
+            // `2024-05-01T14:00`, as copied from elsewhere
+            if let Some((date, time)) = day.split_once('t')
+                && time.contains(':')
+                && let Some(date) = parse_date(date)
+            {
+                let time = parse_time_of_day(&[time]).ok_or_else(unrecognized)?;
+                return local(date, time, tz);
+            }
+
+            match parse_day(day, today)? {
+                Some(date) => {
+                    let time = if time.is_empty() {
+                        Time::MIDNIGHT
+                    } else {
+                        parse_time_of_day(time).ok_or_else(unrecognized)?
+                    };
+                    local(date, time, tz)
+                }
+                None => {
+                    let time = parse_time_of_day(&words).ok_or_else(unrecognized)?;
+                    local(today, time, tz)
+                }
+            }
+        }
+    }
+}
+
+/// The moment a date and time of day in the timezone happened.
+///
+/// When the clocks go back the time happens twice, and the first one is used.
+fn local(date: Date, time: Time, tz: &Tz) -> Result<OffsetDateTime, ParseError> {
+    PrimitiveDateTime::new(date, time)
+        .assume_timezone(tz)
+        .take_first()
+        .ok_or(ParseError::Skipped)
+}
+
+/// A day, relative to today or as `YYYY-MM-DD`. [`None`] if the word isn't a day
+fn parse_day(word: &str, today: Date) -> Result<Option<Date>, ParseError> {
+    let offset = match word {
+        "today" => 0,
+        "yesterday" => -1,
+        "tomorrow" => 1,
+        _ => return Ok(parse_date(word)),
+    };
+
+    today
+        .checked_add(Duration::days(offset))
+        .map(Some)
+        .ok_or(ParseError::OutOfRange)
+}
+
+fn parse_date(word: &str) -> Option<Date> {
+    Date::parse(word, format_description!("[year]-[month]-[day]")).ok()
+}
+
+/// A time of day, like `22:00`, `9:30:15`, `10pm`, or `10:30 pm`
+fn parse_time_of_day(words: &[&str]) -> Option<Time> {
+    let text = words.concat();
+
+    let (clock, meridiem) = if let Some(clock) = text.strip_suffix("am") {
+        (clock, Some(false))
+    } else if let Some(clock) = text.strip_suffix("pm") {
+        (clock, Some(true))
+    } else {
+        (text.as_str(), None)
+    };
+
+    let mut parts = clock.split(':');
+    let hour = parts.next()?.parse::<u8>().ok()?;
+    let minute = parts.next().map_or(Some(0), two_digits)?;
+    let second = parts.next().map_or(Some(0), two_digits)?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let hour = match meridiem {
+        // A bare number is too easy to confuse with a duration, so it needs am/pm or minutes
+        None if !clock.contains(':') => return None,
+        None => hour,
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(false) => hour % 12,
+        Some(true) => hour % 12 + 12,
+    };
+
+    Time::from_hms(hour, minute, second).ok()
+}
+
+/// Minutes or seconds, which always have two digits (`9:05`, not `9:5`)
+fn two_digits(part: &str) -> Option<u8> {
+    if part.len() == 2 {
+        part.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// A length of time, like `2h`, `1h30m`, `1 hour 30 minutes`, or `3 days`
+fn parse_duration(words: &[&str]) -> Option<Duration> {
+    let text = words.concat();
+    let mut rest = text.as_str();
+    let mut total = Duration::ZERO;
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let amount = rest[..digits].parse::<i32>().ok()?;
+        rest = &rest[digits..];
+
+        let unit_length = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_length] {
+            "s" | "sec" | "secs" | "second" | "seconds" => Duration::SECOND,
+            "m" | "min" | "mins" | "minute" | "minutes" => Duration::MINUTE,
+            "h" | "hr" | "hrs" | "hour" | "hours" => Duration::HOUR,
+            "d" | "day" | "days" => Duration::DAY,
+            "w" | "week" | "weeks" => Duration::WEEK,
+            _ => return None,
+        };
+        rest = &rest[unit_length..];
+
+        total = total.checked_add(unit.checked_mul(amount)?)?;
+    }
+
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{datetime, time};
+    use time_tz::timezones;
+
+    use super::*;
+
+    /// A Wednesday afternoon, outside of daylight saving time changes
+    const NOW: OffsetDateTime = datetime!(2024-05-01 15:00 UTC);
+
+    fn london() -> &'static Tz {
+        timezones::get_by_name("Europe/London").expect("London to be a timezone")
+    }
+
+    fn parse_utc(input: &str) -> Result<OffsetDateTime, ParseError> {
+        parse(input, NOW, timezones::db::UTC)
+    }
+
+    #[test]
+    fn now() {
+        assert_eq!(parse_utc("now").unwrap(), NOW);
+        assert_eq!(parse_utc("  NOW ").unwrap(), NOW);
+    }
+
+    #[test]
+    fn relative() {
+        assert_eq!(parse_utc("in 2h").unwrap(), NOW + Duration::hours(2));
+        assert_eq!(parse_utc("90m ago").unwrap(), NOW - Duration::minutes(90));
+        assert_eq!(
+            parse_utc("1h 30m ago").unwrap(),
+            NOW - Duration::minutes(90)
+        );
+        assert_eq!(
+            parse_utc("in 1 hour 30 minutes").unwrap(),
+            NOW + Duration::minutes(90)
+        );
+        assert_eq!(parse_utc("3 days ago").unwrap(), NOW - Duration::days(3));
+    }
+
+    #[test]
+    fn days() {
+        assert_eq!(
+            parse_utc("yesterday 22:00").unwrap(),
+            datetime!(2024-04-30 22:00 UTC)
+        );
+        assert_eq!(
+            parse_utc("tomorrow 9am").unwrap(),
+            datetime!(2024-05-02 9:00 UTC)
+        );
+        assert_eq!(parse_utc("today").unwrap(), datetime!(2024-05-01 0:00 UTC));
+        assert_eq!(
+            parse_utc("2024-04-01 14:00").unwrap(),
+            datetime!(2024-04-01 14:00 UTC)
+        );
+        assert_eq!(
+            parse_utc("2024-04-01T14:00").unwrap(),
+            datetime!(2024-04-01 14:00 UTC)
+        );
+    }
+
+    #[test]
+    fn times_of_day() {
+        assert_eq!(parse_utc("22:00").unwrap(), datetime!(2024-05-01 22:00 UTC));
+        assert_eq!(
+            parse_utc("10:30pm").unwrap(),
+            datetime!(2024-05-01 22:30 UTC)
+        );
+        assert_eq!(
+            parse_utc("10:30 pm").unwrap(),
+            datetime!(2024-05-01 22:30 UTC)
+        );
+        assert_eq!(parse_utc("12am").unwrap(), datetime!(2024-05-01 0:00 UTC));
+        assert_eq!(parse_utc("12pm").unwrap(), datetime!(2024-05-01 12:00 UTC));
+        assert_eq!(
+            parse_utc("9:30:15").unwrap(),
+            datetime!(2024-05-01 9:30:15 UTC)
+        );
+    }
+
+    #[test]
+    fn uses_the_timezone() {
+        // London is on BST (UTC+1) in May
+        assert_eq!(
+            parse("2024-05-01 14:00", NOW, london()).unwrap(),
+            datetime!(2024-05-01 13:00 UTC)
+        );
+
+        // 00:30 UTC is already the next day in London
+        let late = datetime!(2024-05-01 23:30 UTC);
+        assert_eq!(
+            parse("today", late, london()).unwrap(),
+            datetime!(2024-05-01 23:00 UTC)
+        );
+    }
+
+    #[test]
+    fn clock_changes() {
+        // Clocks go forward from 01:00 to 02:00
+        assert!(matches!(
+            parse("2024-03-31 01:30", NOW, london()),
+            Err(ParseError::Skipped)
+        ));
+
+        // Clocks go back from 02:00 to 01:00, so 01:30 happens twice. The first is in BST
+        assert_eq!(
+            parse("2024-10-27 01:30", NOW, london()).unwrap(),
+            datetime!(2024-10-27 00:30 UTC)
+        );
+    }
+
+    #[test]
+    fn rejects() {
+        assert!(matches!(parse_utc(""), Err(ParseError::Empty)));
+        assert!(matches!(parse_utc("   "), Err(ParseError::Empty)));
+
+        for input in [
+            "10",
+            "9:5",
+            "13pm",
+            "25:00",
+            "in",
+            "ago",
+            "in 2 fortnights",
+            "someday",
+            "yesterday at noon",
+        ] {
+            assert!(
+                matches!(parse_utc(input), Err(ParseError::Unrecognized(_))),
+                "{input} shouldn't parse"
+            );
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert!(matches!(
+            parse_utc("in 999999999 weeks"),
+            Err(ParseError::OutOfRange)
+        ));
+    }
+
+    #[test]
+    fn time_of_day_parts() {
+        assert_eq!(parse_time_of_day(&["10", "pm"]), Some(time!(22:00)));
+        assert_eq!(parse_time_of_day(&["0:00"]), Some(time!(0:00)));
+        assert_eq!(parse_time_of_day(&["1:00:00:00"]), None);
+    }
+}
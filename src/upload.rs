@@ -0,0 +1,94 @@
+//! Uploading files to Slack as the bot, like exports and files re-shared alongside proxied messages.
+//!
+//! Uses Slack's external upload flow: get an upload URL, upload the file to it, then share the file to a channel.
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+
+use crate::{BOT_TOKEN, slack_error};
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while opening the DM with the user
+    OpenDm,
+    /// Error while uploading the file
+    Upload,
+}
+
+/// A file to upload
+pub struct File<'a> {
+    pub filename: &'a str,
+    pub content_type: &'a str,
+    pub data: Vec<u8>,
+}
+
+/// Uploads a file and shares it in the user's DM with the bot, along with a comment
+#[tracing::instrument(skip(client, file), fields(filename = file.filename))]
+pub async fn send_to_dm(
+    client: &SlackHyperClient,
+    user_id: &SlackUserId,
+    file: File<'_>,
+    comment: &str,
+) -> Result<(), Error> {
+    let channel = slack_error::traced(
+        "conversations.open",
+        client.open_session(&BOT_TOKEN).conversations_open(
+            &SlackApiConversationsOpenRequest::new().with_users(vec![user_id.clone()]),
+        ),
+    )
+    .await
+    .change_context(Error::OpenDm)?
+    .channel;
+
+    share(client, channel.id, None, file, Some(comment)).await
+}
+
+/// Uploads a file and shares it in a channel, or in a thread if `thread_ts` is set
+#[tracing::instrument(skip(client, file, comment), fields(filename = file.filename))]
+pub async fn share(
+    client: &SlackHyperClient,
+    channel_id: SlackChannelId,
+    thread_ts: Option<SlackTs>,
+    file: File<'_>,
+    comment: Option<&str>,
+) -> Result<(), Error> {
+    let session = client.open_session(&BOT_TOKEN);
+
+    let upload = slack_error::traced(
+        "files.getUploadURLExternal",
+        session.get_upload_url_external(&SlackApiFilesGetUploadUrlExternalRequest::new(
+            file.filename.to_string(),
+            file.data.len(),
+        )),
+    )
+    .await
+    .change_context(Error::Upload)?;
+
+    // Not an API method, but it's where most of an upload's time goes
+    slack_error::traced(
+        "files.uploadViaUrl",
+        session.files_upload_via_url(&SlackApiFilesUploadViaUrlRequest::new(
+            upload.upload_url,
+            file.data,
+            file.content_type.to_string(),
+        )),
+    )
+    .await
+    .change_context(Error::Upload)?;
+
+    slack_error::traced(
+        "files.completeUploadExternal",
+        session.files_complete_upload_external(
+            &SlackApiFilesCompleteUploadExternalRequest::new(vec![
+                SlackApiFilesComplete::new(upload.file_id).with_title(file.filename.to_string()),
+            ])
+            .with_channel_id(channel_id)
+            .opt_thread_ts(thread_ts)
+            .opt_initial_comment(comment.map(ToString::to_string)),
+        ),
+    )
+    .await
+    .change_context(Error::Upload)?;
+
+    Ok(())
+}